@@ -0,0 +1,131 @@
+package main
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+	"os"
+	"strings"
+
+	"github.com/influxdata/flux"
+	"github.com/influxdata/flux/ast"
+	"github.com/influxdata/flux/codes"
+	"github.com/influxdata/flux/dependency"
+	"github.com/influxdata/flux/execute"
+	"github.com/influxdata/flux/fluxinit"
+	"github.com/influxdata/flux/internal/errors"
+	"github.com/influxdata/flux/parser"
+	"github.com/influxdata/flux/runtime"
+	"github.com/influxdata/flux/semantic"
+	"github.com/influxdata/flux/values"
+	"github.com/spf13/cobra"
+)
+
+var evalFlags struct {
+	JSON bool
+}
+
+func evalE(cmd *cobra.Command, args []string) error {
+	fluxinit.FluxInit()
+	v, err := eval(context.Background(), args[0])
+	if err != nil {
+		return err
+	}
+	if evalFlags.JSON {
+		out, err := evalJSON(v)
+		if err != nil {
+			return err
+		}
+		fmt.Println(string(out))
+		return nil
+	}
+	if err := values.Display(os.Stdout, v); err != nil {
+		return err
+	}
+	fmt.Printf("\ntype: %s\n", v.Type())
+	return nil
+}
+
+// evalJSON encodes a value and its type as a JSON object, {"value": ..., "type": "..."}.
+// See values.ToJSON for how values are represented.
+func evalJSON(v values.Value) ([]byte, error) {
+	value, err := values.ToJSON(v)
+	if err != nil {
+		return nil, err
+	}
+	return json.Marshal(struct {
+		Value interface{} `json:"value"`
+		Type  string      `json:"type"`
+	}{value, v.Type().String()})
+}
+
+// eval evaluates a single Flux expression and returns its value.
+// Standard library packages the expression refers to, such as `strings`
+// in `strings.title(v: "a")`, are imported if the expression does not import them.
+//
+// The expression is evaluated without the dependencies that functions with
+// side effects need, such as an HTTP client or a filesystem, so those functions fail.
+func eval(ctx context.Context, expr string) (values.Value, error) {
+	src, err := evalSource(expr)
+	if err != nil {
+		return nil, err
+	}
+	ctx, span := dependency.Inject(ctx, execute.DefaultExecutionDependencies())
+	defer span.Finish()
+	ses, _, err := runtime.Eval(ctx, src)
+	if err != nil {
+		return nil, err
+	}
+	for _, se := range ses {
+		if _, ok := se.Node.(*semantic.ExpressionStatement); !ok {
+			continue
+		}
+		if _, ok := se.Value.(*flux.TableObject); ok {
+			return nil, errors.New(codes.Invalid, "the expression is a stream of tables, run it as a query with flux -e instead")
+		}
+		return se.Value, nil
+	}
+	return nil, errors.New(codes.Internal, "the expression produced no value")
+}
+
+// evalSource checks that expr is a single expression, optionally preceded by imports,
+// and returns it with imports added for the standard library packages it refers to.
+func evalSource(expr string) (string, error) {
+	pkg := parser.ParseSource(expr)
+	if ast.Check(pkg) > 0 {
+		return "", errors.Wrap(ast.GetError(pkg), codes.Invalid, "invalid expression")
+	}
+	file := pkg.Files[0]
+	if len(file.Body) != 1 {
+		return "", errors.New(codes.Invalid, "expected a single expression")
+	}
+	if _, ok := file.Body[0].(*ast.ExpressionStatement); !ok {
+		return "", errors.New(codes.Invalid, "expected a single expression")
+	}
+
+	imported := make(map[string]bool)
+	for _, imp := range file.Imports {
+		name := imp.Path.Value[strings.LastIndex(imp.Path.Value, "/")+1:]
+		if imp.As != nil {
+			name = imp.As.Name
+		}
+		imported[name] = true
+	}
+	var imports []string
+	stdlib := runtime.StdLib()
+	ast.Visit(file.Body[0], func(n ast.Node) {
+		m, ok := n.(*ast.MemberExpression)
+		if !ok {
+			return
+		}
+		id, ok := m.Object.(*ast.Identifier)
+		if !ok || imported[id.Name] {
+			return
+		}
+		imported[id.Name] = true
+		if _, err := stdlib.ImportPackageObject(id.Name); err == nil {
+			imports = append(imports, fmt.Sprintf("import %q\n", id.Name))
+		}
+	})
+	return strings.Join(imports, "") + expr, nil
+}
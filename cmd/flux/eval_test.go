@@ -0,0 +1,61 @@
+package main
+
+import (
+	"context"
+	"testing"
+
+	"github.com/influxdata/flux/values"
+)
+
+func TestEval(t *testing.T) {
+	for _, tc := range []struct {
+		expr string
+		want values.Value
+		typ  string
+	}{
+		{expr: "1 + 2", want: values.NewInt(3), typ: "int"},
+		{expr: `strings.joinStr(arr: ["a", "b"], v: ",")`, want: values.NewString("a,b"), typ: "string"},
+		{expr: `import s "strings"
+s.toUpper(v: "a")`, want: values.NewString("A"), typ: "string"},
+	} {
+		t.Run(tc.expr, func(t *testing.T) {
+			got, err := eval(context.Background(), tc.expr)
+			if err != nil {
+				t.Fatal(err)
+			}
+			if !got.Equal(tc.want) {
+				t.Errorf("unexpected value: want %v, got %v", tc.want, got)
+			}
+			if typ := got.Type().String(); typ != tc.typ {
+				t.Errorf("unexpected type: want %s, got %s", tc.typ, typ)
+			}
+		})
+	}
+}
+
+func TestEvalInvalid(t *testing.T) {
+	for _, expr := range []string{
+		"x = 1",
+		"1\n2",
+		"1 +",
+		`from(bucket: "b")`,
+	} {
+		if _, err := eval(context.Background(), expr); err == nil {
+			t.Errorf("expected an error evaluating %q", expr)
+		}
+	}
+}
+
+func TestEvalJSON(t *testing.T) {
+	v, err := eval(context.Background(), `{a: 1, b: ["x"], c: 1h}`)
+	if err != nil {
+		t.Fatal(err)
+	}
+	got, err := evalJSON(v)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if want := `{"value":{"a":1,"b":["x"],"c":"1h"},"type":"{a: int, b: [string], c: duration}"}`; string(got) != want {
+		t.Errorf("unexpected JSON: want %s, got %s", want, got)
+	}
+}
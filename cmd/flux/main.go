@@ -143,6 +143,16 @@ func main() {
 	fmtCmd.Flags().BoolVarP(&fmtFlags.AnalyzeCurrentDirectory, "analyze-current-directory", "c", false, "analyze the current <directory | file> and report if file(s) are not formatted")
 	fluxCmd.AddCommand(fmtCmd)
 
+	evalCmd := &cobra.Command{
+		Use:   "eval",
+		Short: "Evaluate a Flux expression",
+		Long:  "Evaluate a single Flux expression and print its value and type (flux eval [--json] '<expression>')",
+		Args:  cobra.ExactArgs(1),
+		RunE:  evalE,
+	}
+	evalCmd.Flags().BoolVar(&evalFlags.JSON, "json", false, "print the value and its type as a JSON object")
+	fluxCmd.AddCommand(evalCmd)
+
 	testCmd := cmd.TestCommand(NewTestExecutor)
 	fluxCmd.AddCommand(testCmd)
 
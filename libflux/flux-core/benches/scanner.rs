@@ -26,5 +26,26 @@ fn scanner_scan(c: &mut Criterion) {
     });
 }
 
-criterion_group!(scanner, scanner_scan);
+/// Scan a query with long comments and indentation, which the `simd` feature speeds up.
+fn scanner_scan_comments(c: &mut Criterion) {
+    let source = format!(
+        "{}{}",
+        "    // A long comment describing the query that follows it, as found in saved queries.\n"
+            .repeat(20),
+        FLUX,
+    );
+    c.bench_function("scanner.scan_comments", |b| {
+        b.iter(black_box(|| {
+            let mut s = scanner::Scanner::new(&source);
+            loop {
+                let token = s.scan();
+                if token.tok == scanner::TokenType::Eof {
+                    break;
+                }
+            }
+        }));
+    });
+}
+
+criterion_group!(scanner, scanner_scan, scanner_scan_comments);
 criterion_main!(scanner);
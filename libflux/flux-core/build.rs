@@ -0,0 +1,46 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    env, fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+};
+
+// Collects the Rust source files below dir, sorted so that the hash does not depend on the order
+// in which the file system lists them.
+fn source_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        if path.is_dir() {
+            source_files(&path, files)?;
+        } else if path.extension().map_or(false, |ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Sets FLUX_CORE_SOURCE_HASH to a hash of the sources of the crate, which identifies the compiler
+// in `bootstrap::stdlib_hash` more precisely than its version.
+fn main() -> io::Result<()> {
+    let root = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR"));
+    let src = root.join("src");
+    println!("cargo:rerun-if-changed={}", src.display());
+
+    let mut files = Vec::new();
+    source_files(&src, &mut files)?;
+    let mut hasher = DefaultHasher::new();
+    for file in files {
+        file.strip_prefix(&src)
+            .expect("source files are below src")
+            .hash(&mut hasher);
+        fs::read(&file)?.hash(&mut hasher);
+    }
+    println!(
+        "cargo:rustc-env=FLUX_CORE_SOURCE_HASH={:016x}",
+        hasher.finish()
+    );
+    Ok(())
+}
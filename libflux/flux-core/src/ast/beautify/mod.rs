@@ -0,0 +1,254 @@
+//! Restructuring of machine-generated queries into a readable form.
+//!
+//! Queries generated by tools are often a single line that repeats the same pipeline several
+//! times, e.g. once for each series of a chart. [`beautify`] sorts and groups the imports
+//! and extracts the longest pipeline prefix shared by several pipelines into a variable, until
+//! no pipelines share a prefix. [`beautify_source`] then formats the result.
+//!
+//! Only pipelines outside of functions are extracted, since the body of a function may refer to
+//! its parameters, which are not in scope at the top level.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use crate::{
+    ast::{
+        self,
+        comments::{replace_expr, take_leading_comments},
+        inline::for_each_child,
+        pattern::{equivalent, equivalent_calls},
+        walk::{self, Node, Visitor},
+        BaseNode, Expression, File, Identifier, PipeExpr, Statement, VariableAssgn,
+    },
+    formatter, parser,
+};
+
+// Pipelines must share at least this many stages after their head to be extracted.
+const MIN_SHARED_STAGES: usize = 2;
+
+/// Sorts the imports of `file` and extracts the pipeline prefixes shared by several pipelines
+/// into variables. Returns the number of extracted variables.
+pub fn beautify(file: &mut File) -> usize {
+    formatter::sort_imports(file);
+    let mut count = 0;
+    while extract_shared_pipeline(file) {
+        count += 1;
+    }
+    count
+}
+
+/// Parses, beautifies and formats Flux source code.
+pub fn beautify_source(source: &str) -> Result<String> {
+    let mut file = parser::parse_string("".to_string(), source);
+    ast::check::check(Node::File(&file))?;
+    beautify(&mut file);
+    formatter::convert_to_string_with(
+        &file,
+        formatter::Options {
+            sort_imports: true,
+            ..formatter::Options::default()
+        },
+    )
+}
+
+struct Pipeline<'a> {
+    // The index of the top-level statement containing the pipeline.
+    stmt: usize,
+    head: &'a Expression,
+    // The nested pipe expressions that make up the pipeline, innermost first.
+    prefixes: Vec<&'a PipeExpr>,
+}
+
+impl<'a> Pipeline<'a> {
+    fn new(stmt: usize, pipe: &'a PipeExpr) -> Self {
+        let mut prefixes = vec![pipe];
+        let mut head = &pipe.argument;
+        loop {
+            match head {
+                Expression::PipeExpr(inner) => {
+                    prefixes.push(inner);
+                    head = &inner.argument;
+                }
+                Expression::Paren(paren) => head = &paren.expression,
+                _ => break,
+            }
+        }
+        prefixes.reverse();
+        Pipeline {
+            stmt,
+            head,
+            prefixes,
+        }
+    }
+
+    // Returns the number of leading stages two pipelines have in common.
+    fn shared_stages(&self, other: &Pipeline) -> usize {
+        if !equivalent(self.head, other.head) {
+            return 0;
+        }
+        self.prefixes
+            .iter()
+            .zip(&other.prefixes)
+            .take_while(|(a, b)| equivalent_calls(&a.call, &b.call))
+            .count()
+    }
+}
+
+// Collects the outermost pipe expression of every pipeline outside of functions.
+struct PipelineCollector<'a> {
+    stmt: usize,
+    inner: HashSet<*const PipeExpr>,
+    pipelines: Vec<Pipeline<'a>>,
+}
+
+impl<'a> Visitor<'a> for PipelineCollector<'a> {
+    fn visit(&mut self, node: Node<'a>) -> bool {
+        match node {
+            Node::FunctionExpr(_) => return false,
+            Node::PipeExpr(pipe) if !self.inner.contains(&(pipe as *const _)) => {
+                let pipeline = Pipeline::new(self.stmt, pipe);
+                self.inner
+                    .extend(pipeline.prefixes.iter().map(|p| *p as *const _));
+                self.pipelines.push(pipeline);
+            }
+            _ => (),
+        }
+        true
+    }
+}
+
+// The expression of a top-level statement in which pipelines are extracted.
+fn stmt_expr(stmt: &Statement) -> Option<&Expression> {
+    match stmt {
+        Statement::Expr(stmt) => Some(&stmt.expression),
+        Statement::Variable(assgn) => Some(&assgn.init),
+        _ => None,
+    }
+}
+
+fn stmt_expr_mut(stmt: &mut Statement) -> Option<&mut Expression> {
+    match stmt {
+        Statement::Expr(stmt) => Some(&mut stmt.expression),
+        Statement::Variable(assgn) => Some(&mut assgn.init),
+        _ => None,
+    }
+}
+
+// Extracts the longest pipeline prefix shared by several pipelines into a variable. Returns
+// whether a prefix was extracted.
+fn extract_shared_pipeline(file: &mut File) -> bool {
+    let (prefix, first_stmt) = {
+        let mut collector = PipelineCollector {
+            stmt: 0,
+            inner: HashSet::new(),
+            pipelines: Vec::new(),
+        };
+        for (i, stmt) in file.body.iter().enumerate() {
+            collector.stmt = i;
+            if let Some(expr) = stmt_expr(stmt) {
+                walk::walk(&mut collector, Node::from_expr(expr));
+            }
+        }
+        let pipelines = &collector.pipelines;
+        let mut best: Option<(usize, &Pipeline)> = None;
+        for (i, a) in pipelines.iter().enumerate() {
+            for b in &pipelines[i + 1..] {
+                let shared = a.shared_stages(b);
+                if shared >= MIN_SHARED_STAGES && best.map_or(true, |(n, _)| shared > n) {
+                    best = Some((shared, a));
+                }
+            }
+        }
+        match best {
+            Some((shared, pipeline)) => (
+                Expression::PipeExpr(Box::new(pipeline.prefixes[shared - 1].clone())),
+                pipeline.stmt,
+            ),
+            None => return false,
+        }
+    };
+
+    // Reuse the variable the first pipeline is assigned to if the prefix is all of it.
+    let (name, start) = match &file.body[first_stmt] {
+        Statement::Variable(assgn) if equivalent(&assgn.init, &prefix) => {
+            (assgn.id.name.clone(), first_stmt + 1)
+        }
+        _ => {
+            let name = fresh_name(file);
+            // The comments before the prefix stay where it is replaced by the variable.
+            let mut init = prefix.clone();
+            take_leading_comments(&mut init);
+            file.body.insert(
+                first_stmt,
+                Statement::Variable(Box::new(VariableAssgn {
+                    base: BaseNode::default(),
+                    id: Identifier {
+                        base: BaseNode::default(),
+                        name: name.clone(),
+                    },
+                    init,
+                })),
+            );
+            (name, first_stmt + 1)
+        }
+    };
+    for stmt in &mut file.body[start..] {
+        if let Some(expr) = stmt_expr_mut(stmt) {
+            replace(expr, &prefix, &name);
+        }
+    }
+    true
+}
+
+// Returns a variable name that is not used anywhere in `file`.
+fn fresh_name(file: &File) -> String {
+    fresh_variable(&identifiers(Node::File(file)))
+}
+
+// Returns the names of the identifiers in the tree rooted at `node`.
+pub(crate) fn identifiers(node: Node) -> HashSet<String> {
+    let mut names = HashSet::new();
+    walk::walk(
+        &mut |node: Node| {
+            if let Node::Identifier(id) = node {
+                names.insert(id.name.clone());
+            }
+        },
+        node,
+    );
+    names
+}
+
+// Returns the name of the variable that an extracted pipeline is assigned to, which is not in
+// `names`.
+pub(crate) fn fresh_variable(names: &HashSet<String>) -> String {
+    let mut name = "data".to_string();
+    for n in 2.. {
+        if !names.contains(&name) {
+            break;
+        }
+        name = format!("data{}", n);
+    }
+    name
+}
+
+// Replaces the expressions equivalent to `target` outside of functions with `name`.
+fn replace(expr: &mut Expression, target: &Expression, name: &str) {
+    match expr {
+        Expression::Function(_) => (),
+        _ if equivalent(expr, target) => {
+            replace_expr(
+                expr,
+                Expression::Identifier(Identifier {
+                    base: BaseNode::default(),
+                    name: name.to_string(),
+                }),
+            );
+        }
+        _ => for_each_child(expr, &mut |child| replace(child, target, name)),
+    }
+}
@@ -0,0 +1,79 @@
+use super::*;
+
+fn assert_beautified(source: &str, want: &str) {
+    assert_eq!(beautify_source(source).unwrap().trim_end(), want);
+}
+
+#[test]
+fn extract_shared_pipeline() {
+    assert_beautified(
+        r#"from(bucket: "b") |> range(start: -1h) |> filter(fn: (r) => r.a == 1) |> mean()
+from(bucket: "b") |> range(start: -1h) |> filter(fn: (r) => r.a == 1) |> max()"#,
+        r#"data = from(bucket: "b") |> range(start: -1h) |> filter(fn: (r) => r.a == 1)
+
+data |> mean()
+data |> max()"#,
+    );
+}
+
+#[test]
+fn reuse_variable() {
+    assert_beautified(
+        r#"a = from(bucket: "b") |> range(start: -1h) |> filter(fn: (r) => r.a == 1)
+b = from(bucket: "b") |> range(start: -1h) |> filter(fn: (r) => r.a == 1) |> max()"#,
+        r#"a = from(bucket: "b") |> range(start: -1h) |> filter(fn: (r) => r.a == 1)
+b = a |> max()"#,
+    );
+}
+
+#[test]
+fn fresh_variable_name() {
+    assert_beautified(
+        r#"data = 1
+union(tables: [from(bucket: "b") |> range(start: -1h) |> filter(fn: (r) => r.a == 1) |> sum(), from(bucket: "b") |> range(start: -1h) |> filter(fn: (r) => r.a == 1) |> max()])"#,
+        r#"data = 1
+data2 = from(bucket: "b") |> range(start: -1h) |> filter(fn: (r) => r.a == 1)
+
+union(tables: [data2 |> sum(), data2 |> max()])"#,
+    );
+}
+
+#[test]
+fn pipelines_in_functions() {
+    let source = r#"f = (t) => t |> range(start: -1h) |> filter(fn: (r) => r.a == 1) |> max()
+g = (t) => t |> range(start: -1h) |> filter(fn: (r) => r.a == 1) |> min()"#;
+    assert_beautified(source, source);
+}
+
+#[test]
+fn sort_imports() {
+    assert_beautified(
+        r#"import "strings"
+import "experimental/array"
+import "array"
+import "strings"
+
+x = 1"#,
+        r#"import "array"
+import "strings"
+
+import "experimental/array"
+
+x = 1"#,
+    );
+}
+
+#[test]
+fn keep_comments() {
+    assert_beautified(
+        r#"a = // cpu
+from(bucket: "b") |> range(start: -1h) |> filter(fn: (r) => r._measurement == "cpu") |> max()
+b = from(bucket: "b") |> range(start: -1h) |> filter(fn: (r) => r._measurement == "cpu") |> min()"#,
+        r#"data = from(bucket: "b") |> range(start: -1h) |> filter(fn: (r) => r._measurement == "cpu")
+a =
+    // cpu
+    data
+        |> max()
+b = data |> min()"#,
+    );
+}
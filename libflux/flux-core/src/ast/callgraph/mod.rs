@@ -0,0 +1,580 @@
+//! Call graph of the user-defined functions in a package.
+//!
+//! A function is any top-level variable assignment whose value is a function
+//! expression. The graph has an edge from `f` to `g` whenever the body of `f`
+//! refers to `g`, either by calling it or by passing it as a value. References to
+//! members of imported packages (`strings.trimSpace`) are recorded as edges to
+//! external functions named by the import path and member (`strings.trimSpace`).
+//! References to names the package does not define, such as the functions of the
+//! prelude, are not edges of the graph; they are listed by [`CallGraph::free_references`].
+//!
+//! [`CallGraph::link`] adds the functions of the user packages a package imports, as
+//! provided by an [`Importer`], so that the graph spans every package of a query.
+//!
+//! The graph is built from the AST, so it is available even when semantic
+//! analysis fails, which is the case for recursive functions.
+
+#[cfg(test)]
+mod tests;
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    fmt::Write,
+};
+
+use codespan_reporting::diagnostic;
+use indexmap::IndexMap;
+use thiserror::Error;
+
+use crate::{
+    ast::{
+        walk::{self, Node, Visitor},
+        Assignment, Expression, File, FunctionBody, Identifier, Package, PropertyKey,
+        SourceLocation, Statement,
+    },
+    errors::{located, AsDiagnostic, Errors, Located},
+    semantic::import::Importer,
+};
+
+/// A user-defined function in the call graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    /// Name of the function.
+    pub name: String,
+    /// Location of the function definition.
+    pub location: SourceLocation,
+    /// Import path of the package defining the function, or `None` for the package
+    /// the graph was built from.
+    pub package: Option<String>,
+}
+
+/// A reference from the body of one function to another function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    /// Name of the referenced function. Functions of imported packages are
+    /// named by the import path and the member, e.g. `strings.trimSpace`.
+    pub callee: String,
+    /// Location of the reference.
+    pub location: SourceLocation,
+}
+
+/// The call graph of a package.
+#[derive(Debug, Default, Clone)]
+pub struct CallGraph {
+    functions: IndexMap<String, Function>,
+    calls: IndexMap<String, Vec<Call>>,
+    // References from each function to names that are not defined in the package
+    free: IndexMap<String, Vec<Call>>,
+    // Functions referenced outside of any function body
+    top_level_uses: HashSet<String>,
+}
+
+impl CallGraph {
+    /// Builds the call graph of every file in a package.
+    pub fn from_package(pkg: &Package) -> Self {
+        Self::from_files(&pkg.files)
+    }
+
+    /// Builds the call graph of a set of files that make up a package.
+    pub fn from_files(files: &[File]) -> Self {
+        let mut graph = CallGraph::default();
+        let defined: HashSet<&str> = files
+            .iter()
+            .flat_map(|file| &file.body)
+            .filter_map(|stmt| defined_name(stmt).map(|id| id.name.as_str()))
+            .collect();
+        for file in files {
+            for stmt in &file.body {
+                if let Some((id, _)) = function_definition(stmt) {
+                    graph.functions.insert(
+                        id.name.clone(),
+                        Function {
+                            name: id.name.clone(),
+                            location: id.base.location.clone(),
+                            package: None,
+                        },
+                    );
+                    graph.calls.entry(id.name.clone()).or_default();
+                }
+            }
+        }
+        for file in files {
+            let imports: HashMap<&str, &str> = file
+                .imports
+                .iter()
+                .map(|import| {
+                    let path = import.path.value.as_str();
+                    let name = match &import.alias {
+                        Some(alias) => alias.name.as_str(),
+                        None => path.rsplit('/').next().unwrap_or(path),
+                    };
+                    (name, path)
+                })
+                .collect();
+            for stmt in &file.body {
+                let owner = function_definition(stmt).map(|(id, _)| id.name.clone());
+                let (references, free) = {
+                    let mut collector = ReferenceCollector {
+                        functions: &graph.functions,
+                        defined: &defined,
+                        imports: &imports,
+                        skip: HashSet::new(),
+                        scopes: Vec::new(),
+                        references: Vec::new(),
+                        free: Vec::new(),
+                    };
+                    walk::walk(&mut collector, Node::from_stmt(stmt));
+                    (collector.references, collector.free)
+                };
+                if let Some(owner) = &owner {
+                    graph.free.entry(owner.clone()).or_default().extend(free);
+                }
+                for call in references {
+                    match &owner {
+                        Some(owner) => graph.calls[owner].push(call),
+                        None => {
+                            graph.top_level_uses.insert(call.callee);
+                        }
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    /// Adds the functions of the user packages referenced by the graph, and in turn of the
+    /// packages they reference, for every package whose call graph the importer provides.
+    /// Functions of an imported package are named by its import path and their name, so
+    /// references to them become edges into their own calls.
+    pub fn link<I: Importer + ?Sized>(&mut self, importer: &mut I) {
+        let mut linked = HashSet::new();
+        loop {
+            let paths: BTreeSet<String> = self
+                .calls
+                .values()
+                .flatten()
+                .map(|call| call.callee.as_str())
+                .chain(self.top_level_uses.iter().map(String::as_str))
+                .filter(|callee| !self.functions.contains_key(*callee))
+                .filter_map(|callee| callee.rsplit_once('.').map(|(path, _)| path))
+                .filter(|path| !linked.contains(*path))
+                .map(str::to_string)
+                .collect();
+            if paths.is_empty() {
+                return;
+            }
+            for path in paths {
+                if let Some(graph) = importer.call_graph(&path) {
+                    self.import(&path, graph);
+                }
+                linked.insert(path);
+            }
+        }
+    }
+
+    // Adds the functions of the package imported at `path`.
+    fn import(&mut self, path: &str, graph: CallGraph) {
+        let qualify = |name: &str| match graph.functions.get(name) {
+            Some(function) if function.package.is_none() => format!("{}.{}", path, name),
+            _ => name.to_string(),
+        };
+        for (name, function) in &graph.functions {
+            let name = qualify(name);
+            self.functions.insert(
+                name.clone(),
+                Function {
+                    name: name.clone(),
+                    location: function.location.clone(),
+                    package: Some(function.package.clone().unwrap_or_else(|| path.to_string())),
+                },
+            );
+            self.calls.entry(name).or_default();
+        }
+        for (caller, calls) in &graph.calls {
+            self.calls[&qualify(caller)].extend(calls.iter().map(|call| Call {
+                callee: qualify(&call.callee),
+                location: call.location.clone(),
+            }));
+        }
+    }
+
+    /// Returns the user-defined functions in definition order, followed by the functions
+    /// of the imported packages added by [`CallGraph::link`].
+    pub fn functions(&self) -> impl Iterator<Item = &Function> {
+        self.functions.values()
+    }
+
+    /// Returns the function with the given name, if it is defined.
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.get(name)
+    }
+
+    /// Returns the references made by the body of a function, in source order.
+    pub fn calls(&self, name: &str) -> &[Call] {
+        self.calls.get(name).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Returns the references made by the body of a function to names that are neither
+    /// defined in the package, imported nor bound locally, in source order. These are the
+    /// names the package takes from the prelude, or names that are not defined at all.
+    pub fn free_references(&self, name: &str) -> &[Call] {
+        self.free.get(name).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Returns the names of the functions whose bodies refer to `name`.
+    pub fn callers(&self, name: &str) -> Vec<&str> {
+        self.calls
+            .iter()
+            .filter(|(_, calls)| calls.iter().any(|call| call.callee == name))
+            .map(|(caller, _)| caller.as_str())
+            .collect()
+    }
+
+    /// Returns the functions of the package that are never referenced, neither at the
+    /// top level nor by another function.
+    ///
+    /// Every top-level identifier of a library package is exported, so this is
+    /// only meaningful for the main package of a query.
+    pub fn unused(&self) -> Vec<&Function> {
+        self.functions
+            .values()
+            .filter(|f| {
+                f.package.is_none()
+                    && !self.top_level_uses.contains(&f.name)
+                    && self.callers(&f.name).iter().all(|caller| *caller == f.name)
+            })
+            .collect()
+    }
+
+    /// Returns the groups of mutually recursive functions of the package.
+    /// A function that calls itself forms a group of its own.
+    pub fn cycles(&self) -> Vec<Vec<&str>> {
+        let mut tarjan = Tarjan {
+            graph: self,
+            index: 0,
+            indices: HashMap::new(),
+            lowlinks: HashMap::new(),
+            stack: Vec::new(),
+            on_stack: HashSet::new(),
+            components: Vec::new(),
+        };
+        for name in self.functions.keys() {
+            if !tarjan.indices.contains_key(name.as_str()) {
+                tarjan.connect(name);
+            }
+        }
+        let mut cycles: Vec<Vec<&str>> = tarjan
+            .components
+            .into_iter()
+            .filter(|component| self.functions[component[0]].package.is_none())
+            .filter(|component| {
+                component.len() > 1
+                    || self
+                        .calls(component[0])
+                        .iter()
+                        .any(|call| call.callee == component[0])
+            })
+            .map(|mut component| {
+                component.sort_by_key(|name| self.functions.get_index_of(*name));
+                component
+            })
+            .collect();
+        cycles.sort_by_key(|cycle| self.functions.get_index_of(cycle[0]));
+        cycles
+    }
+
+    /// Reports every recursive function as an error, since Flux does not allow
+    /// recursion.
+    ///
+    /// Each cycle is reported at the references that close it, that is the
+    /// references from a function to itself or to a function of the same cycle
+    /// that is defined before it.
+    pub fn check(&self) -> Result<(), Errors<Error>> {
+        let mut errors = Errors::new();
+        for cycle in self.cycles() {
+            let members: HashSet<&str> = cycle.iter().copied().collect();
+            for caller in &cycle {
+                for call in self.calls(caller) {
+                    if members.contains(call.callee.as_str())
+                        && self.functions.get_index_of(&call.callee)
+                            <= self.functions.get_index_of(*caller)
+                    {
+                        errors.push(located(
+                            call.location.clone(),
+                            ErrorKind::Recursion {
+                                caller: caller.to_string(),
+                                path: self.path(&call.callee, caller, &members),
+                            },
+                        ));
+                    }
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    // Returns the shortest chain of references from `from` back around to `from`
+    // that ends with a reference made by `to`, staying within `members`.
+    fn path(&self, from: &str, to: &str, members: &HashSet<&str>) -> Vec<String> {
+        let mut previous: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::from(vec![from]);
+        while let Some(name) = queue.pop_front() {
+            if name == to {
+                break;
+            }
+            for call in self.calls(name) {
+                let callee = call.callee.as_str();
+                if members.contains(callee) && callee != from && !previous.contains_key(callee) {
+                    previous.insert(callee, name);
+                    queue.push_back(callee);
+                }
+            }
+        }
+        let mut path = vec![from.to_string()];
+        let mut name = to;
+        while name != from {
+            path.insert(1, name.to_string());
+            name = previous[name];
+        }
+        path.push(from.to_string());
+        path
+    }
+
+    /// Renders the call graph in the DOT format of Graphviz.
+    /// External functions are drawn with dashed outlines.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph callgraph {\n");
+        let mut external = BTreeSet::new();
+        for function in self.functions.values() {
+            if function.package.is_none() {
+                writeln!(dot, "    {:?};", function.name).unwrap();
+            }
+        }
+        for (caller, calls) in &self.calls {
+            let mut seen = HashSet::new();
+            for call in calls {
+                if !seen.insert(&call.callee) {
+                    continue;
+                }
+                if self
+                    .function(&call.callee)
+                    .map_or(true, |f| f.package.is_some())
+                {
+                    external.insert(&call.callee);
+                }
+                writeln!(dot, "    {:?} -> {:?};", caller, call.callee).unwrap();
+            }
+        }
+        for name in external {
+            writeln!(dot, "    {:?} [style=dashed];", name).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn function_definition(stmt: &Statement) -> Option<(&Identifier, &Expression)> {
+    match stmt {
+        Statement::Variable(assgn) => {
+            let mut init = &assgn.init;
+            while let Expression::Paren(paren) = init {
+                init = &paren.expression;
+            }
+            match init {
+                Expression::Function(_) => Some((&assgn.id, init)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// Returns the identifier of a statement that defines a package value.
+fn defined_name(stmt: &Statement) -> Option<&Identifier> {
+    match stmt {
+        Statement::Variable(assgn) => Some(&assgn.id),
+        Statement::Builtin(builtin) => Some(&builtin.id),
+        Statement::Option(opt) => match &opt.assignment {
+            Assignment::Variable(assgn) => Some(&assgn.id),
+            Assignment::Member(_) => None,
+        },
+        _ => None,
+    }
+}
+
+struct ReferenceCollector<'g, 'a> {
+    functions: &'g IndexMap<String, Function>,
+    // Every name defined at the top level of the package, functions or not.
+    defined: &'g HashSet<&'g str>,
+    imports: &'g HashMap<&'a str, &'a str>,
+    // Identifiers that name something rather than refer to it, such as property keys.
+    skip: HashSet<*const Identifier>,
+    // Names bound by the enclosing function expressions, which shadow top-level names.
+    scopes: Vec<HashSet<&'a str>>,
+    references: Vec<Call>,
+    free: Vec<Call>,
+}
+
+impl<'g, 'a> ReferenceCollector<'g, 'a> {
+    fn is_shadowed(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(name))
+    }
+}
+
+impl<'g, 'a> Visitor<'a> for ReferenceCollector<'g, 'a> {
+    fn visit(&mut self, node: Node<'a>) -> bool {
+        match node {
+            Node::TypeExpression(_) | Node::PackageClause(_) | Node::ImportDeclaration(_) => {
+                return false
+            }
+            Node::VariableAssgn(assgn) => {
+                self.skip.insert(&assgn.id);
+            }
+            Node::BuiltinStmt(builtin) => {
+                self.skip.insert(&builtin.id);
+            }
+            Node::TestCaseStmt(test) => {
+                self.skip.insert(&test.id);
+            }
+            Node::Property(prop) => {
+                // A property without a value is shorthand for a reference to its key.
+                if let (PropertyKey::Identifier(id), Some(_)) = (&prop.key, &prop.value) {
+                    self.skip.insert(id);
+                }
+            }
+            Node::FunctionExpr(func) => {
+                let mut scope = HashSet::new();
+                for param in &func.params {
+                    if let PropertyKey::Identifier(id) = &param.key {
+                        self.skip.insert(id);
+                        scope.insert(id.name.as_str());
+                    }
+                }
+                if let FunctionBody::Block(block) = &func.body {
+                    for stmt in &block.body {
+                        if let Statement::Variable(assgn) = stmt {
+                            scope.insert(assgn.id.name.as_str());
+                        }
+                    }
+                }
+                self.scopes.push(scope);
+            }
+            Node::MemberExpr(member) => {
+                if let PropertyKey::Identifier(id) = &member.property {
+                    self.skip.insert(id);
+                }
+                if let Expression::Identifier(object) = &member.object {
+                    if let Some(path) = self.imports.get(object.name.as_str()) {
+                        if !self.is_shadowed(&object.name) {
+                            self.references.push(Call {
+                                callee: format!("{}.{}", path, member.property.key()),
+                                location: member.base.location.clone(),
+                            });
+                            return false;
+                        }
+                    }
+                }
+            }
+            Node::Identifier(id) => {
+                if !self.skip.contains(&(id as *const Identifier)) && !self.is_shadowed(&id.name) {
+                    let call = Call {
+                        callee: id.name.clone(),
+                        location: id.base.location.clone(),
+                    };
+                    if self.functions.contains_key(&id.name) {
+                        self.references.push(call);
+                    } else if !self.defined.contains(id.name.as_str())
+                        && !self.imports.contains_key(id.name.as_str())
+                    {
+                        self.free.push(call);
+                    }
+                }
+            }
+            _ => (),
+        }
+        true
+    }
+
+    fn done(&mut self, node: Node<'a>) {
+        if let Node::FunctionExpr(_) = node {
+            self.scopes.pop();
+        }
+    }
+}
+
+struct Tarjan<'g> {
+    graph: &'g CallGraph,
+    index: usize,
+    indices: HashMap<&'g str, usize>,
+    lowlinks: HashMap<&'g str, usize>,
+    stack: Vec<&'g str>,
+    on_stack: HashSet<&'g str>,
+    components: Vec<Vec<&'g str>>,
+}
+
+impl<'g> Tarjan<'g> {
+    fn connect(&mut self, name: &'g str) {
+        self.indices.insert(name, self.index);
+        self.lowlinks.insert(name, self.index);
+        self.index += 1;
+        self.stack.push(name);
+        self.on_stack.insert(name);
+
+        let graph = self.graph;
+        for call in graph.calls(name) {
+            let callee = call.callee.as_str();
+            if !graph.functions.contains_key(callee) {
+                continue;
+            }
+            if !self.indices.contains_key(callee) {
+                self.connect(callee);
+                let lowlink = self.lowlinks[name].min(self.lowlinks[callee]);
+                self.lowlinks.insert(name, lowlink);
+            } else if self.on_stack.contains(callee) {
+                let lowlink = self.lowlinks[name].min(self.indices[callee]);
+                self.lowlinks.insert(name, lowlink);
+            }
+        }
+
+        if self.lowlinks[name] == self.indices[name] {
+            let mut component = Vec::new();
+            loop {
+                let member = self.stack.pop().expect("name is on the stack");
+                self.on_stack.remove(member);
+                component.push(member);
+                if member == name {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+/// An error found in the call graph.
+pub type Error = Located<ErrorKind>;
+
+/// An error found in the call graph.
+#[derive(Error, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// A function refers to itself, directly or through other functions.
+    #[error("function \"{caller}\" is recursive: {}", .path.join(" -> "))]
+    Recursion {
+        /// The function making the recursive reference.
+        caller: String,
+        /// The chain of references that leads back to the first function.
+        path: Vec<String>,
+    },
+}
+
+impl AsDiagnostic for ErrorKind {
+    fn as_diagnostic(&self, _source: &dyn crate::semantic::Source) -> diagnostic::Diagnostic<()> {
+        diagnostic::Diagnostic::error()
+            .with_message(self.to_string())
+            .with_notes(vec!["Flux does not support recursion".to_string()])
+    }
+}
@@ -0,0 +1,207 @@
+use super::*;
+use crate::parser::parse_string;
+
+fn graph(source: &str) -> CallGraph {
+    let file = parse_string("callgraph".to_string(), source);
+    CallGraph::from_files(&[file])
+}
+
+fn callees(graph: &CallGraph, name: &str) -> Vec<String> {
+    graph
+        .calls(name)
+        .iter()
+        .map(|call| call.callee.clone())
+        .collect()
+}
+
+#[test]
+fn calls_between_functions() {
+    let graph = graph(
+        r#"
+import "strings"
+
+clean = (s) => strings.trimSpace(v: s)
+label = (r) => ({r with host: clean(s: r.host)})
+apply = (tables=<-) => tables |> map(fn: label)
+"#,
+    );
+    assert_eq!(callees(&graph, "clean"), vec!["strings.trimSpace"]);
+    assert_eq!(callees(&graph, "label"), vec!["clean"]);
+    assert_eq!(callees(&graph, "apply"), vec!["label"]);
+    assert_eq!(graph.callers("clean"), vec!["label"]);
+}
+
+#[test]
+fn free_references() {
+    let graph = graph(
+        r#"
+import "strings"
+
+limit = 10
+builtin helper : (v: int) => int
+f = (tables=<-, n) => {
+    m = n + limit
+    return tables |> filter(fn: (r) => strings.hasPrefix(v: r._value, prefix: "a")) |> limit(n: helper(v: m))
+}
+"#,
+    );
+    let free: Vec<_> = graph
+        .free_references("f")
+        .iter()
+        .map(|call| call.callee.as_str())
+        .collect();
+    assert_eq!(free, vec!["filter"]);
+}
+
+#[test]
+fn shadowed_names_are_not_calls() {
+    let graph = graph(
+        r#"
+f = (x) => x + 1
+g = (f) => f
+h = () => {
+    f = 2
+    return {f: f}
+}
+k = (r) => ({r with f: 1}).f
+"#,
+    );
+    assert!(callees(&graph, "g").is_empty());
+    assert!(callees(&graph, "h").is_empty());
+    assert!(callees(&graph, "k").is_empty());
+}
+
+#[test]
+fn unused_functions() {
+    let graph = graph(
+        r#"
+a = () => 1
+b = () => a()
+c = () => c()
+d = () => 2
+d()
+"#,
+    );
+    let unused: Vec<&str> = graph.unused().iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(unused, vec!["b", "c"]);
+}
+
+#[test]
+fn recursion() {
+    let graph = graph(
+        r#"
+a = () => b()
+b = () => c()
+c = () => a()
+d = () => d()
+e = () => a()
+"#,
+    );
+    assert_eq!(graph.cycles(), vec![vec!["a", "b", "c"], vec!["d"]]);
+
+    let errors: Vec<String> = graph
+        .check()
+        .unwrap_err()
+        .iter()
+        .map(|err| err.error.to_string())
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            r#"function "c" is recursive: a -> b -> c -> a"#,
+            r#"function "d" is recursive: d -> d"#,
+        ],
+    );
+}
+
+#[test]
+fn dot() {
+    let graph = graph(
+        r#"
+import "strings"
+
+a = (s) => strings.trimSpace(v: s)
+b = (s) => a(s: a(s: s))
+"#,
+    );
+    expect_test::expect![[r#"
+        digraph callgraph {
+            "a";
+            "b";
+            "a" -> "strings.trimSpace";
+            "b" -> "a";
+            "strings.trimSpace" [style=dashed];
+        }
+    "#]]
+    .assert_eq(&graph.to_dot());
+}
+
+#[test]
+fn linked_packages() {
+    use crate::semantic::import::{CallGraphImporter, Packages};
+
+    let mut importer = CallGraphImporter::<Packages>::default();
+    importer.call_graphs.insert(
+        "example.com/util".to_string(),
+        graph(
+            r#"
+package util
+
+trim = (s) => s
+clean = (s) => trim(s: s)
+"#,
+        ),
+    );
+    let mut lib = graph(
+        r#"
+package lib
+
+import "example.com/util"
+
+label = (s) => util.clean(s: s)
+"#,
+    );
+    lib.link(&mut importer);
+    importer
+        .call_graphs
+        .insert("example.com/lib".to_string(), lib);
+
+    let mut graph = graph(
+        r#"
+import "example.com/lib"
+
+f = (s) => lib.label(s: s)
+f(s: "a")
+"#,
+    );
+    graph.link(&mut importer);
+    assert_eq!(callees(&graph, "f"), vec!["example.com/lib.label"]);
+    assert_eq!(
+        callees(&graph, "example.com/lib.label"),
+        vec!["example.com/util.clean"]
+    );
+    assert_eq!(
+        callees(&graph, "example.com/util.clean"),
+        vec!["example.com/util.trim"]
+    );
+    assert_eq!(
+        graph
+            .function("example.com/util.trim")
+            .and_then(|f| f.package.as_deref()),
+        Some("example.com/util")
+    );
+    assert!(graph.unused().is_empty());
+    assert!(graph.cycles().is_empty());
+    expect_test::expect![[r#"
+        digraph callgraph {
+            "f";
+            "f" -> "example.com/lib.label";
+            "example.com/lib.label" -> "example.com/util.clean";
+            "example.com/util.clean" -> "example.com/util.trim";
+            "example.com/lib.label" [style=dashed];
+            "example.com/util.clean" [style=dashed];
+            "example.com/util.trim" [style=dashed];
+        }
+    "#]]
+    .assert_eq(&graph.to_dot());
+}
@@ -0,0 +1,438 @@
+//! Attachment of comments to the nodes of the AST.
+//!
+//! The parser attaches each comment to the token that follows it. Tokens that start a node, such
+//! as identifiers and literals, keep their comments in the [`BaseNode`](crate::ast::BaseNode) of
+//! the node, while other tokens, such as `(` or `else`, have a comment list of their own in the
+//! node they belong to. [`attachments`] lists the comment lists of a tree and the [`Slot`] each
+//! is attached to.
+//!
+//! [`clear_comments`] removes the comments of a file, e.g. before it is shared.
+//!
+//! A rewrite that replaces an expression drops the comments attached to the tokens of the
+//! expression it replaces. [`replace_expr`] moves the comments that precede the replaced
+//! expression to the expression that replaces it, so that the formatter prints them at the
+//! same place.
+
+#[cfg(test)]
+mod tests;
+
+use std::mem;
+
+use crate::ast::{
+    walk::{self, Node},
+    Assignment, Block, CallExpr, Comment, Expression, File, FunctionBody, MemberExpr, Property,
+    PropertyKey, Statement, StringExprPart, VariableAssgn,
+};
+
+/// The token of a node that a comment list is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Slot {
+    /// The first token of the node, or the operator of an operator expression such as `|>`.
+    Base,
+    /// The `(` of a call, function or parenthesized expression.
+    Lparen,
+    /// The `)` of a call, function or parenthesized expression.
+    Rparen,
+    /// The `[` of an array, dictionary, index or member expression.
+    Lbrack,
+    /// The `]` of an array, dictionary, index or member expression.
+    Rbrack,
+    /// The `{` of a record or block.
+    Lbrace,
+    /// The `}` of a record or block.
+    Rbrace,
+    /// The `:` of a builtin statement.
+    Colon,
+    /// The `=>` of a function.
+    Arrow,
+    /// The `with` of a record.
+    With,
+    /// The `if` of a conditional expression.
+    If,
+    /// The `then` of a conditional expression.
+    Then,
+    /// The `else` of a conditional expression.
+    Else,
+    /// The `:` of a property.
+    Separator,
+    /// The comma after a property.
+    Comma,
+    /// The comma after the item at this index of an array or dictionary.
+    ItemComma(usize),
+    /// The end of a file.
+    Eof,
+}
+
+/// A non-empty comment list and the token it is attached to.
+#[derive(Debug, Clone)]
+pub struct Attachment<'a> {
+    /// The node the token belongs to.
+    pub node: Node<'a>,
+    /// The token of the node the comments precede.
+    pub slot: Slot,
+    /// The comments, in source order.
+    pub comments: Vec<Comment>,
+}
+
+/// Returns the comment lists of `node` and its descendants, in the order they are visited.
+pub fn attachments<'a>(node: Node<'a>) -> Vec<Attachment<'a>> {
+    let mut attachments = Vec::new();
+    walk::walk(
+        &mut |node: Node<'a>| {
+            let mut slots = vec![(Slot::Base, &node.base().comments)];
+            match &node {
+                Node::File(n) => slots.push((Slot::Eof, &n.eof)),
+                Node::Block(n) => {
+                    slots.extend([(Slot::Lbrace, &n.lbrace), (Slot::Rbrace, &n.rbrace)])
+                }
+                Node::BuiltinStmt(n) => slots.push((Slot::Colon, &n.colon)),
+                Node::ParenExpr(n) => {
+                    slots.extend([(Slot::Lparen, &n.lparen), (Slot::Rparen, &n.rparen)])
+                }
+                Node::CallExpr(n) => {
+                    slots.extend([(Slot::Lparen, &n.lparen), (Slot::Rparen, &n.rparen)])
+                }
+                Node::MemberExpr(n) => {
+                    slots.extend([(Slot::Lbrack, &n.lbrack), (Slot::Rbrack, &n.rbrack)])
+                }
+                Node::IndexExpr(n) => {
+                    slots.extend([(Slot::Lbrack, &n.lbrack), (Slot::Rbrack, &n.rbrack)])
+                }
+                Node::FunctionExpr(n) => slots.extend([
+                    (Slot::Lparen, &n.lparen),
+                    (Slot::Rparen, &n.rparen),
+                    (Slot::Arrow, &n.arrow),
+                ]),
+                Node::ArrayExpr(n) => {
+                    slots.push((Slot::Lbrack, &n.lbrack));
+                    slots.extend(
+                        n.elements
+                            .iter()
+                            .enumerate()
+                            .map(|(i, item)| (Slot::ItemComma(i), &item.comma)),
+                    );
+                    slots.push((Slot::Rbrack, &n.rbrack));
+                }
+                Node::DictExpr(n) => {
+                    slots.push((Slot::Lbrack, &n.lbrack));
+                    slots.extend(
+                        n.elements
+                            .iter()
+                            .enumerate()
+                            .map(|(i, item)| (Slot::ItemComma(i), &item.comma)),
+                    );
+                    slots.push((Slot::Rbrack, &n.rbrack));
+                }
+                Node::ObjectExpr(n) => {
+                    slots.push((Slot::Lbrace, &n.lbrace));
+                    if let Some(with) = &n.with {
+                        slots.push((Slot::With, &with.with));
+                    }
+                    slots.push((Slot::Rbrace, &n.rbrace));
+                }
+                Node::ConditionalExpr(n) => slots.extend([
+                    (Slot::If, &n.tk_if),
+                    (Slot::Then, &n.tk_then),
+                    (Slot::Else, &n.tk_else),
+                ]),
+                Node::Property(n) => {
+                    slots.extend([(Slot::Separator, &n.separator), (Slot::Comma, &n.comma)])
+                }
+                _ => (),
+            }
+            for (slot, comments) in slots {
+                if !comments.is_empty() {
+                    attachments.push(Attachment {
+                        node: node.clone(),
+                        slot,
+                        comments: comments.to_vec(),
+                    });
+                }
+            }
+        },
+        node,
+    );
+    attachments
+}
+
+/// Returns the comment list that the formatter prints before everything else of `expr`, which
+/// is attached to the first token of the expression.
+pub fn leading_comments_mut(expr: &mut Expression) -> &mut Vec<Comment> {
+    match expr {
+        Expression::Array(e) => &mut e.lbrack,
+        Expression::Dict(e) => &mut e.lbrack,
+        Expression::Function(e) => &mut e.lparen,
+        Expression::Object(e) => &mut e.lbrace,
+        Expression::Conditional(e) => &mut e.tk_if,
+        Expression::Paren(e) => &mut e.lparen,
+        Expression::Logical(e) => leading_comments_mut(&mut e.left),
+        Expression::Member(e) => leading_comments_mut(&mut e.object),
+        Expression::Index(e) => leading_comments_mut(&mut e.array),
+        Expression::Binary(e) => leading_comments_mut(&mut e.left),
+        Expression::PipeExpr(e) => leading_comments_mut(&mut e.argument),
+        Expression::Call(e) => leading_comments_mut(&mut e.callee),
+        Expression::Identifier(e) => &mut e.base.comments,
+        Expression::Unary(e) => &mut e.base.comments,
+        Expression::StringExpr(e) => &mut e.base.comments,
+        Expression::Integer(e) => &mut e.base.comments,
+        Expression::Float(e) => &mut e.base.comments,
+        Expression::StringLit(e) => &mut e.base.comments,
+        Expression::Duration(e) => &mut e.base.comments,
+        Expression::Uint(e) => &mut e.base.comments,
+        Expression::Boolean(e) => &mut e.base.comments,
+        Expression::DateTime(e) => &mut e.base.comments,
+        Expression::Regexp(e) => &mut e.base.comments,
+        Expression::PipeLit(e) => &mut e.base.comments,
+        Expression::Bad(e) => &mut e.base.comments,
+    }
+}
+
+/// Removes and returns the comments that precede `expr`.
+pub fn take_leading_comments(expr: &mut Expression) -> Vec<Comment> {
+    mem::take(leading_comments_mut(expr))
+}
+
+/// Attaches `comments` before `expr`, ahead of the comments that already precede it.
+pub fn prepend_comments(expr: &mut Expression, mut comments: Vec<Comment>) {
+    let leading = leading_comments_mut(expr);
+    comments.append(leading);
+    *leading = comments;
+}
+
+/// Replaces `target` with `replacement`, moving the comments that precede `target` before
+/// `replacement`. Returns the replaced expression, without those comments.
+pub fn replace_expr(target: &mut Expression, replacement: Expression) -> Expression {
+    let comments = take_leading_comments(target);
+    let replaced = mem::replace(target, replacement);
+    prepend_comments(target, comments);
+    replaced
+}
+
+/// Removes the comments of `file`, except those in the types of builtin statements.
+pub fn clear_comments(file: &mut File) {
+    file.base.comments.clear();
+    file.eof.clear();
+    if let Some(pkg) = &mut file.package {
+        pkg.base.comments.clear();
+        pkg.name.base.comments.clear();
+    }
+    for import in &mut file.imports {
+        import.base.comments.clear();
+        if let Some(alias) = &mut import.alias {
+            alias.base.comments.clear();
+        }
+        import.path.base.comments.clear();
+    }
+    for stmt in &mut file.body {
+        clear_stmt(stmt);
+    }
+}
+
+fn clear_stmt(stmt: &mut Statement) {
+    match stmt {
+        Statement::Expr(s) => {
+            s.base.comments.clear();
+            clear_expr(&mut s.expression);
+        }
+        Statement::Variable(s) => clear_variable(s),
+        Statement::Option(s) => {
+            s.base.comments.clear();
+            match &mut s.assignment {
+                Assignment::Variable(assgn) => clear_variable(assgn),
+                Assignment::Member(assgn) => {
+                    assgn.base.comments.clear();
+                    clear_member(&mut assgn.member);
+                    clear_expr(&mut assgn.init);
+                }
+            }
+        }
+        Statement::Return(s) => {
+            s.base.comments.clear();
+            clear_expr(&mut s.argument);
+        }
+        Statement::Bad(s) => s.base.comments.clear(),
+        Statement::TestCase(s) => {
+            s.base.comments.clear();
+            s.id.base.comments.clear();
+            if let Some(extends) = &mut s.extends {
+                extends.base.comments.clear();
+            }
+            clear_block(&mut s.block);
+        }
+        Statement::Builtin(s) => {
+            s.base.comments.clear();
+            s.colon.clear();
+            s.id.base.comments.clear();
+        }
+    }
+}
+
+fn clear_variable(assgn: &mut VariableAssgn) {
+    assgn.base.comments.clear();
+    assgn.id.base.comments.clear();
+    clear_expr(&mut assgn.init);
+}
+
+fn clear_block(block: &mut Block) {
+    block.base.comments.clear();
+    block.lbrace.clear();
+    block.rbrace.clear();
+    for stmt in &mut block.body {
+        clear_stmt(stmt);
+    }
+}
+
+fn clear_key(key: &mut PropertyKey) {
+    match key {
+        PropertyKey::Identifier(id) => id.base.comments.clear(),
+        PropertyKey::StringLit(lit) => lit.base.comments.clear(),
+    }
+}
+
+fn clear_property(property: &mut Property) {
+    property.base.comments.clear();
+    property.separator.clear();
+    property.comma.clear();
+    clear_key(&mut property.key);
+    if let Some(value) = &mut property.value {
+        clear_expr(value);
+    }
+}
+
+fn clear_member(member: &mut MemberExpr) {
+    member.base.comments.clear();
+    member.lbrack.clear();
+    member.rbrack.clear();
+    clear_expr(&mut member.object);
+    clear_key(&mut member.property);
+}
+
+fn clear_call(call: &mut CallExpr) {
+    call.base.comments.clear();
+    call.lparen.clear();
+    call.rparen.clear();
+    clear_expr(&mut call.callee);
+    for arg in &mut call.arguments {
+        clear_expr(arg);
+    }
+}
+
+fn clear_expr(expr: &mut Expression) {
+    match expr {
+        Expression::Array(e) => {
+            e.base.comments.clear();
+            e.lbrack.clear();
+            e.rbrack.clear();
+            for item in &mut e.elements {
+                item.comma.clear();
+                clear_expr(&mut item.expression);
+            }
+        }
+        Expression::Dict(e) => {
+            e.base.comments.clear();
+            e.lbrack.clear();
+            e.rbrack.clear();
+            for item in &mut e.elements {
+                item.comma.clear();
+                clear_expr(&mut item.key);
+                clear_expr(&mut item.val);
+            }
+        }
+        Expression::Function(e) => {
+            e.base.comments.clear();
+            e.lparen.clear();
+            e.rparen.clear();
+            e.arrow.clear();
+            for param in &mut e.params {
+                clear_property(param);
+            }
+            match &mut e.body {
+                FunctionBody::Block(block) => clear_block(block),
+                FunctionBody::Expr(body) => clear_expr(body),
+            }
+        }
+        Expression::Logical(e) => {
+            e.base.comments.clear();
+            clear_expr(&mut e.left);
+            clear_expr(&mut e.right);
+        }
+        Expression::Object(e) => {
+            e.base.comments.clear();
+            e.lbrace.clear();
+            e.rbrace.clear();
+            if let Some(with) = &mut e.with {
+                with.with.clear();
+                with.source.base.comments.clear();
+            }
+            for property in &mut e.properties {
+                clear_property(property);
+            }
+        }
+        Expression::Member(e) => clear_member(e),
+        Expression::Index(e) => {
+            e.base.comments.clear();
+            e.lbrack.clear();
+            e.rbrack.clear();
+            clear_expr(&mut e.array);
+            clear_expr(&mut e.index);
+        }
+        Expression::Binary(e) => {
+            e.base.comments.clear();
+            clear_expr(&mut e.left);
+            clear_expr(&mut e.right);
+        }
+        Expression::Unary(e) => {
+            e.base.comments.clear();
+            clear_expr(&mut e.argument);
+        }
+        Expression::PipeExpr(e) => {
+            e.base.comments.clear();
+            clear_expr(&mut e.argument);
+            clear_call(&mut e.call);
+        }
+        Expression::Call(e) => clear_call(e),
+        Expression::Conditional(e) => {
+            e.base.comments.clear();
+            e.tk_if.clear();
+            e.tk_then.clear();
+            e.tk_else.clear();
+            clear_expr(&mut e.test);
+            clear_expr(&mut e.consequent);
+            clear_expr(&mut e.alternate);
+        }
+        Expression::StringExpr(e) => {
+            e.base.comments.clear();
+            for part in &mut e.parts {
+                match part {
+                    StringExprPart::Text(text) => text.base.comments.clear(),
+                    StringExprPart::Interpolated(part) => {
+                        part.base.comments.clear();
+                        clear_expr(&mut part.expression);
+                    }
+                }
+            }
+        }
+        Expression::Paren(e) => {
+            e.base.comments.clear();
+            e.lparen.clear();
+            e.rparen.clear();
+            clear_expr(&mut e.expression);
+        }
+        Expression::Bad(e) => {
+            e.base.comments.clear();
+            if let Some(expr) = &mut e.expression {
+                clear_expr(expr);
+            }
+        }
+        Expression::Identifier(e) => e.base.comments.clear(),
+        Expression::Integer(e) => e.base.comments.clear(),
+        Expression::Float(e) => e.base.comments.clear(),
+        Expression::StringLit(e) => e.base.comments.clear(),
+        Expression::Duration(e) => e.base.comments.clear(),
+        Expression::Uint(e) => e.base.comments.clear(),
+        Expression::Boolean(e) => e.base.comments.clear(),
+        Expression::DateTime(e) => e.base.comments.clear(),
+        Expression::Regexp(e) => e.base.comments.clear(),
+        Expression::PipeLit(e) => e.base.comments.clear(),
+    }
+}
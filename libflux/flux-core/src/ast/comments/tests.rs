@@ -0,0 +1,154 @@
+use expect_test::expect;
+
+use super::*;
+use crate::{
+    ast::{BaseNode, Identifier, Statement},
+    formatter, parser,
+};
+
+fn describe(attachments: &[Attachment]) -> String {
+    attachments
+        .iter()
+        .map(|a| {
+            let texts: Vec<_> = a.comments.iter().map(|c| c.text.trim_end()).collect();
+            format!("{} {:?}: {}\n", a.node, a.slot, texts.join(" "))
+        })
+        .collect()
+}
+
+fn identifier(name: &str) -> Expression {
+    Expression::Identifier(Identifier {
+        base: BaseNode::default(),
+        name: name.to_string(),
+    })
+}
+
+fn expression(stmt: &mut Statement) -> &mut Expression {
+    match stmt {
+        Statement::Expr(stmt) => &mut stmt.expression,
+        Statement::Variable(assgn) => &mut assgn.init,
+        _ => panic!("expected an expression or variable statement"),
+    }
+}
+
+#[test]
+fn list_attachments() {
+    let file = parser::parse_string(
+        "".to_string(),
+        r#"// one
+x = (1 + 2
+    // close
+    )
+from()
+    // pipe
+    |> to()
+// eof"#,
+    );
+    expect![[r#"
+        File Eof: // eof
+        Identifier Base: // one
+        ParenExpr Rparen: // close
+        PipeExpr Base: // pipe
+    "#]]
+    .assert_eq(&describe(&attachments(Node::File(&file))));
+}
+
+#[test]
+fn replace_keeps_leading_comments() {
+    let mut file = parser::parse_string(
+        "".to_string(),
+        r#"// the answer
+f(a: 1) + 1"#,
+    );
+    let replaced = match expression(&mut file.body[0]) {
+        Expression::Binary(binary) => replace_expr(&mut binary.left, identifier("g")),
+        _ => panic!("expected a binary expression"),
+    };
+
+    assert_eq!(
+        formatter::convert_to_string(&file).unwrap(),
+        "// the answer\ng + 1\n"
+    );
+    assert!(attachments(Node::from_expr(&replaced)).is_empty());
+}
+
+#[test]
+fn prepend_before_existing_comments() {
+    let mut expr = identifier("x");
+    leading_comments_mut(&mut expr).push(Comment {
+        text: "// second\n".to_string(),
+    });
+    prepend_comments(
+        &mut expr,
+        vec![Comment {
+            text: "// first\n".to_string(),
+        }],
+    );
+    let texts: Vec<_> = take_leading_comments(&mut expr)
+        .into_iter()
+        .map(|c| c.text)
+        .collect();
+    assert_eq!(texts, ["// first\n", "// second\n"]);
+    assert!(leading_comments_mut(&mut expr).is_empty());
+}
+
+// The formatter prints each comment before the token it is attached to, so the comments of a
+// rewritten tree are attached to the same tokens once it is formatted and parsed again.
+#[test]
+fn rewritten_attachments_survive_formatting() {
+    let mut file = parser::parse_string(
+        "".to_string(),
+        r#"// the source
+from(bucket: "b")
+    // last hour
+    |> range(start: -1h)"#,
+    );
+    match expression(&mut file.body[0]) {
+        Expression::PipeExpr(pipe) => {
+            replace_expr(&mut pipe.argument, identifier("data"));
+        }
+        _ => panic!("expected a pipe expression"),
+    }
+    let formatted = formatter::convert_to_string(&file).unwrap();
+    let reparsed = parser::parse_string("".to_string(), &formatted);
+
+    expect![[r#"
+        PipeExpr Base: // last hour
+        Identifier Base: // the source
+    "#]]
+    .assert_eq(&describe(&attachments(Node::File(&file))));
+    assert_eq!(
+        describe(&attachments(Node::File(&reparsed))),
+        describe(&attachments(Node::File(&file)))
+    );
+}
+
+#[test]
+fn clear_all_comments() {
+    let mut file = parser::parse_string(
+        "".to_string(),
+        r#"// package
+package main
+
+// import
+import "strings"
+
+// f
+f = (
+    // a
+    a,
+) => {
+    // x
+    x = [1, // one
+        2]
+    return {x: x, // with
+    y: 1}
+}
+option now = () => 2021-01-01T00:00:00Z
+g = if true then 1 // else
+else 2
+// eof"#,
+    );
+    clear_comments(&mut file);
+    expect![[""]].assert_eq(&describe(&attachments(Node::File(&file))));
+}
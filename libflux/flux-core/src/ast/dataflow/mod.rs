@@ -0,0 +1,183 @@
+//! Dataflow between the stages of the pipelines in a query.
+//!
+//! Every call that appears as the head or a stage of a pipeline becomes a node
+//! of the graph. Edges connect each stage to the next one, a variable to the
+//! stages it is piped or passed into, and the pipelines passed as arguments,
+//! such as the tables of a `join` or `union`, to the call they are passed to.
+//! Function bodies are not part of the dataflow and are not inspected.
+
+#[cfg(test)]
+mod tests;
+
+use std::{collections::HashMap, fmt::Write};
+
+use crate::ast::{CallExpr, Expression, File, SourceLocation, Statement};
+
+// Labels longer than this are shortened so the rendered graph stays readable.
+const MAX_LABEL_LEN: usize = 60;
+
+/// A call in the dataflow graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stage {
+    /// The source of the call, with whitespace collapsed.
+    pub label: String,
+    /// The name of the called function, e.g. `range` or `join.inner`.
+    pub function: String,
+    /// Location of the call.
+    pub location: SourceLocation,
+}
+
+/// The dataflow graph of a query.
+#[derive(Debug, Default, Clone)]
+pub struct Dataflow {
+    stages: Vec<Stage>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl Dataflow {
+    /// Builds the dataflow graph of a file.
+    pub fn from_file(file: &File) -> Self {
+        let mut builder = Builder {
+            graph: Dataflow::default(),
+            vars: HashMap::new(),
+        };
+        for stmt in &file.body {
+            builder.statement(stmt);
+        }
+        builder.graph
+    }
+
+    /// Returns the stages in the order they appear in the source.
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    /// Returns the edges as pairs of indices into [`Dataflow::stages`],
+    /// pointing from the producer of the data to its consumer.
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    /// Renders the graph in the DOT format of Graphviz.
+    /// Calls to `yield` are drawn as double octagons.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph pipeline {\n    node [shape=box];\n");
+        for (i, stage) in self.stages.iter().enumerate() {
+            let shape = if stage.function == "yield" {
+                " shape=doubleoctagon"
+            } else {
+                ""
+            };
+            writeln!(dot, "    n{} [label={:?}{}];", i, stage.label, shape).unwrap();
+        }
+        for (from, to) in &self.edges {
+            writeln!(dot, "    n{} -> n{};", from, to).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+struct Builder {
+    graph: Dataflow,
+    // The stage that produces the value of each variable
+    vars: HashMap<String, usize>,
+}
+
+impl Builder {
+    fn statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Variable(assgn) => {
+                if let Some(output) = self.expr(&assgn.init) {
+                    self.vars.insert(assgn.id.name.clone(), output);
+                }
+            }
+            Statement::Expr(stmt) => {
+                self.expr(&stmt.expression);
+            }
+            _ => (),
+        }
+    }
+
+    // Adds the calls of an expression to the graph and returns the stage that
+    // produces its value, if any.
+    fn expr(&mut self, expr: &Expression) -> Option<usize> {
+        match expr {
+            Expression::PipeExpr(pipe) => {
+                let input = self.expr(&pipe.argument);
+                let stage = self.call(&pipe.call);
+                if let Some(input) = input {
+                    self.graph.edges.push((input, stage));
+                }
+                Some(stage)
+            }
+            Expression::Call(call) => Some(self.call(call)),
+            Expression::Identifier(id) => self.vars.get(&id.name).copied(),
+            Expression::Paren(paren) => self.expr(&paren.expression),
+            _ => None,
+        }
+    }
+
+    fn call(&mut self, call: &CallExpr) -> usize {
+        let mut inputs = Vec::new();
+        for arg in &call.arguments {
+            self.inputs(arg, &mut inputs);
+        }
+
+        let stage = self.graph.stages.len();
+        let source = call.base.location.source.as_deref().unwrap_or_default();
+        self.graph.stages.push(Stage {
+            label: shorten(&source.split_whitespace().collect::<Vec<_>>().join(" ")),
+            function: callee_name(&call.callee),
+            location: call.base.location.clone(),
+        });
+        for input in inputs {
+            self.graph.edges.push((input, stage));
+        }
+        stage
+    }
+
+    // Finds the values passed into a call through its arguments, descending
+    // into records and arrays such as the `tables` of `join` and `union`.
+    fn inputs(&mut self, expr: &Expression, inputs: &mut Vec<usize>) {
+        match expr {
+            Expression::Object(obj) => {
+                for prop in &obj.properties {
+                    match &prop.value {
+                        Some(value) => self.inputs(value, inputs),
+                        // `{a}` is shorthand for `{a: a}`
+                        None => inputs.extend(self.vars.get(prop.key.key()).copied()),
+                    }
+                }
+            }
+            Expression::Array(arr) => {
+                for item in &arr.elements {
+                    self.inputs(&item.expression, inputs);
+                }
+            }
+            Expression::Paren(paren) => self.inputs(&paren.expression, inputs),
+            _ => inputs.extend(self.expr(expr)),
+        }
+    }
+}
+
+fn callee_name(callee: &Expression) -> String {
+    match callee {
+        Expression::Identifier(id) => id.name.clone(),
+        Expression::Member(member) => {
+            format!("{}.{}", callee_name(&member.object), member.property.key())
+        }
+        Expression::Paren(paren) => callee_name(&paren.expression),
+        _ => String::new(),
+    }
+}
+
+fn shorten(label: &str) -> String {
+    if label.chars().count() <= MAX_LABEL_LEN {
+        label.to_string()
+    } else {
+        let mut short: String = label.chars().take(MAX_LABEL_LEN - 3).collect();
+        short.push_str("...");
+        short
+    }
+}
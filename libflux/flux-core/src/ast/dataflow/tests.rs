@@ -0,0 +1,74 @@
+use super::*;
+use crate::parser::parse_string;
+
+fn dataflow(source: &str) -> Dataflow {
+    Dataflow::from_file(&parse_string("dataflow".to_string(), source))
+}
+
+fn edges(graph: &Dataflow) -> Vec<(&str, &str)> {
+    graph
+        .edges()
+        .iter()
+        .map(|(from, to)| {
+            (
+                graph.stages()[*from].function.as_str(),
+                graph.stages()[*to].function.as_str(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn pipeline_stages() {
+    let graph = dataflow(r#"from(bucket: "b") |> range(start: -1h) |> yield(name: "out")"#);
+    assert_eq!(edges(&graph), vec![("from", "range"), ("range", "yield")]);
+}
+
+#[test]
+fn variables_and_joins() {
+    let graph = dataflow(
+        r#"
+import "join"
+
+left = from(bucket: "a") |> range(start: -1h)
+right = from(bucket: "b") |> range(start: -1h)
+join.inner(left: left, right: right |> filter(fn: (r) => true), on: (l, r) => l.id == r.id, as: (l, r) => l)
+    |> yield()
+union(tables: [left, right]) |> yield(name: "all")
+"#,
+    );
+    assert_eq!(
+        edges(&graph),
+        vec![
+            ("from", "range"),
+            ("from", "range"),
+            ("range", "filter"),
+            ("range", "join.inner"),
+            ("filter", "join.inner"),
+            ("join.inner", "yield"),
+            ("range", "union"),
+            ("range", "union"),
+            ("union", "yield"),
+        ],
+    );
+}
+
+#[test]
+fn dot() {
+    let graph = dataflow(
+        r#"data = from(bucket: "b")
+    |> range(start: -1h)
+data |> yield()"#,
+    );
+    expect_test::expect![[r#"
+        digraph pipeline {
+            node [shape=box];
+            n0 [label="from(bucket: \"b\")"];
+            n1 [label="range(start: -1h)"];
+            n2 [label="yield()" shape=doubleoctagon];
+            n0 -> n1;
+            n1 -> n2;
+        }
+    "#]]
+    .assert_eq(&graph.to_dot());
+}
@@ -0,0 +1,757 @@
+//! Inlining of small user-defined functions.
+//!
+//! A function is a candidate for inlining when it is defined by a top-level
+//! variable assignment, its body is a single expression, it does not refer to
+//! itself and its body is no larger than [`Options::max_size`] nodes. Calls to
+//! a candidate, including pipe calls, are replaced by its body with the
+//! arguments substituted for the parameters.
+//!
+//! A variable of the block of a function nested in the body that would capture
+//! a name an argument refers to is renamed to a fresh name. Parameters in Flux
+//! are named, so the parameters of nested functions cannot be renamed without
+//! changing the meaning of the code that calls them. Instead, a call is left
+//! untouched whenever inlining it would let a parameter of a nested function
+//! capture an argument, or a local variable at the call site capture a name the
+//! body refers to.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{
+    beautify::fresh_variable,
+    comments::{prepend_comments, replace_expr, take_leading_comments},
+    walk::{self, Node, Visitor},
+    Assignment, BaseNode, CallExpr, Expression, File, FunctionBody, FunctionExpr, Identifier,
+    ParenExpr, PropertyKey, Statement, StringExprPart,
+};
+
+/// Options that control which functions are inlined.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The largest function body, counted in AST nodes, that is inlined.
+    pub max_size: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options { max_size: 32 }
+    }
+}
+
+/// Inlines calls to small user-defined functions in `file`.
+/// Returns the number of call sites that were inlined.
+///
+/// Definitions are left in place, since they may be exported.
+pub fn inline_functions(file: &mut File, options: &Options) -> usize {
+    let mut inliner = Inliner {
+        candidates: HashMap::new(),
+        scopes: Vec::new(),
+        count: 0,
+    };
+    for stmt in &mut file.body {
+        inliner.rewrite_stmt(stmt);
+        // Flux requires definitions to come before their uses, so the body of a
+        // candidate has already had any earlier candidates inlined into it.
+        if let Some((name, candidate)) = candidate(stmt, options) {
+            inliner.candidates.insert(name, candidate);
+        }
+    }
+    inliner.count
+}
+
+/// Returns the expression a call, or pipe call, in the tree rooted at `root` is replaced with
+/// when it is inlined, or `None` if it does not call a candidate defined in `root` or cannot be
+/// inlined.
+///
+/// Calls to other candidates in the arguments, and in the body of the called function, are
+/// inlined too.
+pub fn inline_call(root: Node, call: Node, options: &Options) -> Option<Expression> {
+    let mut expr = match call {
+        Node::CallExpr(call) => Expression::Call(Box::new(call.clone())),
+        Node::PipeExpr(pipe) => Expression::PipeExpr(Box::new(pipe.clone())),
+        _ => return None,
+    };
+    let files = match root {
+        Node::Package(pkg) => pkg.files.iter().collect(),
+        Node::File(file) => vec![file],
+        _ => Vec::new(),
+    };
+    let mut inliner = Inliner {
+        candidates: HashMap::new(),
+        scopes: Vec::new(),
+        count: 0,
+    };
+    for file in files {
+        for stmt in &file.body {
+            let mut stmt = stmt.clone();
+            inliner.rewrite_stmt(&mut stmt);
+            if let Some((name, candidate)) = candidate(&stmt, options) {
+                inliner.candidates.insert(name, candidate);
+            }
+        }
+    }
+    inliner.count = 0;
+    inliner.scopes = enclosing_scopes(root, call);
+    inliner.rewrite_expr(&mut expr);
+    if inliner.count > 0 {
+        Some(expr)
+    } else {
+        None
+    }
+}
+
+// Returns the names bound by each function enclosing `target`, outermost first.
+fn enclosing_scopes(root: Node, target: Node) -> Vec<HashSet<String>> {
+    struct Scopes<'a> {
+        target: Node<'a>,
+        stack: Vec<HashSet<String>>,
+        found: Option<Vec<HashSet<String>>>,
+    }
+    impl<'a> Visitor<'a> for Scopes<'a> {
+        fn visit(&mut self, node: Node<'a>) -> bool {
+            if self.found.is_some() {
+                return false;
+            }
+            let is_target = match (&node, &self.target) {
+                (Node::CallExpr(a), Node::CallExpr(b)) => std::ptr::eq(*a, *b),
+                (Node::PipeExpr(a), Node::PipeExpr(b)) => std::ptr::eq(*a, *b),
+                _ => false,
+            };
+            if is_target {
+                self.found = Some(self.stack.clone());
+                return false;
+            }
+            if let Node::FunctionExpr(func) = node {
+                self.stack.push(bound_names(func));
+            }
+            true
+        }
+
+        fn done(&mut self, node: Node<'a>) {
+            if let Node::FunctionExpr(_) = node {
+                self.stack.pop();
+            }
+        }
+    }
+    let mut scopes = Scopes {
+        target,
+        stack: Vec::new(),
+        found: None,
+    };
+    walk::walk(&mut scopes, root);
+    scopes.found.unwrap_or_default()
+}
+
+struct Param {
+    name: String,
+    default: Option<Expression>,
+    pipe: bool,
+}
+
+struct Candidate {
+    params: Vec<Param>,
+    body: Expression,
+    // How many times the body refers to each of its free variables
+    uses: HashMap<String, usize>,
+    // Parameters of functions nested in the body
+    params_bound: HashSet<String>,
+    // Variables of the blocks of functions nested in the body
+    locals_bound: HashSet<String>,
+    // Every name that appears in the body
+    names: HashSet<String>,
+    // Parameters used as the source of a `with` record
+    with_sources: HashSet<String>,
+}
+
+fn candidate(stmt: &Statement, options: &Options) -> Option<(String, Candidate)> {
+    let assgn = match stmt {
+        Statement::Variable(assgn) => assgn,
+        _ => return None,
+    };
+    let func = match strip_parens(&assgn.init) {
+        Expression::Function(func) => func,
+        _ => return None,
+    };
+    let body = match &func.body {
+        FunctionBody::Expr(body) => body,
+        FunctionBody::Block(_) => return None,
+    };
+
+    let mut size = 0;
+    walk::walk(
+        &mut |_: Node| {
+            size += 1;
+        },
+        Node::from_expr(body),
+    );
+    if size > options.max_size {
+        return None;
+    }
+
+    let mut params = Vec::new();
+    for param in &func.params {
+        let name = match &param.key {
+            PropertyKey::Identifier(id) => id.name.clone(),
+            PropertyKey::StringLit(_) => return None,
+        };
+        let (default, pipe) = match &param.value {
+            Some(Expression::PipeLit(_)) => (None, true),
+            Some(value) => (Some(value.clone()), false),
+            None => (None, false),
+        };
+        params.push(Param {
+            name,
+            default,
+            pipe,
+        });
+    }
+
+    let uses = free_variables(body);
+    if uses.contains_key(&assgn.id.name) {
+        return None;
+    }
+
+    let mut params_bound = HashSet::new();
+    let mut locals_bound = HashSet::new();
+    let mut names = HashSet::new();
+    let mut with_sources = HashSet::new();
+    walk::walk(
+        &mut |node: Node| match node {
+            Node::FunctionExpr(func) => {
+                params_bound.extend(param_names(func));
+                locals_bound.extend(local_names(func));
+            }
+            Node::Identifier(id) => {
+                names.insert(id.name.clone());
+            }
+            Node::ObjectExpr(obj) => {
+                if let Some(with) = &obj.with {
+                    with_sources.insert(with.source.name.clone());
+                }
+            }
+            _ => (),
+        },
+        Node::from_expr(body),
+    );
+
+    Some((
+        assgn.id.name.clone(),
+        Candidate {
+            params,
+            body: body.clone(),
+            uses,
+            params_bound,
+            locals_bound,
+            names,
+            with_sources,
+        },
+    ))
+}
+
+struct Inliner {
+    candidates: HashMap<String, Candidate>,
+    // Names bound by the functions enclosing the expression being rewritten
+    scopes: Vec<HashSet<String>>,
+    count: usize,
+}
+
+impl Inliner {
+    fn is_shadowed(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(name))
+    }
+
+    fn rewrite_stmt(&mut self, stmt: &mut Statement) {
+        match stmt {
+            Statement::Expr(stmt) => self.rewrite_expr(&mut stmt.expression),
+            Statement::Variable(assgn) => self.rewrite_expr(&mut assgn.init),
+            Statement::Option(opt) => match &mut opt.assignment {
+                Assignment::Variable(assgn) => self.rewrite_expr(&mut assgn.init),
+                Assignment::Member(assgn) => self.rewrite_expr(&mut assgn.init),
+            },
+            Statement::Return(stmt) => self.rewrite_expr(&mut stmt.argument),
+            Statement::TestCase(test) => {
+                for stmt in &mut test.block.body {
+                    self.rewrite_stmt(stmt);
+                }
+            }
+            Statement::Builtin(_) | Statement::Bad(_) => (),
+        }
+    }
+
+    // Rewrites the children of an expression before the expression itself, so
+    // that calls in arguments are inlined before the call they are passed to.
+    fn rewrite_expr(&mut self, expr: &mut Expression) {
+        match expr {
+            Expression::Call(call) => {
+                self.rewrite_call(call);
+                if let Some(inlined) = self.inline(call, None) {
+                    replace_expr(expr, inlined);
+                    self.count += 1;
+                }
+            }
+            Expression::PipeExpr(pipe) => {
+                self.rewrite_expr(&mut pipe.argument);
+                self.rewrite_call(&mut pipe.call);
+                // The comments before the pipeline are taken off its argument, which may be
+                // substituted anywhere in the inlined body, and put before the body instead.
+                let comments = take_leading_comments(&mut pipe.argument);
+                match self.inline(&pipe.call, Some(&pipe.argument)) {
+                    Some(inlined) => {
+                        *expr = inlined;
+                        prepend_comments(expr, comments);
+                        self.count += 1;
+                    }
+                    None => prepend_comments(&mut pipe.argument, comments),
+                }
+            }
+            Expression::Function(func) => {
+                self.scopes.push(bound_names(func));
+                for_each_child(expr, &mut |child| self.rewrite_expr(child));
+                self.scopes.pop();
+            }
+            _ => for_each_child(expr, &mut |child| self.rewrite_expr(child)),
+        }
+    }
+
+    fn rewrite_call(&mut self, call: &mut CallExpr) {
+        self.rewrite_expr(&mut call.callee);
+        for arg in &mut call.arguments {
+            self.rewrite_expr(arg);
+        }
+    }
+
+    // Returns the body of the called function with the arguments substituted,
+    // or `None` if the call cannot be inlined.
+    fn inline(&self, call: &CallExpr, pipe: Option<&Expression>) -> Option<Expression> {
+        let name = match strip_parens(&call.callee) {
+            Expression::Identifier(id) => &id.name,
+            _ => return None,
+        };
+        let candidate = self.candidates.get(name)?;
+        if self.is_shadowed(name) {
+            return None;
+        }
+
+        let mut explicit: HashMap<String, Expression> = HashMap::new();
+        match &call.arguments[..] {
+            [] => (),
+            [Expression::Object(obj)] if obj.with.is_none() => {
+                for prop in &obj.properties {
+                    let key = match &prop.key {
+                        PropertyKey::Identifier(id) => id.clone(),
+                        PropertyKey::StringLit(_) => return None,
+                    };
+                    let value = match &prop.value {
+                        Some(value) => value.clone(),
+                        None => Expression::Identifier(key.clone()),
+                    };
+                    explicit.insert(key.name, value);
+                }
+            }
+            _ => return None,
+        }
+
+        let mut args = HashMap::new();
+        let mut captured = HashSet::new();
+        for param in &candidate.params {
+            let (arg, is_default) = match explicit.remove(&param.name) {
+                Some(arg) => (arg, false),
+                None if param.pipe => (pipe?.clone(), false),
+                None => (param.default.clone()?, true),
+            };
+            let arg_uses = free_variables(&arg);
+            if arg_uses
+                .keys()
+                .any(|var| candidate.params_bound.contains(var))
+            {
+                return None;
+            }
+            captured.extend(
+                arg_uses
+                    .keys()
+                    .filter(|var| candidate.locals_bound.contains(*var))
+                    .cloned(),
+            );
+            // Defaults are evaluated where the function is defined, so the names
+            // they refer to must mean the same thing at the call site.
+            if is_default && arg_uses.keys().any(|var| self.is_shadowed(var)) {
+                return None;
+            }
+            let trivial = matches!(
+                strip_parens(&arg),
+                Expression::Identifier(_)
+                    | Expression::Integer(_)
+                    | Expression::Float(_)
+                    | Expression::StringLit(_)
+                    | Expression::Duration(_)
+                    | Expression::Uint(_)
+                    | Expression::Boolean(_)
+                    | Expression::DateTime(_)
+                    | Expression::Regexp(_)
+            );
+            let uses = candidate.uses.get(&param.name).copied().unwrap_or(0);
+            // Only duplicate arguments that are cheap to evaluate
+            if uses > 1 && !trivial {
+                return None;
+            }
+            if candidate.with_sources.contains(&param.name)
+                && !matches!(strip_parens(&arg), Expression::Identifier(_))
+            {
+                return None;
+            }
+            args.insert(param.name.clone(), arg);
+        }
+        // An argument that does not match any parameter is a type error, so leave
+        // the call for the type checker to report.
+        if !explicit.is_empty() {
+            return None;
+        }
+        // The body must not refer to a top-level name that is shadowed at the call site.
+        if candidate
+            .uses
+            .keys()
+            .any(|var| !args.contains_key(var) && self.is_shadowed(var))
+        {
+            return None;
+        }
+
+        let mut body = candidate.body.clone();
+        if !captured.is_empty() {
+            // The fresh names must not capture, or be captured by, any name of the body or of
+            // the arguments.
+            let mut taken = candidate.names.clone();
+            for arg in args.values() {
+                taken.extend(free_variables(arg).into_keys());
+            }
+            let mut captured: Vec<String> = captured.into_iter().collect();
+            captured.sort();
+            for name in captured {
+                let fresh = fresh_variable(&taken);
+                rename_locals(&mut body, &name, &fresh);
+                taken.insert(fresh);
+            }
+        }
+        substitute(&mut body, &args);
+        Some(parenthesize(body))
+    }
+}
+
+// Renames the variables named `old` of the blocks of the functions in `expr`, and the references
+// to them, to `new`.
+fn rename_locals(expr: &mut Expression, old: &str, new: &str) {
+    if let Expression::Function(func) = expr {
+        if local_names(func).contains(old) {
+            let args = HashMap::from([(
+                old.to_string(),
+                Expression::Identifier(Identifier {
+                    base: BaseNode::default(),
+                    name: new.to_string(),
+                }),
+            )]);
+            if let FunctionBody::Block(block) = &mut func.body {
+                for stmt in &mut block.body {
+                    match stmt {
+                        Statement::Variable(assgn) => {
+                            if assgn.id.name == old {
+                                assgn.id.name = new.to_string();
+                            }
+                            substitute(&mut assgn.init, &args);
+                        }
+                        Statement::Expr(stmt) => substitute(&mut stmt.expression, &args),
+                        Statement::Return(stmt) => substitute(&mut stmt.argument, &args),
+                        _ => (),
+                    }
+                }
+            }
+        }
+    }
+    // Nested functions may declare variables of the same name, which capture the argument too.
+    for_each_child(expr, &mut |child| rename_locals(child, old, new));
+}
+
+// Wraps an operator expression in parentheses so that it keeps its meaning wherever it is
+// substituted. The formatter drops the parentheses where the precedence does not need them.
+fn parenthesize(expr: Expression) -> Expression {
+    match expr {
+        Expression::Binary(_)
+        | Expression::Logical(_)
+        | Expression::Unary(_)
+        | Expression::Conditional(_) => Expression::Paren(Box::new(ParenExpr {
+            base: expr.base().clone(),
+            lparen: Vec::new(),
+            expression: expr,
+            rparen: Vec::new(),
+        })),
+        expr => expr,
+    }
+}
+
+// Replaces the free occurrences of the names in `args` in `expr` with their values. Names bound
+// inside `expr` are not renamed, so the values must not be captured by them.
+pub(crate) fn substitute(expr: &mut Expression, args: &HashMap<String, Expression>) {
+    Substituter {
+        args,
+        scopes: Vec::new(),
+    }
+    .substitute(expr);
+}
+
+struct Substituter<'a> {
+    args: &'a HashMap<String, Expression>,
+    scopes: Vec<HashSet<String>>,
+}
+
+impl Substituter<'_> {
+    fn arg(&self, name: &str) -> Option<&Expression> {
+        if self.scopes.iter().any(|scope| scope.contains(name)) {
+            None
+        } else {
+            self.args.get(name)
+        }
+    }
+
+    fn substitute(&mut self, expr: &mut Expression) {
+        match expr {
+            Expression::Identifier(id) => {
+                if let Some(arg) = self.arg(&id.name) {
+                    *expr = parenthesize(arg.clone());
+                }
+            }
+            Expression::Object(obj) => {
+                if let Some(with) = &mut obj.with {
+                    if let Some(Expression::Identifier(arg)) = self.arg(&with.source.name) {
+                        with.source.name = arg.name.clone();
+                    }
+                }
+                for prop in &mut obj.properties {
+                    // Expand `{x}` to `{x: x}` so the value can be substituted.
+                    if let (PropertyKey::Identifier(key), None) = (&prop.key, &prop.value) {
+                        if self.arg(&key.name).is_some() {
+                            prop.value = Some(Expression::Identifier(key.clone()));
+                        }
+                    }
+                }
+                for_each_child(expr, &mut |child| self.substitute(child));
+            }
+            Expression::Function(func) => {
+                self.scopes.push(bound_names(func));
+                for_each_child(expr, &mut |child| self.substitute(child));
+                self.scopes.pop();
+            }
+            _ => for_each_child(expr, &mut |child| self.substitute(child)),
+        }
+    }
+}
+
+fn strip_parens(mut expr: &Expression) -> &Expression {
+    while let Expression::Paren(p) = expr {
+        expr = &p.expression;
+    }
+    expr
+}
+
+// Returns the names bound by a function: its parameters and the variables of its block.
+pub(crate) fn bound_names(func: &FunctionExpr) -> HashSet<String> {
+    let mut names = param_names(func);
+    names.extend(local_names(func));
+    names
+}
+
+fn param_names(func: &FunctionExpr) -> HashSet<String> {
+    func.params
+        .iter()
+        .filter_map(|param| match &param.key {
+            PropertyKey::Identifier(id) => Some(id.name.clone()),
+            PropertyKey::StringLit(_) => None,
+        })
+        .collect()
+}
+
+fn local_names(func: &FunctionExpr) -> HashSet<String> {
+    match &func.body {
+        FunctionBody::Block(block) => block
+            .body
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Statement::Variable(assgn) => Some(assgn.id.name.clone()),
+                _ => None,
+            })
+            .collect(),
+        FunctionBody::Expr(_) => HashSet::new(),
+    }
+}
+
+// Calls `f` with every expression directly contained in `expr`.
+pub(crate) fn for_each_child(expr: &mut Expression, f: &mut impl FnMut(&mut Expression)) {
+    match expr {
+        Expression::Array(arr) => {
+            for item in &mut arr.elements {
+                f(&mut item.expression);
+            }
+        }
+        Expression::Dict(dict) => {
+            for item in &mut dict.elements {
+                f(&mut item.key);
+                f(&mut item.val);
+            }
+        }
+        Expression::Function(func) => {
+            for param in &mut func.params {
+                if let Some(value) = &mut param.value {
+                    f(value);
+                }
+            }
+            match &mut func.body {
+                FunctionBody::Expr(body) => f(body),
+                FunctionBody::Block(block) => {
+                    for stmt in &mut block.body {
+                        match stmt {
+                            Statement::Expr(stmt) => f(&mut stmt.expression),
+                            Statement::Variable(assgn) => f(&mut assgn.init),
+                            Statement::Return(stmt) => f(&mut stmt.argument),
+                            _ => (),
+                        }
+                    }
+                }
+            }
+        }
+        Expression::Logical(expr) => {
+            f(&mut expr.left);
+            f(&mut expr.right);
+        }
+        Expression::Object(obj) => {
+            for prop in &mut obj.properties {
+                if let Some(value) = &mut prop.value {
+                    f(value);
+                }
+            }
+        }
+        Expression::Member(member) => f(&mut member.object),
+        Expression::Index(index) => {
+            f(&mut index.array);
+            f(&mut index.index);
+        }
+        Expression::Binary(expr) => {
+            f(&mut expr.left);
+            f(&mut expr.right);
+        }
+        Expression::Unary(expr) => f(&mut expr.argument),
+        Expression::PipeExpr(pipe) => {
+            f(&mut pipe.argument);
+            f(&mut pipe.call.callee);
+            for arg in &mut pipe.call.arguments {
+                f(arg);
+            }
+        }
+        Expression::Call(call) => {
+            f(&mut call.callee);
+            for arg in &mut call.arguments {
+                f(arg);
+            }
+        }
+        Expression::Conditional(cond) => {
+            f(&mut cond.test);
+            f(&mut cond.consequent);
+            f(&mut cond.alternate);
+        }
+        Expression::StringExpr(string) => {
+            for part in &mut string.parts {
+                if let StringExprPart::Interpolated(part) = part {
+                    f(&mut part.expression);
+                }
+            }
+        }
+        Expression::Paren(paren) => f(&mut paren.expression),
+        Expression::Bad(bad) => {
+            if let Some(expr) = &mut bad.expression {
+                f(expr);
+            }
+        }
+        Expression::Identifier(_)
+        | Expression::Integer(_)
+        | Expression::Float(_)
+        | Expression::StringLit(_)
+        | Expression::Duration(_)
+        | Expression::Uint(_)
+        | Expression::Boolean(_)
+        | Expression::DateTime(_)
+        | Expression::Regexp(_)
+        | Expression::PipeLit(_) => (),
+    }
+}
+
+// Returns the free variables of an expression along with the number of times each is used.
+pub(crate) fn free_variables(expr: &Expression) -> HashMap<String, usize> {
+    let mut collector = FreeVariables {
+        skip: HashSet::new(),
+        scopes: Vec::new(),
+        uses: HashMap::new(),
+    };
+    walk::walk(&mut collector, Node::from_expr(expr));
+    collector.uses
+}
+
+struct FreeVariables {
+    // Identifiers that name something rather than refer to it, such as property keys.
+    skip: HashSet<*const Identifier>,
+    scopes: Vec<HashSet<String>>,
+    uses: HashMap<String, usize>,
+}
+
+impl<'a> Visitor<'a> for FreeVariables {
+    fn visit(&mut self, node: Node<'a>) -> bool {
+        match node {
+            Node::Property(prop) => {
+                // A property without a value is shorthand for a reference to its key.
+                if let (PropertyKey::Identifier(id), Some(_)) = (&prop.key, &prop.value) {
+                    self.skip.insert(id);
+                }
+            }
+            Node::MemberExpr(member) => {
+                if let PropertyKey::Identifier(id) = &member.property {
+                    self.skip.insert(id);
+                }
+            }
+            Node::VariableAssgn(assgn) => {
+                self.skip.insert(&assgn.id);
+            }
+            Node::FunctionExpr(func) => {
+                for param in &func.params {
+                    if let PropertyKey::Identifier(id) = &param.key {
+                        self.skip.insert(id);
+                    }
+                }
+                self.scopes.push(bound_names(func));
+            }
+            Node::ObjectExpr(obj) => {
+                if let Some(with) = &obj.with {
+                    self.skip.insert(&with.source);
+                    if !self
+                        .scopes
+                        .iter()
+                        .any(|scope| scope.contains(&with.source.name))
+                    {
+                        *self.uses.entry(with.source.name.clone()).or_default() += 1;
+                    }
+                }
+            }
+            Node::Identifier(id) => {
+                if !self.skip.contains(&(id as *const Identifier))
+                    && !self.scopes.iter().any(|scope| scope.contains(&id.name))
+                {
+                    *self.uses.entry(id.name.clone()).or_default() += 1;
+                }
+            }
+            _ => (),
+        }
+        true
+    }
+
+    fn done(&mut self, node: Node<'a>) {
+        if let Node::FunctionExpr(_) = node {
+            self.scopes.pop();
+        }
+    }
+}
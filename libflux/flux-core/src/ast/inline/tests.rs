@@ -0,0 +1,136 @@
+use super::*;
+use crate::{formatter::convert_to_string, parser::parse_string};
+
+fn inline(source: &str) -> (String, usize) {
+    let mut file = parse_string("inline".to_string(), source);
+    let count = inline_functions(&mut file, &Options::default());
+    (convert_to_string(&file).unwrap(), count)
+}
+
+#[test]
+fn inline_call() {
+    let (output, count) = inline(
+        r#"add = (a, b=1) => a + b
+x = add(a: 2) * add(a: 3, b: 4)
+"#,
+    );
+    assert_eq!(
+        output,
+        r#"add = (a, b=1) => a + b
+x = (2 + 1) * (3 + 4)
+"#,
+    );
+    assert_eq!(count, 2);
+}
+
+#[test]
+fn inline_pipe_call() {
+    let (output, count) = inline(
+        r#"cpu = (tables=<-, host) => tables |> filter(fn: (r) => r.host == host)
+from(bucket: "b") |> cpu(host: "a") |> yield()
+"#,
+    );
+    assert_eq!(
+        output,
+        r#"cpu = (tables=<-, host) => tables |> filter(fn: (r) => r.host == host)
+
+from(bucket: "b") |> filter(fn: (r) => r.host == "a") |> yield()
+"#,
+    );
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn inline_nested_candidates() {
+    let (output, _) = inline(
+        r#"double = (v) => v * 2
+quadruple = (v) => double(v: double(v: v))
+x = quadruple(v: y)
+"#,
+    );
+    assert_eq!(
+        output,
+        r#"double = (v) => v * 2
+quadruple = (v) => v * 2 * 2
+x = y * 2 * 2
+"#,
+    );
+}
+
+#[test]
+fn record_shorthand_and_with() {
+    let (output, _) = inline(
+        r#"tag = (r, host) => ({r with host})
+x = tag(r: row, host: "a")
+"#,
+    );
+    assert_eq!(
+        output,
+        r#"tag = (r, host) => ({r with host})
+x = {row with host: "a"}
+"#,
+    );
+}
+
+#[test]
+fn no_capture() {
+    let source = r#"scale = (f) => map(fn: (r) => ({r with _value: r._value * f}))
+x = (r) => scale(f: r.factor)
+g = 2
+twice = (v) => v * g
+z = (g) => twice(v: g)
+"#;
+    let (output, count) = inline(source);
+    assert_eq!(output, source);
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn no_duplicated_work() {
+    let source = r#"sq = (v) => v * v
+x = sq(v: f())
+y = sq(v: a)
+"#;
+    let (output, count) = inline(source);
+    assert_eq!(
+        output,
+        r#"sq = (v) => v * v
+x = sq(v: f())
+y = a * a
+"#,
+    );
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn rename_captured_locals() {
+    let (output, count) = inline(
+        r#"scale = (f) => map(fn: (r) => {
+    data = r._value * f
+    return {r with _value: data}
+})
+x = (data) => scale(f: data)
+"#,
+    );
+    assert_eq!(
+        output,
+        r#"scale = (f) =>
+    map(
+        fn: (r) => {
+            data = r._value * f
+
+            return {r with _value: data}
+        },
+    )
+x = (data) =>
+    map(
+        fn: (r) => {
+            data2 = r._value * data
+
+            return {r with _value: data2}
+        },
+    )
+"#,
+    );
+    assert_eq!(count, 1);
+}
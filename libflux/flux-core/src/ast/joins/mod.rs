@@ -0,0 +1,272 @@
+//! Uses of the different ways to join streams of tables.
+//!
+//! Queries join tables with the `join` function of the universe, with `experimental.join`, with
+//! the functions of the `join` package, or by pivoting the union of the streams. Each use is
+//! reported along with a rewrite to the `join` package where one can be suggested.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    ast::{
+        inline,
+        walk::{self, Node, Visitor},
+        BaseNode, CallExpr, Expression, File, FunctionExpr, Identifier, PipeExpr, Property,
+        PropertyKey, SourceLocation,
+    },
+    formatter,
+};
+
+/// A way of joining tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum JoinIdiom {
+    /// `join(tables: {a: a, b: b}, on: [...])`
+    Universe,
+    /// `experimental.join(left: a, right: b, fn: ...)`
+    Experimental,
+    /// The functions of the `join` package, such as `join.inner`.
+    JoinPackage,
+    /// `union(tables: [a, b]) |> pivot(...)`
+    UnionPivot,
+}
+
+/// A use of a join idiom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinUse {
+    /// The idiom used.
+    pub idiom: JoinIdiom,
+    /// Location of the call that joins the tables.
+    pub location: SourceLocation,
+    /// An equivalent call to the `join` package, if one can be suggested.
+    pub suggestion: Option<String>,
+}
+
+/// Returns the uses of join idioms in a file, in source order.
+pub fn join_uses(file: &File) -> Vec<JoinUse> {
+    let mut imports = HashMap::new();
+    for import in &file.imports {
+        let path = import.path.value.as_str();
+        let name = match &import.alias {
+            Some(alias) => alias.name.as_str(),
+            None => path.rsplit('/').next().unwrap_or(path),
+        };
+        imports.insert(name, path);
+    }
+    let mut collector = JoinCollector {
+        imports,
+        uses: Vec::new(),
+    };
+    walk::walk(&mut collector, Node::File(file));
+    collector.uses
+}
+
+/// Counts the uses of each idiom.
+pub fn count_idioms<'a>(uses: impl IntoIterator<Item = &'a JoinUse>) -> BTreeMap<JoinIdiom, usize> {
+    let mut counts = BTreeMap::new();
+    for u in uses {
+        *counts.entry(u.idiom).or_insert(0) += 1;
+    }
+    counts
+}
+
+struct JoinCollector<'a> {
+    // Import path of each imported package by the name it is referred to.
+    imports: HashMap<&'a str, &'a str>,
+    uses: Vec<JoinUse>,
+}
+
+impl<'a> JoinCollector<'a> {
+    fn call(&mut self, call: &CallExpr) {
+        let (idiom, suggestion) = match &call.callee {
+            Expression::Identifier(id)
+                if id.name == "join" && !self.imports.contains_key("join") =>
+            {
+                (JoinIdiom::Universe, universe_suggestion(call))
+            }
+            Expression::Member(member) => match &member.object {
+                Expression::Identifier(object) => {
+                    match (
+                        self.imports.get(object.name.as_str()),
+                        member.property.key(),
+                    ) {
+                        (Some(&"experimental"), "join") => {
+                            (JoinIdiom::Experimental, experimental_suggestion(call))
+                        }
+                        (Some(&"join"), _) => (JoinIdiom::JoinPackage, None),
+                        _ => return,
+                    }
+                }
+                _ => return,
+            },
+            _ => return,
+        };
+        self.uses.push(JoinUse {
+            idiom,
+            location: call.base.location.clone(),
+            suggestion,
+        });
+    }
+
+    fn pipe(&mut self, pipe: &PipeExpr) {
+        if !is_call_to(&pipe.call, "pivot") {
+            return;
+        }
+        // Look for a union earlier in the pipeline.
+        let mut arg = &pipe.argument;
+        loop {
+            match arg {
+                Expression::PipeExpr(inner) => {
+                    if is_call_to(&inner.call, "union") {
+                        break;
+                    }
+                    arg = &inner.argument;
+                }
+                Expression::Call(call) if is_call_to(call, "union") => break,
+                _ => return,
+            }
+        }
+        self.uses.push(JoinUse {
+            idiom: JoinIdiom::UnionPivot,
+            location: pipe.base.location.clone(),
+            suggestion: None,
+        });
+    }
+}
+
+impl<'a, 'b> Visitor<'b> for JoinCollector<'a> {
+    fn visit(&mut self, node: Node<'b>) -> bool {
+        match node {
+            Node::CallExpr(call) => self.call(call),
+            Node::PipeExpr(pipe) => self.pipe(pipe),
+            _ => (),
+        }
+        true
+    }
+}
+
+fn is_call_to(call: &CallExpr, name: &str) -> bool {
+    matches!(&call.callee, Expression::Identifier(id) if id.name == name)
+}
+
+fn source(expr: &Expression) -> Option<&str> {
+    expr.base().location.source.as_deref()
+}
+
+// Returns the value of each argument of a call by name.
+fn arguments(call: &CallExpr) -> HashMap<&str, Option<&Expression>> {
+    call.arguments
+        .iter()
+        .flat_map(|arg| match arg {
+            Expression::Object(obj) => obj.properties.as_slice(),
+            _ => &[][..],
+        })
+        .map(|prop| (prop.key.key(), prop.value.as_ref()))
+        .collect()
+}
+
+// Suggests a rewrite of `join(tables: {a: x, b: y}, on: ["c"])`.
+// The universe join renames the `_value` columns of both tables by suffixing the table names,
+// which the suggestion preserves.
+fn universe_suggestion(call: &CallExpr) -> Option<String> {
+    let args = arguments(call);
+    let tables = match args.get("tables") {
+        Some(Some(Expression::Object(obj))) if obj.properties.len() == 2 => obj,
+        _ => return None,
+    };
+    let on = match args.get("on") {
+        Some(Some(Expression::Array(arr))) => arr
+            .elements
+            .iter()
+            .map(|item| match &item.expression {
+                Expression::StringLit(lit) => Some(format!("l.{0} == r.{0}", lit.value)),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?,
+        _ => return None,
+    };
+    if on.is_empty() {
+        return None;
+    }
+    let (left, right) = (&tables.properties[0], &tables.properties[1]);
+    let table = |prop: &Property| -> Option<(String, String)> {
+        let value = match &prop.value {
+            Some(value) => source(value)?.to_string(),
+            // `{a}` is shorthand for `{a: a}`
+            None => prop.key.key().to_string(),
+        };
+        Some((prop.key.key().to_string(), value))
+    };
+    let ((left_name, left), (right_name, right)) = (table(left)?, table(right)?);
+    Some(format!(
+        "join.inner(left: {}, right: {}, on: (l, r) => {}, as: (l, r) => ({{l with _value_{}: l._value, _value_{}: r._value}}))",
+        left,
+        right,
+        on.join(" and "),
+        left_name,
+        right_name,
+    ))
+}
+
+// Suggests a rewrite of `experimental.join(left: x, right: y, fn: f)`, which joins on the group
+// key and `_time` like `join.time`.
+fn experimental_suggestion(call: &CallExpr) -> Option<String> {
+    let args = arguments(call);
+    let arg = |name| args.get(name).copied().flatten().and_then(source);
+    let func = match args.get("fn") {
+        Some(Some(Expression::Function(func))) => func,
+        _ => return None,
+    };
+    Some(format!(
+        "join.time(left: {}, right: {}, as: {})",
+        arg("left")?,
+        arg("right")?,
+        as_function(func)?,
+    ))
+}
+
+// Rewrites the `fn: (left, right) => ...` function of `experimental.join` into the
+// `as: (l, r) => ...` function of `join.time`, if `l` and `r` would not be captured.
+fn as_function(func: &FunctionExpr) -> Option<String> {
+    let renames = [("left", "l"), ("right", "r")];
+    let free = inline::free_variables(&Expression::Function(Box::new(func.clone())));
+    if renames.iter().any(|(_, to)| free.contains_key(*to)) {
+        return None;
+    }
+    let mut captures = false;
+    walk::walk(
+        &mut |node: Node| {
+            if let Node::FunctionExpr(func) = node {
+                let bound = inline::bound_names(func);
+                captures |= renames.iter().any(|(_, to)| bound.contains(*to));
+            }
+        },
+        Node::FunctionExpr(func),
+    );
+    if captures {
+        return None;
+    }
+
+    let mut func = Box::new(func.clone());
+    let mut args = HashMap::new();
+    for param in &mut func.params {
+        match &mut param.key {
+            PropertyKey::Identifier(id) => {
+                let to = renames.iter().find(|(from, _)| id.name == *from)?.1;
+                args.insert(
+                    id.name.clone(),
+                    Expression::Identifier(Identifier {
+                        base: BaseNode::default(),
+                        name: to.to_string(),
+                    }),
+                );
+                id.name = to.to_string();
+            }
+            PropertyKey::StringLit(_) => return None,
+        }
+    }
+    let mut expr = Expression::Function(func);
+    inline::substitute(&mut expr, &args);
+    formatter::expression_to_string(expr).ok()
+}
@@ -0,0 +1,78 @@
+use super::*;
+use crate::parser::parse_string;
+
+fn uses(source: &str) -> Vec<JoinUse> {
+    join_uses(&parse_string("joins".to_string(), source))
+}
+
+#[test]
+fn universe_join() {
+    let uses = uses(r#"join(tables: {cpu: cpu, mem}, on: ["_time", "host"])"#);
+    assert_eq!(uses.len(), 1);
+    assert_eq!(uses[0].idiom, JoinIdiom::Universe);
+    assert_eq!(
+        uses[0].suggestion.as_deref(),
+        Some("join.inner(left: cpu, right: mem, on: (l, r) => l._time == r._time and l.host == r.host, as: (l, r) => ({l with _value_cpu: l._value, _value_mem: r._value}))"),
+    );
+}
+
+#[test]
+fn experimental_join() {
+    let uses = uses(
+        r#"import "experimental"
+
+experimental.join(left: a, right: b |> range(start: -1h), fn: (left, right) => ({left with v: right._value}))"#,
+    );
+    assert_eq!(uses.len(), 1);
+    assert_eq!(uses[0].idiom, JoinIdiom::Experimental);
+    assert_eq!(
+        uses[0].suggestion.as_deref(),
+        Some("join.time(left: a, right: b |> range(start: -1h), as: (l, r) => ({l with v: r._value}))"),
+    );
+}
+
+#[test]
+fn experimental_join_captures() {
+    let suggestion = |source: &str| {
+        uses(&format!("import \"experimental\"\n\n{}", source))[0]
+            .suggestion
+            .clone()
+    };
+    assert_eq!(
+        suggestion(
+            "experimental.join(left: a, right: b, fn: (left, right) => ({left with r: right.l, n: f(left)}))"
+        )
+        .as_deref(),
+        Some("join.time(left: a, right: b, as: (l, r) => ({l with r: r.l, n: f(left: l)}))"),
+    );
+    // `r` refers to a variable outside of the function.
+    assert_eq!(
+        suggestion("experimental.join(left: a, right: b, fn: (left, right) => ({left with v: r}))"),
+        None,
+    );
+    // `l` is bound inside the function.
+    assert_eq!(
+        suggestion(
+            "experimental.join(left: a, right: b, fn: (left, right) => ({left with v: ((l) => l + right._value)(l: 1)}))"
+        ),
+        None,
+    );
+}
+
+#[test]
+fn counts() {
+    let uses = uses(
+        r#"import "join"
+
+join.inner(left: a, right: b, on: (l, r) => l.id == r.id, as: (l, r) => l)
+join.left(left: a, right: b, on: (l, r) => l.id == r.id, as: (l, r) => l)
+union(tables: [a, b]) |> group() |> pivot(rowKey: ["_time"], columnKey: ["_field"], valueColumn: "_value")
+a |> pivot(rowKey: ["_time"], columnKey: ["_field"], valueColumn: "_value")
+"#,
+    );
+    let mut want = BTreeMap::new();
+    want.insert(JoinIdiom::JoinPackage, 2);
+    want.insert(JoinIdiom::UnionPivot, 1);
+    assert_eq!(count_idioms(&uses), want);
+    assert!(uses.iter().all(|u| u.suggestion.is_none()));
+}
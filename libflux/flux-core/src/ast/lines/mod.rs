@@ -0,0 +1,247 @@
+//! Lines of source code, and columns of positions in the units an editor counts.
+//!
+//! Columns in the AST are 1-based and count UTF-8 bytes. Editors count columns in other units:
+//! LSP clients count UTF-16 code units by default, and users perceive grapheme clusters. The
+//! functions of this module convert columns between bytes and a [`ColumnEncoding`], so that
+//! positions sent to or received from an editor point at the same character on lines with
+//! non-ASCII identifiers or strings.
+//!
+//! Columns past the end of a line count one unit per byte in every encoding.
+//!
+//! A [`LineIndex`] converts between byte offsets and positions, and converts the columns of many
+//! positions of a source without scanning the source again.
+
+#[cfg(test)]
+mod tests;
+
+use std::{ops::Range, str::FromStr};
+
+use anyhow::{bail, Error};
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::ast::{Position, SourceLocation};
+
+/// The unit columns are counted in.
+///
+/// In configurations, encodings are named as by [`FromStr`], e.g. `"utf-16"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColumnEncoding {
+    /// UTF-8 bytes, the unit of the columns in the AST.
+    #[serde(rename = "utf-8", alias = "utf8")]
+    Utf8,
+    /// UTF-16 code units, the default unit of LSP.
+    #[serde(rename = "utf-16", alias = "utf16")]
+    Utf16,
+    /// Extended grapheme clusters, the characters a user perceives.
+    #[serde(rename = "graphemes")]
+    Graphemes,
+}
+
+impl ColumnEncoding {
+    /// Reports whether the encoding is UTF-8, in which columns are left as they are.
+    pub fn is_utf8(&self) -> bool {
+        *self == ColumnEncoding::Utf8
+    }
+}
+
+impl Default for ColumnEncoding {
+    fn default() -> Self {
+        ColumnEncoding::Utf8
+    }
+}
+
+impl FromStr for ColumnEncoding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "utf-8" | "utf8" => ColumnEncoding::Utf8,
+            "utf-16" | "utf16" => ColumnEncoding::Utf16,
+            "graphemes" => ColumnEncoding::Graphemes,
+            _ => bail!(
+                "unknown column encoding `{}`, expected `utf-8`, `utf-16` or `graphemes`",
+                s
+            ),
+        })
+    }
+}
+
+// The byte offset and the width in units of the encoding of each character of a line.
+fn characters(
+    line: &str,
+    encoding: ColumnEncoding,
+) -> Box<dyn Iterator<Item = (usize, usize)> + '_> {
+    match encoding {
+        ColumnEncoding::Utf8 => Box::new(line.char_indices().map(|(i, c)| (i, c.len_utf8()))),
+        ColumnEncoding::Utf16 => Box::new(line.char_indices().map(|(i, c)| (i, c.len_utf16()))),
+        ColumnEncoding::Graphemes => Box::new(line.grapheme_indices(true).map(|(i, _)| (i, 1))),
+    }
+}
+
+/// Converts a column counting bytes to a column counting units of `encoding`.
+///
+/// A column inside a character is moved to the column after it.
+pub fn encode_column(line: &str, column: u32, encoding: ColumnEncoding) -> u32 {
+    let offset = (column as usize).saturating_sub(1);
+    let mut units = 0;
+    for (start, width) in characters(line, encoding) {
+        if start >= offset {
+            return units as u32 + 1;
+        }
+        units += width;
+    }
+    (units + offset.saturating_sub(line.len())) as u32 + 1
+}
+
+/// Converts a column counting units of `encoding` to a column counting bytes.
+///
+/// A column inside a character is moved to the column after it.
+pub fn decode_column(line: &str, column: u32, encoding: ColumnEncoding) -> u32 {
+    let mut remaining = (column as usize).saturating_sub(1);
+    for (start, width) in characters(line, encoding) {
+        if remaining == 0 {
+            return start as u32 + 1;
+        }
+        remaining = remaining.saturating_sub(width);
+    }
+    (line.len() + remaining) as u32 + 1
+}
+
+/// An index of the lines of a source, built once to convert between byte offsets, lines and
+/// columns in logarithmic time. Lines end with `\n` or `\r\n`.
+///
+/// The index does not keep the source; methods that need its text take it as an argument,
+/// which must be the source the index was built from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    // The byte offset of the start of each line.
+    starts: Vec<usize>,
+    // The byte offset of the end of each line, before its terminator.
+    ends: Vec<usize>,
+}
+
+impl LineIndex {
+    /// Indexes the lines of `source`.
+    pub fn new(source: &str) -> Self {
+        let mut starts = vec![0];
+        let mut ends = Vec::new();
+        for (i, _) in source.match_indices('\n') {
+            let end = if source[..i].ends_with('\r') {
+                i - 1
+            } else {
+                i
+            };
+            ends.push(end);
+            starts.push(i + 1);
+        }
+        ends.push(source.len());
+        LineIndex { starts, ends }
+    }
+
+    /// Returns the number of lines. A source ending with a line terminator ends with an empty
+    /// line.
+    pub fn line_count(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Returns the byte range of a 1-based line, without its terminator.
+    pub fn line_range(&self, line: u32) -> Option<Range<usize>> {
+        let i = (line as usize).checked_sub(1)?;
+        Some(*self.starts.get(i)?..self.ends[i])
+    }
+
+    /// Returns the text of a 1-based line of `source`, without its terminator. Lines past the
+    /// end of the source are empty.
+    pub fn line<'a>(&self, source: &'a str, line: u32) -> &'a str {
+        self.line_range(line)
+            .map(|range| &source[range])
+            .unwrap_or_default()
+    }
+
+    /// Returns the position of a byte offset, with a column counting bytes.
+    pub fn position(&self, offset: usize) -> Position {
+        let line = self.starts.partition_point(|start| *start <= offset);
+        Position {
+            line: line as u32,
+            column: (offset - self.starts[line - 1]) as u32 + 1,
+        }
+    }
+
+    /// Returns the byte offset of a position with a column counting bytes, or `None` if the line
+    /// does not exist. Columns past the end of the line are moved to its end.
+    pub fn offset(&self, position: Position) -> Option<usize> {
+        let range = self.line_range(position.line)?;
+        Some((range.start + (position.column as usize).saturating_sub(1)).min(range.end))
+    }
+
+    /// Returns the byte range of a location, for slicing or replacing its text in the source.
+    pub fn range(&self, location: &SourceLocation) -> Option<Range<usize>> {
+        Some(self.offset(location.start)?..self.offset(location.end)?)
+    }
+
+    /// Converts a position of `source` with a column counting bytes to one counting units of
+    /// `encoding`.
+    pub fn encode_position(
+        &self,
+        source: &str,
+        position: Position,
+        encoding: ColumnEncoding,
+    ) -> Position {
+        Position {
+            line: position.line,
+            column: encode_column(self.line(source, position.line), position.column, encoding),
+        }
+    }
+
+    /// Converts a position of `source` with a column counting units of `encoding` to one
+    /// counting bytes.
+    pub fn decode_position(
+        &self,
+        source: &str,
+        position: Position,
+        encoding: ColumnEncoding,
+    ) -> Position {
+        Position {
+            line: position.line,
+            column: decode_column(self.line(source, position.line), position.column, encoding),
+        }
+    }
+
+    /// Converts the start and end of a location of `source` to columns counting units of
+    /// `encoding`.
+    pub fn encode_location(
+        &self,
+        source: &str,
+        location: &SourceLocation,
+        encoding: ColumnEncoding,
+    ) -> SourceLocation {
+        SourceLocation {
+            start: self.encode_position(source, location.start, encoding),
+            end: self.encode_position(source, location.end, encoding),
+            ..location.clone()
+        }
+    }
+}
+
+/// Converts a position of `source` with a column counting bytes to one counting units of
+/// `encoding`. Use a [`LineIndex`] to convert many positions of the same source.
+pub fn encode_position(source: &str, position: Position, encoding: ColumnEncoding) -> Position {
+    LineIndex::new(source).encode_position(source, position, encoding)
+}
+
+/// Converts a position of `source` with a column counting units of `encoding` to one counting
+/// bytes. Use a [`LineIndex`] to convert many positions of the same source.
+pub fn decode_position(source: &str, position: Position, encoding: ColumnEncoding) -> Position {
+    LineIndex::new(source).decode_position(source, position, encoding)
+}
+
+/// Converts the start and end of a location of `source` to columns counting units of
+/// `encoding`. Use a [`LineIndex`] to convert many locations of the same source.
+pub fn encode_location(
+    source: &str,
+    location: &SourceLocation,
+    encoding: ColumnEncoding,
+) -> SourceLocation {
+    LineIndex::new(source).encode_location(source, location, encoding)
+}
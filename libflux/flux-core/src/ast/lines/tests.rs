@@ -0,0 +1,103 @@
+use super::*;
+
+#[test]
+fn encode() {
+    // `é` is 2 bytes and 1 UTF-16 unit, `😀` is 4 bytes and 2 UTF-16 units, and `e\u{301}` is
+    // 3 bytes, 2 UTF-16 units and a single grapheme.
+    let line = "a = \"é😀e\u{301}\" + b";
+    let b = line.find('b').unwrap() as u32 + 1;
+    assert_eq!(encode_column(line, b, ColumnEncoding::Utf8), b);
+    assert_eq!(encode_column(line, b, ColumnEncoding::Utf16), 15);
+    assert_eq!(encode_column(line, b, ColumnEncoding::Graphemes), 13);
+    assert_eq!(encode_column(line, 1, ColumnEncoding::Utf16), 1);
+
+    // Past the end of the line.
+    let end = line.len() as u32 + 1;
+    assert_eq!(encode_column(line, end + 2, ColumnEncoding::Utf16), 16 + 2);
+}
+
+#[test]
+fn decode() {
+    let line = "a = \"é😀e\u{301}\" + b";
+    let b = line.find('b').unwrap() as u32 + 1;
+    assert_eq!(decode_column(line, 15, ColumnEncoding::Utf16), b);
+    assert_eq!(decode_column(line, 13, ColumnEncoding::Graphemes), b);
+    assert_eq!(decode_column(line, b, ColumnEncoding::Utf8), b);
+
+    for column in 1..=16 {
+        let encoded = encode_column(
+            line,
+            decode_column(line, column, ColumnEncoding::Utf16),
+            ColumnEncoding::Utf16,
+        );
+        // Only the column inside the surrogate pair of `😀` moves.
+        assert_eq!(encoded, if column == 8 { 9 } else { column });
+    }
+}
+
+#[test]
+fn location() {
+    let source = "x = 1\r\ny = \"ü\" + z\n";
+    let file = crate::parser::parse_string("".to_string(), source);
+    let z = match &file.body[1] {
+        crate::ast::Statement::Variable(assign) => match &assign.init {
+            crate::ast::Expression::Binary(binary) => binary.right.base().location.clone(),
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    };
+    let encoded = encode_location(source, &z, ColumnEncoding::Utf16);
+    assert_eq!((encoded.start.line, encoded.start.column), (2, 11));
+    assert_eq!((encoded.end.line, encoded.end.column), (2, 12));
+    assert_eq!(
+        decode_position(source, encoded.start, ColumnEncoding::Utf16),
+        z.start
+    );
+}
+
+#[test]
+fn parse_encoding() {
+    assert_eq!(
+        "utf-16".parse::<ColumnEncoding>().unwrap(),
+        ColumnEncoding::Utf16
+    );
+    assert!("utf-32".parse::<ColumnEncoding>().is_err());
+}
+
+#[test]
+fn line_index() {
+    let source = "a = 1\r\nb = \"é\"\n\nc";
+    let index = LineIndex::new(source);
+    assert_eq!(index.line_count(), 4);
+    assert_eq!(index.line(source, 1), "a = 1");
+    assert_eq!(index.line(source, 2), "b = \"é\"");
+    assert_eq!(index.line(source, 3), "");
+    assert_eq!(index.line(source, 5), "");
+
+    let c = source.find('c').unwrap();
+    assert_eq!(index.position(c), Position { line: 4, column: 1 });
+    assert_eq!(index.offset(Position { line: 4, column: 1 }), Some(c));
+    assert_eq!(index.position(0), Position { line: 1, column: 1 });
+    // The line terminator belongs to the line it ends.
+    assert_eq!(index.position(5), Position { line: 1, column: 6 });
+    assert_eq!(index.position(6), Position { line: 1, column: 7 });
+    assert_eq!(
+        index.offset(Position {
+            line: 1,
+            column: 20
+        }),
+        Some(5)
+    );
+    assert_eq!(index.offset(Position { line: 6, column: 1 }), None);
+
+    for offset in 0..source.len() {
+        let position = index.position(offset);
+        if offset <= index.line_range(position.line).unwrap().end {
+            assert_eq!(index.offset(position), Some(offset));
+        }
+    }
+
+    let file = crate::parser::parse_string("".to_string(), source);
+    let b = &file.body[1].base().location;
+    assert_eq!(&source[index.range(b).unwrap()], "b = \"é\"");
+}
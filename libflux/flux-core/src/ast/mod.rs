@@ -1,6 +1,23 @@
 //! Abstract syntax tree (AST).
 
+pub mod beautify;
+pub mod callgraph;
 pub mod check;
+pub mod comments;
+pub mod dataflow;
+pub mod inline;
+pub mod joins;
+pub mod lines;
+pub mod params;
+pub mod pattern;
+pub mod ranges;
+pub mod redact;
+pub mod similar;
+pub mod snippets;
+pub mod sources;
+pub mod suppressions;
+pub mod tasks;
+pub mod versions;
 pub mod walk;
 
 use std::{collections::HashMap, fmt, str::FromStr, vec::Vec};
@@ -18,6 +35,11 @@ use crate::scanner;
 /// The default package name.
 pub const DEFAULT_PACKAGE_NAME: &str = "main";
 
+/// The name of the identifier the parser produces for a typed hole, `_?`.
+/// A typed hole stands in for an expression that has not been written yet;
+/// type inference reports the type it is expected to have.
+pub const TYPED_HOLE: &str = "_?";
+
 /// Position is the AST counterpart of [`scanner::Position`].
 /// It adds serde capabilities.
 #[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
@@ -0,0 +1,255 @@
+//! Extraction of query parameters.
+//!
+//! Literal values that select the data a query reads or filters, such as bucket names, time
+//! ranges and thresholds, are replaced by members of a `params` record. The result is a template
+//! that can be run again with other values for the parameters, and that is the same for queries
+//! that only differ in those values.
+//!
+//! A literal is a parameter when it is the value of a named argument, where it is named after the
+//! argument, or when it is compared to a column, such as `r._value > 10.0`, where it is named
+//! after the column. Other literals are part of the template.
+
+#[cfg(test)]
+mod tests;
+
+use anyhow::Result;
+
+use crate::{
+    ast::{
+        inline::for_each_child, Assignment, BaseNode, BinaryExpr, CallExpr, Expression, File,
+        Identifier, MemberExpr, Operator, PropertyKey, Statement,
+    },
+    formatter,
+};
+
+/// The name of the record the parameters are members of in a template.
+pub const PARAMS: &str = "params";
+
+/// A literal value replaced by a parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    /// Name of the parameter in the `params` record.
+    pub name: String,
+    /// The Flux type of the parameter.
+    pub typ: &'static str,
+    /// The literal the parameter replaced.
+    pub value: Expression,
+}
+
+/// A query with its parameters replaced by members of the `params` record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    /// The query with parameters replaced.
+    pub file: File,
+    /// The parameters, in the order they first appear.
+    pub parameters: Vec<Parameter>,
+}
+
+impl Template {
+    /// Returns the source code of the template.
+    pub fn source(&self) -> Result<String> {
+        formatter::convert_to_string(&self.file)
+    }
+
+    /// Returns the type of the `params` record, such as `{bucket: string, start: duration}`.
+    pub fn parameter_type(&self) -> String {
+        let fields: Vec<_> = self
+            .parameters
+            .iter()
+            .map(|p| format!("{}: {}", p.name, p.typ))
+            .collect();
+        format!("{{{}}}", fields.join(", "))
+    }
+}
+
+/// Extracts the parameters of a query.
+pub fn extract(file: &File) -> Template {
+    let mut extractor = Extractor {
+        parameters: Vec::new(),
+    };
+    let mut file = file.clone();
+    for stmt in &mut file.body {
+        extractor.rewrite_stmt(stmt);
+    }
+    Template {
+        file,
+        parameters: extractor.parameters,
+    }
+}
+
+struct Extractor {
+    parameters: Vec<Parameter>,
+}
+
+impl Extractor {
+    fn rewrite_stmt(&mut self, stmt: &mut Statement) {
+        match stmt {
+            Statement::Expr(stmt) => self.rewrite_expr(&mut stmt.expression),
+            Statement::Variable(assgn) => self.rewrite_expr(&mut assgn.init),
+            Statement::Option(opt) => match &mut opt.assignment {
+                Assignment::Variable(assgn) => self.rewrite_expr(&mut assgn.init),
+                Assignment::Member(assgn) => self.rewrite_expr(&mut assgn.init),
+            },
+            Statement::Return(stmt) => self.rewrite_expr(&mut stmt.argument),
+            Statement::TestCase(test) => {
+                for stmt in &mut test.block.body {
+                    self.rewrite_stmt(stmt);
+                }
+            }
+            Statement::Builtin(_) | Statement::Bad(_) => (),
+        }
+    }
+
+    fn rewrite_expr(&mut self, expr: &mut Expression) {
+        match expr {
+            Expression::Call(call) => self.rewrite_call(call),
+            Expression::PipeExpr(pipe) => {
+                self.rewrite_expr(&mut pipe.argument);
+                self.rewrite_call(&mut pipe.call);
+            }
+            Expression::Binary(binary) if is_comparison(&binary.operator) => {
+                self.rewrite_comparison(binary)
+            }
+            _ => for_each_child(expr, &mut |child| self.rewrite_expr(child)),
+        }
+    }
+
+    fn rewrite_call(&mut self, call: &mut CallExpr) {
+        self.rewrite_expr(&mut call.callee);
+        for arg in &mut call.arguments {
+            match arg {
+                Expression::Object(obj) => {
+                    for prop in &mut obj.properties {
+                        if let Some(value) = &mut prop.value {
+                            let name = prop.key.key().to_string();
+                            if !self.replace(name, value) {
+                                self.rewrite_expr(value);
+                            }
+                        }
+                    }
+                }
+                _ => self.rewrite_expr(arg),
+            }
+        }
+    }
+
+    fn rewrite_comparison(&mut self, binary: &mut BinaryExpr) {
+        if let Some(column) = column(&binary.left) {
+            if self.replace(column, &mut binary.right) {
+                return;
+            }
+        }
+        if let Some(column) = column(&binary.right) {
+            if self.replace(column, &mut binary.left) {
+                return;
+            }
+        }
+        self.rewrite_expr(&mut binary.left);
+        self.rewrite_expr(&mut binary.right);
+    }
+
+    // Replaces expr by a parameter named after name if it is a literal.
+    // Returns whether it was replaced.
+    fn replace(&mut self, name: String, expr: &mut Expression) -> bool {
+        let typ = match literal_type(expr) {
+            Some(typ) => typ,
+            None => return false,
+        };
+        let name = self.parameter_name(name, expr);
+        if !self.parameters.iter().any(|p| p.name == name) {
+            self.parameters.push(Parameter {
+                name: name.clone(),
+                typ,
+                value: expr.clone(),
+            });
+        }
+        *expr = Expression::Member(Box::new(MemberExpr {
+            base: BaseNode::default(),
+            object: Expression::Identifier(identifier(PARAMS)),
+            lbrack: Vec::new(),
+            property: PropertyKey::Identifier(identifier(&name)),
+            rbrack: Vec::new(),
+        }));
+        true
+    }
+
+    // Returns the name of the parameter for a literal. Literals with the same name, kind and value
+    // share a parameter, while different literals for the same name are numbered, such as
+    // `start_2`.
+    fn parameter_name(&self, name: String, value: &Expression) -> String {
+        let mut candidate = name.clone();
+        for n in 2.. {
+            match self.parameters.iter().find(|p| p.name == candidate) {
+                Some(p) if !same_literal(&p.value, value) => candidate = format!("{}_{}", name, n),
+                _ => break,
+            }
+        }
+        candidate
+    }
+}
+
+fn identifier(name: &str) -> Identifier {
+    Identifier {
+        base: BaseNode::default(),
+        name: name.to_string(),
+    }
+}
+
+fn is_comparison(op: &Operator) -> bool {
+    matches!(
+        op,
+        Operator::EqualOperator
+            | Operator::NotEqualOperator
+            | Operator::LessThanOperator
+            | Operator::LessThanEqualOperator
+            | Operator::GreaterThanOperator
+            | Operator::GreaterThanEqualOperator
+    )
+}
+
+// Returns the column a member expression such as `r._value` or `r["_value"]` refers to.
+fn column(expr: &Expression) -> Option<String> {
+    match expr {
+        Expression::Member(member) if matches!(member.object, Expression::Identifier(_)) => {
+            Some(member.property.key().to_string())
+        }
+        _ => None,
+    }
+}
+
+// Reports whether two literals are of the same kind and have the same value, however they are
+// written.
+fn same_literal(a: &Expression, b: &Expression) -> bool {
+    match (a, b) {
+        (Expression::Integer(a), Expression::Integer(b)) => a.value == b.value,
+        (Expression::Uint(a), Expression::Uint(b)) => a.value == b.value,
+        (Expression::Float(a), Expression::Float(b)) => a.value == b.value,
+        (Expression::StringLit(a), Expression::StringLit(b)) => a.value == b.value,
+        (Expression::Duration(a), Expression::Duration(b)) => a.values == b.values,
+        (Expression::DateTime(a), Expression::DateTime(b)) => a.value == b.value,
+        (Expression::Unary(a), Expression::Unary(b)) => {
+            a.operator == b.operator && same_literal(&a.argument, &b.argument)
+        }
+        _ => false,
+    }
+}
+
+fn literal_type(expr: &Expression) -> Option<&'static str> {
+    match expr {
+        Expression::Integer(_) => Some("int"),
+        Expression::Uint(_) => Some("uint"),
+        Expression::Float(_) => Some("float"),
+        Expression::StringLit(_) => Some("string"),
+        Expression::Duration(_) => Some("duration"),
+        Expression::DateTime(_) => Some("time"),
+        Expression::Unary(unary) if unary.operator == Operator::SubtractionOperator => {
+            match &unary.argument {
+                Expression::Integer(_) | Expression::Float(_) | Expression::Duration(_) => {
+                    literal_type(&unary.argument)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
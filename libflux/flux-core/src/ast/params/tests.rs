@@ -0,0 +1,62 @@
+use super::*;
+use crate::parser::parse_string;
+
+#[test]
+fn extract_parameters() {
+    let file = parse_string(
+        "params".to_string(),
+        r#"from(bucket: "telegraf")
+    |> range(start: -1h)
+    |> filter(fn: (r) => r.host == "a" and r._value > 90.0)
+    |> aggregateWindow(every: 5m, fn: mean, createEmpty: false)
+"#,
+    );
+    let template = extract(&file);
+    assert_eq!(
+        template.source().unwrap(),
+        r#"from(bucket: params.bucket)
+    |> range(start: params.start)
+    |> filter(fn: (r) => r.host == params.host and r._value > params._value)
+    |> aggregateWindow(every: params.every, fn: mean, createEmpty: false)
+"#,
+    );
+    assert_eq!(
+        template.parameter_type(),
+        "{bucket: string, start: duration, host: string, _value: float, every: duration}",
+    );
+}
+
+#[test]
+fn repeated_names() {
+    let file = parse_string(
+        "params".to_string(),
+        r#"a = from(bucket: "a") |> range(start: -1h)
+b = from(bucket: "b") |> range(start: -1h)
+"#,
+    );
+    let template = extract(&file);
+    let names: Vec<_> = template
+        .parameters
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["bucket", "start", "bucket_2"]);
+}
+
+#[test]
+fn literals_compared_by_kind_and_value() {
+    let file = parse_string(
+        "params".to_string(),
+        r#"a = from(bucket: "a") |> filter(fn: (r) => r._value > 90.0)
+b = from(bucket: "a") |> filter(fn: (r) => r._value > 90.00)
+c = from(bucket: "a") |> filter(fn: (r) => r._value > 90)
+"#,
+    );
+    let template = extract(&file);
+    let names: Vec<_> = template
+        .parameters
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["bucket", "_value", "_value_2"]);
+}
@@ -0,0 +1,535 @@
+//! Structural search over the AST.
+//!
+//! A pattern is a Flux expression in which `$name` metavariables stand in for
+//! arbitrary subexpressions, for example:
+//!
+//! ```text
+//! from(bucket: $b) |> range(start: $s) |> $rest
+//! ```
+//!
+//! A metavariable that appears more than once must bind structurally equal
+//! expressions each time. A metavariable in the final stage of a pipeline
+//! (`|> $rest`, written without call parentheses) binds all of the remaining
+//! stages of the matched pipeline, so the pattern above matches any pipeline
+//! that starts with `from` and `range`.
+//!
+//! `$name` in the text of a string literal or in a comment is not a metavariable,
+//! so `"$5"` matches the string `"$5"`.
+//!
+//! Named arguments and record properties are matched by key, so their order is
+//! irrelevant, but the set of keys must be the same. Parentheses and source
+//! locations are ignored.
+
+pub mod rules;
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{anyhow, bail, Result};
+use regex::Regex;
+
+use crate::{
+    ast::{
+        walk::{self, Node, Visitor},
+        *,
+    },
+    parser,
+};
+
+const VAR_PREFIX: &str = "__patternvar_";
+const REST_PREFIX: &str = "__patternrest_";
+const METAVAR_REGEX: &str = r"\$([A-Za-z_][A-Za-z0-9_]*)";
+
+/// A structural pattern that can be searched for in the AST.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    expr: Expression,
+    metavariables: BTreeSet<String>,
+}
+
+/// The value a metavariable was bound to in a match.
+#[derive(Debug, Clone)]
+pub enum Binding<'a> {
+    /// A metavariable bound to a single expression.
+    Expr(&'a Expression),
+    /// A metavariable in the tail of a pipeline bound to the remaining stages.
+    Stages(Vec<&'a CallExpr>),
+}
+
+impl Binding<'_> {
+    /// Returns the source text the metavariable was bound to.
+    /// Pipeline stages are joined with `|>`.
+    pub fn source(&self) -> String {
+        match self {
+            Binding::Expr(expr) => source_of(expr.base()),
+            Binding::Stages(calls) => calls
+                .iter()
+                .map(|call| source_of(&call.base))
+                .collect::<Vec<_>>()
+                .join(" |> "),
+        }
+    }
+}
+
+fn source_of(base: &BaseNode) -> String {
+    base.location.source.clone().unwrap_or_default()
+}
+
+/// A single match of a [`Pattern`].
+#[derive(Debug, Clone)]
+pub struct Match<'a> {
+    /// The node that matched the pattern.
+    pub node: Node<'a>,
+    /// The values bound to each metavariable, keyed by name without the leading `$`.
+    pub bindings: BTreeMap<String, Binding<'a>>,
+}
+
+impl Pattern {
+    /// Parses a pattern from its source text.
+    pub fn new(src: &str) -> Result<Self> {
+        let file = parser::parse_string("pattern".to_string(), &desugar(src));
+        check::check(Node::File(&file)).map_err(|err| anyhow!("invalid pattern: {}", err))?;
+
+        if file.package.is_some() || !file.imports.is_empty() {
+            bail!("pattern must consist of a single expression");
+        }
+        let expr = match &file.body[..] {
+            [Statement::Expr(stmt)] => stmt.expression.clone(),
+            _ => bail!("pattern must consist of a single expression"),
+        };
+        if metavar(&expr).is_some() {
+            bail!("pattern must not be a bare metavariable");
+        }
+        let metavariables = metavar_captures(src)
+            .iter()
+            .map(|caps| caps[1].to_string())
+            .collect();
+        Ok(Pattern {
+            expr,
+            metavariables,
+        })
+    }
+
+    /// Returns the names of the metavariables in the pattern, without the leading `$`.
+    pub fn metavariables(&self) -> impl Iterator<Item = &str> {
+        self.metavariables.iter().map(String::as_str)
+    }
+
+    /// Returns every match of the pattern in the tree rooted at `node`.
+    ///
+    /// Once a node matches, its children are not searched, so matches never overlap.
+    pub fn find<'a>(&self, node: Node<'a>) -> Vec<Match<'a>> {
+        let mut finder = Finder {
+            pattern: &self.expr,
+            matches: Vec::new(),
+        };
+        walk::walk(&mut finder, node);
+        finder.matches
+    }
+}
+
+/// Reports whether two expressions are structurally equal, ignoring
+/// parentheses, comments and source locations.
+pub fn equivalent(a: &Expression, b: &Expression) -> bool {
+    Matcher::default().match_expr(a, b)
+}
+
+/// Reports whether two calls are structurally equal, ignoring parentheses,
+/// comments and source locations.
+pub fn equivalent_calls(a: &CallExpr, b: &CallExpr) -> bool {
+    Matcher::default().match_call(a, b)
+}
+
+// Returns, for each byte of `src`, whether it is Flux code rather than the text of a string
+// literal or a comment. The expressions interpolated into strings are code.
+fn code_bytes(src: &str) -> Vec<bool> {
+    // The brace depth of each level of code, outermost first. Strings are between two levels.
+    let mut depths = vec![0_usize];
+    let mut in_string = false;
+    let mut code = vec![false; src.len()];
+    let bytes = src.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if in_string {
+            match bytes[i] {
+                b'\\' => i += 1,
+                b'"' => in_string = false,
+                b'$' if bytes.get(i + 1) == Some(&b'{') => {
+                    depths.push(0);
+                    in_string = false;
+                    i += 1;
+                }
+                _ => (),
+            }
+        } else {
+            let interpolated = depths.len() > 1;
+            let depth = depths.last_mut().expect("code is always nested in a level");
+            match bytes[i] {
+                b'"' => in_string = true,
+                b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                    while i + 1 < bytes.len() && bytes[i + 1] != b'\n' {
+                        i += 1;
+                    }
+                }
+                b'}' if *depth == 0 && interpolated => {
+                    depths.pop();
+                    in_string = true;
+                }
+                b => {
+                    match b {
+                        b'{' => *depth += 1,
+                        b'}' => *depth = depth.saturating_sub(1),
+                        _ => (),
+                    }
+                    code[i] = true;
+                }
+            }
+        }
+        i += 1;
+    }
+    code
+}
+
+// Returns the `$name` metavariables of `src`, skipping string literals and comments.
+fn metavar_captures(src: &str) -> Vec<regex::Captures<'_>> {
+    let code = code_bytes(src);
+    Regex::new(METAVAR_REGEX)
+        .expect("valid metavariable regex")
+        .captures_iter(src)
+        .filter(|caps| code[caps.get(0).expect("capture group 0 always exists").start()])
+        .collect()
+}
+
+// Rewrites `$name` metavariables into identifiers the parser accepts.
+fn desugar(src: &str) -> String {
+    let mut out = String::with_capacity(src.len());
+    let mut last = 0;
+    for caps in metavar_captures(src) {
+        let m = caps.get(0).expect("capture group 0 always exists");
+        out.push_str(&src[last..m.start()]);
+
+        let after_pipe = src[..m.start()].trim_end().ends_with("|>");
+        let followed_by_call = src[m.end()..].trim_start().starts_with('(');
+        if after_pipe && !followed_by_call {
+            out.push_str(REST_PREFIX);
+            out.push_str(&caps[1]);
+            out.push_str("()");
+        } else {
+            out.push_str(VAR_PREFIX);
+            out.push_str(&caps[1]);
+        }
+        last = m.end();
+    }
+    out.push_str(&src[last..]);
+    out
+}
+
+fn strip_parens(mut expr: &Expression) -> &Expression {
+    while let Expression::Paren(p) = expr {
+        expr = &p.expression;
+    }
+    expr
+}
+
+fn metavar(expr: &Expression) -> Option<&str> {
+    match strip_parens(expr) {
+        Expression::Identifier(id) => id.name.strip_prefix(VAR_PREFIX),
+        _ => None,
+    }
+}
+
+fn rest_metavar(call: &CallExpr) -> Option<&str> {
+    match (strip_parens(&call.callee), &call.arguments[..]) {
+        (Expression::Identifier(id), []) => id.name.strip_prefix(REST_PREFIX),
+        _ => None,
+    }
+}
+
+// Splits a pipeline into its head expression and its stages in source order.
+fn flatten_pipe(pipe: &PipeExpr) -> (&Expression, Vec<&CallExpr>) {
+    let mut calls = vec![&pipe.call];
+    let mut head = strip_parens(&pipe.argument);
+    while let Expression::PipeExpr(inner) = head {
+        calls.push(&inner.call);
+        head = strip_parens(&inner.argument);
+    }
+    calls.reverse();
+    (head, calls)
+}
+
+struct Finder<'p, 'a> {
+    pattern: &'p Expression,
+    matches: Vec<Match<'a>>,
+}
+
+impl<'p, 'a> Visitor<'a> for Finder<'p, 'a> {
+    fn visit(&mut self, node: Node<'a>) -> bool {
+        let mut matcher = Matcher::default();
+        if matcher.match_node(self.pattern, node.clone()) {
+            self.matches.push(Match {
+                node,
+                bindings: matcher.bindings,
+            });
+            false
+        } else {
+            true
+        }
+    }
+}
+
+#[derive(Default)]
+struct Matcher<'a> {
+    bindings: BTreeMap<String, Binding<'a>>,
+}
+
+impl<'a> Matcher<'a> {
+    fn match_node(&mut self, p: &Expression, node: Node<'a>) -> bool {
+        match (strip_parens(p), node) {
+            (Expression::Identifier(p), Node::Identifier(t)) => p.name == t.name,
+            (Expression::Array(p), Node::ArrayExpr(t)) => self.match_array(p, t),
+            (Expression::Dict(p), Node::DictExpr(t)) => self.match_dict(p, t),
+            (Expression::Function(p), Node::FunctionExpr(t)) => self.match_function(p, t),
+            (Expression::Logical(p), Node::LogicalExpr(t)) => self.match_logical(p, t),
+            (Expression::Object(p), Node::ObjectExpr(t)) => self.match_object(p, t),
+            (Expression::Member(p), Node::MemberExpr(t)) => self.match_member(p, t),
+            (Expression::Index(p), Node::IndexExpr(t)) => self.match_index(p, t),
+            (Expression::Binary(p), Node::BinaryExpr(t)) => self.match_binary(p, t),
+            (Expression::Unary(p), Node::UnaryExpr(t)) => self.match_unary(p, t),
+            (Expression::PipeExpr(p), Node::PipeExpr(t)) => self.match_pipe(p, t),
+            (Expression::Call(p), Node::CallExpr(t)) => self.match_call(p, t),
+            (Expression::Conditional(p), Node::ConditionalExpr(t)) => self.match_conditional(p, t),
+            (Expression::StringExpr(p), Node::StringExpr(t)) => self.match_string_expr(p, t),
+            (Expression::Integer(p), Node::IntegerLit(t)) => p.value == t.value,
+            (Expression::Float(p), Node::FloatLit(t)) => p.value == t.value,
+            (Expression::StringLit(p), Node::StringLit(t)) => p.value == t.value,
+            (Expression::Duration(p), Node::DurationLit(t)) => p.values == t.values,
+            (Expression::Uint(p), Node::UintLit(t)) => p.value == t.value,
+            (Expression::Boolean(p), Node::BooleanLit(t)) => p.value == t.value,
+            (Expression::DateTime(p), Node::DateTimeLit(t)) => p.value == t.value,
+            (Expression::Regexp(p), Node::RegexpLit(t)) => p.value == t.value,
+            _ => false,
+        }
+    }
+
+    fn match_expr(&mut self, p: &Expression, t: &'a Expression) -> bool {
+        let (p, t) = (strip_parens(p), strip_parens(t));
+        if let Some(name) = metavar(p) {
+            return self.bind(name, Binding::Expr(t));
+        }
+        match (p, t) {
+            (Expression::Identifier(p), Expression::Identifier(t)) => p.name == t.name,
+            (Expression::Array(p), Expression::Array(t)) => self.match_array(p, t),
+            (Expression::Dict(p), Expression::Dict(t)) => self.match_dict(p, t),
+            (Expression::Function(p), Expression::Function(t)) => self.match_function(p, t),
+            (Expression::Logical(p), Expression::Logical(t)) => self.match_logical(p, t),
+            (Expression::Object(p), Expression::Object(t)) => self.match_object(p, t),
+            (Expression::Member(p), Expression::Member(t)) => self.match_member(p, t),
+            (Expression::Index(p), Expression::Index(t)) => self.match_index(p, t),
+            (Expression::Binary(p), Expression::Binary(t)) => self.match_binary(p, t),
+            (Expression::Unary(p), Expression::Unary(t)) => self.match_unary(p, t),
+            (Expression::PipeExpr(p), Expression::PipeExpr(t)) => self.match_pipe(p, t),
+            (Expression::Call(p), Expression::Call(t)) => self.match_call(p, t),
+            (Expression::Conditional(p), Expression::Conditional(t)) => {
+                self.match_conditional(p, t)
+            }
+            (Expression::StringExpr(p), Expression::StringExpr(t)) => self.match_string_expr(p, t),
+            (Expression::Integer(p), Expression::Integer(t)) => p.value == t.value,
+            (Expression::Float(p), Expression::Float(t)) => p.value == t.value,
+            (Expression::StringLit(p), Expression::StringLit(t)) => p.value == t.value,
+            (Expression::Duration(p), Expression::Duration(t)) => p.values == t.values,
+            (Expression::Uint(p), Expression::Uint(t)) => p.value == t.value,
+            (Expression::Boolean(p), Expression::Boolean(t)) => p.value == t.value,
+            (Expression::DateTime(p), Expression::DateTime(t)) => p.value == t.value,
+            (Expression::Regexp(p), Expression::Regexp(t)) => p.value == t.value,
+            (Expression::PipeLit(_), Expression::PipeLit(_)) => true,
+            _ => false,
+        }
+    }
+
+    fn bind(&mut self, name: &str, binding: Binding<'a>) -> bool {
+        let prev = match self.bindings.get(name) {
+            Some(prev) => prev.clone(),
+            None => {
+                self.bindings.insert(name.to_string(), binding);
+                return true;
+            }
+        };
+        // A repeated metavariable must bind structurally equal code each time
+        match (prev, binding) {
+            (Binding::Expr(prev), Binding::Expr(t)) => Matcher::default().match_expr(prev, t),
+            (Binding::Stages(prev), Binding::Stages(t)) => {
+                prev.len() == t.len()
+                    && prev
+                        .into_iter()
+                        .zip(t)
+                        .all(|(prev, t)| Matcher::default().match_call(prev, t))
+            }
+            _ => false,
+        }
+    }
+
+    fn match_array(&mut self, p: &ArrayExpr, t: &'a ArrayExpr) -> bool {
+        p.elements.len() == t.elements.len()
+            && p.elements
+                .iter()
+                .zip(&t.elements)
+                .all(|(p, t)| self.match_expr(&p.expression, &t.expression))
+    }
+
+    fn match_dict(&mut self, p: &DictExpr, t: &'a DictExpr) -> bool {
+        p.elements.len() == t.elements.len()
+            && p.elements
+                .iter()
+                .zip(&t.elements)
+                .all(|(p, t)| self.match_expr(&p.key, &t.key) && self.match_expr(&p.val, &t.val))
+    }
+
+    fn match_function(&mut self, p: &FunctionExpr, t: &'a FunctionExpr) -> bool {
+        if !self.match_properties(&p.params, &t.params) {
+            return false;
+        }
+        match (&p.body, &t.body) {
+            (FunctionBody::Expr(p), FunctionBody::Expr(t)) => self.match_expr(p, t),
+            (FunctionBody::Block(p), FunctionBody::Block(t)) => self.match_block(p, t),
+            _ => false,
+        }
+    }
+
+    fn match_block(&mut self, p: &Block, t: &'a Block) -> bool {
+        p.body.len() == t.body.len()
+            && p.body
+                .iter()
+                .zip(&t.body)
+                .all(|(p, t)| self.match_statement(p, t))
+    }
+
+    fn match_statement(&mut self, p: &Statement, t: &'a Statement) -> bool {
+        match (p, t) {
+            (Statement::Expr(p), Statement::Expr(t)) => {
+                self.match_expr(&p.expression, &t.expression)
+            }
+            (Statement::Variable(p), Statement::Variable(t)) => {
+                p.id.name == t.id.name && self.match_expr(&p.init, &t.init)
+            }
+            (Statement::Return(p), Statement::Return(t)) => {
+                self.match_expr(&p.argument, &t.argument)
+            }
+            _ => false,
+        }
+    }
+
+    fn match_logical(&mut self, p: &LogicalExpr, t: &'a LogicalExpr) -> bool {
+        p.operator == t.operator
+            && self.match_expr(&p.left, &t.left)
+            && self.match_expr(&p.right, &t.right)
+    }
+
+    fn match_binary(&mut self, p: &BinaryExpr, t: &'a BinaryExpr) -> bool {
+        p.operator == t.operator
+            && self.match_expr(&p.left, &t.left)
+            && self.match_expr(&p.right, &t.right)
+    }
+
+    fn match_unary(&mut self, p: &UnaryExpr, t: &'a UnaryExpr) -> bool {
+        p.operator == t.operator && self.match_expr(&p.argument, &t.argument)
+    }
+
+    fn match_object(&mut self, p: &ObjectExpr, t: &'a ObjectExpr) -> bool {
+        let with_matches = match (&p.with, &t.with) {
+            (None, None) => true,
+            (Some(p), Some(t)) => p.source.name == t.source.name,
+            _ => false,
+        };
+        with_matches && self.match_properties(&p.properties, &t.properties)
+    }
+
+    // Properties are matched by key so that their order does not matter.
+    fn match_properties(&mut self, p: &[Property], t: &'a [Property]) -> bool {
+        p.len() == t.len()
+            && p.iter()
+                .all(|p| match t.iter().find(|t| t.key.key() == p.key.key()) {
+                    Some(t) => self.match_property_value(p, t),
+                    None => false,
+                })
+    }
+
+    fn match_property_value(&mut self, p: &Property, t: &'a Property) -> bool {
+        match (&p.value, &t.value) {
+            (Some(p), Some(t)) => self.match_expr(p, t),
+            (None, None) => true,
+            // `{a}` is shorthand for `{a: a}`
+            (Some(p), None) => {
+                matches!(strip_parens(p), Expression::Identifier(id) if id.name == t.key.key())
+            }
+            (None, Some(t)) => {
+                matches!(strip_parens(t), Expression::Identifier(id) if id.name == p.key.key())
+            }
+        }
+    }
+
+    fn match_member(&mut self, p: &MemberExpr, t: &'a MemberExpr) -> bool {
+        p.property.key() == t.property.key() && self.match_expr(&p.object, &t.object)
+    }
+
+    fn match_index(&mut self, p: &IndexExpr, t: &'a IndexExpr) -> bool {
+        self.match_expr(&p.array, &t.array) && self.match_expr(&p.index, &t.index)
+    }
+
+    fn match_call(&mut self, p: &CallExpr, t: &'a CallExpr) -> bool {
+        if !self.match_expr(&p.callee, &t.callee) {
+            return false;
+        }
+        match (&p.arguments[..], &t.arguments[..]) {
+            ([], []) => true,
+            ([p], [t]) => self.match_expr(p, t),
+            _ => false,
+        }
+    }
+
+    fn match_pipe(&mut self, p: &PipeExpr, t: &'a PipeExpr) -> bool {
+        let (p_head, p_calls) = flatten_pipe(p);
+        let (t_head, t_calls) = flatten_pipe(t);
+
+        let rest = p_calls.last().and_then(|call| rest_metavar(call));
+        let fixed = if rest.is_some() {
+            &p_calls[..p_calls.len() - 1]
+        } else {
+            &p_calls[..]
+        };
+        let arity_matches = if rest.is_some() {
+            t_calls.len() >= fixed.len()
+        } else {
+            t_calls.len() == fixed.len()
+        };
+
+        arity_matches
+            && self.match_expr(p_head, t_head)
+            && fixed
+                .iter()
+                .zip(t_calls.iter().copied())
+                .all(|(p, t)| self.match_call(p, t))
+            && match rest {
+                Some(name) => self.bind(name, Binding::Stages(t_calls[fixed.len()..].to_vec())),
+                None => true,
+            }
+    }
+
+    fn match_conditional(&mut self, p: &ConditionalExpr, t: &'a ConditionalExpr) -> bool {
+        self.match_expr(&p.test, &t.test)
+            && self.match_expr(&p.consequent, &t.consequent)
+            && self.match_expr(&p.alternate, &t.alternate)
+    }
+
+    fn match_string_expr(&mut self, p: &StringExpr, t: &'a StringExpr) -> bool {
+        p.parts.len() == t.parts.len()
+            && p.parts.iter().zip(&t.parts).all(|parts| match parts {
+                (StringExprPart::Text(p), StringExprPart::Text(t)) => p.value == t.value,
+                (StringExprPart::Interpolated(p), StringExprPart::Interpolated(t)) => {
+                    self.match_expr(&p.expression, &t.expression)
+                }
+                _ => false,
+            })
+    }
+}
@@ -0,0 +1,262 @@
+//! Lint rules defined declaratively as structural patterns.
+//!
+//! Rules are loaded from a JSON document containing a list of rules:
+//!
+//! ```text
+//! [
+//!     {
+//!         "name": "no-explicit-yield",
+//!         "pattern": "$t |> yield()",
+//!         "message": "the final table stream is yielded implicitly"
+//!     },
+//!     {
+//!         "name": "prefer-exists",
+//!         "pattern": "$r.host != \"\"",
+//!         "message": "use exists to check for the host column",
+//!         "rewrite": "exists $r.host"
+//!     }
+//! ]
+//! ```
+//!
+//! or from a TOML document with a `[[rule]]` table for each rule:
+//!
+//! ```text
+//! [[rule]]
+//! name = "no-explicit-yield"
+//! pattern = "$t |> yield()"
+//! message = "the final table stream is yielded implicitly"
+//! ```
+//!
+//! The optional `rewrite` is a template in which each `$name` outside of string
+//! literals is replaced by the source text its metavariable was bound to.
+//!
+//! Instead of a `rewrite`, a rule may have an `action` computing the
+//! replacement. The only action is `"inline"`, which replaces a call matched by
+//! the pattern with the body of the called function, as
+//! [`ast::inline`](crate::ast::inline) does:
+//!
+//! ```text
+//! {
+//!     "name": "inline-scale",
+//!     "pattern": "scale(f: $f)",
+//!     "message": "scale is inlined",
+//!     "action": "inline"
+//! }
+//! ```
+//!
+//! Violations are suppressed by comments naming their rule, see
+//! [`ast::suppressions`](crate::ast::suppressions).
+
+#[cfg(test)]
+mod tests;
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::{
+    ast::{
+        inline,
+        pattern::{metavar_captures, Match, Pattern},
+        suppressions::{Suppressions, UNUSED_SUPPRESSION},
+        walk::Node,
+        SourceLocation,
+    },
+    formatter,
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleDefinition {
+    name: String,
+    pattern: String,
+    message: String,
+    #[serde(default)]
+    rewrite: Option<String>,
+    #[serde(default)]
+    action: Option<Action>,
+}
+
+/// A computed rewrite of the code matched by a [`Rule`].
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Action {
+    /// Replaces a call with the body of the called function, see [`inline::inline_call`].
+    Inline,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlRules {
+    #[serde(default)]
+    rule: Vec<RuleDefinition>,
+}
+
+/// A lint rule that reports every match of a pattern.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    /// Name of the rule, reported with each violation.
+    pub name: String,
+    /// Message reported with each violation.
+    pub message: String,
+    /// Template for the code that should replace each match.
+    pub rewrite: Option<String>,
+    /// Action that computes the code that should replace each match.
+    pub action: Option<Action>,
+    pattern: Pattern,
+}
+
+/// A match of a [`Rule`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// Name of the rule that was violated.
+    pub rule: String,
+    /// Message of the rule that was violated.
+    pub message: String,
+    /// Location of the offending code.
+    pub location: SourceLocation,
+    /// Suggested replacement for the offending code, if the rule has a rewrite or an action
+    /// that applies to it.
+    pub replacement: Option<String>,
+}
+
+impl Rule {
+    /// Creates a rule from a pattern and a message and an optional rewrite template.
+    pub fn new(name: &str, pattern: &str, message: &str, rewrite: Option<&str>) -> Result<Self> {
+        let pattern =
+            Pattern::new(pattern).with_context(|| format!("invalid pattern in rule `{}`", name))?;
+        if let Some(rewrite) = rewrite {
+            for caps in metavar_captures(rewrite) {
+                if !pattern.metavariables().any(|var| var == &caps[1]) {
+                    bail!(
+                        "rewrite in rule `{}` refers to unknown metavariable `${}`",
+                        name,
+                        &caps[1]
+                    );
+                }
+            }
+        }
+        Ok(Rule {
+            name: name.to_string(),
+            message: message.to_string(),
+            rewrite: rewrite.map(str::to_string),
+            action: None,
+            pattern,
+        })
+    }
+
+    /// Sets the action that computes the replacement of each match. A rule cannot have both a
+    /// rewrite and an action.
+    pub fn with_action(mut self, action: Action) -> Result<Self> {
+        if self.rewrite.is_some() {
+            bail!("rule `{}` has both a rewrite and an action", self.name);
+        }
+        self.action = Some(action);
+        Ok(self)
+    }
+
+    /// Returns a violation for every match of the rule in the tree rooted at `node`.
+    pub fn check(&self, node: Node) -> Vec<Violation> {
+        self.pattern
+            .find(node.clone())
+            .iter()
+            .map(|m| Violation {
+                rule: self.name.clone(),
+                message: self.message.clone(),
+                location: m.node.base().location.clone(),
+                replacement: match (&self.rewrite, self.action) {
+                    (Some(rewrite), _) => Some(expand(rewrite, m)),
+                    (None, Some(action)) => apply(action, node.clone(), m),
+                    (None, None) => None,
+                },
+            })
+            .collect()
+    }
+}
+
+fn apply(action: Action, root: Node, m: &Match) -> Option<String> {
+    match action {
+        Action::Inline => inline::inline_call(root, m.node.clone(), &inline::Options::default())
+            .and_then(|expr| formatter::expression_to_string(expr).ok()),
+    }
+}
+
+fn expand(rewrite: &str, m: &Match) -> String {
+    let mut out = String::with_capacity(rewrite.len());
+    let mut last = 0;
+    for caps in metavar_captures(rewrite) {
+        let var = caps.get(0).expect("capture group 0 always exists");
+        out.push_str(&rewrite[last..var.start()]);
+        if let Some(binding) = m.bindings.get(&caps[1]) {
+            out.push_str(&binding.source());
+        }
+        last = var.end();
+    }
+    out.push_str(&rewrite[last..]);
+    out
+}
+
+/// Parses a list of rules from a JSON document.
+pub fn parse_rules(json: &str) -> Result<Vec<Rule>> {
+    rules(serde_json::from_str(json)?)
+}
+
+/// Parses a list of rules from a TOML document.
+pub fn parse_toml_rules(toml: &str) -> Result<Vec<Rule>> {
+    let document: TomlRules = toml::from_str(toml)?;
+    rules(document.rule)
+}
+
+fn rules(definitions: Vec<RuleDefinition>) -> Result<Vec<Rule>> {
+    definitions
+        .iter()
+        .map(|def| {
+            let rule = Rule::new(
+                &def.name,
+                &def.pattern,
+                &def.message,
+                def.rewrite.as_deref(),
+            )?;
+            match def.action {
+                Some(action) => rule.with_action(action),
+                None => Ok(rule),
+            }
+        })
+        .collect()
+}
+
+/// Loads a list of rules from a file, a TOML document if its extension is `.toml` and a JSON
+/// document otherwise.
+pub fn load_rules(path: &Path) -> Result<Vec<Rule>> {
+    let source = fs::read_to_string(path)
+        .with_context(|| format!("reading rules from {}", path.display()))?;
+    if path.extension() == Some("toml".as_ref()) {
+        parse_toml_rules(&source)
+    } else {
+        parse_rules(&source)
+    }
+    .with_context(|| format!("loading rules from {}", path.display()))
+}
+
+/// Checks the tree rooted at `node` against every rule, returning the violations
+/// ordered by their position in the source. Suppressed violations are left out, and
+/// suppressions of a rule that suppress nothing are reported as violations of
+/// `unused-suppression`.
+pub fn check_rules(rules: &[Rule], node: Node) -> Vec<Violation> {
+    let mut suppressions = Suppressions::new(node.clone());
+    let mut violations: Vec<Violation> = rules
+        .iter()
+        .flat_map(|rule| rule.check(node.clone()))
+        .filter(|v| !suppressions.suppresses(&v.rule, &v.location))
+        .collect();
+    let unused = suppressions.unused(|name| rules.iter().any(|rule| rule.name == name));
+    violations.extend(unused.into_iter().map(|(name, location)| Violation {
+        rule: UNUSED_SUPPRESSION.to_string(),
+        message: format!("suppression of `{}` is never used", name),
+        location: location.clone(),
+        replacement: None,
+    }));
+    violations.sort_by_key(|v| (v.location.start.line, v.location.start.column));
+    violations
+}
@@ -0,0 +1,167 @@
+use super::*;
+use crate::parser::parse_string;
+
+#[test]
+fn rules_report_matches_with_rewrites() {
+    let rules = parse_rules(
+        r#"[
+                {
+                    "name": "prefer-exists",
+                    "pattern": "$r.host != \"\"",
+                    "message": "use exists",
+                    "rewrite": "exists $r.host"
+                },
+                {
+                    "name": "no-yield",
+                    "pattern": "$t |> yield()",
+                    "message": "do not yield explicitly"
+                }
+            ]"#,
+    )
+    .unwrap();
+    let file = parse_string(
+        "rules".to_string(),
+        "a |> filter(fn: (r) => r.host != \"\")\nb |> yield()",
+    );
+    let violations: Vec<_> = check_rules(&rules, Node::File(&file))
+        .into_iter()
+        .map(|v| (v.rule, v.location.start.line, v.replacement))
+        .collect();
+    assert_eq!(
+        violations,
+        vec![
+            (
+                "prefer-exists".to_string(),
+                1,
+                Some("exists r.host".to_string())
+            ),
+            ("no-yield".to_string(), 2, None),
+        ],
+    );
+}
+
+#[test]
+fn suppressed_violations() {
+    let rules = parse_rules(
+        r#"[
+                {
+                    "name": "no-yield",
+                    "pattern": "$t |> yield()",
+                    "message": "do not yield explicitly"
+                }
+            ]"#,
+    )
+    .unwrap();
+    let file = parse_string(
+        "rules".to_string(),
+        r#"// flux-lint: ignore[no-yield]
+a |> yield()
+// flux-lint: ignore[no-yield, unused-symbol]
+b = 1
+c |> yield()"#,
+    );
+    let violations: Vec<_> = check_rules(&rules, Node::File(&file))
+        .into_iter()
+        .map(|v| (v.rule, v.location.start.line))
+        .collect();
+    assert_eq!(
+        violations,
+        vec![
+            ("unused-suppression".to_string(), 4),
+            ("no-yield".to_string(), 5),
+        ],
+    );
+}
+
+#[test]
+fn unknown_rewrite_metavariable() {
+    let err = Rule::new("r", "f(x: $x)", "m", Some("g(x: $y)")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "rewrite in rule `r` refers to unknown metavariable `$y`"
+    );
+    assert!(Rule::new("r", "f(x: $x)", "m", Some(r#"g(x: $x, y: "$y")"#)).is_ok());
+}
+
+#[test]
+fn toml_rules() {
+    let rules = parse_toml_rules(
+        r#"
+[[rule]]
+name = "prefer-exists"
+pattern = '$r.host != ""'
+message = "use exists"
+rewrite = "exists $r.host"
+
+[[rule]]
+name = "no-yield"
+pattern = "$t |> yield()"
+message = "do not yield explicitly"
+"#,
+    )
+    .unwrap();
+    let names: Vec<_> = rules.iter().map(|rule| rule.name.as_str()).collect();
+    assert_eq!(names, ["prefer-exists", "no-yield"]);
+    assert_eq!(rules[0].rewrite.as_deref(), Some("exists $r.host"));
+
+    assert!(parse_toml_rules(
+        "[[rule]]\nname = \"r\"\npattern = \"f()\"\nmessage = \"m\"\nlevel = 1"
+    )
+    .is_err());
+}
+
+#[test]
+fn inline_action() {
+    let rules = parse_rules(
+        r#"[
+                {
+                    "name": "inline-scale",
+                    "pattern": "scale(f: $f)",
+                    "message": "scale is inlined",
+                    "action": "inline"
+                }
+            ]"#,
+    )
+    .unwrap();
+    let file = parse_string(
+        "rules".to_string(),
+        r#"scale = (f) => map(fn: (r) => ({r with _value: r._value * f}))
+a |> scale(f: 2)
+x = (data) => scale(f: data + 1)
+"#,
+    );
+    let violations: Vec<_> = check_rules(&rules, Node::File(&file))
+        .into_iter()
+        .map(|v| (v.location.start.line, v.replacement))
+        .collect();
+    assert_eq!(
+        violations,
+        vec![
+            (
+                2,
+                Some("map(fn: (r) => ({r with _value: r._value * 2}))".to_string())
+            ),
+            (
+                3,
+                Some("map(fn: (r) => ({r with _value: r._value * (data + 1)}))".to_string())
+            ),
+        ],
+    );
+
+    let err = parse_rules(
+        r#"[
+                {
+                    "name": "both",
+                    "pattern": "scale(f: $f)",
+                    "message": "both",
+                    "rewrite": "$f",
+                    "action": "inline"
+                }
+            ]"#,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "rule `both` has both a rewrite and an action"
+    );
+}
@@ -0,0 +1,138 @@
+use super::{Pattern, *};
+use crate::parser::parse_string;
+
+fn find_sources(pattern: &str, source: &str) -> Vec<String> {
+    let pattern = Pattern::new(pattern).unwrap();
+    let file = parse_string("test_pattern".to_string(), source);
+    pattern
+        .find(Node::File(&file))
+        .iter()
+        .map(|m| source_of(m.node.base()))
+        .collect()
+}
+
+fn find_bindings(pattern: &str, source: &str) -> Vec<Vec<(String, String)>> {
+    let pattern = Pattern::new(pattern).unwrap();
+    let file = parse_string("test_pattern".to_string(), source);
+    pattern
+        .find(Node::File(&file))
+        .iter()
+        .map(|m| {
+            m.bindings
+                .iter()
+                .map(|(name, binding)| (name.clone(), binding.source()))
+                .collect()
+        })
+        .collect()
+}
+
+fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn literal_pattern() {
+    assert_eq!(
+        find_sources("x + 1", "a = x + 1\nb = x + 2\nc = (x + 1) * 2"),
+        vec!["x + 1", "x + 1"],
+    );
+}
+
+#[test]
+fn metavariable_binds_expression() {
+    assert_eq!(
+        find_bindings("from(bucket: $b)", r#"from(bucket: "telegraf")"#),
+        vec![pairs(&[("b", r#""telegraf""#)])],
+    );
+}
+
+#[test]
+fn repeated_metavariable_must_be_equal() {
+    assert_eq!(
+        find_sources("$a == $a", "x == x\nx == y\nz = (a.b + 1) == (a.b + 1)",),
+        vec!["x == x", "(a.b + 1) == (a.b + 1)"],
+    );
+}
+
+#[test]
+fn named_arguments_ignore_order() {
+    assert_eq!(
+        find_sources(
+            "range(start: $s, stop: $e)",
+            "range(stop: now(), start: -1h)\nrange(start: -1h)",
+        ),
+        vec!["range(stop: now(), start: -1h)"],
+    );
+}
+
+#[test]
+fn pipeline_rest() {
+    let source = r#"
+from(bucket: "a") |> range(start: -1h) |> filter(fn: (r) => r._measurement == "cpu") |> mean()
+from(bucket: "b") |> range(start: -5m)
+from(bucket: "c") |> filter(fn: (r) => true)
+"#;
+    assert_eq!(
+        find_bindings("from(bucket: $b) |> range(start: $s) |> $rest", source),
+        vec![
+            pairs(&[
+                ("b", r#""a""#),
+                (
+                    "rest",
+                    r#"filter(fn: (r) => r._measurement == "cpu") |> mean()"#,
+                ),
+                ("s", "-1h"),
+            ]),
+            pairs(&[("b", r#""b""#), ("rest", ""), ("s", "-5m")]),
+        ],
+    );
+}
+
+#[test]
+fn pipeline_without_rest_is_exact() {
+    assert_eq!(
+        find_sources(
+            "$t |> mean()",
+            "a |> mean()\nb |> mean() |> yield()\nc |> filter(fn: (r) => true) |> mean()",
+        ),
+        vec!["a |> mean()", "b |> mean()"],
+    );
+}
+
+#[test]
+fn matches_do_not_overlap() {
+    assert_eq!(
+        find_sources("f(x: $x)", "f(x: f(x: 1))"),
+        vec!["f(x: f(x: 1))"]
+    );
+}
+
+#[test]
+fn record_shorthand() {
+    assert_eq!(find_sources("{a: a}", "x = {a}"), vec!["{a}"]);
+}
+
+#[test]
+fn metavariables_outside_strings() {
+    let pattern = Pattern::new(r#"f(a: "$5", b: "$x ${$y}", c: $z) // $w"#).unwrap();
+    assert_eq!(pattern.metavariables().collect::<Vec<_>>(), vec!["y", "z"]);
+    assert_eq!(
+        find_bindings(
+            r#"f(a: "$5", b: "$x ${$y}", c: $z)"#,
+            r#"f(a: "$5", b: "$x ${v}", c: 1)
+f(a: "$6", b: "$x ${v}", c: 1)"#,
+        ),
+        vec![pairs(&[("y", "v"), ("z", "1")])],
+    );
+}
+
+#[test]
+fn invalid_patterns() {
+    assert!(Pattern::new("$x").is_err());
+    assert!(Pattern::new("a = 1").is_err());
+    assert!(Pattern::new("f(").is_err());
+    assert!(Pattern::new("a\nb").is_err());
+}
@@ -0,0 +1,239 @@
+//! Folding ranges and selection ranges for editors.
+//!
+//! Lines and columns are 1-based, like every [`Position`] in the AST. When the
+//! `lsp` feature is enabled, the ranges convert into their 0-based
+//! `lsp_types` counterparts. Columns count units of a [`ColumnEncoding`].
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashSet;
+
+use crate::{
+    ast::{
+        lines::{ColumnEncoding, LineIndex},
+        walk::{self, Node},
+        Expression, File, Position, SourceLocation,
+    },
+    parser,
+};
+
+/// The kind of a [`FoldingRange`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldingRangeKind {
+    /// A run of consecutive line comments.
+    Comment,
+    /// The import declarations of a file.
+    Imports,
+    /// A multi-line expression or block.
+    Region,
+}
+
+/// A range of lines an editor can fold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldingRange {
+    /// The first line of the range.
+    pub start_line: u32,
+    /// The last line of the range.
+    pub end_line: u32,
+    /// What the range contains.
+    pub kind: FoldingRangeKind,
+}
+
+/// A range an editor can select, along with the enclosing range that
+/// expanding the selection moves to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectionRange {
+    /// The start of the range.
+    pub start: Position,
+    /// The end of the range.
+    pub end: Position,
+    /// The range that contains this one, if any.
+    pub parent: Option<Box<SelectionRange>>,
+}
+
+/// Returns the folding ranges of Flux source code: multi-line functions, records,
+/// arrays, dictionaries, pipelines and call arguments, runs of line comments and
+/// the import declarations. At most one range starts on any line; the outermost
+/// range is kept.
+pub fn folding_ranges(source: &str) -> Vec<FoldingRange> {
+    let file = parser::parse_string("".to_string(), source);
+    let mut ranges = Vec::new();
+
+    if let (Some(first), Some(last)) = (file.imports.first(), file.imports.last()) {
+        ranges.push(FoldingRange {
+            start_line: first.base.location.start.line,
+            end_line: last.base.location.end.line,
+            kind: FoldingRangeKind::Imports,
+        });
+    }
+
+    // Pipe expressions nest to the left, so only the outermost one of a
+    // pipeline is folded.
+    let mut inner_pipes = HashSet::new();
+    walk::walk(
+        &mut |node: Node| {
+            let foldable = match node {
+                Node::FunctionExpr(_)
+                | Node::Block(_)
+                | Node::ObjectExpr(_)
+                | Node::ArrayExpr(_)
+                | Node::DictExpr(_)
+                | Node::CallExpr(_)
+                | Node::TestCaseStmt(_) => true,
+                Node::PipeExpr(pipe) => {
+                    if let Expression::PipeExpr(inner) = &pipe.argument {
+                        inner_pipes.insert(&**inner as *const _);
+                    }
+                    !inner_pipes.contains(&(pipe as *const _))
+                }
+                _ => false,
+            };
+            let location = &node.base().location;
+            if foldable && location.is_multiline() {
+                ranges.push(FoldingRange {
+                    start_line: location.start.line,
+                    end_line: location.end.line,
+                    kind: FoldingRangeKind::Region,
+                });
+            }
+        },
+        Node::File(&file),
+    );
+
+    ranges.extend(comment_ranges(source));
+
+    ranges.sort_by_key(|range| (range.start_line, std::cmp::Reverse(range.end_line)));
+    ranges.dedup_by_key(|range| range.start_line);
+    ranges
+}
+
+// Returns the runs of two or more lines that contain only a line comment.
+fn comment_ranges(source: &str) -> Vec<FoldingRange> {
+    let mut ranges = Vec::new();
+    let mut push = |start_line, end_line| {
+        if end_line > start_line {
+            ranges.push(FoldingRange {
+                start_line,
+                end_line,
+                kind: FoldingRangeKind::Comment,
+            });
+        }
+    };
+    let mut start = None;
+    let mut last_line = 0;
+    for (i, line) in source.lines().enumerate() {
+        last_line = i as u32 + 1;
+        if line.trim_start().starts_with("//") {
+            start.get_or_insert(last_line);
+        } else if let Some(start_line) = start.take() {
+            push(start_line, last_line - 1);
+        }
+    }
+    if let Some(start_line) = start {
+        push(start_line, last_line);
+    }
+    ranges
+}
+
+/// Returns a selection range for each position, starting with the smallest node
+/// that contains the position and expanding through its enclosing nodes up to
+/// the whole file. The columns of the positions and of the ranges count units of
+/// `encoding`.
+pub fn selection_ranges(
+    source: &str,
+    positions: &[Position],
+    encoding: ColumnEncoding,
+) -> Vec<SelectionRange> {
+    let file = parser::parse_string("".to_string(), source);
+    let lines = LineIndex::new(source);
+    let encode = |range| encode_range(&lines, source, range, encoding);
+    positions
+        .iter()
+        .map(|position| {
+            encode(selection_range(
+                &file,
+                lines.decode_position(source, *position, encoding),
+            ))
+        })
+        .collect()
+}
+
+fn encode_range(
+    lines: &LineIndex,
+    source: &str,
+    range: SelectionRange,
+    encoding: ColumnEncoding,
+) -> SelectionRange {
+    SelectionRange {
+        start: lines.encode_position(source, range.start, encoding),
+        end: lines.encode_position(source, range.end, encoding),
+        parent: range
+            .parent
+            .map(|parent| Box::new(encode_range(lines, source, *parent, encoding))),
+    }
+}
+
+fn selection_range(file: &File, position: Position) -> SelectionRange {
+    let key = |p: &Position| (p.line, p.column);
+    let contains = |location: &SourceLocation| {
+        location.is_valid()
+            && key(&location.start) <= key(&position)
+            && key(&position) <= key(&location.end)
+    };
+
+    // The extents of the nodes that contain the position, outermost first,
+    // since the walk visits a node before its children.
+    let mut extents: Vec<(Position, Position)> = Vec::new();
+    walk::walk(
+        &mut |node: Node| {
+            let location = &node.base().location;
+            let extent = (location.start, location.end);
+            if contains(location) && extents.last() != Some(&extent) {
+                extents.push(extent);
+            }
+        },
+        Node::File(file),
+    );
+
+    let mut range = SelectionRange {
+        start: position,
+        end: position,
+        parent: None,
+    };
+    for (i, (start, end)) in extents.into_iter().enumerate() {
+        let parent = if i == 0 { None } else { Some(Box::new(range)) };
+        range = SelectionRange { start, end, parent };
+    }
+    range
+}
+
+#[cfg(feature = "lsp")]
+impl From<FoldingRange> for lsp_types::FoldingRange {
+    fn from(range: FoldingRange) -> Self {
+        Self {
+            start_line: range.start_line - 1,
+            start_character: None,
+            end_line: range.end_line - 1,
+            end_character: None,
+            kind: Some(match range.kind {
+                FoldingRangeKind::Comment => lsp_types::FoldingRangeKind::Comment,
+                FoldingRangeKind::Imports => lsp_types::FoldingRangeKind::Imports,
+                FoldingRangeKind::Region => lsp_types::FoldingRangeKind::Region,
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "lsp")]
+impl From<SelectionRange> for lsp_types::SelectionRange {
+    fn from(range: SelectionRange) -> Self {
+        Self {
+            range: lsp_types::Range {
+                start: range.start.into(),
+                end: range.end.into(),
+            },
+            parent: range.parent.map(|parent| Box::new((*parent).into())),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use super::*;
+
+fn folds(source: &str) -> Vec<(u32, u32, FoldingRangeKind)> {
+    folding_ranges(source)
+        .into_iter()
+        .map(|range| (range.start_line, range.end_line, range.kind))
+        .collect()
+}
+
+#[test]
+fn folding() {
+    let source = r#"import "array"
+import "strings"
+
+// A query that
+// spans several lines
+data = from(bucket: "b")
+    |> range(start: -1h)
+    |> map(
+        fn: (r) => ({r with
+            host: strings.toUpper(v: r.host),
+        }),
+    )
+
+f = (x) => {
+    y = x + 1
+
+    return y
+}
+a = [1, 2]
+"#;
+    assert_eq!(
+        folds(source),
+        vec![
+            (1, 2, FoldingRangeKind::Imports),
+            (4, 5, FoldingRangeKind::Comment),
+            (6, 12, FoldingRangeKind::Region),
+            (8, 12, FoldingRangeKind::Region),
+            (9, 11, FoldingRangeKind::Region),
+            (14, 18, FoldingRangeKind::Region),
+        ],
+    );
+}
+
+#[test]
+fn selection() {
+    let source = "x = f(a: 1 + 2)";
+    let ranges = selection_ranges(
+        source,
+        &[Position {
+            line: 1,
+            column: 10,
+        }],
+        ColumnEncoding::Utf8,
+    );
+
+    let mut extents = Vec::new();
+    let mut range = Some(&ranges[0]);
+    while let Some(r) = range {
+        extents.push(&source[r.start.column as usize - 1..r.end.column as usize - 1]);
+        range = r.parent.as_deref();
+    }
+    assert_eq!(
+        extents,
+        vec!["1", "1 + 2", "a: 1 + 2", "f(a: 1 + 2)", "x = f(a: 1 + 2)"],
+    );
+}
+
+#[test]
+fn selection_utf16() {
+    // `😀` is 4 bytes and 2 UTF-16 code units.
+    let source = "x = \"😀\" + y";
+    let ranges = selection_ranges(
+        source,
+        &[Position {
+            line: 1,
+            column: 12,
+        }],
+        ColumnEncoding::Utf16,
+    );
+    let range = &ranges[0];
+    assert_eq!((range.start.column, range.end.column), (12, 13));
+    let parent = range.parent.as_deref().unwrap();
+    assert_eq!((parent.start.column, parent.end.column), (5, 13));
+}
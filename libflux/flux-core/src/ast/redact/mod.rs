@@ -0,0 +1,97 @@
+//! Redaction of the literals of a query, so that it can be shared without the data it names.
+//!
+//! [`redact`] replaces the values of string and regular expression literals, and the text of
+//! string interpolations, with placeholders such as `"s1"` and `/r1/`. Equal values are replaced
+//! with the same placeholder, so the comparisons and repeated arguments of the query keep their
+//! structure. Bucket, measurement and tag names are strings and are replaced with them.
+//!
+//! Strings starting with `_` name the system columns of Flux, such as `_measurement` and
+//! `_field`, and are kept. The keys of records and members are kept too, since they name columns
+//! rather than values, as are the paths of imports. [`redact_source`] also removes comments.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::{
+    ast::{
+        self, comments::clear_comments, inline::for_each_child, walk::Node, Assignment, Expression,
+        File, Statement, StringExprPart,
+    },
+    formatter, parser,
+};
+
+/// Replaces the string and regular expression literals of `file` with placeholders. Returns the
+/// number of distinct values that were replaced.
+pub fn redact(file: &mut File) -> usize {
+    let mut redactor = Redactor::default();
+    for stmt in &mut file.body {
+        let expr = match stmt {
+            Statement::Expr(stmt) => &mut stmt.expression,
+            Statement::Variable(assgn) => &mut assgn.init,
+            Statement::Option(stmt) => match &mut stmt.assignment {
+                Assignment::Variable(assgn) => &mut assgn.init,
+                Assignment::Member(assgn) => &mut assgn.init,
+            },
+            Statement::Return(stmt) => &mut stmt.argument,
+            _ => continue,
+        };
+        redactor.redact(expr);
+    }
+    redactor.strings.len() + redactor.regexes.len()
+}
+
+/// Parses Flux source code, removes its comments, redacts its literals and formats it.
+pub fn redact_source(source: &str) -> Result<String> {
+    let mut file = parser::parse_string("".to_string(), source);
+    ast::check::check(Node::File(&file))?;
+    clear_comments(&mut file);
+    redact(&mut file);
+    formatter::convert_to_string(&file)
+}
+
+#[derive(Default)]
+struct Redactor {
+    strings: HashMap<String, String>,
+    regexes: HashMap<String, String>,
+}
+
+impl Redactor {
+    fn redact(&mut self, expr: &mut Expression) {
+        match expr {
+            Expression::StringLit(lit) => {
+                if !lit.value.starts_with('_') {
+                    lit.value = placeholder(&mut self.strings, "s", &lit.value);
+                    // The formatter prints the source of a literal when it has one
+                    lit.base.location.source = None;
+                }
+            }
+            Expression::Regexp(lit) => lit.value = placeholder(&mut self.regexes, "r", &lit.value),
+            Expression::StringExpr(string) => {
+                for part in &mut string.parts {
+                    match part {
+                        StringExprPart::Text(text) if !text.value.is_empty() => {
+                            text.value = placeholder(&mut self.strings, "s", &text.value)
+                        }
+                        StringExprPart::Text(_) => (),
+                        StringExprPart::Interpolated(part) => self.redact(&mut part.expression),
+                    }
+                }
+            }
+            _ => for_each_child(expr, &mut |child| self.redact(child)),
+        }
+    }
+}
+
+// Returns the placeholder of `value`, numbering the values of `placeholders` in the order they
+// are first seen.
+fn placeholder(placeholders: &mut HashMap<String, String>, prefix: &str, value: &str) -> String {
+    let next = placeholders.len() + 1;
+    placeholders
+        .entry(value.to_string())
+        .or_insert_with(|| format!("{}{}", prefix, next))
+        .clone()
+}
@@ -0,0 +1,42 @@
+use super::*;
+
+fn assert_redacted(source: &str, want: &str) {
+    assert_eq!(redact_source(source).unwrap().trim_end(), want);
+}
+
+#[test]
+fn redact_literals() {
+    assert_redacted(
+        r#"import "strings"
+
+// the production bucket
+from(bucket: "prod") |> filter(fn: (r) => r._measurement == "cpu" and r.host =~ /web-\d+/)
+from(bucket: "prod") |> filter(fn: (r) => r._measurement == "mem" and r["_field"] == "used")"#,
+        r#"import "strings"
+
+from(bucket: "s1") |> filter(fn: (r) => r._measurement == "s2" and r.host =~ /r1/)
+from(bucket: "s1") |> filter(fn: (r) => r._measurement == "s3" and r["_field"] == "s4")"#,
+    );
+}
+
+#[test]
+fn redact_interpolations() {
+    assert_redacted(
+        r#"f = (tag="web", n) => "host ${tag}-${n}"
+x = f(n: "_value")"#,
+        r#"f = (tag="s1", n) => "s2${tag}s3${n}"
+x = f(n: "_value")"#,
+    );
+}
+
+#[test]
+fn count_values() {
+    let mut file = parser::parse_string(
+        "".to_string(),
+        r#"a = "x"
+b = "x"
+c = /x/
+option o = {d: "y"}"#,
+    );
+    assert_eq!(redact(&mut file), 3);
+}
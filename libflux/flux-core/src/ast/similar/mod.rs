@@ -0,0 +1,118 @@
+//! Search for the queries most similar to a query.
+//!
+//! The [`Fingerprint`] of a query is the set of its features: the functions its pipelines call,
+//! the pairs of stages that feed into each other and the names of its parameters, as extracted
+//! by [`params::extract`]. Literal values are not part of it, so queries that only differ in
+//! the data they select have the same fingerprint. The similarity of two queries is the Jaccard
+//! index of their fingerprints, the number of shared features over the number of features.
+//!
+//! An [`Index`] holds the fingerprints of a corpus of queries and returns the queries most
+//! similar to a given one.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::BTreeSet;
+
+use crate::ast::{dataflow::Dataflow, params, File};
+
+/// The features of a query that its similarity to other queries is computed from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fingerprint {
+    features: BTreeSet<String>,
+}
+
+impl Fingerprint {
+    /// Computes the fingerprint of a file.
+    pub fn of(file: &File) -> Self {
+        let mut features = BTreeSet::new();
+        let graph = Dataflow::from_file(file);
+        let stages = graph.stages();
+        for stage in stages {
+            features.insert(format!("call {}", stage.function));
+        }
+        for (from, to) in graph.edges() {
+            features.insert(format!(
+                "edge {} |> {}",
+                stages[*from].function, stages[*to].function
+            ));
+        }
+        for parameter in params::extract(file).parameters {
+            features.insert(format!("param {}", parameter.name));
+        }
+        Fingerprint { features }
+    }
+
+    /// Returns the features of the fingerprint, in sorted order.
+    pub fn features(&self) -> impl Iterator<Item = &str> {
+        self.features.iter().map(String::as_str)
+    }
+
+    /// Returns the similarity of two fingerprints, from 0 for queries without shared features
+    /// to 1 for queries with the same features.
+    pub fn similarity(&self, other: &Fingerprint) -> f64 {
+        let shared = self.features.intersection(&other.features).count();
+        let all = self.features.len() + other.features.len() - shared;
+        if all == 0 {
+            // Two queries without features, e.g. two empty files, are the same.
+            return 1.0;
+        }
+        shared as f64 / all as f64
+    }
+}
+
+/// A query of an [`Index`] found by [`Index::similar`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Similar<'a> {
+    /// The name the query was inserted with.
+    pub name: &'a str,
+    /// The similarity of the query to the searched one.
+    pub score: f64,
+}
+
+/// The fingerprints of a corpus of queries.
+#[derive(Debug, Clone, Default)]
+pub struct Index {
+    entries: Vec<(String, Fingerprint)>,
+}
+
+impl Index {
+    /// Returns an empty index.
+    pub fn new() -> Self {
+        Index::default()
+    }
+
+    /// Adds a query to the index under `name`, e.g. the path it was read from.
+    pub fn insert(&mut self, name: impl Into<String>, file: &File) {
+        self.entries.push((name.into(), Fingerprint::of(file)));
+    }
+
+    /// Returns the number of queries in the index.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Reports whether the index contains no queries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns at most `limit` queries with the highest similarity to `query`, most similar
+    /// first. Queries that share no feature with `query` are not returned, and queries with
+    /// the same score are returned in the order they were inserted.
+    pub fn similar(&self, query: &Fingerprint, limit: usize) -> Vec<Similar> {
+        let mut similar: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(name, fingerprint)| Similar {
+                name,
+                score: query.similarity(fingerprint),
+            })
+            .filter(|similar| similar.score > 0.0)
+            .collect();
+        // The sort is stable, so ties keep the order of insertion.
+        similar.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        similar.truncate(limit);
+        similar
+    }
+}
@@ -0,0 +1,67 @@
+use super::*;
+use crate::parser::parse_string;
+
+fn fingerprint(source: &str) -> Fingerprint {
+    Fingerprint::of(&parse_string("similar".to_string(), source))
+}
+
+#[test]
+fn fingerprint_features() {
+    let fingerprint = fingerprint(
+        r#"from(bucket: "telegraf")
+    |> range(start: -1h)
+    |> filter(fn: (r) => r.host == "a")"#,
+    );
+    let features: Vec<_> = fingerprint.features().collect();
+    assert_eq!(
+        features,
+        vec![
+            "call filter",
+            "call from",
+            "call range",
+            "edge from |> range",
+            "edge range |> filter",
+            "param bucket",
+            "param host",
+            "param start",
+        ]
+    );
+}
+
+#[test]
+fn literals_do_not_matter() {
+    let a = fingerprint(r#"from(bucket: "a") |> range(start: -1h) |> max()"#);
+    let b = fingerprint(r#"from(bucket: "b") |> range(start: -5m) |> max()"#);
+    assert_eq!(a, b);
+    assert_eq!(a.similarity(&b), 1.0);
+}
+
+#[test]
+fn most_similar_first() {
+    let mut index = Index::new();
+    for (name, source) in [
+        ("max", r#"from(bucket: "a") |> range(start: -1h) |> max()"#),
+        (
+            "count",
+            r#"from(bucket: "a") |> range(start: -1h) |> count()"#,
+        ),
+        ("other", "x = 1"),
+        ("same", r#"from(bucket: "b") |> range(start: -2h) |> max()"#),
+    ] {
+        index.insert(name, &parse_string(name.to_string(), source));
+    }
+    assert_eq!(index.len(), 4);
+
+    let query = fingerprint(r#"from(bucket: "c") |> range(start: -1d) |> max()"#);
+    let names: Vec<_> = index.similar(&query, 10).iter().map(|s| s.name).collect();
+    assert_eq!(names, vec!["max", "same", "count"]);
+
+    let similar = index.similar(&query, 1);
+    assert_eq!(
+        similar,
+        vec![Similar {
+            name: "max",
+            score: 1.0
+        }]
+    );
+}
@@ -0,0 +1,208 @@
+//! Expansion of host-defined snippets before parsing.
+//!
+//! Queries written for a product may call pseudo-functions, such as `__dashboardAll()`, that the
+//! product replaces with Flux code before it runs the query. [`Snippets`] holds the templates a
+//! host registers by name and replaces each call `name()` of a source with the template of
+//! `name`. The [`Expansion`] maps locations in the expanded source back to the source the user
+//! wrote, so that diagnostics point at the call rather than at text the user never saw.
+//!
+//! Calls are found in the text of the source, outside of string literals and comments, before
+//! the source is parsed. Only calls without arguments are expanded, templates are inserted as
+//! they are, and the text of a template is not expanded again.
+//!
+//! Only the locations of diagnostics are mapped back to the original source.
+//! The nodes of the AST, and of the semantic graph, parsed from an expansion
+//! keep their locations in the expanded source.
+
+#[cfg(test)]
+mod tests;
+
+use std::{collections::BTreeMap, ops::Range};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::{lines::LineIndex, SourceLocation};
+
+/// Templates registered by name, expanded in place of calls to the name.
+///
+/// Snippets are read from a JSON or TOML map from names to templates, e.g.
+/// `{"__all": "from(bucket: \"all\") |> range(start: -1h)"}`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Snippets {
+    templates: BTreeMap<String, String>,
+}
+
+impl Snippets {
+    /// Returns an empty set of snippets, which expands nothing.
+    pub fn new() -> Self {
+        Snippets::default()
+    }
+
+    /// Registers `template` as the expansion of calls to `name`, returning the template that
+    /// was previously registered for `name`.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        template: impl Into<String>,
+    ) -> Option<String> {
+        self.templates.insert(name.into(), template.into())
+    }
+
+    /// Registers every snippet of `other`, replacing the templates registered under the same
+    /// names.
+    pub fn merge(&mut self, other: Snippets) {
+        self.templates.extend(other.templates);
+    }
+
+    /// Reports whether no snippets are registered.
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+
+    /// Expands the calls to registered snippets in `source`.
+    pub fn expand(&self, source: &str) -> Expansion {
+        let mut expanded = String::with_capacity(source.len());
+        let mut spans = Vec::new();
+        let mut copied = 0;
+        if !self.is_empty() {
+            for (call, template) in self.calls(source) {
+                expanded.push_str(&source[copied..call.start]);
+                let start = expanded.len();
+                expanded.push_str(template);
+                copied = call.end;
+                spans.push((call, start..expanded.len()));
+            }
+        }
+        expanded.push_str(&source[copied..]);
+        Expansion {
+            original_lines: LineIndex::new(source),
+            expanded_lines: LineIndex::new(&expanded),
+            original: source.to_string(),
+            source: expanded,
+            spans,
+        }
+    }
+
+    // Returns the byte range of each call to a registered snippet and its template.
+    fn calls<'a>(&'a self, source: &str) -> Vec<(Range<usize>, &'a str)> {
+        let bytes = source.as_bytes();
+        let skip_whitespace = |mut i: usize| {
+            while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            i
+        };
+        let mut calls = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'"' => {
+                    i += 1;
+                    while i < bytes.len() && bytes[i] != b'"' {
+                        i += if bytes[i] == b'\\' { 2 } else { 1 };
+                    }
+                    i += 1;
+                }
+                b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                    while i < bytes.len() && bytes[i] != b'\n' {
+                        i += 1;
+                    }
+                }
+                b if is_identifier_byte(b) => {
+                    let start = i;
+                    while i < bytes.len() && is_identifier_byte(bytes[i]) {
+                        i += 1;
+                    }
+                    // Words starting with a digit, such as `1h`, are literals.
+                    if b.is_ascii_digit() {
+                        continue;
+                    }
+                    let template = match self.templates.get(&source[start..i]) {
+                        // A member such as `r.name()` is not a call to a snippet.
+                        Some(template) if !source[..start].trim_end().ends_with('.') => template,
+                        _ => continue,
+                    };
+                    let open = skip_whitespace(i);
+                    if bytes.get(open) != Some(&b'(') {
+                        continue;
+                    }
+                    let close = skip_whitespace(open + 1);
+                    if bytes.get(close) == Some(&b')') {
+                        i = close + 1;
+                        calls.push((start..i, template.as_str()));
+                    }
+                }
+                _ => i += 1,
+            }
+        }
+        calls
+    }
+}
+
+// Non-ASCII bytes are treated as part of identifiers, so that identifiers with letters outside
+// of ASCII are not split.
+fn is_identifier_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || !b.is_ascii()
+}
+
+/// A source with the calls to snippets expanded, and the map from its locations to the
+/// original source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expansion {
+    /// The expanded source.
+    pub source: String,
+    original: String,
+    original_lines: LineIndex,
+    expanded_lines: LineIndex,
+    // The byte range of each call in the original source and of its template in the expanded
+    // source, in source order.
+    spans: Vec<(Range<usize>, Range<usize>)>,
+}
+
+impl Expansion {
+    /// Reports whether any call was expanded.
+    pub fn is_expanded(&self) -> bool {
+        !self.spans.is_empty()
+    }
+
+    /// Maps a location in the expanded source to the original source. The parts of a location
+    /// inside of a template are mapped to the call it replaced.
+    pub fn original_location(&self, location: &SourceLocation) -> SourceLocation {
+        let range = match self.expanded_lines.range(location) {
+            Some(range) if self.is_expanded() => range,
+            _ => return location.clone(),
+        };
+        let start = self.original_offset(range.start, false);
+        let end = self.original_offset(range.end, true).max(start);
+        SourceLocation {
+            file: location.file.clone(),
+            start: self.original_lines.position(start),
+            end: self.original_lines.position(end),
+            source: location
+                .source
+                .as_ref()
+                .map(|_| self.original[start..end].to_string()),
+        }
+    }
+
+    fn original_offset(&self, offset: usize, end: bool) -> usize {
+        // The last offsets of the expanded and of the original source known to correspond.
+        let (mut expanded_end, mut original_end) = (0, 0);
+        for (call, template) in &self.spans {
+            if offset < template.start {
+                break;
+            }
+            if offset < template.end {
+                return if end && offset > template.start {
+                    call.end
+                } else {
+                    call.start
+                };
+            }
+            expanded_end = template.end;
+            original_end = call.end;
+        }
+        original_end + (offset - expanded_end)
+    }
+}
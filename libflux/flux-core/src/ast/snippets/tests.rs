@@ -0,0 +1,87 @@
+use super::*;
+use crate::ast::Position;
+
+fn snippets() -> Snippets {
+    let mut snippets = Snippets::new();
+    snippets.register("__all", "from(bucket: \"all\")\n    |> range(start: -1h)");
+    snippets.register("__cpu", "filter(fn: (r) => r._measurement == \"cpu\")");
+    snippets
+}
+
+fn location(start: (u32, u32), end: (u32, u32)) -> SourceLocation {
+    SourceLocation {
+        file: None,
+        start: Position {
+            line: start.0,
+            column: start.1,
+        },
+        end: Position {
+            line: end.0,
+            column: end.1,
+        },
+        source: None,
+    }
+}
+
+#[test]
+fn expand_calls() {
+    let expansion = snippets().expand("__all() |> __cpu ( ) |> yield()");
+    assert_eq!(
+        expansion.source,
+        "from(bucket: \"all\")\n    |> range(start: -1h) |> filter(fn: (r) => r._measurement == \"cpu\") |> yield()"
+    );
+    assert!(expansion.is_expanded());
+}
+
+#[test]
+fn skip_strings_comments_and_members() {
+    let source = r#"// __all()
+x = "__all() \" __all()"
+r.__all()
+__all(n: 1)
+__all2()
+1__all()"#;
+    let expansion = snippets().expand(source);
+    assert_eq!(expansion.source, source);
+    assert!(!expansion.is_expanded());
+}
+
+#[test]
+fn no_snippets() {
+    let expansion = Snippets::new().expand("__all()");
+    assert_eq!(expansion.source, "__all()");
+    assert_eq!(
+        expansion.original_location(&location((1, 1), (1, 8))),
+        location((1, 1), (1, 8))
+    );
+}
+
+#[test]
+fn map_locations() {
+    let expansion = snippets().expand("x = __all()\ny = x |> max()");
+    assert_eq!(
+        expansion.source,
+        "x = from(bucket: \"all\")\n    |> range(start: -1h)\ny = x |> max()"
+    );
+
+    // Before the call.
+    assert_eq!(
+        expansion.original_location(&location((1, 1), (1, 2))),
+        location((1, 1), (1, 2))
+    );
+    // Inside of the template.
+    assert_eq!(
+        expansion.original_location(&location((2, 8), (2, 25))),
+        location((1, 5), (1, 12))
+    );
+    // From before the call to inside of the template.
+    assert_eq!(
+        expansion.original_location(&location((1, 1), (1, 9))),
+        location((1, 1), (1, 12))
+    );
+    // After the call.
+    assert_eq!(
+        expansion.original_location(&location((3, 5), (3, 14))),
+        location((2, 5), (2, 14))
+    );
+}
@@ -0,0 +1,158 @@
+//! Buckets, measurements and tag keys that a query refers to by name.
+//!
+//! Names are only found where they are written literally: string literals passed as the
+//! `bucket`, `measurement` or `tag` argument of a call, string literals in the `columns`
+//! argument of a call, and comparisons of a column of a record with a string literal, such as
+//! `r._measurement == "cpu"` or `r.host == "a"`. Names computed at runtime are not found.
+
+#[cfg(test)]
+mod tests;
+
+use crate::ast::{
+    walk::{self, Node, Visitor},
+    CallExpr, Expression, Operator, PropertyKey, SourceLocation, StringLit,
+};
+
+/// What a [`Reference`] names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReferenceKind {
+    /// A bucket.
+    Bucket,
+    /// A measurement.
+    Measurement,
+    /// A tag key, or any other column that is not a system column starting with `_`.
+    TagKey,
+}
+
+/// A bucket, measurement or tag key named in a query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    /// What the name refers to.
+    pub kind: ReferenceKind,
+    /// The name.
+    pub value: String,
+    /// Location of the name in the source.
+    pub location: SourceLocation,
+    /// The function called by the innermost call that contains the name, e.g. `from` or
+    /// `schema.tagValues`. Empty if the name is outside of any call.
+    pub context: String,
+}
+
+/// Returns the buckets, measurements and tag keys named in a node, in source order.
+pub fn references(node: Node) -> Vec<Reference> {
+    let mut collector = ReferenceCollector {
+        calls: Vec::new(),
+        references: Vec::new(),
+    };
+    walk::walk(&mut collector, node);
+    collector.references
+}
+
+struct ReferenceCollector {
+    // The names of the functions called by the enclosing calls, innermost last.
+    calls: Vec<String>,
+    references: Vec<Reference>,
+}
+
+impl ReferenceCollector {
+    fn push(&mut self, kind: ReferenceKind, value: &str, location: &SourceLocation) {
+        self.references.push(Reference {
+            kind,
+            value: value.to_string(),
+            location: location.clone(),
+            context: self.calls.last().cloned().unwrap_or_default(),
+        });
+    }
+
+    fn arguments(&mut self, call: &CallExpr) {
+        let properties = call.arguments.iter().flat_map(|arg| match arg {
+            Expression::Object(obj) => obj.properties.as_slice(),
+            _ => &[],
+        });
+        for prop in properties {
+            let kind = match prop.key.key() {
+                "bucket" => ReferenceKind::Bucket,
+                "measurement" => ReferenceKind::Measurement,
+                "tag" => ReferenceKind::TagKey,
+                "columns" => {
+                    if let Some(Expression::Array(arr)) = &prop.value {
+                        for item in &arr.elements {
+                            if let Expression::StringLit(lit) = &item.expression {
+                                self.column(lit);
+                            }
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            if let Some(Expression::StringLit(lit)) = &prop.value {
+                self.push(kind, &lit.value, &lit.base.location);
+            }
+        }
+    }
+
+    fn column(&mut self, lit: &StringLit) {
+        if !lit.value.starts_with('_') {
+            self.push(ReferenceKind::TagKey, &lit.value, &lit.base.location);
+        }
+    }
+
+    // Records the column compared with a string literal, and the literal if the column is the
+    // measurement.
+    fn comparison(&mut self, column: &Expression, value: &Expression) {
+        let (member, value) = match (column, value) {
+            (Expression::Member(member), Expression::StringLit(value)) => (member, value),
+            _ => return,
+        };
+        let location = match &member.property {
+            PropertyKey::Identifier(id) => &id.base.location,
+            PropertyKey::StringLit(lit) => &lit.base.location,
+        };
+        match member.property.key() {
+            "_measurement" => self.push(
+                ReferenceKind::Measurement,
+                &value.value,
+                &value.base.location,
+            ),
+            key if !key.starts_with('_') => self.push(ReferenceKind::TagKey, key, location),
+            _ => (),
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for ReferenceCollector {
+    fn visit(&mut self, node: Node<'a>) -> bool {
+        match node {
+            Node::CallExpr(call) => {
+                self.calls.push(callee_name(&call.callee));
+                self.arguments(call);
+            }
+            Node::BinaryExpr(expr) => {
+                if let Operator::EqualOperator | Operator::NotEqualOperator = expr.operator {
+                    self.comparison(&expr.left, &expr.right);
+                    self.comparison(&expr.right, &expr.left);
+                }
+            }
+            _ => (),
+        }
+        true
+    }
+
+    fn done(&mut self, node: Node<'a>) {
+        if let Node::CallExpr(_) = node {
+            self.calls.pop();
+        }
+    }
+}
+
+fn callee_name(callee: &Expression) -> String {
+    match callee {
+        Expression::Identifier(id) => id.name.clone(),
+        Expression::Member(member) => {
+            format!("{}.{}", callee_name(&member.object), member.property.key())
+        }
+        Expression::Paren(paren) => callee_name(&paren.expression),
+        _ => String::new(),
+    }
+}
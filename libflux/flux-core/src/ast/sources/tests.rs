@@ -0,0 +1,84 @@
+use super::*;
+use crate::parser::parse_string;
+
+fn find(source: &str) -> Vec<(ReferenceKind, String, String, u32)> {
+    let file = parse_string("sources".to_string(), source);
+    references(Node::File(&file))
+        .into_iter()
+        .map(|r| (r.kind, r.value, r.context, r.location.start.column))
+        .collect()
+}
+
+#[test]
+fn buckets_and_measurements() {
+    assert_eq!(
+        find(
+            r#"from(bucket: "telegraf") |> filter(fn: (r) => r._measurement == "cpu" and r.host != "a") |> to(bucket: "out")"#
+        ),
+        vec![
+            (
+                ReferenceKind::Bucket,
+                "telegraf".to_string(),
+                "from".to_string(),
+                14
+            ),
+            (
+                ReferenceKind::Measurement,
+                "cpu".to_string(),
+                "filter".to_string(),
+                65
+            ),
+            (
+                ReferenceKind::TagKey,
+                "host".to_string(),
+                "filter".to_string(),
+                77
+            ),
+            (
+                ReferenceKind::Bucket,
+                "out".to_string(),
+                "to".to_string(),
+                104
+            ),
+        ],
+    );
+}
+
+#[test]
+fn schema_and_columns() {
+    assert_eq!(
+        find(
+            r#"import "influxdata/influxdb/schema"
+schema.tagValues(bucket: "b", tag: "region")
+data |> group(columns: ["_time", "host"])
+x = r["_measurement"] == "mem"
+"#
+        ),
+        vec![
+            (
+                ReferenceKind::Bucket,
+                "b".to_string(),
+                "schema.tagValues".to_string(),
+                26
+            ),
+            (
+                ReferenceKind::TagKey,
+                "region".to_string(),
+                "schema.tagValues".to_string(),
+                36
+            ),
+            (
+                ReferenceKind::TagKey,
+                "host".to_string(),
+                "group".to_string(),
+                34
+            ),
+            (
+                ReferenceKind::Measurement,
+                "mem".to_string(),
+                "".to_string(),
+                26
+            ),
+        ],
+    );
+}
@@ -0,0 +1,129 @@
+//! Comments that suppress lint rules and warnings.
+//!
+//! A comment of the form
+//!
+//! ```text
+//! // flux-lint: ignore[unused-symbol, no-explicit-yield]
+//! ```
+//!
+//! suppresses the named lint rules and warnings on the line of the code that follows it, so that
+//! new checks can be adopted without fixing all existing code at once. Warnings are named by
+//! [`WarningKind::name`](crate::semantic::WarningKind::name) and lint rules by their name.
+//!
+//! A comment is attached to the token that follows it, see [`ast::comments`](super::comments),
+//! so only comments that precede the first token of a node or the operator of an operator
+//! expression, such as `|>`, suppress anything.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashSet;
+
+use crate::ast::{
+    comments::{attachments, Slot},
+    walk::Node,
+    SourceLocation,
+};
+
+/// The name of the rule and warning reporting suppressions that suppress nothing.
+pub const UNUSED_SUPPRESSION: &str = "unused-suppression";
+
+// The prefix of the comments that suppress rules and warnings, after `//`.
+const PREFIX: &str = "flux-lint: ignore[";
+
+/// A comment suppressing rules and warnings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suppression {
+    /// The names of the suppressed rules and warnings.
+    pub names: Vec<String>,
+    /// The location of the node the comment is attached to.
+    pub location: SourceLocation,
+    // The line the suppression applies to.
+    line: u32,
+}
+
+/// The suppression comments of a tree, and which of their names suppressed anything.
+#[derive(Debug, Clone, Default)]
+pub struct Suppressions {
+    suppressions: Vec<Suppression>,
+    // The index of each suppression and the name it suppressed something with.
+    used: HashSet<(usize, String)>,
+}
+
+impl Suppressions {
+    /// Collects the suppression comments of the tree rooted at `node`.
+    pub fn new(node: Node) -> Self {
+        let mut suppressions = Vec::new();
+        for attachment in attachments(node) {
+            if attachment.slot != Slot::Base {
+                continue;
+            }
+            let line = match &attachment.node {
+                Node::PipeExpr(pipe) => pipe.call.base.location.start.line,
+                Node::BinaryExpr(binary) => binary.right.base().location.start.line,
+                Node::LogicalExpr(logical) => logical.right.base().location.start.line,
+                node => node.base().location.start.line,
+            };
+            for comment in &attachment.comments {
+                if let Some(names) = parse(&comment.text) {
+                    suppressions.push(Suppression {
+                        names,
+                        location: attachment.node.base().location.clone(),
+                        line,
+                    });
+                }
+            }
+        }
+        Suppressions {
+            suppressions,
+            used: HashSet::new(),
+        }
+    }
+
+    /// Reports whether the rule or warning `name` reported at `location` is suppressed, and
+    /// records that the suppression was used.
+    pub fn suppresses(&mut self, name: &str, location: &SourceLocation) -> bool {
+        let mut suppressed = false;
+        for (i, suppression) in self.suppressions.iter().enumerate() {
+            if suppression.line == location.start.line
+                && suppression.location.file == location.file
+                && suppression.names.iter().any(|n| n == name)
+            {
+                self.used.insert((i, name.to_string()));
+                suppressed = true;
+            }
+        }
+        suppressed
+    }
+
+    /// Returns the names for which `known` holds that did not suppress anything, with the
+    /// location of their suppression. Names that are not known may be checked by another tool.
+    pub fn unused(&self, known: impl Fn(&str) -> bool) -> Vec<(&str, &SourceLocation)> {
+        let mut unused = Vec::new();
+        for (i, suppression) in self.suppressions.iter().enumerate() {
+            for name in &suppression.names {
+                if known(name) && !self.used.contains(&(i, name.clone())) {
+                    unused.push((name.as_str(), &suppression.location));
+                }
+            }
+        }
+        unused
+    }
+}
+
+// Returns the names suppressed by a comment, if it is a suppression comment.
+fn parse(comment: &str) -> Option<Vec<String>> {
+    let names = comment
+        .trim_start_matches("//")
+        .trim()
+        .strip_prefix(PREFIX)?
+        .strip_suffix(']')?;
+    Some(
+        names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
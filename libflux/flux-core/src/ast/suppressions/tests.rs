@@ -0,0 +1,52 @@
+use super::*;
+use crate::{
+    ast::{Position, SourceLocation},
+    parser::parse_string,
+};
+
+fn suppressions(source: &str) -> Suppressions {
+    Suppressions::new(Node::File(&parse_string("".to_string(), source)))
+}
+
+fn line(line: u32) -> SourceLocation {
+    SourceLocation {
+        start: Position { line, column: 1 },
+        end: Position { line, column: 2 },
+        ..SourceLocation::default()
+    }
+}
+
+#[test]
+fn parse_comments() {
+    assert_eq!(
+        parse("// flux-lint: ignore[a, b ,c]\n"),
+        Some(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+    );
+    assert_eq!(parse("//flux-lint: ignore[]"), Some(vec![]));
+    assert_eq!(parse("// flux-lint: ignore a\n"), None);
+    assert_eq!(parse("// ignore[a]\n"), None);
+}
+
+#[test]
+fn suppress_next_line() {
+    let mut suppressions = suppressions(
+        r#"// flux-lint: ignore[a]
+x = 1
+// flux-lint: ignore[b, c]
+from(bucket: "b")
+    // flux-lint: ignore[d]
+    |> range(start: -1h)"#,
+    );
+    assert!(suppressions.suppresses("a", &line(2)));
+    assert!(!suppressions.suppresses("a", &line(4)));
+    assert!(suppressions.suppresses("b", &line(4)));
+    assert!(!suppressions.suppresses("d", &line(4)));
+    assert!(suppressions.suppresses("d", &line(6)));
+
+    let unused: Vec<_> = suppressions
+        .unused(|name| name != "e")
+        .into_iter()
+        .map(|(name, location)| (name, location.start.line))
+        .collect();
+    assert_eq!(unused, vec![("c", 4)]);
+}
@@ -0,0 +1,114 @@
+//! Compilation of exploratory Flux code into a task.
+//!
+//! Code written while exploring data, e.g. in a notebook, usually looks at several results on
+//! its way to the one that matters. [`compile_task`] keeps the last result as the output of the
+//! task and removes the other expression statements, replaces any `option task` with the given
+//! [`TaskOptions`] and sorts and deduplicates the imports. Variables, functions and other
+//! options are kept, since the output may depend on them.
+
+#[cfg(test)]
+mod tests;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::{
+    ast::{self, walk::Node, Assignment, Expression, File, Statement},
+    formatter::{self, escape_string},
+    parser,
+};
+
+/// The schedule and name of a task.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskOptions {
+    /// The name of the task.
+    pub name: String,
+    /// How often the task runs, as a Flux duration such as `1h`.
+    pub every: Option<String>,
+    /// When the task runs, as a cron expression such as `0 * * * *`.
+    pub cron: Option<String>,
+    /// How long the task waits after its scheduled time before it runs, as a Flux duration.
+    pub offset: Option<String>,
+}
+
+impl TaskOptions {
+    // Returns the `option task` statement of the options.
+    fn statement(&self) -> Result<Statement> {
+        let schedule = match (&self.every, &self.cron) {
+            (Some(every), None) => format!("every: {}", duration("every", every)?),
+            (None, Some(cron)) => format!("cron: \"{}\"", escape_string(cron)),
+            _ => bail!("a task must run either every duration or on a cron schedule"),
+        };
+        let offset = match &self.offset {
+            Some(offset) => format!(", offset: {}", duration("offset", offset)?),
+            None => String::new(),
+        };
+        let source = format!(
+            "option task = {{name: \"{}\", {}{}}}",
+            escape_string(&self.name),
+            schedule,
+            offset
+        );
+        let file = parser::parse_string("".to_string(), &source);
+        ast::check::check(Node::File(&file))
+            .map_err(|err| anyhow!("invalid task options: {}", err))?;
+        match file.body.into_iter().next() {
+            Some(stmt @ Statement::Option(_)) => Ok(stmt),
+            _ => bail!("invalid task options: {}", source),
+        }
+    }
+}
+
+/// Compiles exploratory Flux code into a task that runs on the schedule of `options`, and
+/// formats it.
+pub fn compile_task(source: &str, options: &TaskOptions) -> Result<String> {
+    let mut file = parser::parse_string("".to_string(), source);
+    ast::check::check(Node::File(&file))?;
+    compile(&mut file, options)?;
+    formatter::convert_to_string_with(
+        &file,
+        formatter::Options {
+            sort_imports: true,
+            ..formatter::Options::default()
+        },
+    )
+}
+
+fn compile(file: &mut File, options: &TaskOptions) -> Result<()> {
+    let output = file
+        .body
+        .iter()
+        .rposition(|stmt| matches!(stmt, Statement::Expr(_)))
+        .ok_or_else(|| anyhow!("the code has no result to output from the task"))?;
+    let mut index = 0;
+    file.body.retain(|stmt| {
+        index += 1;
+        match stmt {
+            Statement::Expr(_) => index - 1 == output,
+            Statement::Option(option) => !is_task_option(&option.assignment),
+            _ => true,
+        }
+    });
+    file.body.insert(0, options.statement()?);
+    formatter::sort_imports(file);
+    Ok(())
+}
+
+// Checks that `value` of the option `name` is a duration literal.
+fn duration<'a>(name: &str, value: &'a str) -> Result<&'a str> {
+    let file = parser::parse_string("".to_string(), value);
+    match &file.body[..] {
+        [Statement::Expr(stmt)] if matches!(stmt.expression, Expression::Duration(_)) => {
+            ast::check::check(Node::File(&file))
+                .map_err(|err| anyhow!("invalid task {}: {}", name, err))?;
+            Ok(value)
+        }
+        _ => bail!("invalid task {}: `{}` is not a duration", name, value),
+    }
+}
+
+fn is_task_option(assignment: &Assignment) -> bool {
+    match assignment {
+        Assignment::Variable(assgn) => assgn.id.name == "task",
+        Assignment::Member(_) => false,
+    }
+}
@@ -0,0 +1,72 @@
+use super::*;
+
+fn every(every: &str) -> TaskOptions {
+    TaskOptions {
+        name: "counts".to_string(),
+        every: Some(every.to_string()),
+        ..TaskOptions::default()
+    }
+}
+
+#[test]
+fn keep_last_result() {
+    let task = compile_task(
+        r#"import "strings"
+import "array"
+import "strings"
+
+data = array.from(rows: [{x: 1}])
+
+count(tables: data)
+
+option task = {name: "old", every: 1m}
+
+max(tables: data)"#,
+        &every("1h"),
+    )
+    .unwrap();
+    assert_eq!(
+        task,
+        r#"import "array"
+import "strings"
+
+option task = {name: "counts", every: 1h}
+
+data = array.from(rows: [{x: 1}])
+
+max(tables: data)
+"#
+    );
+}
+
+#[test]
+fn cron_and_offset() {
+    let options = TaskOptions {
+        name: "a \"quoted\" name".to_string(),
+        cron: Some("0 * * * *".to_string()),
+        offset: Some("5m".to_string()),
+        ..TaskOptions::default()
+    };
+    let task = compile_task("max(tables: data)", &options).unwrap();
+    assert_eq!(
+        task,
+        r#"option task = {name: "a \"quoted\" name", cron: "0 * * * *", offset: 5m}
+
+max(tables: data)
+"#
+    );
+}
+
+#[test]
+fn invalid_options() {
+    let mut options = every("1h");
+    options.cron = Some("0 * * * *".to_string());
+    assert!(compile_task("max(tables: data)", &options).is_err());
+    assert!(compile_task("max(tables: data)", &TaskOptions::default()).is_err());
+    assert!(compile_task("max(tables: data)", &every("an hour")).is_err());
+}
+
+#[test]
+fn no_result() {
+    assert!(compile_task("x = 1", &every("1h")).is_err());
+}
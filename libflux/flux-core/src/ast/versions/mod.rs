@@ -0,0 +1,143 @@
+//! Versioned JSON serialization of the AST.
+//!
+//! The JSON form of the AST changes when nodes are added to it. [`to_json`] records the version
+//! of the format in the `version` key of the root node, usually a package or a file, and can emit
+//! older versions for consumers that have not been updated, failing if the AST uses nodes an
+//! older version cannot represent. [`from_json`] reads any known version, upgrading older ones.
+//!
+//! Each change of the format is described by a [`Shim`] converting ASTs between the version it
+//! introduced and the previous one:
+//!
+//! - Version 2 adds typed holes, identifiers named [`TYPED_HOLE`](crate::ast::TYPED_HOLE).
+//!   Version 1 represents them as bad expressions with the text `_?`.
+//!
+//! JSON without a `version` key, such as the JSON written before the format was versioned, is
+//! read as version 1.
+
+#[cfg(test)]
+mod tests;
+
+use anyhow::{bail, Context as _, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use crate::ast::TYPED_HOLE;
+
+/// The oldest version of the JSON format that can be read or written.
+pub const OLDEST_VERSION: u32 = 1;
+
+/// The version of the JSON format written by default.
+pub const CURRENT_VERSION: u32 = OLDEST_VERSION + SHIMS.len() as u32;
+
+/// Converts ASTs between a version of the JSON format and the previous version.
+pub struct Shim {
+    /// The version introduced by the change.
+    pub version: u32,
+    /// Describes the change.
+    pub description: &'static str,
+    /// Converts an AST from the previous version.
+    pub upgrade: fn(&mut Value) -> Result<()>,
+    /// Converts an AST to the previous version.
+    pub downgrade: fn(&mut Value) -> Result<()>,
+}
+
+/// The changes of the JSON format, oldest first. The shim of version `n` is at index `n - 2`.
+pub const SHIMS: &[Shim] = &[Shim {
+    version: 2,
+    description: "typed holes",
+    upgrade: upgrade_typed_holes,
+    downgrade: downgrade_typed_holes,
+}];
+
+fn upgrade_typed_holes(value: &mut Value) -> Result<()> {
+    for_each_object(value, &mut |obj| {
+        if obj.get("type") == Some(&Value::from("BadExpression"))
+            && obj.get("text") == Some(&Value::from(TYPED_HOLE))
+            && obj.get("expression").map_or(true, Value::is_null)
+        {
+            obj.remove("text");
+            obj.remove("expression");
+            obj.insert("type".to_string(), Value::from("Identifier"));
+            obj.insert("name".to_string(), Value::from(TYPED_HOLE));
+        }
+    });
+    Ok(())
+}
+
+fn downgrade_typed_holes(value: &mut Value) -> Result<()> {
+    for_each_object(value, &mut |obj| {
+        if obj.get("type") == Some(&Value::from("Identifier"))
+            && obj.get("name") == Some(&Value::from(TYPED_HOLE))
+        {
+            obj.remove("name");
+            obj.insert("type".to_string(), Value::from("BadExpression"));
+            obj.insert("text".to_string(), Value::from(TYPED_HOLE));
+            obj.insert("expression".to_string(), Value::Null);
+        }
+    });
+    Ok(())
+}
+
+// Calls `f` on every object of `value`, children first.
+fn for_each_object(value: &mut Value, f: &mut impl FnMut(&mut Map<String, Value>)) {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                for_each_object(value, f);
+            }
+        }
+        Value::Object(obj) => {
+            for value in obj.values_mut() {
+                for_each_object(value, f);
+            }
+            f(obj);
+        }
+        _ => (),
+    }
+}
+
+fn check_version(version: u32) -> Result<()> {
+    if !(OLDEST_VERSION..=CURRENT_VERSION).contains(&version) {
+        bail!(
+            "unsupported AST version {}, expected a version from {} to {}",
+            version,
+            OLDEST_VERSION,
+            CURRENT_VERSION,
+        );
+    }
+    Ok(())
+}
+
+/// Serializes an AST node to the given version of the JSON format.
+pub fn to_json<T: Serialize>(node: &T, version: u32) -> Result<Value> {
+    check_version(version)?;
+    let mut value = serde_json::to_value(node)?;
+    for shim in SHIMS.iter().rev().filter(|shim| shim.version > version) {
+        (shim.downgrade)(&mut value)
+            .with_context(|| format!("downgrading to AST version {}", shim.version - 1))?;
+    }
+    if let Value::Object(obj) = &mut value {
+        obj.insert("version".to_string(), Value::from(version));
+    }
+    Ok(value)
+}
+
+/// Deserializes an AST node from any supported version of the JSON format.
+pub fn from_json<T: DeserializeOwned>(mut value: Value) -> Result<T> {
+    let version = match value.get("version") {
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .context("AST version must be a non-negative integer")?,
+        None => OLDEST_VERSION,
+    };
+    check_version(version)?;
+    for shim in SHIMS.iter().filter(|shim| shim.version > version) {
+        (shim.upgrade)(&mut value)
+            .with_context(|| format!("upgrading to AST version {}", shim.version))?;
+    }
+    if let Value::Object(obj) = &mut value {
+        obj.remove("version");
+    }
+    Ok(serde_json::from_value(value)?)
+}
@@ -0,0 +1,65 @@
+use serde_json::json;
+
+use super::*;
+use crate::{
+    ast::Package,
+    parser::{parse_string, Parser},
+};
+
+fn parse(source: &str) -> Package {
+    Package::from(parse_string("main.flux".to_string(), source))
+}
+
+#[test]
+fn round_trip() {
+    let pkg = parse("x = 1\nbuiltin f : (a: A) => A where A: Record");
+    for version in OLDEST_VERSION..=CURRENT_VERSION {
+        let value = to_json(&pkg, version).unwrap();
+        assert_eq!(value["version"], json!(version));
+        assert_eq!(from_json::<Package>(value).unwrap(), pkg);
+    }
+}
+
+#[test]
+fn current_version() {
+    assert_eq!(CURRENT_VERSION, 2);
+
+    let pkg = parse(r#"builtin f : (a: "x") => int"#);
+    let mut value = serde_json::to_value(&pkg).unwrap();
+    value["version"] = json!(CURRENT_VERSION);
+    assert_eq!(to_json(&pkg, CURRENT_VERSION).unwrap(), value);
+}
+
+#[test]
+fn typed_holes() {
+    let file = Parser::new("x = 1 + _?")
+        .with_typed_holes()
+        .parse_file("main.flux".to_string());
+    let pkg = Package::from(file);
+    let hole = |value: &Value| value["files"][0]["body"][0]["init"]["right"].clone();
+
+    let v2 = to_json(&pkg, 2).unwrap();
+    assert_eq!(hole(&v2)["type"], json!("Identifier"));
+    assert_eq!(hole(&v2)["name"], json!("_?"));
+
+    let v1 = to_json(&pkg, 1).unwrap();
+    assert_eq!(v1["version"], json!(1));
+    assert_eq!(hole(&v1)["type"], json!("BadExpression"));
+    assert_eq!(hole(&v1)["text"], json!("_?"));
+    assert_eq!(hole(&v1)["expression"], Value::Null);
+    assert_eq!(hole(&v1)["location"], hole(&v2)["location"]);
+
+    assert_eq!(from_json::<Package>(v1).unwrap(), pkg);
+}
+
+#[test]
+fn unversioned() {
+    let pkg = parse("x = 1");
+    let value = serde_json::to_value(&pkg).unwrap();
+    assert_eq!(from_json::<Package>(value).unwrap(), pkg);
+
+    let mut value = to_json(&pkg, CURRENT_VERSION).unwrap();
+    value["version"] = json!(CURRENT_VERSION + 1);
+    assert!(from_json::<Package>(value).is_err());
+    assert!(to_json(&pkg, 0).is_err());
+}
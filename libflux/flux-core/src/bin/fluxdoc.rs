@@ -1,6 +1,6 @@
 use std::{
     collections::BinaryHeap,
-    fs::File,
+    fs::{self, File},
     io::{self, Write},
     path::{Path, PathBuf},
     process::Command,
@@ -8,15 +8,23 @@ use std::{
 
 use anyhow::{bail, Context, Result};
 use rayon::prelude::*;
-use structopt::StructOpt;
+use structopt::{clap::Shell, StructOpt};
 
 use fluxcore::{
+    cli::{config::ConfigArgs, man},
     doc::{self, example},
-    semantic::{bootstrap, env::Environment, Analyzer},
+    semantic::{
+        bootstrap::{self, StdlibIndex},
+        env::Environment,
+        Analyzer, AnalyzerConfig, PackageExports,
+    },
 };
 
 #[derive(Debug, StructOpt)]
-#[structopt(about = "generate and validate Flux source code documentation")]
+#[structopt(
+    name = "fluxdoc",
+    about = "generate and validate Flux source code documentation"
+)]
 enum FluxDoc {
     /// Dump JSON encoding of documentation from Flux source code.
     Dump {
@@ -53,6 +61,32 @@ enum FluxDoc {
         /// Limit the number of diagnostics to report. Default 10. 0 means no limit.
         #[structopt(short, long)]
         limit: Option<i64>,
+        /// Report the exported values missing documentation per package instead of linting.
+        #[structopt(long)]
+        coverage: bool,
+    },
+    /// Type check the examples in the documentation of the Flux standard library
+    CheckExamples {
+        /// Directory containing the Flux standard library source code.
+        #[structopt(short, long, parse(from_os_str))]
+        dir: PathBuf,
+        /// List the examples that passed, grouped by the package or member they document.
+        #[structopt(short, long)]
+        verbose: bool,
+        #[structopt(flatten)]
+        config: ConfigArgs,
+    },
+    /// Write the man page of this command.
+    GenMan {
+        /// Directory to write the man page to, stdout if not present.
+        #[structopt(short, long, parse(from_os_str))]
+        outdir: Option<PathBuf>,
+    },
+    /// Print a shell completion script to stdout.
+    Completions {
+        /// The shell to generate completions for.
+        #[structopt(possible_values = &Shell::variants(), case_insensitive = true)]
+        shell: Shell,
     },
 }
 
@@ -81,11 +115,50 @@ fn main() -> Result<()> {
             flux_cmd_path,
             dir,
             limit,
-        } => lint(stdlib_dir.as_deref(), flux_cmd_path.as_deref(), &dir, limit)?,
+            coverage,
+        } => {
+            if coverage {
+                report_coverage(stdlib_dir.as_deref(), &dir)?
+            } else {
+                lint(stdlib_dir.as_deref(), flux_cmd_path.as_deref(), &dir, limit)?
+            }
+        }
+        FluxDoc::CheckExamples {
+            dir,
+            verbose,
+            config,
+        } => {
+            let resolved = config.resolve()?;
+            if !config.print_if_requested(&resolved)? {
+                check_examples(&dir, verbose, resolved)?
+            }
+        }
+        FluxDoc::GenMan { outdir } => gen_man(outdir.as_deref())?,
+        FluxDoc::Completions { shell } => {
+            FluxDoc::clap().gen_completions_to("fluxdoc", shell, &mut io::stdout())
+        }
     };
     Ok(())
 }
 
+fn gen_man(outdir: Option<&Path>) -> Result<()> {
+    let page = man::man_page(
+        &FluxDoc::clap(),
+        &[
+            (0, "The command succeeded."),
+            (1, "The command failed or found problems."),
+        ],
+    );
+    match outdir {
+        Some(outdir) => {
+            fs::create_dir_all(outdir)?;
+            fs::write(outdir.join("fluxdoc.1"), page)?;
+        }
+        None => print!("{}", page),
+    }
+    Ok(())
+}
+
 const DEFAULT_STDLIB_PATH: &str = "./stdlib-compiled";
 const DEFAULT_FLUX_CMD_PATH: &str = "flux";
 
@@ -120,6 +193,17 @@ fn dump(
     };
 
     let (mut docs, diagnostics) = parse_docs(stdlib_dir, dir).context("parsing source code")?;
+    match StdlibIndex::read(stdlib_dir).context("reading the stdlib index")? {
+        Some(index) => {
+            for d in docs.iter_mut() {
+                doc::add_used_by(d, &index);
+            }
+        }
+        None => log::warn!(
+            "{} has no stdlib index, the docs do not list where values are used",
+            stdlib_dir.display()
+        ),
+    }
     if !diagnostics.is_empty() {
         bail!(
             "found {} diagnostics when building documentation:\n{}",
@@ -214,6 +298,74 @@ fn lint(
     }
 }
 
+fn report_coverage(stdlib_dir: Option<&Path>, dir: &Path) -> Result<()> {
+    let (stdlib_dir, _) = resolve_default_paths(stdlib_dir, None);
+    let (docs, _) = parse_docs_with_types(stdlib_dir, dir)?;
+    let (mut total, mut documented) = (0, 0);
+    for (doc, types) in &docs {
+        let coverage = doc::coverage(doc, types);
+        total += coverage.total;
+        documented += coverage.documented;
+        println!(
+            "{:>6.1}% {} ({}/{})",
+            coverage.percent(),
+            coverage.path,
+            coverage.documented,
+            coverage.total
+        );
+        for name in &coverage.undocumented {
+            println!("        missing comment for {}.{}", coverage.path, name);
+        }
+        for (name, param) in &coverage.undocumented_parameters {
+            println!(
+                "        missing parameter {} of {}.{}",
+                param, coverage.path, name
+            );
+        }
+    }
+    if total > 0 {
+        println!(
+            "{:>6.1}% total ({}/{})",
+            documented as f64 * 100.0 / total as f64,
+            documented,
+            total
+        );
+    }
+    Ok(())
+}
+
+fn check_examples(dir: &Path, verbose: bool, config: AnalyzerConfig) -> Result<()> {
+    let checks = example::check_stdlib_examples(dir, config)?;
+    let mut failures = 0;
+    for check in &checks {
+        if let Some(error) = &check.error {
+            failures += 1;
+            if check.title.is_empty() {
+                eprintln!("warning: docs of {}:\n{}\n", check.path, error);
+            } else {
+                eprintln!(
+                    "warning: example `{}` of {}:\n{}\n",
+                    check.title, check.path, error
+                );
+            }
+        }
+    }
+    if verbose {
+        for (path, titles) in example::validated_examples(&checks) {
+            println!("{}: {}", path, titles.join(", "));
+        }
+    }
+    if failures > 0 {
+        bail!(
+            "{} of {} examples failed to type check",
+            failures,
+            checks.len()
+        )
+    }
+    eprintln!("Checked {} examples", checks.len());
+    Ok(())
+}
+
 /// Iterates through `iter` in parallel however each item is passed to `consume` in the same order
 /// that they were produced by the iterator as long as the iterator uses `enumerate` to supply
 /// indices. Will deadlock if the indicies passed are not a complete 0..N sequence.
@@ -271,6 +423,16 @@ fn consume_sequentially<T>(
 
 /// Parse documentation for the specified directory.
 fn parse_docs(stdlib_dir: &Path, dir: &Path) -> Result<(Vec<doc::PackageDoc>, doc::Diagnostics)> {
+    let (docs, diagnostics) = parse_docs_with_types(stdlib_dir, dir)?;
+    Ok((docs.into_iter().map(|(doc, _)| doc).collect(), diagnostics))
+}
+
+/// Parse documentation for the specified directory along with the types of each package.
+#[allow(clippy::type_complexity)]
+fn parse_docs_with_types(
+    stdlib_dir: &Path,
+    dir: &Path,
+) -> Result<(Vec<(doc::PackageDoc, PackageExports)>, doc::Diagnostics)> {
     let (prelude, stdlib_importer) = bootstrap::stdlib(stdlib_dir)?;
 
     let mut analyzer = Analyzer::new_with_defaults(Environment::from(&prelude), stdlib_importer);
@@ -282,7 +444,7 @@ fn parse_docs(stdlib_dir: &Path, dir: &Path) -> Result<(Vec<doc::PackageDoc>, do
         let (doc, mut diags) = doc::parse_package_doc_comments(&ast_pkg, &pkgpath, &pkgtypes)
             .context(format!("generating docs for \"{}\"", &pkgpath))?;
         diagnostics.append(&mut diags);
-        docs.push(doc);
+        docs.push((doc, pkgtypes));
     }
     Ok((docs, diagnostics))
 }
@@ -0,0 +1,186 @@
+//! Configuration of the command line tools.
+//!
+//! The analyzer configuration is resolved from several layers, from highest to lowest
+//! precedence: command line flags, environment variables, a JSON or TOML config file and the
+//! defaults. The features come from the highest layer that specifies any. The schema, limits,
+//! error style and bindings are only read from the config file.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use structopt::StructOpt;
+
+use crate::semantic::{AnalyzerConfig, Feature};
+
+/// Environment variable naming the config file used when `--config` is not given.
+pub const CONFIG_ENV: &str = "FLUX_CONFIG";
+
+/// Environment variable listing features to enable, separated by commas, used when no
+/// `--feature` flag is given.
+pub const FEATURES_ENV: &str = "FLUX_FEATURES";
+
+// structopt turns a doc comment of a flattened struct into the description of the subcommands
+// that flatten it, but ignores a doc attribute whose value is not a literal.
+#[doc = concat!(
+    "Command line flags that configure the analyzer, to be flattened into the flags of a ",
+    "tool."
+)]
+#[derive(Debug, Default, StructOpt)]
+pub struct ConfigArgs {
+    /// JSON or TOML file containing the analyzer configuration, such as its features, limits,
+    /// error style and bindings. Defaults to $FLUX_CONFIG.
+    #[structopt(long, parse(from_os_str))]
+    pub config: Option<PathBuf>,
+    /// Feature to enable, e.g. `labelPolymorphism`. May be repeated.
+    /// Replaces the features of $FLUX_FEATURES and of the config file.
+    #[structopt(long = "feature")]
+    pub features: Vec<Feature>,
+    /// Print the resolved configuration instead of running the command.
+    #[structopt(long)]
+    pub print_config: bool,
+}
+
+impl ConfigArgs {
+    /// Resolves the configuration from the flags, the environment of the process, the config
+    /// file and the defaults.
+    pub fn resolve(&self) -> Result<AnalyzerConfig> {
+        let config = resolve(self.config.as_deref(), &self.features, |name| {
+            std::env::var(name).ok()
+        })?;
+        #[cfg(not(feature = "timezones"))]
+        if config.features.contains(&Feature::TimezoneWarnings) {
+            log::warn!("timezoneWarnings has no effect, this build has no time zone database");
+        }
+        Ok(config)
+    }
+
+    /// Prints the resolved configuration as JSON when `--print-config` is given, in which case
+    /// the tool should exit without running its command. Returns whether it was printed. The
+    /// schema of the configuration is left out, since schema providers are not serialized.
+    pub fn print_if_requested(&self, config: &AnalyzerConfig) -> Result<bool> {
+        if self.print_config {
+            println!("{}", serde_json::to_string_pretty(config)?);
+        }
+        Ok(self.print_config)
+    }
+}
+
+/// Resolves the analyzer configuration from the given config file and features, which come from
+/// flags, and from the environment variables looked up by `env`.
+pub fn resolve(
+    config_file: Option<&Path>,
+    features: &[Feature],
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<AnalyzerConfig> {
+    let config_file = config_file
+        .map(Path::to_path_buf)
+        .or_else(|| env(CONFIG_ENV).map(PathBuf::from));
+    let mut config = match config_file {
+        Some(path) => AnalyzerConfig::from_file(&path)?,
+        None => AnalyzerConfig::default(),
+    };
+
+    if !features.is_empty() {
+        config.features = features.to_vec();
+    } else if let Some(value) = env(FEATURES_ENV) {
+        config.features = value
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                name.parse()
+                    .with_context(|| format!("invalid feature `{}` in ${}", name, FEATURES_ENV))
+            })
+            .collect::<Result<_>>()?;
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs};
+
+    use super::*;
+    use crate::semantic::ErrorStyle;
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        move |name| vars.get(name).map(|value| value.to_string())
+    }
+
+    #[test]
+    fn layers() -> Result<()> {
+        let file = std::env::temp_dir().join(format!("cli_config_{}.json", std::process::id()));
+        fs::write(&file, r#"{"features": ["vectorizedMap"]}"#)?;
+        let file_env = [(CONFIG_ENV, file.to_str().unwrap())];
+
+        assert_eq!(resolve(None, &[], env(&[]))?, AnalyzerConfig::default());
+        assert_eq!(
+            resolve(None, &[], env(&file_env))?.features,
+            vec![Feature::VectorizedMap],
+        );
+        assert_eq!(
+            resolve(
+                None,
+                &[],
+                env(&[file_env[0], (FEATURES_ENV, "labelPolymorphism, typedHoles")])
+            )?
+            .features,
+            vec![Feature::LabelPolymorphism, Feature::TypedHoles],
+        );
+        assert_eq!(
+            resolve(
+                Some(&file),
+                &[Feature::UnusedSymbolWarnings],
+                env(&[(FEATURES_ENV, "labelPolymorphism")])
+            )?
+            .features,
+            vec![Feature::UnusedSymbolWarnings],
+        );
+        assert!(resolve(None, &[], env(&[(FEATURES_ENV, "notAFeature")])).is_err());
+
+        fs::remove_file(&file)?;
+        Ok(())
+    }
+
+    #[test]
+    fn toml_file() -> Result<()> {
+        let file = std::env::temp_dir().join(format!("cli_config_{}.toml", std::process::id()));
+        fs::write(
+            &file,
+            r#"
+features = ["typedHoles"]
+errorStyle = "short"
+
+[limits]
+maxErrors = 10
+
+[bindings]
+bucket = "string"
+"#,
+        )?;
+        let config = resolve(Some(&file), &[], env(&[]))?;
+        assert_eq!(config.features, vec![Feature::TypedHoles]);
+        assert_eq!(config.limits.max_errors, Some(10));
+        assert_eq!(config.error_style, Some(ErrorStyle::Short));
+        assert_eq!(
+            config.bindings,
+            maplit::btreemap! {"bucket".to_string() => "string".to_string()},
+        );
+
+        fs::write(
+            &file,
+            "[bindings]
+bucket = \"strin g\"\n",
+        )?;
+        let err = resolve(Some(&file), &[], env(&[])).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("invalid type of binding `bucket`"),
+            "{:#}",
+            err
+        );
+
+        fs::remove_file(&file)?;
+        Ok(())
+    }
+}
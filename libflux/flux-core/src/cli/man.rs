@@ -0,0 +1,168 @@
+//! Man pages generated from the definitions of the command line tools, also printed as plain
+//! text by their `--help-long` flag.
+
+use std::fmt::Write;
+
+use structopt::clap::{App, ErrorKind};
+
+// The contents of the man page of a tool.
+struct Page<'a> {
+    name: &'a str,
+    help: String,
+    // The name and help of each subcommand.
+    subcommands: Vec<(String, String)>,
+    exit_status: &'a [(i32, &'a str)],
+}
+
+impl<'a> Page<'a> {
+    fn new(app: &'a App<'_, '_>, exit_status: &'a [(i32, &'a str)]) -> Self {
+        let help = long_help(app, &[]);
+        let subcommands = subcommands(&help)
+            .into_iter()
+            .map(|name| (name.to_string(), long_help(app, &[name])))
+            .collect();
+        Page {
+            name: app.get_name(),
+            help,
+            subcommands,
+            exit_status,
+        }
+    }
+}
+
+/// Renders the man page of a command line tool in roff format. The page contains the help of
+/// the tool, a section with the help of each of its subcommands, and the exit status of the tool
+/// described by pairs of status codes and their meaning.
+pub fn man_page(app: &App<'_, '_>, exit_status: &[(i32, &str)]) -> String {
+    let page = Page::new(app, exit_status);
+
+    let mut roff = String::new();
+    writeln!(roff, ".TH {} 1", page.name.to_uppercase()).unwrap();
+    roff.push_str(".SH NAME\n");
+    writeln!(roff, "{}", escape(page.name)).unwrap();
+    roff.push_str(".SH DESCRIPTION\n");
+    preformatted(&mut roff, &page.help);
+
+    if !page.subcommands.is_empty() {
+        roff.push_str(".SH COMMANDS\n");
+        for (name, help) in &page.subcommands {
+            writeln!(roff, ".SS {}", escape(name)).unwrap();
+            preformatted(&mut roff, help);
+        }
+    }
+
+    if !page.exit_status.is_empty() {
+        roff.push_str(".SH EXIT STATUS\n");
+        for (code, meaning) in page.exit_status {
+            writeln!(roff, ".TP\n{}\n{}", code, escape(meaning)).unwrap();
+        }
+    }
+    roff
+}
+
+/// Renders the contents of the man page of a command line tool, as built by [`man_page`], as
+/// plain text.
+pub fn long_help_text(app: &App<'_, '_>, exit_status: &[(i32, &str)]) -> String {
+    let page = Page::new(app, exit_status);
+
+    let mut text = page.help;
+    if !page.subcommands.is_empty() {
+        text.push_str("\nCOMMANDS:\n");
+        for (name, help) in &page.subcommands {
+            writeln!(text, "\n{}", name).unwrap();
+            for line in help.lines() {
+                if line.is_empty() {
+                    text.push('\n');
+                } else {
+                    writeln!(text, "    {}", line).unwrap();
+                }
+            }
+        }
+    }
+
+    if !page.exit_status.is_empty() {
+        text.push_str("\nEXIT STATUS:\n");
+        for (code, meaning) in page.exit_status {
+            writeln!(text, "    {}    {}", code, meaning).unwrap();
+        }
+    }
+    text
+}
+
+// Returns the help printed by `--help` for the (sub)command named by `args`.
+fn long_help(app: &App<'_, '_>, args: &[&str]) -> String {
+    let mut argv = vec![app.get_name()];
+    argv.extend(args);
+    argv.push("--help");
+    match app.clone().get_matches_from_safe(argv) {
+        Err(err) if err.kind == ErrorKind::HelpDisplayed => err.message,
+        _ => String::new(),
+    }
+}
+
+// Returns the names listed in the SUBCOMMANDS section of a help text.
+fn subcommands(help: &str) -> Vec<&str> {
+    help.lines()
+        .skip_while(|line| line.trim() != "SUBCOMMANDS:")
+        .skip(1)
+        .take_while(|line| line.is_empty() || line.starts_with(' '))
+        // Descriptions that wrap are indented further than the names.
+        .filter(|line| line.len() - line.trim_start().len() == 4)
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|name| *name != "help")
+        .collect()
+}
+
+fn preformatted(page: &mut String, text: &str) {
+    page.push_str(".nf\n");
+    for line in text.lines() {
+        writeln!(page, "{}", escape(line)).unwrap();
+    }
+    page.push_str(".fi\n");
+}
+
+// Escapes text so that roff does not interpret it as a request or an escape sequence.
+fn escape(text: &str) -> String {
+    let text = text.replace('\\', "\\e");
+    if text.starts_with('.') || text.starts_with('\'') {
+        format!("\\&{}", text)
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use structopt::clap::SubCommand;
+
+    use super::*;
+
+    #[test]
+    fn page() {
+        let app = App::new("tool")
+            .about("does things")
+            .subcommand(SubCommand::with_name("run").about(".runs a thing"))
+            .subcommand(SubCommand::with_name("stop").about("stops a thing"));
+        let page = man_page(&app, &[(0, "success"), (1, "failure")]);
+
+        assert!(page.starts_with(".TH TOOL 1\n.SH NAME\ntool\n.SH DESCRIPTION\n.nf\n"));
+        assert!(page.contains(".SS run\n"));
+        assert!(page.contains("\\&.runs a thing\n"));
+        assert!(page.contains(".SS stop\n"));
+        assert!(!page.contains(".SS help\n"));
+        assert!(page.ends_with(".SH EXIT STATUS\n.TP\n0\nsuccess\n.TP\n1\nfailure\n"));
+    }
+
+    #[test]
+    fn text() {
+        let app = App::new("tool")
+            .about("does things")
+            .subcommand(SubCommand::with_name("run").about("runs a thing"));
+        let text = long_help_text(&app, &[(0, "success")]);
+
+        assert!(text.starts_with("tool \ndoes things\n"));
+        assert!(text.contains("\nCOMMANDS:\n\nrun\n    tool-run \n    runs a thing\n"));
+        assert!(!text.contains("\nhelp\n"));
+        assert!(text.ends_with("\nEXIT STATUS:\n    0    success\n"));
+    }
+}
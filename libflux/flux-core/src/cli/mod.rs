@@ -0,0 +1,4 @@
+//! Shared functionality of the command line tools.
+
+pub mod config;
+pub mod man;
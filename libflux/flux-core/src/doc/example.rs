@@ -1,16 +1,26 @@
 //! Parse documentation examples for their code and execute them collecting their inputs and
 //! outputs.
 
-use std::{collections::HashMap, ops::Range};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Range,
+    path::Path,
+};
 
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use csv::StringRecord;
 use pad::PadStr;
 use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag};
 
 use crate::{
-    doc::{Doc, Example, PackageDoc, Table},
+    doc::{parse_package_doc_comments, Doc, Example, PackageDoc, Table},
     formatter::format,
+    semantic::{
+        bootstrap::{self, ASTPackageMap},
+        env::Environment,
+        import::Packages,
+        Analyzer, AnalyzerConfig, ErrorStyle, PackageExports,
+    },
 };
 
 /// Executes Flux code producing input and output tables.
@@ -92,6 +102,147 @@ fn evaluate_example(example: &mut Example, executor: &impl Executor) -> Result<(
     Ok(())
 }
 
+/// Type checks Flux code without executing it.
+pub trait Checker {
+    /// Type check the provided Flux code, returning an error describing any type errors.
+    fn check(&self, code: &str) -> Result<()>;
+}
+
+/// Checks Flux code against the types of an inferred standard library.
+pub struct TypeChecker<'a> {
+    prelude: &'a PackageExports,
+    imports: &'a Packages,
+    config: AnalyzerConfig,
+}
+
+impl<'a> TypeChecker<'a> {
+    /// Creates a checker that resolves identifiers in the prelude and imports in `imports`.
+    pub fn new(prelude: &'a PackageExports, imports: &'a Packages, config: AnalyzerConfig) -> Self {
+        TypeChecker {
+            prelude,
+            imports,
+            config,
+        }
+    }
+}
+
+impl<'a> Checker for TypeChecker<'a> {
+    fn check(&self, code: &str) -> Result<()> {
+        let mut analyzer = Analyzer::new(
+            Environment::from(self.prelude),
+            self.imports,
+            self.config.clone(),
+        );
+        match analyzer.analyze_source("main".to_string(), "example.flux".to_string(), code) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(anyhow!(
+                "{}",
+                err.error
+                    .render(code, self.config.error_style.unwrap_or(ErrorStyle::Short))
+            )),
+        }
+    }
+}
+
+/// The result of type checking a single documentation example, or of reading the documentation
+/// of a package if the title is empty.
+#[derive(Debug)]
+pub struct ExampleCheck {
+    /// The package or member the example documents, e.g. `strings.toUpper`.
+    pub path: String,
+    /// The title of the example, empty if the documentation of the package could not be read.
+    pub title: String,
+    /// The error found while checking the example, if any.
+    pub error: Option<String>,
+}
+
+/// Type checks the code blocks of all examples in the documentation of a package, including
+/// blocks marked `no_run`.
+pub fn check_package_examples(docs: &PackageDoc, checker: &impl Checker) -> Vec<ExampleCheck> {
+    let mut checks: Vec<ExampleCheck> = docs
+        .examples
+        .iter()
+        .map(|example| check_example(&docs.path, example, checker))
+        .collect();
+    for (name, doc) in &docs.members {
+        let path = format!("{}.{}", docs.path, name);
+        match doc {
+            Doc::Package(pkg) => checks.extend(check_package_examples(pkg, checker)),
+            Doc::Value(v) => checks.extend(
+                v.examples
+                    .iter()
+                    .map(|example| check_example(&path, example, checker)),
+            ),
+            Doc::Function(f) => checks.extend(
+                f.examples
+                    .iter()
+                    .map(|example| check_example(&path, example, checker)),
+            ),
+        }
+    }
+    checks
+}
+
+fn check_example(path: &str, example: &Example, checker: &impl Checker) -> ExampleCheck {
+    let result = preprocess_code_blocks(&example.content).and_then(|blocks| {
+        for b in blocks {
+            checker.check(&b.exec)?;
+        }
+        Ok(())
+    });
+    ExampleCheck {
+        path: path.to_string(),
+        title: example.title.clone(),
+        error: result.err().map(|err| format!("{:#}", err)),
+    }
+}
+
+/// Infers the standard library at `dir` and type checks the examples in its documentation.
+pub fn check_stdlib_examples(dir: &Path, config: AnalyzerConfig) -> Result<Vec<ExampleCheck>> {
+    let (prelude, imports, _) = bootstrap::infer_stdlib_dir(dir, config.clone())?;
+    let ast_packages = bootstrap::parse_dir(dir)?;
+    Ok(check_examples(&ast_packages, &prelude, &imports, config))
+}
+
+/// Type checks the examples in the documentation of `ast_packages` against an inferred standard
+/// library. A package whose documentation cannot be read, or that is missing from `imports`, is
+/// reported as a failed check without a title, and the other packages are still checked.
+pub fn check_examples(
+    ast_packages: &ASTPackageMap,
+    prelude: &PackageExports,
+    imports: &Packages,
+    config: AnalyzerConfig,
+) -> Vec<ExampleCheck> {
+    let checker = TypeChecker::new(prelude, imports, config);
+    let mut checks = Vec::new();
+    for (path, ast_pkg) in ast_packages {
+        let docs = imports
+            .get(path)
+            .ok_or_else(|| anyhow!("the package was not inferred"))
+            .and_then(|types| parse_package_doc_comments(ast_pkg, path, types))
+            .context("generating docs");
+        match docs {
+            Ok((docs, _)) => checks.extend(check_package_examples(&docs, &checker)),
+            Err(err) => checks.push(ExampleCheck {
+                path: path.clone(),
+                title: String::new(),
+                error: Some(format!("{:#}", err)),
+            }),
+        }
+    }
+    checks
+}
+
+/// Returns the titles of the examples that passed their check, grouped by the package or member
+/// they document.
+pub fn validated_examples(checks: &[ExampleCheck]) -> BTreeMap<&str, Vec<&str>> {
+    let mut validated: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for check in checks.iter().filter(|check| check.error.is_none()) {
+        validated.entry(&check.path).or_default().push(&check.title);
+    }
+    validated
+}
+
 enum BlockMode {
     Run,
     NoRun,
@@ -444,8 +595,16 @@ mod tests {
     use anyhow::Result;
     use expect_test::{expect, Expect};
 
-    use super::{evaluate_package_examples, parse_results, preprocess, Executor};
-    use crate::doc::{Example, PackageDoc};
+    use super::{
+        check_examples, check_package_examples, evaluate_package_examples, parse_results,
+        preprocess, validated_examples, Checker, Executor,
+    };
+    use crate::{
+        ast,
+        doc::{Doc, Example, PackageDoc, ValueDoc},
+        parser,
+        semantic::{bootstrap::ASTPackageMap, import::Packages, PackageExports},
+    };
 
     struct MockExecutor<'a> {
         code: Expect,
@@ -458,6 +617,96 @@ mod tests {
         }
     }
 
+    struct MockChecker;
+    impl Checker for MockChecker {
+        fn check(&self, code: &str) -> Result<()> {
+            if code.contains("bad") {
+                anyhow::bail!("undefined identifier bad")
+            }
+            Ok(())
+        }
+    }
+
+    fn example(title: &str, code: &str) -> Example {
+        Example {
+            title: title.to_string(),
+            content: format!("```\n{}\n```\n", code),
+            input: None,
+            output: None,
+        }
+    }
+
+    #[test]
+    fn test_check_examples() {
+        let mut members = BTreeMap::new();
+        members.insert(
+            "x".to_string(),
+            Doc::Value(Box::new(ValueDoc {
+                name: "x".to_string(),
+                headline: "".to_string(),
+                description: None,
+                flux_type: "int".to_string(),
+                is_option: false,
+                source_location: Default::default(),
+                examples: vec![example("Use x", "pkg.x + 1"), example("Misuse x", "bad")],
+                metadata: None,
+                used_by: Vec::new(),
+            })),
+        );
+        let doc = PackageDoc {
+            path: "pkg".to_string(),
+            name: "pkg".to_string(),
+            headline: "".to_string(),
+            description: None,
+            members,
+            examples: vec![example("Import pkg", "import \"pkg\"")],
+            metadata: None,
+        };
+
+        let checks = check_package_examples(&doc, &MockChecker);
+        let errors: Vec<_> = checks
+            .iter()
+            .filter_map(|check| Some((check.path.as_str(), check.error.as_deref()?)))
+            .collect();
+        assert_eq!(errors, vec![("pkg.x", "undefined identifier bad")]);
+
+        let mut want = BTreeMap::new();
+        want.insert("pkg", vec!["Import pkg"]);
+        want.insert("pkg.x", vec!["Use x"]);
+        assert_eq!(validated_examples(&checks), want);
+    }
+
+    #[test]
+    fn test_check_examples_of_missing_package() {
+        let mut ast_packages = ASTPackageMap::default();
+        let file = parser::parse_string("pkg.flux".to_string(), "package pkg\n\nx = 1\n");
+        ast_packages.insert("pkg".to_string(), ast::Package::from(file));
+        let checks = check_examples(
+            &ast_packages,
+            &PackageExports::new(),
+            &Packages::default(),
+            Default::default(),
+        );
+        let errors: Vec<_> = checks
+            .iter()
+            .map(|check| {
+                (
+                    check.path.as_str(),
+                    check.title.as_str(),
+                    check.error.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            vec![(
+                "pkg",
+                "",
+                Some("generating docs: the package was not inferred")
+            )]
+        );
+    }
+
     #[test]
     fn test_simple() {
         let mut doc = PackageDoc {
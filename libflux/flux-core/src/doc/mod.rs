@@ -18,6 +18,7 @@ use regex::Regex;
 use crate::{
     ast,
     semantic::{
+        bootstrap::StdlibIndex,
         types::{Function, MonoType, PolyType},
         PackageExports,
     },
@@ -90,6 +91,9 @@ pub struct ValueDoc {
     pub examples: Vec<Example>,
     /// any Metadata associated with the value
     pub metadata: Option<Metadata>,
+    /// the functions of the standard library that refer to the value, see [`add_used_by`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub used_by: Vec<String>,
 }
 
 /// FunctionDoc represents the documentation for a single Function within a package.
@@ -113,6 +117,9 @@ pub struct FunctionDoc {
     pub examples: Vec<Example>,
     /// any Metadata associated with the function
     pub metadata: Option<Metadata>,
+    /// the functions of the standard library that refer to the function, see [`add_used_by`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub used_by: Vec<String>,
 }
 
 /// ParameterDoc represents the documentation for a single parameter within a function.
@@ -583,11 +590,7 @@ fn parse_function_doc(
         })
     }
     // Validate all parameters were documented
-    let mut params_on_type: Vec<&String> = fun_typ.req.keys().chain(fun_typ.opt.keys()).collect();
-    if let Some(pipe) = &fun_typ.pipe {
-        // Add pipe parameter to set if it exists
-        params_on_type.push(&pipe.k)
-    }
+    let params_on_type = type_parameters(fun_typ);
     for name in &params_on_type {
         if !contains_parameter(&parameters, name.as_str()) {
             diagnostics.push(Diagnostic {
@@ -617,9 +620,19 @@ fn parse_function_doc(
         source_location: loc.clone(),
         examples: pr.examples,
         metadata: pr.metadata,
+        used_by: Vec::new(),
     })
 }
 
+// Returns the names of the parameters of a function type, including its pipe parameter.
+fn type_parameters(fun_typ: &Function) -> Vec<&String> {
+    let mut params: Vec<&String> = fun_typ.req.keys().chain(fun_typ.opt.keys()).collect();
+    if let Some(pipe) = &fun_typ.pipe {
+        params.push(&pipe.k)
+    }
+    params
+}
+
 fn contains_parameter(params: &[ParameterDoc], name: &str) -> bool {
     params.iter().any(|pd| pd.name == name)
 }
@@ -650,6 +663,7 @@ fn parse_value_doc(
         source_location: loc.clone(),
         examples: pr.examples,
         metadata: pr.metadata,
+        used_by: Vec::new(),
     })
 }
 
@@ -705,6 +719,26 @@ fn shorten_doc(doc: &mut Doc) {
     }
 }
 
+/// Lists the functions of the standard library that refer to each value of a package, as recorded
+/// by the cross-reference index of the standard library.
+pub fn add_used_by(doc: &mut PackageDoc, index: &StdlibIndex) {
+    for (name, member) in doc.members.iter_mut() {
+        let used_by = match member {
+            Doc::Package(p) => {
+                add_used_by(p, index);
+                continue;
+            }
+            Doc::Value(v) => &mut v.used_by,
+            Doc::Function(f) => &mut f.used_by,
+        };
+        *used_by = index
+            .used_by(&format!("{}.{}", doc.path, name))
+            .into_iter()
+            .map(String::from)
+            .collect();
+    }
+}
+
 /// Restructures the Vector of PackageDocs into a hierarchical format where subpackages are in the member section
 /// of their parent packages. Ex: monitor.flux docs are in the members section of influxdb docs which are in the members of InfluxData docs.
 pub fn nest_docs(original_docs: Vec<PackageDoc>) -> PackageDoc {
@@ -727,6 +761,72 @@ pub fn nest_docs(original_docs: Vec<PackageDoc>) -> PackageDoc {
     nested_docs
 }
 
+/// Coverage describes how much of the exported values of a package are documented.
+#[derive(PartialEq, Debug, Serialize)]
+pub struct Coverage {
+    /// the relative path to the package
+    pub path: String,
+    /// the number of exported values
+    pub total: usize,
+    /// the number of exported values with a comment that documents all of their parameters
+    pub documented: usize,
+    /// the exported values without a comment
+    pub undocumented: Vec<String>,
+    /// the parameters without documentation, as pairs of function and parameter names
+    pub undocumented_parameters: Vec<(String, String)>,
+}
+
+impl Coverage {
+    /// Returns the percentage of exported values that are documented.
+    /// A package without exported values is fully documented.
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.documented as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+/// Computes the documentation coverage of a package from its docs and the types of its exported
+/// values.
+pub fn coverage(doc: &PackageDoc, types: &PackageExports) -> Coverage {
+    let mut coverage = Coverage {
+        path: doc.path.clone(),
+        total: 0,
+        documented: 0,
+        undocumented: Vec::new(),
+        undocumented_parameters: Vec::new(),
+    };
+    for (name, member) in &doc.members {
+        let (headline, parameters) = match member {
+            // Nested packages have their own coverage.
+            Doc::Package(_) => continue,
+            Doc::Value(v) => (&v.headline, &[][..]),
+            Doc::Function(f) => (&f.headline, &f.parameters[..]),
+        };
+        coverage.total += 1;
+        let mut documented = !headline.is_empty();
+        if !documented {
+            coverage.undocumented.push(name.clone());
+        }
+        if let Some(MonoType::Fun(f)) = types.lookup(name).map(|typ| &typ.expr) {
+            for param in type_parameters(f) {
+                if !contains_parameter(parameters, param) {
+                    documented = false;
+                    coverage
+                        .undocumented_parameters
+                        .push((name.clone(), param.clone()));
+                }
+            }
+        }
+        if documented {
+            coverage.documented += 1;
+        }
+    }
+    coverage
+}
+
 /// Find the package directly above the input package and returns it so that
 /// we can insert documentation into its members section.
 /// Creates an empty parent package if one did not exist.
@@ -1141,13 +1241,14 @@ mod test {
     use std::collections::BTreeMap;
 
     use super::{
-        parse_package_doc_comments, shorten, Diagnostic, Diagnostics, Doc, Example, FunctionDoc,
-        PackageDoc, ParameterDoc, Parser, Token, ValueDoc,
+        add_used_by, coverage, parse_package_doc_comments, shorten, Coverage, Diagnostic,
+        Diagnostics, Doc, Example, FunctionDoc, PackageDoc, ParameterDoc, Parser, Token, ValueDoc,
     };
     use crate::{
         ast::{self, tests::Locator},
         parser::parse_string,
-        semantic::{env::Environment, import::Packages, Analyzer},
+        semantic::{bootstrap::StdlibIndex, env::Environment, import::Packages, Analyzer},
+        semantic_map,
     };
 
     macro_rules! map {
@@ -1203,6 +1304,67 @@ mod test {
         assert_eq!(pkg, got_pkg, "want:\n{:#?}\ngot:\n{:#?}\n", pkg, got_pkg);
     }
     #[test]
+    fn test_coverage() {
+        let src = "
+        // Package foo does a thing.
+        package foo
+
+        // a is documented.
+        a = 1
+        b = 2
+
+        // f adds two numbers.
+        //
+        // ## Parameters
+        //
+        // - x: is the first number.
+        f = (x, y) => x + y
+        ";
+        let mut analyzer = Analyzer::new_with_defaults(Environment::empty(true), Packages::new());
+        let ast_pkg = parse_program(src);
+        let (types, _) = analyzer.analyze_ast(&ast_pkg).unwrap();
+        let (doc, _) = parse_package_doc_comments(&ast_pkg, "path", &types).unwrap();
+        let got = coverage(&doc, &types);
+        assert_eq!(
+            got,
+            Coverage {
+                path: "path".to_string(),
+                total: 3,
+                documented: 1,
+                undocumented: vec!["b".to_string()],
+                undocumented_parameters: vec![("f".to_string(), "y".to_string())],
+            }
+        );
+        assert!((got.percent() - 100.0 / 3.0).abs() < f64::EPSILON);
+    }
+    #[test]
+    fn test_used_by() {
+        let src = "
+        // Package foo does a thing.
+        package foo
+
+        // f adds one.
+        f = (x) => x + 1
+
+        // g adds two.
+        g = (x) => f(x: f(x: x))
+        ";
+        let mut analyzer = Analyzer::new_with_defaults(Environment::empty(true), Packages::new());
+        let ast_pkg = parse_program(src);
+        let (types, _) = analyzer.analyze_ast(&ast_pkg).unwrap();
+        let (mut doc, _) = parse_package_doc_comments(&ast_pkg, "path", &types).unwrap();
+        let index = StdlibIndex::from_packages(&semantic_map! {
+            "path".to_string() => ast_pkg,
+        });
+        add_used_by(&mut doc, &index);
+        let used_by = |name| match &doc.members[name] {
+            Doc::Function(f) => f.used_by.clone(),
+            _ => panic!("{} is not a function", name),
+        };
+        assert_eq!(used_by("f"), vec!["path.g"]);
+        assert!(used_by("g").is_empty());
+    }
+    #[test]
     fn test_package_doc() {
         let src = "
         // Package foo does a thing.
@@ -1296,6 +1458,7 @@ mod test {
                         source_location: loc.get(6,9,6,14),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -1332,6 +1495,7 @@ mod test {
                         source_location: loc.get(7,9,7,14),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -1367,6 +1531,7 @@ mod test {
                         source_location: loc.get(6,9,6,14),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -1402,6 +1567,7 @@ mod test {
                         source_location: loc.get(6,9,6,14),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -1445,6 +1611,7 @@ mod test {
                         source_location: loc.get(11,9,11,14),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -1537,6 +1704,7 @@ mod test {
                         source_location: loc.get(29,9,29,14),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                     "f" => Doc::Function(Box::new(FunctionDoc{
                         name: "f".to_string(),
@@ -1553,6 +1721,7 @@ mod test {
                         source_location: loc.get(49,9,49,21),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                     "o" => Doc::Value(Box::new(ValueDoc{
                         name: "o".to_string(),
@@ -1563,6 +1732,7 @@ mod test {
                         source_location: loc.get(63,9,63,21),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -1663,6 +1833,7 @@ foo.a
                             output: None,
                         }],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                     "f" => Doc::Function(Box::new(FunctionDoc{
                         name: "f".to_string(),
@@ -1688,6 +1859,7 @@ foo.f(x:1)
                             output: None,
                         }],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                     "o" => Doc::Value(Box::new(ValueDoc{
                         name: "o".to_string(),
@@ -1707,6 +1879,7 @@ option foo.o = 2
                             output: None,
                         }],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: vec![Example {
@@ -1795,6 +1968,7 @@ foo.a
                             "k4" => "v4".to_string(),
                             "k5" => "v5".to_string(),
                         ]),
+                        used_by: Vec::new(),
                     })),
                     "f" => Doc::Function(Box::new(FunctionDoc{
                         name: "f".to_string(),
@@ -1815,6 +1989,7 @@ foo.a
                             "k7" => "v7".to_string(),
                             "k8" => "v8".to_string(),
                         ]),
+                        used_by: Vec::new(),
                     })),
                     "o" => Doc::Value(Box::new(ValueDoc{
                         name: "o".to_string(),
@@ -1828,6 +2003,7 @@ foo.a
                             "k9" => "v9".to_string(),
                             "k0" => "v0".to_string(),
                         ]),
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -1960,6 +2136,7 @@ foo.a
                         source_location: loc.get(15,9,15,30),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -2027,6 +2204,7 @@ foo.a
                         source_location: loc.get(14,9,14,27),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -2063,6 +2241,7 @@ foo.a
                         source_location: loc.get(6,9,6,20),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -2127,6 +2306,7 @@ foo.a
                         source_location: loc.get(20,9,20,27),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -2188,6 +2368,7 @@ foo.a
                         source_location: loc.get(20,9,20,27),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -2241,6 +2422,7 @@ foo.a
                         source_location: loc.get(6, 9, 6, 21),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -2288,6 +2470,7 @@ foo.a
                         source_location: loc.get(9, 9, 9, 29),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -2335,6 +2518,7 @@ foo.a
                         source_location: loc.get(9, 9, 9, 32),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -2382,6 +2566,7 @@ foo.a
                         source_location: loc.get(9, 9, 9, 31),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -2429,6 +2614,7 @@ foo.a
                         source_location: loc.get(9, 9, 9, 22),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -2468,6 +2654,7 @@ foo.a
                         source_location: loc.get(6, 9, 6, 22),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -2503,6 +2690,7 @@ foo.a
                         source_location: loc.get(6, 9, 6, 23),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
@@ -2539,6 +2727,7 @@ foo.a
                         source_location: loc.get(6, 9, 6, 29),
                         examples: vec![],
                         metadata: None,
+                        used_by: Vec::new(),
                     })),
                 ],
                 examples: Vec::new(),
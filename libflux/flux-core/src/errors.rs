@@ -11,7 +11,10 @@ use codespan_reporting::diagnostic;
 use derive_more::Display;
 
 use crate::{
-    ast,
+    ast::{
+        self,
+        lines::{ColumnEncoding, LineIndex},
+    },
     semantic::sub::{Substitutable, Substituter},
 };
 
@@ -188,6 +191,17 @@ impl<E> From<E> for Located<E> {
 }
 
 impl<E> Located<E> {
+    /// Returns the location of the error in `source`, indexed by `lines`, with columns counting
+    /// units of `encoding`.
+    pub fn location_in(
+        &self,
+        lines: &LineIndex,
+        source: &str,
+        encoding: ColumnEncoding,
+    ) -> ast::SourceLocation {
+        lines.encode_location(source, &self.location, encoding)
+    }
+
     pub(crate) fn map<F>(self, f: impl FnOnce(E) -> F) -> Located<F> {
         Located {
             location: self.location,
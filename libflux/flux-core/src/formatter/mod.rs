@@ -1,11 +1,13 @@
 //! Source code formatter.
 
+use std::str::FromStr;
+
 use anyhow::{anyhow, Error, Result};
 use chrono::SecondsFormat;
 use pretty::{docs, DocAllocator};
 
 use crate::{
-    ast::{self, walk::Node, File, Statement},
+    ast::{self, comments::clear_comments, lines::LineIndex, walk::Node, File, Statement},
     parser::parse_string,
 };
 
@@ -14,6 +16,35 @@ pub fn convert_to_string(file: &File) -> Result<String> {
     format_to_string(file, true, Options::default())
 }
 
+/// Format a [`File`] with the given [`Options`].
+pub fn convert_to_string_with(file: &File, options: Options) -> Result<String> {
+    format_to_string(file, true, options)
+}
+
+/// Format a single [`ast::Expression`], without a trailing newline.
+pub fn expression_to_string(expression: ast::Expression) -> Result<String> {
+    let file = File {
+        base: ast::BaseNode::default(),
+        name: String::new(),
+        metadata: String::new(),
+        package: None,
+        imports: Vec::new(),
+        body: vec![Statement::Expr(Box::new(ast::ExprStmt {
+            base: ast::BaseNode::default(),
+            expression,
+        }))],
+        eof: Vec::new(),
+    };
+    format_to_string(
+        &file,
+        true,
+        Options {
+            trailing_newline: false,
+            ..Options::default()
+        },
+    )
+}
+
 /// Format a string of Flux code.
 ///
 /// # Example
@@ -35,7 +66,8 @@ pub fn format(contents: &str) -> Result<String> {
 /// ```rust
 /// # use fluxcore::formatter::{format_with, Options};
 /// let source = "(r) => r.user ==              \"user1\"";
-/// let formatted = format_with(source, Options { trailing_newline: false }).unwrap();
+/// let options = Options { trailing_newline: false, ..Options::default() };
+/// let formatted = format_with(source, options).unwrap();
 /// assert_eq!(formatted, "(r) => r.user == \"user1\"");
 /// ```
 pub fn format_with(contents: &str, options: Options) -> Result<String> {
@@ -47,19 +79,254 @@ pub fn format_with(contents: &str, options: Options) -> Result<String> {
 }
 
 /// Options used to alter how the flux code is formatted
+///
+/// Options can be parsed from a comma separated list of `name=value` pairs, such as
+/// `trailing_newline=false,sort_imports=true`. Options that are not listed keep their default
+/// value.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Options {
     /// Ensures that the formatted output has a trailing newline.
     ///
     /// Default: true
     pub trailing_newline: bool,
+    /// Sorts the imports as [`sort_imports`] does and separates the groups of imports with a
+    /// blank line.
+    ///
+    /// Default: false
+    pub sort_imports: bool,
+    /// Where the `|>` operator is placed when a pipeline is broken over several lines.
+    ///
+    /// Default: [`PipeStyle::Leading`]
+    pub pipe_style: PipeStyle,
+    /// Pipelines with at most this many stages are laid out on a single line if they fit, even
+    /// if they span several lines in the source. Longer pipelines keep the line breaks of the
+    /// source.
+    ///
+    /// Default: 0
+    pub collapse_pipe_stages: usize,
 }
 
 impl Default for Options {
     fn default() -> Self {
         Self {
             trailing_newline: true,
+            sort_imports: false,
+            pipe_style: PipeStyle::Leading,
+            collapse_pipe_stages: 0,
+        }
+    }
+}
+
+impl FromStr for Options {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut options = Options::default();
+        for option in s.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+            let (name, value) = option
+                .split_once('=')
+                .ok_or_else(|| anyhow!("expected `name=value`, found `{}`", option))?;
+            match name.trim() {
+                "trailing_newline" => options.trailing_newline = value.trim().parse()?,
+                "sort_imports" => options.sort_imports = value.trim().parse()?,
+                "pipe_style" => options.pipe_style = value.trim().parse()?,
+                "collapse_pipe_stages" => options.collapse_pipe_stages = value.trim().parse()?,
+                name => return Err(anyhow!("unknown formatter option `{}`", name)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// The placement of the `|>` operator in pipelines broken over several lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipeStyle {
+    /// Each stage starts with `|>`.
+    ///
+    /// ```flux
+    /// from(bucket: "b")
+    ///     |> range(start: -1h)
+    /// ```
+    Leading,
+    /// Each stage but the last ends with `|>`.
+    ///
+    /// ```flux
+    /// from(bucket: "b") |>
+    ///     range(start: -1h)
+    /// ```
+    Trailing,
+}
+
+impl FromStr for PipeStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "leading" => Ok(PipeStyle::Leading),
+            "trailing" => Ok(PipeStyle::Trailing),
+            _ => Err(anyhow!(
+                "unknown pipe style `{}`, expected `leading` or `trailing`",
+                s
+            )),
+        }
+    }
+}
+
+/// The groups imports are sorted into, in the order they are formatted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ImportGroup {
+    /// Packages of the standard library, such as `strings` or `influxdata/influxdb/v1`.
+    Stdlib,
+    /// Packages of the standard library under `experimental`.
+    Experimental,
+    /// Packages outside of the standard library, whose path starts with a domain such as
+    /// `github.com/user/pkg`, or is relative.
+    User,
+}
+
+impl ImportGroup {
+    /// Returns the group of an import path.
+    pub fn of(path: &str) -> Self {
+        let first = path.split('/').next().unwrap_or_default();
+        if first.contains('.') {
+            ImportGroup::User
+        } else if first == "experimental" {
+            ImportGroup::Experimental
+        } else {
+            ImportGroup::Stdlib
+        }
+    }
+}
+
+fn import_key(import: &ast::ImportDeclaration) -> (ImportGroup, &str, Option<&str>) {
+    (
+        ImportGroup::of(&import.path.value),
+        &import.path.value,
+        import.alias.as_ref().map(|alias| alias.name.as_str()),
+    )
+}
+
+/// Sorts the imports of `file` by [`ImportGroup`], then by path and alias, and removes imports
+/// that are repeated with the same alias. Comments move with the import they precede.
+///
+/// The formatter does the same when [`Options::sort_imports`] is set; this function applies
+/// the order to the AST itself, e.g. before it is rewritten further.
+pub fn sort_imports(file: &mut File) {
+    file.imports
+        .sort_by(|a, b| import_key(a).cmp(&import_key(b)));
+    file.imports.dedup_by(|a, b| import_key(a) == import_key(b));
+}
+
+/// Sorts and groups the imports of Flux source code as [`Options::sort_imports`] does, leaving
+/// the rest of the source unchanged.
+pub fn fix_imports(source: &str) -> Result<String> {
+    let file = parse_string("".to_string(), source);
+    ast::check::check(Node::File(&file))?;
+    let last = match file.imports.last() {
+        Some(last) => &last.base.location,
+        None => return Ok(source.to_string()),
+    };
+
+    // The comments of the first import are before its location, so the imports are replaced
+    // from the first comment or import after the package clause. The blank lines before them
+    // are kept as they are.
+    let lines = LineIndex::new(source);
+    let start = match &file.package {
+        Some(pkg) => lines.range(&pkg.base.location).map(|range| range.end),
+        None => Some(0),
+    };
+    let (start, end) = match (start, lines.range(last)) {
+        (Some(start), Some(end)) => (start, end.end),
+        _ => return Err(anyhow!("import locations are outside of the source")),
+    };
+    let start = start + (source[start..].len() - source[start..].trim_start().len());
+
+    let mut imports = File {
+        package: None,
+        body: Vec::new(),
+        eof: Vec::new(),
+        ..file
+    };
+    sort_imports(&mut imports);
+    let options = Options {
+        trailing_newline: false,
+        sort_imports: true,
+        ..Options::default()
+    };
+    Ok(format!(
+        "{}{}{}",
+        &source[..start],
+        convert_to_string_with(&imports, options)?,
+        &source[end..]
+    ))
+}
+
+/// A way in which formatting Flux source code is not stable.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instability {
+    /// Formatting the formatted source changes it again.
+    NotIdempotent {
+        /// The source formatted once.
+        once: String,
+        /// The source formatted twice.
+        twice: String,
+    },
+    /// The formatted source parses to a different AST than the source, ignoring comments and
+    /// locations.
+    AstChanged {
+        /// The source formatted once.
+        formatted: String,
+    },
+}
+
+/// Formats Flux source code twice and parses the formatted source again, returning how
+/// formatting is not stable, if it is not. Fails if the source itself does not parse.
+///
+/// The imports of the source are sorted before it is compared with the formatted source when
+/// [`Options::sort_imports`] is set.
+pub fn check_stability(source: &str, options: Options) -> Result<Option<Instability>> {
+    let mut file = parse_string("".to_string(), source);
+    ast::check::check(Node::File(&file))?;
+    let once = format_to_string(&file, true, options.clone())?;
+    let formatted = parse_string("".to_string(), &once);
+    // Formatted source that does not parse is a change of the AST.
+    if ast::check::check(Node::File(&formatted)).is_err() {
+        return Ok(Some(Instability::AstChanged { formatted: once }));
+    }
+    let twice = format_to_string(&formatted, true, options.clone())?;
+    if once != twice {
+        return Ok(Some(Instability::NotIdempotent { once, twice }));
+    }
+    if options.sort_imports {
+        sort_imports(&mut file);
+    }
+    if normalized_ast(&file)? != normalized_ast(&formatted)? {
+        return Ok(Some(Instability::AstChanged { formatted: once }));
+    }
+    Ok(None)
+}
+
+// Returns a file as JSON without the comments, locations and parentheses that formatting
+// changes.
+fn normalized_ast(file: &File) -> Result<serde_json::Value> {
+    fn strip_locations(value: &mut serde_json::Value) {
+        while value.get("type").and_then(serde_json::Value::as_str) == Some("ParenExpression") {
+            *value = value["expression"].take();
+        }
+        match value {
+            serde_json::Value::Object(fields) => {
+                fields.remove("location");
+                fields.values_mut().for_each(strip_locations);
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(strip_locations),
+            _ => (),
         }
     }
+    let mut file = file.clone();
+    clear_comments(&mut file);
+    let mut value = serde_json::to_value(&file)?;
+    strip_locations(&mut value);
+    Ok(value)
 }
 
 const MULTILINE: usize = 4;
@@ -350,16 +617,22 @@ impl<'doc> Formatter<'doc> {
             }
         }
 
-        doc = docs![
-            arena,
-            doc,
-            arena.intersperse(
-                file.imports
-                    .iter()
-                    .map(|import| self.format_import_declaration(import)),
-                arena.hardline()
-            ),
-        ];
+        let mut imports: Vec<_> = file.imports.iter().collect();
+        if self.options.sort_imports {
+            imports.sort_by(|a, b| import_key(a).cmp(&import_key(b)));
+            imports.dedup_by(|a, b| import_key(a) == import_key(b));
+        }
+        let mut group = None;
+        for (i, import) in imports.into_iter().enumerate() {
+            if i > 0 {
+                doc = doc.append(arena.hardline());
+                if self.options.sort_imports && group != Some(import_key(import).0) {
+                    doc = doc.append(arena.hardline());
+                }
+            }
+            group = Some(import_key(import).0);
+            doc = doc.append(self.format_import_declaration(import));
+        }
         if !file.imports.is_empty() && !file.body.is_empty() {
             doc = docs![arena, doc, arena.hardline(), arena.hardline(),];
         }
@@ -1465,10 +1738,11 @@ impl<'doc> Formatter<'doc> {
 
     fn format_pipe_expression(&mut self, mut pipe: &'doc ast::PipeExpr) -> Doc<'doc> {
         let arena = self.arena;
+        let trailing = self.options.pipe_style == PipeStyle::Trailing;
 
         let mut arguments = Vec::new();
         let mut operators = Vec::new();
-        let line = self.base_multiline(&pipe.base);
+        let multiline = pipe.base.is_multiline();
         loop {
             arguments.push(
                 self.format_right_child_with_parens(
@@ -1477,11 +1751,12 @@ impl<'doc> Formatter<'doc> {
                 )
                 .group(),
             );
-            operators.push(docs![
-                arena,
-                self.format_comments(&pipe.base.comments),
-                arena.text("|> "),
-            ]);
+            let comments = self.format_comments(&pipe.base.comments);
+            operators.push(if trailing {
+                comments
+            } else {
+                docs![arena, comments, arena.text("|> ")]
+            });
             match &pipe.argument {
                 ast::Expression::PipeExpr(expr) => {
                     pipe = expr;
@@ -1498,6 +1773,19 @@ impl<'doc> Formatter<'doc> {
                 }
             }
         }
+
+        let line = self.multiline(multiline && operators.len() > self.options.collapse_pipe_stages);
+        if trailing {
+            // The comments of a stage are placed after the line break, since a comment before
+            // the `|>` would end the line.
+            let mut arguments = arguments.into_iter().rev();
+            let first = arguments.next().unwrap_or_else(|| arena.nil());
+            let mut doc = arena.nil();
+            for (comments, arg) in operators.into_iter().rev().zip(arguments) {
+                doc += docs![arena, " |>", line.clone(), comments, arg];
+            }
+            return docs![arena, first, doc.nest(INDENT)].group();
+        }
         self.layout_binary_expressions(
             arguments.into_iter().rev(),
             operators.into_iter().rev(),
@@ -1610,7 +1898,7 @@ impl<'doc> Formatter<'doc> {
     }
 }
 
-fn escape_string(s: &str) -> String {
+pub(crate) fn escape_string(s: &str) -> String {
     if !(s.contains('\"') || s.contains('\\')) {
         return s.to_string();
     }
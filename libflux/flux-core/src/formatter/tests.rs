@@ -10,6 +10,9 @@ use super::*;
 const TEST_OPTIONS: Options = Options {
     // It is easier to read/write testcases without having to add a trailing newline
     trailing_newline: false,
+    sort_imports: false,
+    pipe_style: PipeStyle::Leading,
+    collapse_pipe_stages: 0,
 };
 
 #[track_caller]
@@ -1994,6 +1997,8 @@ fn format_with_trailing_newline() {
             src,
             Options {
                 trailing_newline: true,
+                sort_imports: false,
+                ..Options::default()
             },
         )
         .unwrap(),
@@ -2007,8 +2012,221 @@ fn format_with_trailing_newline() {
             src,
             Options {
                 trailing_newline: false,
+                sort_imports: false,
+                ..Options::default()
             },
         )
         .unwrap(),
     );
 }
+
+#[test]
+fn parse_options() {
+    assert_eq!("".parse::<Options>().unwrap(), Options::default());
+    assert_eq!(
+        "trailing_newline=false".parse::<Options>().unwrap(),
+        Options {
+            trailing_newline: false,
+            sort_imports: false,
+            ..Options::default()
+        },
+    );
+    assert_eq!(
+        "sort_imports=true, trailing_newline=false"
+            .parse::<Options>()
+            .unwrap(),
+        Options {
+            trailing_newline: false,
+            sort_imports: true,
+            ..Options::default()
+        },
+    );
+    assert_eq!(
+        "pipe_style=trailing,collapse_pipe_stages=2"
+            .parse::<Options>()
+            .unwrap(),
+        Options {
+            pipe_style: PipeStyle::Trailing,
+            collapse_pipe_stages: 2,
+            ..Options::default()
+        },
+    );
+    expect![["unknown pipe style `middle`, expected `leading` or `trailing`"]].assert_eq(
+        &"pipe_style=middle"
+            .parse::<Options>()
+            .unwrap_err()
+            .to_string(),
+    );
+    expect![["unknown formatter option `indent`"]]
+        .assert_eq(&"indent=2".parse::<Options>().unwrap_err().to_string());
+    expect![["expected `name=value`, found `trailing_newline`"]].assert_eq(
+        &"trailing_newline"
+            .parse::<Options>()
+            .unwrap_err()
+            .to_string(),
+    );
+}
+
+#[test]
+fn sort_imports() {
+    let src = r#"import "github.com/user/pkg"
+import "strings"
+import "experimental/http"
+// dates
+import "date"
+import "strings"
+import j "experimental/json"
+
+x = 1"#;
+    let options = Options {
+        trailing_newline: false,
+        sort_imports: true,
+        ..Options::default()
+    };
+
+    expect![[r#"
+        // dates
+        import "date"
+        import "strings"
+
+        import "experimental/http"
+        import j "experimental/json"
+
+        import "github.com/user/pkg"
+
+        x = 1"#]]
+    .assert_eq(&format_with(src, options).unwrap());
+    expect![[r#"
+        import "github.com/user/pkg"
+        import "strings"
+        import "experimental/http"
+        // dates
+        import "date"
+        import "strings"
+        import j "experimental/json"
+
+        x = 1"#]]
+    .assert_eq(&format_with(src, TEST_OPTIONS).unwrap());
+
+    let mut file = parse_string("".to_string(), src);
+    super::sort_imports(&mut file);
+    let paths: Vec<_> = file.imports.iter().map(|i| i.path.value.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "date",
+            "strings",
+            "experimental/http",
+            "experimental/json",
+            "github.com/user/pkg"
+        ]
+    );
+    assert_eq!(file.imports[0].base.comments[0].text, "// dates\n");
+}
+
+#[test]
+fn fix_imports() {
+    let src = r#"// The package
+package foo
+import "experimental/http"
+
+// dates
+import "date"
+a   =   1
+"#;
+
+    expect![[r#"
+        // The package
+        package foo
+        // dates
+        import "date"
+
+        import "experimental/http"
+        a   =   1
+    "#]]
+    .assert_eq(&super::fix_imports(src).unwrap());
+    expect![[r#"
+        x = 1
+    "#]]
+    .assert_eq(&super::fix_imports("x = 1\n").unwrap());
+}
+
+#[test]
+fn fix_imports_keeps_sorted_source() {
+    for src in [
+        "package foo\nimport \"date\"\n\nimport \"experimental/http\"\nx = 1\n",
+        "package foo\n\n\n// dates\nimport \"date\"\nimport \"strings\"\n",
+        "\n\nimport \"date\" // trailing\n\nx   =   1",
+    ] {
+        assert_eq!(super::fix_imports(src).unwrap(), src);
+    }
+}
+
+#[test]
+fn pipe_style() {
+    let src = r#"from(bucket: "b")
+    |> range(start: -1h)
+    // only cpu
+    |> filter(fn: (r) => r._measurement == "cpu")"#;
+    let trailing = Options {
+        trailing_newline: false,
+        pipe_style: PipeStyle::Trailing,
+        ..Options::default()
+    };
+
+    expect![[r#"
+        from(bucket: "b") |>
+            range(start: -1h) |>
+            // only cpu
+            filter(fn: (r) => r._measurement == "cpu")"#]]
+    .assert_eq(&format_with(src, trailing.clone()).unwrap());
+    expect![[r#"from(bucket: "b") |> range(start: -1h)"#]]
+        .assert_eq(&format_with(r#"from(bucket: "b") |> range(start: -1h)"#, trailing).unwrap());
+}
+
+#[test]
+fn collapse_pipe_stages() {
+    let src = r#"from(bucket: "b")
+    |> range(start: -1h)
+    |> last()"#;
+    let options = |collapse_pipe_stages| Options {
+        trailing_newline: false,
+        collapse_pipe_stages,
+        ..Options::default()
+    };
+
+    expect![[r#"from(bucket: "b") |> range(start: -1h) |> last()"#]]
+        .assert_eq(&format_with(src, options(2)).unwrap());
+    assert_eq!(format_with(src, options(1)).unwrap(), src);
+}
+
+#[test]
+fn stable_formatting() {
+    let src = r#"import "strings"
+import "array"
+
+// the rows
+rows = array.from(rows: [{a: "x"}])
+rows |> map(fn: (r) => ({r with b: strings.toUpper(v: r.a)}))
+"#;
+    assert_eq!(check_stability(src, Options::default()).unwrap(), None);
+    let sorted = Options {
+        sort_imports: true,
+        ..Options::default()
+    };
+    assert_eq!(check_stability(src, sorted).unwrap(), None);
+    assert_eq!(
+        check_stability("x = ((1 + 2)) * (3)\ny = (a)", Options::default()).unwrap(),
+        None
+    );
+    assert!(check_stability("x = ", Options::default()).is_err());
+}
+
+#[test]
+fn normalized_ast_ignores_comments_and_locations() {
+    let ast = |src: &str| normalized_ast(&parse_string("".to_string(), src)).unwrap();
+    assert_eq!(ast("x = 1 + 2"), ast("// one\nx =\n    1 + 2"));
+    assert_ne!(ast("x = 1 + 2"), ast("x = 1 + 3"));
+    assert_eq!(ast("x = (1 + 2) * 3"), ast("x = ((1 + 2)) * (3)"));
+    assert_ne!(ast("x = (1 + 2) * 3"), ast("x = 1 + 2 * 3"));
+}
@@ -11,7 +11,8 @@
 //! - [`semantic`] performs semantic analysis, including type inference,
 //!   producing a semantic graph.
 //!
-//! In addition, the [`formatter`] module provides functions for code formatting utilities.
+//! In addition, the [`formatter`] module provides functions for code formatting utilities,
+//! and the [`safe`] module contains panics raised while analyzing code.
 //!
 //! [Flux language]: https://github.com/influxdata/flux
 
@@ -28,8 +29,10 @@ extern crate pretty_assertions;
 pub mod doc;
 
 pub mod ast;
+pub mod cli;
 pub mod formatter;
 pub mod parser;
+pub mod safe;
 pub mod scanner;
 pub mod semantic;
 
@@ -31,6 +31,7 @@ pub struct Parser<'input> {
 
     fname: String,
     source: &'input str,
+    typed_holes: bool,
 }
 
 impl<'input> Parser<'input> {
@@ -44,9 +45,17 @@ impl<'input> Parser<'input> {
             blocks: HashMap::default(),
             fname: "".to_string(),
             source: src,
+            typed_holes: false,
         }
     }
 
+    /// Parses `_?` as a typed hole, see [`ast::TYPED_HOLE`]. Typed holes are rejected with an
+    /// error unless they are enabled.
+    pub fn with_typed_holes(mut self) -> Self {
+        self.typed_holes = true;
+        self
+    }
+
     // scan will read the next token from the Scanner. If peek has been used,
     // this will return the peeked token and consume it.
     fn scan(&mut self) -> Token {
@@ -1430,7 +1439,7 @@ impl<'input> Parser<'input> {
     fn parse_primary_expression(&mut self) -> Expression {
         let t = self.peek_with_regex();
         match t.tok {
-            TokenType::Ident => Expression::Identifier(self.parse_identifier()),
+            TokenType::Ident => Expression::Identifier(self.parse_identifier_or_hole()),
             TokenType::Int => Expression::Integer(self.parse_int_literal()),
             TokenType::Float => {
                 let lit = self.parse_float_literal();
@@ -1549,6 +1558,30 @@ impl<'input> Parser<'input> {
             name: t.lit,
         }
     }
+    // Parses an identifier, or a typed hole if the identifier is `_` immediately
+    // followed by `?`. A typed hole is an error unless typed holes are enabled.
+    fn parse_identifier_or_hole(&mut self) -> Identifier {
+        let t = self.expect(TokenType::Ident);
+        if t.lit == "_" {
+            let next = self.peek();
+            if next.tok == TokenType::QuestionMark && next.start_pos == t.end_pos {
+                let q = self.consume();
+                if !self.typed_holes {
+                    self.errs.push(
+                        "typed holes are only parsed with the typedHoles feature".to_string(),
+                    );
+                }
+                return Identifier {
+                    base: self.base_node_from_tokens(&t, &q),
+                    name: ast::TYPED_HOLE.to_string(),
+                };
+            }
+        }
+        Identifier {
+            base: self.base_node_from_token(&t),
+            name: t.lit,
+        }
+    }
     fn parse_int_literal(&mut self) -> IntegerLit {
         let t = self.expect(TokenType::Int);
 
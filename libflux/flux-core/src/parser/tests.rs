@@ -30,6 +30,37 @@ fn parse_ast_roundtrip() {
     assert_eq!(ast, new_ast);
 }
 
+#[test]
+fn parse_typed_hole() {
+    let mut p = Parser::new(r#"filter(fn: _?)"#).with_typed_holes();
+    let parsed = p.parse_file("".to_string());
+    ast::check::check(ast::walk::Node::File(&parsed)).unwrap();
+
+    let mut holes = Vec::new();
+    ast::walk::walk(
+        &mut |node: ast::walk::Node| {
+            if let ast::walk::Node::Identifier(id) = node {
+                if id.name == ast::TYPED_HOLE {
+                    holes.push(id.base.location.source.clone());
+                }
+            }
+        },
+        ast::walk::Node::File(&parsed),
+    );
+    assert_eq!(holes, vec![Some("_?".to_string())]);
+}
+
+#[test]
+fn parse_typed_hole_disabled() {
+    let mut p = Parser::new(r#"filter(fn: _?)"#);
+    let parsed = p.parse_file("".to_string());
+    let err = ast::check::check(ast::walk::Node::File(&parsed)).unwrap_err();
+    expect_test::expect![[
+        r#"error @1:12-1:14: typed holes are only parsed with the typedHoles feature"#
+    ]]
+    .assert_eq(&err.to_string());
+}
+
 #[test]
 fn parse_array_expr_no_rbrack() {
     let mut p = Parser::new(r#"group(columns: ["_time", "_field]", mode: "by")"#);
@@ -0,0 +1,222 @@
+//! Containment of panics raised while analyzing Flux source code.
+//!
+//! A bug in the analyzer should fail the analysis of the source that triggered it, not the
+//! process hosting the analyzer. [`analyze`] reports such a panic as a [`CaughtPanic`] along with
+//! the message and location of the panic.
+//!
+//! When the [`BACKTRACE_ENV`] environment variable is set to a value other than `0`, caught
+//! panics also carry the resolved backtrace of the panic. Resolving it is costly, so it is not
+//! captured by default. Backtraces are only available when flux-core is built with the
+//! `backtrace` cargo feature, which is enabled by default.
+
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Once,
+    },
+};
+
+use thiserror::Error;
+
+use crate::semantic::{import::Importer, nodes, Analyzer, FileErrors, PackageExports};
+
+/// Environment variable enabling the capture of the backtraces of caught panics, read once when
+/// the first panic is caught.
+pub const BACKTRACE_ENV: &str = "FLUX_BACKTRACE";
+
+/// Where a panic was raised in the source of the analyzer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicLocation {
+    /// The Rust source file.
+    pub file: String,
+    /// The line in the file.
+    pub line: u32,
+    /// The column in the line.
+    pub column: u32,
+}
+
+impl fmt::Display for PanicLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// A panic that was caught instead of unwinding further.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaughtPanic {
+    /// The message the panic was raised with.
+    pub message: String,
+    /// Where the panic was raised, if known.
+    pub location: Option<PanicLocation>,
+    /// The backtrace of the panic, only captured when [`BACKTRACE_ENV`] is set and the
+    /// `backtrace` cargo feature is enabled.
+    pub backtrace: Option<String>,
+}
+
+impl fmt::Display for CaughtPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "panic: {}", self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, " at {}", location)?;
+        }
+        if let Some(backtrace) = &self.backtrace {
+            write!(f, "\n{}", backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CaughtPanic {}
+
+/// An error returned by [`analyze`].
+#[derive(Error, Debug)]
+pub enum Error {
+    /// The source has errors.
+    #[error(transparent)]
+    Analysis(#[from] FileErrors),
+    /// The analyzer panicked.
+    #[error(transparent)]
+    CaughtPanic(#[from] CaughtPanic),
+}
+
+thread_local! {
+    // Whether a panic on this thread is going to be caught by `catch`.
+    static CATCHING: Cell<bool> = Cell::new(false);
+    // The location and backtrace of the last panic caught on this thread.
+    static LAST_PANIC: RefCell<(Option<PanicLocation>, Option<String>)> = RefCell::new((None, None));
+}
+
+static INSTALL_HOOK: Once = Once::new();
+
+// Whether the hook captures backtraces, read from `BACKTRACE_ENV` when it is installed.
+static CAPTURE_BACKTRACE: AtomicBool = AtomicBool::new(false);
+
+// Installs a panic hook recording the location and backtrace of the panics raised inside
+// `catch`. Other panics are passed on to the previously installed hook.
+fn install_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let capture = backtrace_enabled(std::env::var(BACKTRACE_ENV).ok().as_deref());
+        CAPTURE_BACKTRACE.store(capture, Ordering::Relaxed);
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if CATCHING.with(|catching| catching.get()) {
+                let location = info.location().map(|location| PanicLocation {
+                    file: location.file().to_string(),
+                    line: location.line(),
+                    column: location.column(),
+                });
+                LAST_PANIC.with(|last| *last.borrow_mut() = (location, capture_backtrace()));
+            } else {
+                previous(info);
+            }
+        }));
+    });
+}
+
+/// Calls `f`, returning the panic it raises, if any, as a [`CaughtPanic`].
+///
+/// Panics raised inside `f` are not printed by the panic hook. Any state that `f` mutates may be
+/// left inconsistent by a panic and should be discarded.
+pub fn catch<T>(f: impl FnOnce() -> T) -> Result<T, CaughtPanic> {
+    install_hook();
+    let catching = CATCHING.with(|catching| catching.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.with(|c| c.set(catching));
+    result.map_err(|payload| {
+        let (location, backtrace) = LAST_PANIC.with(|last| last.take());
+        CaughtPanic {
+            message: panic_message(payload.as_ref()),
+            location,
+            backtrace,
+        }
+    })
+}
+
+// Like `RUST_BACKTRACE`, any value but `0` enables the capture.
+fn backtrace_enabled(value: Option<&str>) -> bool {
+    matches!(value, Some(value) if value != "0")
+}
+
+#[cfg(feature = "backtrace")]
+fn capture_backtrace() -> Option<String> {
+    if CAPTURE_BACKTRACE.load(Ordering::Relaxed) {
+        Some(format!("{:?}", backtrace::Backtrace::new()))
+    } else {
+        None
+    }
+}
+
+#[cfg(not(feature = "backtrace"))]
+fn capture_backtrace() -> Option<String> {
+    None
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    // `panic!` makes the payload a `&str` or a `String`.
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+/// Analyzes Flux source code like [`Analyzer::analyze_source`], reporting a panic of the
+/// analyzer as [`Error::CaughtPanic`].
+///
+/// The analyzer should not be reused after it panicked.
+pub fn analyze<I: Importer>(
+    analyzer: &mut Analyzer<'_, I>,
+    pkgpath: String,
+    file_name: String,
+    src: &str,
+) -> Result<(PackageExports, nodes::Package), Error> {
+    catch(|| analyzer.analyze_source(pkgpath, file_name, src))?
+        .map_err(|salvage| Error::Analysis(salvage.error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::import::Packages;
+
+    #[test]
+    fn catch_panic() {
+        let line = line!() + 1;
+        let err = catch(|| panic!("analyzer bug {}", 1)).unwrap_err();
+        assert_eq!(err.message, "analyzer bug 1");
+        let location = err.location.unwrap();
+        assert_eq!(location.file, file!());
+        assert_eq!(location.line, line);
+        assert_eq!(
+            err.backtrace.is_some(),
+            cfg!(feature = "backtrace") && CAPTURE_BACKTRACE.load(Ordering::Relaxed)
+        );
+
+        assert_eq!(catch(|| 1), Ok(1));
+    }
+
+    #[test]
+    fn backtrace_env() {
+        assert!(!backtrace_enabled(None));
+        assert!(!backtrace_enabled(Some("0")));
+        assert!(backtrace_enabled(Some("1")));
+        assert!(backtrace_enabled(Some("full")));
+    }
+
+    #[test]
+    fn analyze_errors() {
+        let mut analyzer =
+            Analyzer::new(Default::default(), Packages::default(), Default::default());
+        assert!(analyze(&mut analyzer, "main".into(), "main.flux".into(), "x = 1").is_ok());
+        assert!(matches!(
+            analyze(&mut analyzer, "main".into(), "main.flux".into(), "x = y"),
+            Err(Error::Analysis(_)),
+        ));
+    }
+}
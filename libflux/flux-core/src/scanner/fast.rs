@@ -0,0 +1,142 @@
+//! Fast paths of the scanner for whitespace, comments and string literals, enabled by the `simd`
+//! feature.
+//!
+//! Runs of whitespace, comments and string literals are common in hand-written queries and are
+//! scanned one byte at a time by the generated state machine. Here comments and string literals
+//! are scanned by searching for the bytes that end them with `memchr`, which looks at several
+//! bytes at a time using SIMD instructions where the target supports them. Without the feature
+//! the same search is done a byte at a time, so that tests can check in any configuration that
+//! both paths produce the same tokens and positions as the state machine.
+
+use super::{Position, Scanner, Token, TokenType};
+
+#[cfg(feature = "simd")]
+fn find(needle: u8, haystack: &[u8]) -> Option<usize> {
+    memchr::memchr(needle, haystack)
+}
+
+#[cfg(not(feature = "simd"))]
+fn find(needle: u8, haystack: &[u8]) -> Option<usize> {
+    haystack.iter().position(|&b| b == needle)
+}
+
+#[cfg(feature = "simd")]
+fn find3(needle1: u8, needle2: u8, needle3: u8, haystack: &[u8]) -> Option<usize> {
+    memchr::memchr3(needle1, needle2, needle3, haystack)
+}
+
+#[cfg(not(feature = "simd"))]
+fn find3(needle1: u8, needle2: u8, needle3: u8, haystack: &[u8]) -> Option<usize> {
+    haystack
+        .iter()
+        .position(|&b| b == needle1 || b == needle2 || b == needle3)
+}
+
+impl<'a> Scanner<'a> {
+    /// Skips the whitespace before the next token, counting the newlines it contains like the
+    /// state machine does.
+    pub(super) fn skip_whitespace(&mut self) {
+        let mut p = self.p as usize;
+        let end = self.pe as usize;
+        while p < end {
+            match self.data[p] {
+                b'\n' => {
+                    self.cur_line += 1;
+                    self.last_newline = p as i32 + 1;
+                }
+                b' ' | b'\t' | b'\r' | b'\x0b' | b'\x0c' => (),
+                _ => break,
+            }
+            p += 1;
+        }
+        self.p = p as i32;
+    }
+
+    /// Scans the comment that starts at the current position, if any. The comment includes the
+    /// newline that ends it.
+    pub(super) fn scan_comment(&mut self) -> Option<Token> {
+        let start = self.p as usize;
+        if !self.data[start..self.pe as usize].starts_with(b"//") {
+            return None;
+        }
+        let start_pos = self.position(start);
+        let end = match find(b'\n', &self.data[start..self.pe as usize]) {
+            Some(newline) => {
+                self.cur_line += 1;
+                self.last_newline = (start + newline) as i32 + 1;
+                start + newline + 1
+            }
+            None => self.pe as usize,
+        };
+        // Comments end at a newline or at the end of the input, so `end` is on a character
+        // boundary.
+        Some(self.token(TokenType::Comment, start, end, start_pos))
+    }
+
+    /// Scans the string literal that starts at the current position, if any.
+    ///
+    /// Returns `None` for a string that the state machine does not scan as a single
+    /// [`TokenType::String`], such as a string with an interpolation or an invalid escape,
+    /// leaving the scanner where it was.
+    pub(super) fn scan_string(&mut self) -> Option<Token> {
+        let start = self.p as usize;
+        let data = &self.data[..self.pe as usize];
+        if data.get(start) != Some(&b'"') {
+            return None;
+        }
+        let mut p = start + 1;
+        let end = loop {
+            p += find3(b'"', b'\\', b'$', &data[p..])?;
+            match data[p] {
+                b'"' => break p + 1,
+                b'\\' => match data.get(p + 1)? {
+                    b'n' | b'r' | b't' | b'\\' | b'"' => p += 2,
+                    b'$' if data.get(p + 2) == Some(&b'{') => p += 3,
+                    b'x' if data.len() > p + 3
+                        && data[p + 2].is_ascii_hexdigit()
+                        && data[p + 3].is_ascii_hexdigit() =>
+                    {
+                        p += 4
+                    }
+                    _ => return None,
+                },
+                // A `$` followed by any byte but `{` is text, and it can end the string.
+                _ => match data.get(p + 1)? {
+                    b'{' => return None,
+                    b'"' => break p + 2,
+                    _ => p += 2,
+                },
+            }
+        };
+
+        let start_pos = self.position(start);
+        let text = &data[start..end];
+        if let Some(newline) = text.iter().rposition(|&b| b == b'\n') {
+            self.cur_line += text.iter().filter(|&&b| b == b'\n').count() as i32;
+            self.last_newline = (start + newline) as i32 + 1;
+        }
+        // Strings end at a `"`, so `end` is on a character boundary.
+        Some(self.token(TokenType::String, start, end, start_pos))
+    }
+
+    fn position(&self, offset: usize) -> Position {
+        Position {
+            line: self.cur_line as u32,
+            column: (offset as i32 - self.last_newline + 1) as u32,
+        }
+    }
+
+    /// Builds the token that spans `start..end` and moves the scanner past it.
+    fn token(&mut self, tok: TokenType, start: usize, end: usize, start_pos: Position) -> Token {
+        self.p = end as i32;
+        Token {
+            tok,
+            lit: String::from_utf8_lossy(&self.data[start..end]).into_owned(),
+            start_offset: start as u32,
+            end_offset: end as u32,
+            start_pos,
+            end_pos: self.position(end),
+            comments: vec![],
+        }
+    }
+}
@@ -14,6 +14,8 @@ use scanner_generated::scan;
 mod token;
 pub use token::TokenType;
 
+mod fast;
+
 use super::DefaultHasher;
 
 #[cfg(test)]
@@ -33,6 +35,8 @@ pub struct Scanner<'a> {
     checkpoint_last_newline: i32,
     token: TokenType,
     positions: HashMap<Position, u32, DefaultHasher>,
+    /// Whether whitespace, comments and string literals are scanned on the fast path.
+    fast_path: bool,
 
     /// Comments
     pub comments: Vec<Comment>,
@@ -86,6 +90,7 @@ impl<'a> Scanner<'a> {
             checkpoint_line: 1,
             checkpoint_last_newline: 0,
             positions: HashMap::default(),
+            fast_path: cfg!(feature = "simd"),
             comments: Vec::new(),
         }
     }
@@ -100,6 +105,21 @@ impl<'a> Scanner<'a> {
         self.checkpoint_line = self.cur_line;
         self.checkpoint_last_newline = self.last_newline;
 
+        // Whitespace is part of the text of string expressions, scanned in mode 2.
+        if self.fast_path && mode != 2 {
+            self.skip_whitespace();
+            let t = if self.p == self.eof {
+                Some(self.get_eof_token())
+            } else {
+                self.scan_comment().or_else(|| self.scan_string())
+            };
+            if let Some(t) = t {
+                self.positions.insert(t.start_pos, t.start_offset);
+                self.positions.insert(t.end_pos, t.end_offset);
+                return t;
+            }
+        }
+
         let mut token_start = 0_i32;
         let mut token_start_line = 0_i32;
         let mut token_start_col = 0_i32;
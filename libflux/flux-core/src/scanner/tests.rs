@@ -2275,3 +2275,75 @@ c = 1 + 2
         })
     );
 }
+
+#[test]
+fn test_scan_carriage_returns_and_trailing_comment() {
+    let text = "a\r\n\t// c\r\n  b // d";
+    let mut s = Scanner::new(text);
+    assert_eq!(s.scan().lit, "a");
+    assert_eq!(
+        s.scan(),
+        Token {
+            tok: TokenType::Ident,
+            lit: String::from("b"),
+            start_offset: 12,
+            end_offset: 13,
+            start_pos: Position { line: 3, column: 3 },
+            end_pos: Position { line: 3, column: 4 },
+            comments: vec![Comment {
+                text: String::from("// c\r\n"),
+            }],
+        }
+    );
+    assert_eq!(
+        s.scan(),
+        Token {
+            tok: TokenType::Eof,
+            lit: String::from(""),
+            start_offset: 18,
+            end_offset: 18,
+            start_pos: Position { line: 3, column: 9 },
+            end_pos: Position { line: 3, column: 9 },
+            comments: vec![Comment {
+                text: String::from("// d"),
+            }],
+        }
+    );
+}
+
+#[test]
+fn test_scan_fast_path() {
+    fn scan_all(
+        text: &str,
+        fast_path: bool,
+    ) -> (Vec<Token>, HashMap<Position, u32, DefaultHasher>) {
+        let mut s = Scanner::new(text);
+        s.fast_path = fast_path;
+        let mut tokens = Vec::new();
+        loop {
+            let t = s.scan_with_regex();
+            let eof = t.tok == TokenType::Eof;
+            tokens.push(t);
+            if eof {
+                return (tokens, s.positions);
+            }
+        }
+    }
+
+    let texts = [
+        "a\r\n\t// c\r\n  b // d",
+        "from(bucket: \"benchtest\")\n    // a comment\n    |> range(start: -10m)",
+        r#"x = "a\"b\\c\n\t\r\x41\${d}" + "é€""#,
+        "x = \"two\nlines\"\ny = \"three\n\nlines\" z",
+        r#"a = "$" b = "$$" c = "a$\"" d = "$$${" e = "$x$" f = "$""#,
+        r#"a = "${b}" c = "d ${e} f""#,
+        r#"a = "\q" b = "\x4" c = "\xzz" d = "\$x""#,
+        "a = \"unclosed",
+        "a = \"ends with a backslash\\",
+        "a = \"ends with a dollar$",
+        "a = /regex \"with a quote\"/ b = \"/\"",
+    ];
+    for text in texts.iter() {
+        assert_eq!(scan_all(text, true), scan_all(text, false), "{}", text);
+    }
+}
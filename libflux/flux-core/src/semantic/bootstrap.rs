@@ -3,14 +3,24 @@
 //! This package does not assume a location of the source code but does assume which packages are
 //! part of the prelude.
 
-use std::{env::consts, fs, io, io::Write, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env::consts,
+    fmt, fs,
+    hash::{Hash, Hasher},
+    io,
+    io::Write,
+    mem,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{anyhow, bail, Result};
+use fnv::FnvHasher;
 use libflate::gzip::Encoder;
 use walkdir::WalkDir;
 
 use crate::{
-    ast,
+    ast::{self, callgraph::CallGraph},
     map::HashSet,
     parser,
     semantic::{
@@ -24,7 +34,7 @@ use crate::{
             BoundTvar, BoundTvarKinds, MonoType, PolyType, PolyTypeHashMap, Record, RecordLabel,
             SemanticMap, Tvar,
         },
-        Analyzer, AnalyzerConfig, PackageExports,
+        Analyzer, AnalyzerConfig, ErrorStyle, PackageExports,
     },
 };
 
@@ -48,18 +58,49 @@ pub fn infer_stdlib_dir(
     path: &Path,
     config: AnalyzerConfig,
 ) -> Result<(PackageExports, Packages, SemanticPackageMap)> {
-    let ast_packages = parse_dir(path)?;
+    infer_stdlib(&parse_dir(path)?, config)
+}
 
+#[allow(clippy::type_complexity)]
+fn infer_stdlib(
+    ast_packages: &ASTPackageMap,
+    config: AnalyzerConfig,
+) -> Result<(PackageExports, Packages, SemanticPackageMap)> {
     let mut infer_state = InferState {
         config,
         ..InferState::default()
     };
-    let prelude = infer_state.infer_pre(&ast_packages)?;
-    infer_state.infer_std(&ast_packages, &prelude)?;
+    let prelude = match infer_state.infer_pre(ast_packages) {
+        Ok(prelude) => prelude,
+        Err(err) => {
+            infer_state.errors.push(err);
+            return Err(StdlibErrors(infer_state.errors).into());
+        }
+    };
+    infer_state.infer_std(ast_packages, &prelude)?;
 
     Ok((prelude, infer_state.imports, infer_state.sem_pkg_map))
 }
 
+/// The errors found while inferring the standard library, one for each package that failed and
+/// one for each package that was skipped because one of its dependencies failed.
+#[derive(Debug)]
+pub struct StdlibErrors(pub Vec<anyhow::Error>);
+
+impl fmt::Display for StdlibErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, err) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n\n")?;
+            }
+            write!(f, "{:#}", err)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for StdlibErrors {}
+
 /// Recursively parse all flux files within a directory.
 pub fn parse_dir(dir: &Path) -> io::Result<ASTPackageMap> {
     let mut files = Vec::new();
@@ -97,6 +138,158 @@ pub fn parse_dir(dir: &Path) -> io::Result<ASTPackageMap> {
     Ok(ast_map(files))
 }
 
+/// A value defined at the top level of a standard library package.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StdlibSymbol {
+    /// Import path of the package that defines the symbol.
+    pub package: String,
+    /// Name of the symbol within its package.
+    pub name: String,
+    /// Location of the definition.
+    pub location: ast::SourceLocation,
+    /// The doc comment of the definition, without the comment markers.
+    pub doc: String,
+    /// The functions of the standard library that refer to this symbol,
+    /// named by their package and name, e.g. `strings.title`.
+    pub used_by: BTreeSet<String>,
+}
+
+/// Cross-reference index of the standard library.
+///
+/// Symbols are named by the import path of their package and their name,
+/// e.g. `strings.toUpper` or `influxdata/influxdb/v1.databases`.
+/// Only references made from the bodies of user-defined functions are recorded.
+/// References by unqualified name to a value the package does not define are resolved
+/// against the prelude packages, as the analyzer does.
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct StdlibIndex {
+    symbols: BTreeMap<String, StdlibSymbol>,
+}
+
+impl StdlibIndex {
+    /// Builds the index of a set of parsed packages.
+    pub fn from_packages(ast_packages: &ASTPackageMap) -> Self {
+        let mut index = StdlibIndex::default();
+        for (path, pkg) in ast_packages {
+            for stmt in pkg.files.iter().flat_map(|file| &file.body) {
+                if let Some((id, comments)) = definition(stmt) {
+                    index.symbols.insert(
+                        format!("{}.{}", path, id.name),
+                        StdlibSymbol {
+                            package: path.clone(),
+                            name: id.name.clone(),
+                            location: id.base.location.clone(),
+                            doc: comments
+                                .iter()
+                                .map(|c| {
+                                    let text = c.text.strip_prefix("//").unwrap_or(&c.text);
+                                    text.strip_prefix(' ').unwrap_or(text)
+                                })
+                                .collect(),
+                            used_by: BTreeSet::new(),
+                        },
+                    );
+                }
+            }
+        }
+        for (path, pkg) in ast_packages {
+            let graph = CallGraph::from_package(pkg);
+            for caller in graph.functions() {
+                let mut callees: Vec<String> = graph
+                    .calls(&caller.name)
+                    .iter()
+                    .map(|call| {
+                        // Members of imported packages are already qualified by their import path.
+                        if call.callee.contains('.') {
+                            call.callee.clone()
+                        } else {
+                            format!("{}.{}", path, call.callee)
+                        }
+                    })
+                    .collect();
+                callees.extend(
+                    graph
+                        .free_references(&caller.name)
+                        .iter()
+                        .filter_map(|call| index.prelude_symbol(path, &call.callee)),
+                );
+                for callee in callees {
+                    if let Some(symbol) = index.symbols.get_mut(&callee) {
+                        symbol.used_by.insert(format!("{}.{}", path, caller.name));
+                    }
+                }
+            }
+        }
+        index
+    }
+
+    // Returns the qualified name of the prelude symbol that `name` refers to in the package at
+    // `path`. A prelude package only sees the prelude packages before it, and later packages
+    // shadow earlier ones.
+    fn prelude_symbol(&self, path: &str, name: &str) -> Option<String> {
+        let visible = PRELUDE
+            .iter()
+            .position(|pkg| *pkg == path)
+            .unwrap_or(PRELUDE.len());
+        PRELUDE[..visible]
+            .iter()
+            .rev()
+            .map(|pkg| format!("{}.{}", pkg, name))
+            .find(|qualified| self.symbols.contains_key(qualified))
+    }
+
+    /// Reads the index written by [`compile_stdlib`] to the directory of a compiled stdlib.
+    /// Returns `None` if the directory has no index.
+    pub fn read(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(STDLIB_INDEX_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let file = fs::File::open(&path)?;
+        Ok(Some(serde_json::from_reader(io::BufReader::new(file))?))
+    }
+
+    /// Returns the symbol with the given package qualified name.
+    pub fn symbol(&self, name: &str) -> Option<&StdlibSymbol> {
+        self.symbols.get(name)
+    }
+
+    /// Returns every symbol ordered by their package qualified name.
+    pub fn symbols(&self) -> impl Iterator<Item = &StdlibSymbol> {
+        self.symbols.values()
+    }
+
+    /// Returns the functions that refer to the symbol with the given package qualified name.
+    pub fn used_by(&self, name: &str) -> Vec<&str> {
+        self.symbols
+            .get(name)
+            .map(|symbol| symbol.used_by.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+}
+
+// Returns the identifier and doc comments of a statement that defines a package value.
+fn definition(stmt: &ast::Statement) -> Option<(&ast::Identifier, &[ast::Comment])> {
+    match stmt {
+        ast::Statement::Variable(s) => Some((&s.id, &s.id.base.comments)),
+        ast::Statement::Builtin(s) => Some((&s.id, &s.base.comments)),
+        ast::Statement::Option(s) => match &s.assignment {
+            ast::Assignment::Variable(v) => Some((&v.id, &s.base.comments)),
+            ast::Assignment::Member(_) => None,
+        },
+        _ => None,
+    }
+}
+
+/// Name of the file in which [`compile_stdlib`] writes the [`StdlibIndex`] of the stdlib.
+pub const STDLIB_INDEX_FILE: &str = "stdlib_index.json";
+
+/// Parses the Flux standard library at the given path and builds its cross-reference index.
+pub fn index_stdlib_dir(path: &Path) -> Result<StdlibIndex> {
+    Ok(StdlibIndex::from_packages(&parse_dir(path)?))
+}
+
 // Associates an import path with each file
 fn ast_map(files: Vec<(String, ast::File)>) -> ASTPackageMap {
     files
@@ -168,6 +361,9 @@ struct InferState {
     imports: Packages,
     sem_pkg_map: SemanticPackageMap,
     config: AnalyzerConfig,
+    // packages that failed to be inferred, or whose dependencies did
+    failed: HashSet<String>,
+    errors: Vec<anyhow::Error>,
 }
 
 impl InferState {
@@ -183,21 +379,33 @@ impl InferState {
         Ok(prelude_map)
     }
 
+    // Infers every package, continuing past the packages that fail so that the errors of all
+    // packages are reported together.
     #[allow(clippy::type_complexity)]
     fn infer_std(&mut self, ast_packages: &ASTPackageMap, prelude: &PackageExports) -> Result<()> {
         for (path, _) in ast_packages.iter() {
             // No need to infer the package again if it has already been inferred through a
             // dependency
-            if !self.sem_pkg_map.contains_key(path) {
-                let (types, sem_pkg) = self.infer_pkg(path, ast_packages, prelude)?;
-
-                self.sem_pkg_map.insert(path.to_string(), sem_pkg);
-                if !self.imports.contains_key(path) {
-                    self.imports.insert(path.to_string(), types);
+            if !self.sem_pkg_map.contains_key(path) && !self.failed.contains(path) {
+                match self.infer_pkg(path, ast_packages, prelude) {
+                    Ok((types, sem_pkg)) => {
+                        self.sem_pkg_map.insert(path.to_string(), sem_pkg);
+                        if !self.imports.contains_key(path) {
+                            self.imports.insert(path.to_string(), types);
+                        }
+                    }
+                    Err(err) => {
+                        self.failed.insert(path.to_string());
+                        self.errors.push(err);
+                    }
                 }
             }
         }
-        Ok(())
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(StdlibErrors(mem::take(&mut self.errors)).into())
+        }
     }
 
     // Infer the types in a package(file), returning a hash map containing
@@ -224,12 +432,24 @@ impl InferState {
 
         // Infer all dependencies
         for pkg in deps {
-            if self.imports.import(pkg).is_none() {
-                let (env, sem_pkg) =
-                    self.infer_pkg_with_resolved_deps(pkg, ast_packages, prelude)?;
-
-                self.sem_pkg_map.insert(pkg.to_string(), sem_pkg);
-                self.imports.insert(pkg.to_string(), env);
+            if !self.failed.contains(pkg) && self.imports.import(pkg).is_none() {
+                match self.infer_pkg_with_resolved_deps(pkg, ast_packages, prelude) {
+                    Ok((env, sem_pkg)) => {
+                        self.sem_pkg_map.insert(pkg.to_string(), sem_pkg);
+                        self.imports.insert(pkg.to_string(), env);
+                    }
+                    Err(err) => {
+                        self.failed.insert(pkg.to_string());
+                        self.errors.push(err);
+                    }
+                }
+            }
+            if self.failed.contains(pkg) {
+                bail!(
+                    r#"package "{}" skipped because its dependency "{}" failed"#,
+                    name,
+                    pkg
+                );
             }
         }
 
@@ -250,12 +470,13 @@ impl InferState {
             .ok_or_else(|| anyhow!(r#"package import "{}" not found"#, name))?;
 
         let env = Environment::new(prelude.into());
+        let style = self.config.error_style.unwrap_or(ErrorStyle::Rich);
         let mut analyzer = Analyzer::new(env, &mut self.imports, self.config.clone());
         let (exports, sem_pkg) = analyzer.analyze_ast(file).map_err(|mut err| {
             if err.error.source.is_none() {
                 err.error.source = file.base.location.source.clone();
             }
-            err.error.pretty_error()
+            err.error.styled_error(style)
         })?;
 
         Ok((exports, sem_pkg))
@@ -356,7 +577,26 @@ pub fn stdlib(dir: &Path) -> Result<(PackageExports, FileSystemImporter<StdFS>)>
 
 /// Compiles the stdlib found at the srcdir into the outdir.
 pub fn compile_stdlib(srcdir: &Path, outdir: &Path) -> Result<()> {
-    let (_, imports, mut sem_pkgs) = infer_stdlib_dir(srcdir, AnalyzerConfig::default())?;
+    compile_stdlib_with_config(srcdir, outdir, AnalyzerConfig::default())
+}
+
+/// Compiles the stdlib found at the srcdir into the outdir using the given configuration.
+///
+/// The [`StdlibIndex`] of the stdlib is written to [`STDLIB_INDEX_FILE`] in the outdir.
+///
+/// With the `doc` feature, the examples in the documentation of the stdlib are also type checked
+/// and each one that fails is logged as a warning.
+pub fn compile_stdlib_with_config(
+    srcdir: &Path,
+    outdir: &Path,
+    config: AnalyzerConfig,
+) -> Result<()> {
+    let ast_packages = parse_dir(srcdir)?;
+    let (prelude, imports, mut sem_pkgs) = infer_stdlib(&ast_packages, config.clone())?;
+    #[cfg(feature = "doc")]
+    warn_failed_examples(&ast_packages, &prelude, &imports, config)?;
+    #[cfg(not(feature = "doc"))]
+    drop((prelude, config));
     // Write each file as compiled module
     for (path, exports) in &imports {
         if let Some(code) = sem_pkgs.remove(path) {
@@ -380,9 +620,119 @@ pub fn compile_stdlib(srcdir: &Path, outdir: &Path) -> Result<()> {
             bail!("package {} missing code", &path);
         }
     }
+    let index = StdlibIndex::from_packages(&ast_packages);
+    let file = fs::File::create(outdir.join(STDLIB_INDEX_FILE))?;
+    serde_json::to_writer(io::BufWriter::new(file), &index)?;
     Ok(())
 }
 
+// Logs a warning for each example in the documentation of the stdlib that does not type check
+// against the stdlib. See [`crate::doc::example::check_examples`].
+#[cfg(feature = "doc")]
+fn warn_failed_examples(
+    ast_packages: &ASTPackageMap,
+    prelude: &PackageExports,
+    imports: &Packages,
+    config: AnalyzerConfig,
+) -> Result<()> {
+    for check in crate::doc::example::check_examples(ast_packages, prelude, imports, config) {
+        if let Some(error) = check.error {
+            log::warn!(
+                "example `{}` of {} does not type check:\n{}",
+                check.title,
+                check.path,
+                error
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Returns a hash of the Flux source files of the stdlib found at srcdir, of the features enabled
+/// and the bindings of the configuration and of the version and the sources of the compiler. The
+/// hash changes whenever compiling the stdlib could produce a different result.
+pub fn stdlib_hash(srcdir: &Path, config: &AnalyzerConfig) -> Result<u64> {
+    stdlib_hash_with_compiler(srcdir, config, COMPILER_VERSION)
+}
+
+// Identifies the compiler by its version and a hash of its sources, computed by build.rs, so
+// that changes to the compiler between releases change the hash too.
+const COMPILER_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "+",
+    env!("FLUX_CORE_SOURCE_HASH")
+);
+
+fn stdlib_hash_with_compiler(
+    srcdir: &Path,
+    config: &AnalyzerConfig,
+    compiler_version: &str,
+) -> Result<u64> {
+    let mut hasher = FnvHasher::default();
+    compiler_version.hash(&mut hasher);
+    let entries = WalkDir::new(srcdir)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter();
+    for entry in entries {
+        // A file that cannot be read would be left out of the hash, so fail instead.
+        let entry = entry?;
+        if !entry.file_type().is_file()
+            || entry.path().extension().map_or(true, |ext| ext != "flux")
+        {
+            continue;
+        }
+        entry.path().strip_prefix(srcdir)?.hash(&mut hasher);
+        fs::read(entry.path())?.hash(&mut hasher);
+    }
+    let mut features: Vec<String> = config
+        .features
+        .iter()
+        .map(|feature| format!("{:?}", feature))
+        .collect();
+    features.sort();
+    features.hash(&mut hasher);
+    // Bindings change the environment of every package. Without bindings the hash is left as
+    // it was.
+    for binding in &config.bindings {
+        binding.hash(&mut hasher);
+    }
+    Ok(hasher.finish())
+}
+
+/// Compiles the stdlib found at srcdir into a subdirectory of cache_dir named by its
+/// [`stdlib_hash`] and returns the path of that subdirectory. The stdlib is only compiled when
+/// the subdirectory does not exist yet, so repeated invocations skip inference when neither the
+/// stdlib, the features nor the compiler changed.
+pub fn cached_compile_stdlib(
+    srcdir: &Path,
+    cache_dir: &Path,
+    config: AnalyzerConfig,
+) -> Result<PathBuf> {
+    let key = format!("{:016x}", stdlib_hash(srcdir, &config)?);
+    let dir = cache_dir.join(&key);
+    if dir.is_dir() {
+        log::debug!("using cached stdlib {}", dir.display());
+        return Ok(dir);
+    }
+
+    // Compile into a temporary directory first so that an interrupted compilation never leaves a
+    // partial stdlib behind under the final name.
+    let tmp = cache_dir.join(format!("{}.tmp-{}", key, std::process::id()));
+    if tmp.exists() {
+        fs::remove_dir_all(&tmp)?;
+    }
+    fs::create_dir_all(&tmp)?;
+    compile_stdlib_with_config(srcdir, &tmp, config)?;
+    if let Err(err) = fs::rename(&tmp, &dir) {
+        // Another process may have filled the cache in the meantime.
+        fs::remove_dir_all(&tmp)?;
+        if !dir.is_dir() {
+            return Err(err.into());
+        }
+    }
+    Ok(dir)
+}
+
 /// Module represenets the result of compiling Flux source code.
 ///
 /// The polytype represents the type of the entire package as a record type.
@@ -490,6 +840,120 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn stdlib_index() {
+        let a = r#"
+            package a
+
+            // f is the identity.
+            f = (x) => x
+            builtin g : (x: int) => int
+        "#;
+        let b = r#"
+            package b
+
+            import "a"
+
+            h = (x) => a.f(x: a.g(x: x))
+            k = (x) => h(x: x)
+            p = (x) => u(x: x)
+            q = (u) => u(x: 1)
+        "#;
+        let universe = r#"
+            package universe
+
+            u = (x) => x
+        "#;
+        let ast_packages: ASTPackageMap = semantic_map! {
+            String::from("a") => parse_string("a/a.flux".to_string(), a).into(),
+            String::from("b") => parse_string("b/b.flux".to_string(), b).into(),
+            String::from("universe") => parse_string("universe/universe.flux".to_string(), universe).into(),
+        };
+        let index = StdlibIndex::from_packages(&ast_packages);
+
+        assert_eq!(
+            index.symbols().map(|s| s.name.as_str()).collect::<Vec<_>>(),
+            vec!["f", "g", "h", "k", "p", "q", "u"],
+        );
+        assert_eq!(index.symbol("a.f").unwrap().doc, "f is the identity.\n");
+        assert_eq!(index.used_by("a.f"), vec!["b.h"]);
+        assert_eq!(index.used_by("a.g"), vec!["b.h"]);
+        assert_eq!(index.used_by("b.h"), vec!["b.k"]);
+        assert!(index.used_by("b.k").is_empty());
+        // Unqualified names the package does not define are resolved against the prelude,
+        // unless a parameter shadows them.
+        assert_eq!(index.used_by("universe.u"), vec!["b.p"]);
+    }
+
+    #[test]
+    fn infer_std_reports_all_packages() {
+        let a = r#"
+            x = 1 + "a"
+        "#;
+        let b = r#"
+            import "a"
+
+            y = a.x
+        "#;
+        let c = r#"
+            z = 1 + "c"
+        "#;
+        let d = r#"
+            w = 1
+        "#;
+        let ast_packages: ASTPackageMap = semantic_map! {
+            String::from("a") => parse_string("a.flux".to_string(), a).into(),
+            String::from("b") => parse_string("b.flux".to_string(), b).into(),
+            String::from("c") => parse_string("c.flux".to_string(), c).into(),
+            String::from("d") => parse_string("d.flux".to_string(), d).into(),
+        };
+        let mut infer_state = InferState::default();
+        let err = infer_state
+            .infer_std(&ast_packages, &PackageExports::new())
+            .expect_err("expected inference errors");
+        let errors = &err.downcast_ref::<StdlibErrors>().unwrap().0;
+
+        assert_eq!(errors.len(), 3);
+        assert_eq!(
+            errors[1].to_string(),
+            r#"package "b" skipped because its dependency "a" failed"#,
+        );
+        assert!(infer_state.imports.contains_key("d"));
+    }
+
+    #[test]
+    fn stdlib_hash_changes() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("stdlib_hash_{}", std::process::id()));
+        fs::create_dir_all(dir.join("a"))?;
+        fs::write(dir.join("a/a.flux"), "package a\n\nx = 1\n")?;
+
+        let config = AnalyzerConfig::default();
+        let hash = stdlib_hash(&dir, &config)?;
+        assert_eq!(hash, stdlib_hash(&dir, &config)?);
+
+        let features = AnalyzerConfig {
+            features: vec![crate::semantic::Feature::LabelPolymorphism],
+            ..AnalyzerConfig::default()
+        };
+        assert_ne!(hash, stdlib_hash(&dir, &features)?);
+
+        assert_eq!(
+            hash,
+            stdlib_hash_with_compiler(&dir, &config, COMPILER_VERSION)?
+        );
+        assert_ne!(
+            hash,
+            stdlib_hash_with_compiler(&dir, &config, env!("CARGO_PKG_VERSION"))?
+        );
+        assert!(stdlib_hash(&dir.join("missing"), &config).is_err());
+
+        fs::write(dir.join("a/a.flux"), "package a\n\nx = 2\n")?;
+        assert_ne!(hash, stdlib_hash(&dir, &config)?);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
     #[test]
     fn cyclic_dependency() {
         let a = r#"
@@ -0,0 +1,76 @@
+use crate::{
+    ast::Operator,
+    errors::located,
+    semantic::{
+        nodes::{CallExpr, Expression, Package},
+        types::MonoType,
+        walk::{walk, Node},
+        Warning, WarningKind,
+    },
+};
+
+// Returns the `v` argument of a call to the universe conversion function with the given name.
+fn conversion<'a>(call: &'a CallExpr, name: &str) -> Option<&'a Expression> {
+    match &call.callee {
+        Expression::Identifier(id)
+            if id.name.as_str() == name && matches!(id.name.package(), None | Some("universe")) =>
+        {
+            call.arguments
+                .iter()
+                .find(|arg| arg.key.name.as_str() == "v")
+                .map(|arg| &arg.value)
+        }
+        _ => None,
+    }
+}
+
+// Returns whether the expression converts a duration to its number of nanoseconds.
+fn is_nanoseconds(expr: &Expression) -> bool {
+    match expr {
+        Expression::Call(call) => {
+            conversion(call, "int").map_or(false, |v| v.type_of() == MonoType::DURATION)
+        }
+        _ => false,
+    }
+}
+
+/// Finds arithmetic that mixes up the units of durations: durations converted to integers are
+/// counted in nanoseconds, so multiplying two of them, or adding a plain integer to one, is
+/// most likely a mistake.
+pub fn duration_arithmetic(pkg: &Package) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    walk(
+        &mut |node: Node| match node {
+            Node::BinaryExpr(binary) => {
+                let (left, right) = (is_nanoseconds(&binary.left), is_nanoseconds(&binary.right));
+                let kind = match binary.operator {
+                    Operator::MultiplicationOperator if left && right => {
+                        WarningKind::DurationProduct
+                    }
+                    Operator::AdditionOperator | Operator::SubtractionOperator
+                        if left != right
+                            && matches!(
+                                if left { &binary.right } else { &binary.left },
+                                Expression::Integer(_)
+                            ) =>
+                    {
+                        WarningKind::MixedDurationUnits
+                    }
+                    _ => return,
+                };
+                warnings.push(located(binary.loc.clone(), kind));
+            }
+            Node::CallExpr(call) => {
+                if let Some(Expression::Integer(lit)) = conversion(call, "duration") {
+                    warnings.push(located(
+                        call.loc.clone(),
+                        WarningKind::NanosecondDuration(lit.value),
+                    ));
+                }
+            }
+            _ => (),
+        },
+        Node::Package(pkg),
+    );
+    warnings
+}
@@ -14,6 +14,11 @@ use crate::{ast, semantic, semantic::walk};
 
 const UNKNOWNVARIANTNAME: &str = "UNKNOWNSEMANTIC";
 
+/// The version of the serialization of the semantic graph, written in the `version` field of
+/// each serialized package. Readers reject packages of a later version, whose nodes they may not
+/// know. Packages serialized before the serialization was versioned have the version 0.
+pub const SEMANTIC_VERSION: u32 = 1;
+
 /// Serializes a [`semantic::bootstrap::SemanticPackageMap`].
 pub fn build_sem_packages<'a>(
     builder: &mut flatbuffers::FlatBufferBuilder<'a>,
@@ -901,6 +906,7 @@ impl<'a, 'b> semantic::walk::Visitor<'_> for SerializingVisitor<'a, 'b> {
                         loc,
                         package,
                         files,
+                        version: SEMANTIC_VERSION,
                     },
                 ));
             }
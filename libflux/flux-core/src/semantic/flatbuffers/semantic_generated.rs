@@ -5322,6 +5322,7 @@ pub mod fbsemantic {
             args: &'args PackageArgs<'args>,
         ) -> flatbuffers::WIPOffset<Package<'bldr>> {
             let mut builder = PackageBuilder::new(_fbb);
+            builder.add_version(args.version);
             if let Some(x) = args.files {
                 builder.add_files(x);
             }
@@ -5337,6 +5338,7 @@ pub mod fbsemantic {
         pub const VT_LOC: flatbuffers::VOffsetT = 4;
         pub const VT_PACKAGE: flatbuffers::VOffsetT = 6;
         pub const VT_FILES: flatbuffers::VOffsetT = 8;
+        pub const VT_VERSION: flatbuffers::VOffsetT = 10;
 
         #[inline]
         pub fn loc(&self) -> Option<SourceLocation<'a>> {
@@ -5356,6 +5358,10 @@ pub mod fbsemantic {
                 flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<File>>,
             >>(Package::VT_FILES, None)
         }
+        #[inline]
+        pub fn version(&self) -> u32 {
+            self._tab.get::<u32>(Package::VT_VERSION, Some(0)).unwrap()
+        }
     }
 
     impl flatbuffers::Verifiable for Package<'_> {
@@ -5379,6 +5385,7 @@ pub mod fbsemantic {
                 .visit_field::<flatbuffers::ForwardsUOffset<
                     flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<File>>,
                 >>(&"files", Self::VT_FILES, false)?
+                .visit_field::<u32>(&"version", Self::VT_VERSION, false)?
                 .finish();
             Ok(())
         }
@@ -5389,6 +5396,7 @@ pub mod fbsemantic {
         pub files: Option<
             flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<File<'a>>>>,
         >,
+        pub version: u32,
     }
     impl<'a> Default for PackageArgs<'a> {
         #[inline]
@@ -5397,6 +5405,7 @@ pub mod fbsemantic {
                 loc: None,
                 package: None,
                 files: None,
+                version: 0,
             }
         }
     }
@@ -5426,6 +5435,10 @@ pub mod fbsemantic {
                 .push_slot_always::<flatbuffers::WIPOffset<_>>(Package::VT_FILES, files);
         }
         #[inline]
+        pub fn add_version(&mut self, version: u32) {
+            self.fbb_.push_slot::<u32>(Package::VT_VERSION, version, 0);
+        }
+        #[inline]
         pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> PackageBuilder<'a, 'b> {
             let start = _fbb.start_table();
             PackageBuilder {
@@ -5446,6 +5459,7 @@ pub mod fbsemantic {
             ds.field("loc", &self.loc());
             ds.field("package", &self.package());
             ds.field("files", &self.files());
+            ds.field("version", &self.version());
             ds.finish()
         }
     }
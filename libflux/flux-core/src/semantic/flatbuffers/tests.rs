@@ -1,7 +1,7 @@
 use anyhow::{anyhow, Result};
 use chrono::FixedOffset;
 
-use super::semantic_generated::fbsemantic;
+use super::{semantic_generated::fbsemantic, SEMANTIC_VERSION};
 use crate::{
     ast,
     semantic::{
@@ -221,6 +221,13 @@ fn compare_pkg_fb(
 ) -> Result<()> {
     compare_loc(&semantic_pkg.loc, &fb_pkg.loc())?;
     compare_strings("package name", &semantic_pkg.package, &fb_pkg.package())?;
+    if fb_pkg.version() != SEMANTIC_VERSION {
+        return Err(anyhow!(
+            "package version: {} != {}",
+            SEMANTIC_VERSION,
+            fb_pkg.version()
+        ));
+    }
 
     let fb_files = &fb_pkg.files();
     let fb_files = unwrap_or_fail("package files", fb_files)?;
@@ -1,6 +1,8 @@
 //! This module defines methods for serializing and deserializing MonoTypes
 //! and PolyTypes using the flatbuffer encoding.
 
+use std::collections::BTreeSet;
+
 use crate::{
     map::HashMap,
     semantic::{flatbuffers::semantic_generated::fbsemantic as fb, PackageExports},
@@ -34,6 +36,26 @@ impl DeserializeFlatBuffer {
         Some(packages)
     }
 
+    fn deserialize_packages_where(
+        &mut self,
+        fb_packages: fb::Packages<'_>,
+        load: impl Fn(&str) -> bool,
+    ) -> Option<(Packages, BTreeSet<String>)> {
+        let fb_packages = fb_packages.packages()?;
+        let mut packages = Packages::new();
+        let mut skipped = BTreeSet::new();
+        for package in fb_packages.iter() {
+            let id = package.id()?;
+            if load(id) {
+                let (id, package) = self.deserialize_package_entry(package)?;
+                packages.insert(id, package);
+            } else {
+                skipped.insert(id.to_string());
+            }
+        }
+        Some((packages, skipped))
+    }
+
     fn deserialize_package_entry(
         &mut self,
         a: fb::PackageExports<'_>,
@@ -70,6 +92,16 @@ impl From<fb::Packages<'_>> for Option<Packages> {
     }
 }
 
+/// Decodes the packages whose import path satisfies `load`, returning them along with the import
+/// paths of the packages that were skipped. Skipping the packages that are not needed saves the
+/// memory their types would use.
+pub fn deserialize_packages_where(
+    fb_packages: fb::Packages<'_>,
+    load: impl Fn(&str) -> bool,
+) -> Option<(Packages, BTreeSet<String>)> {
+    DeserializeFlatBuffer::default().deserialize_packages_where(fb_packages, load)
+}
+
 impl From<fb::TypeEnvironment<'_>> for Option<PackageExports> {
     fn from(env: fb::TypeEnvironment) -> Option<PackageExports> {
         DeserializeFlatBuffer::default().deserialize_package_exports(env)
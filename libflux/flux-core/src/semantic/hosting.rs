@@ -0,0 +1,281 @@
+//! Hosting of the Flux standard library in long-running processes.
+//!
+//! A [`Stdlib`] is inferred from source once and shared between the analysis sessions that use
+//! it. A [`ReloadableStdlib`] replaces it when its source changes, without interrupting the
+//! sessions that are still using the previous one. A [`StdlibRegistry`] hosts several standard
+//! libraries side by side, such as different versions or feature sets, and routes each request
+//! to the one it names.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::semantic::{
+    bootstrap, env::Environment, import::Packages, Analyzer, AnalyzerConfig, PackageExports,
+};
+
+/// A standard library inferred from source.
+#[derive(Debug)]
+pub struct Stdlib {
+    prelude: PackageExports,
+    imports: Packages,
+    hash: u64,
+}
+
+impl Stdlib {
+    /// Infers the standard library whose source is found at srcdir.
+    pub fn load(srcdir: &Path, config: &AnalyzerConfig) -> Result<Self> {
+        let hash = bootstrap::stdlib_hash(srcdir, config)?;
+        let (prelude, imports, _) = bootstrap::infer_stdlib_dir(srcdir, config.clone())?;
+        Ok(Stdlib {
+            prelude,
+            imports,
+            hash,
+        })
+    }
+
+    /// Creates a standard library from types that were already inferred, such as the ones
+    /// embedded in a binary, and the [`bootstrap::stdlib_hash`] of their source.
+    pub fn new(prelude: PackageExports, imports: Packages, hash: u64) -> Self {
+        Stdlib {
+            prelude,
+            imports,
+            hash,
+        }
+    }
+
+    /// Returns the values in scope in every Flux script.
+    pub fn prelude(&self) -> &PackageExports {
+        &self.prelude
+    }
+
+    /// Returns the types of the packages that can be imported.
+    pub fn imports(&self) -> &Packages {
+        &self.imports
+    }
+
+    /// Returns the [`bootstrap::stdlib_hash`] of the source the standard library was inferred
+    /// from.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Creates an analyzer that resolves the prelude and imports in this standard library.
+    pub fn analyzer(&self, config: AnalyzerConfig) -> Analyzer<'_, &Packages> {
+        Analyzer::new(Environment::from(&self.prelude), &self.imports, config)
+    }
+}
+
+/// A standard library that is inferred again when its source changes.
+///
+/// Sessions hold on to the [`Stdlib`] returned by [`ReloadableStdlib::current`] for as long as
+/// they need it; a reload only affects the sessions started after it.
+#[derive(Debug)]
+pub struct ReloadableStdlib {
+    srcdir: PathBuf,
+    config: AnalyzerConfig,
+    current: RwLock<Arc<Stdlib>>,
+    // Held while reloading so that concurrent reloads do not infer the stdlib twice.
+    reloading: Mutex<()>,
+}
+
+impl ReloadableStdlib {
+    /// Infers the standard library whose source is found at srcdir.
+    pub fn load(srcdir: impl Into<PathBuf>, config: AnalyzerConfig) -> Result<Self> {
+        let srcdir = srcdir.into();
+        let stdlib = Stdlib::load(&srcdir, &config)?;
+        Ok(ReloadableStdlib {
+            srcdir,
+            config,
+            current: RwLock::new(Arc::new(stdlib)),
+            reloading: Mutex::new(()),
+        })
+    }
+
+    /// Returns the most recently loaded standard library.
+    pub fn current(&self) -> Arc<Stdlib> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Infers the standard library again if its source changed since it was last loaded.
+    /// Returns whether a new standard library was loaded. On error the current standard library
+    /// is kept.
+    pub fn reload(&self) -> Result<bool> {
+        let _reloading = self.reloading.lock().unwrap();
+        let hash = bootstrap::stdlib_hash(&self.srcdir, &self.config)?;
+        if hash == self.current().hash {
+            return Ok(false);
+        }
+        let stdlib = Stdlib::load(&self.srcdir, &self.config)?;
+        *self.current.write().unwrap() = Arc::new(stdlib);
+        Ok(true)
+    }
+
+    /// Starts a thread that checks for changes to the source every interval and reloads the
+    /// standard library when it changed. The thread stops once the standard library is dropped.
+    pub fn watch(self: &Arc<Self>, interval: Duration) -> thread::JoinHandle<()> {
+        let stdlib = Arc::downgrade(self);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            match stdlib.upgrade() {
+                Some(stdlib) => match stdlib.reload() {
+                    Ok(true) => log::info!("reloaded stdlib from {}", stdlib.srcdir.display()),
+                    Ok(false) => (),
+                    Err(err) => log::warn!("failed to reload stdlib: {:#}", err),
+                },
+                None => break,
+            }
+        })
+    }
+}
+
+/// A set of standard libraries hosted in the same process, each identified by a name such as a
+/// version or the name of a feature set.
+#[derive(Debug, Default)]
+pub struct StdlibRegistry {
+    stdlibs: RwLock<BTreeMap<String, Arc<ReloadableStdlib>>>,
+}
+
+impl StdlibRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Infers the standard library whose source is found at srcdir and hosts it under the given
+    /// name, replacing any standard library previously hosted under that name.
+    pub fn register(
+        &self,
+        name: impl Into<String>,
+        srcdir: impl Into<PathBuf>,
+        config: AnalyzerConfig,
+    ) -> Result<()> {
+        let stdlib = Arc::new(ReloadableStdlib::load(srcdir, config)?);
+        self.stdlibs.write().unwrap().insert(name.into(), stdlib);
+        Ok(())
+    }
+
+    /// Stops hosting the standard library with the given name. Returns whether it was hosted.
+    pub fn remove(&self, name: &str) -> bool {
+        self.stdlibs.write().unwrap().remove(name).is_some()
+    }
+
+    /// Returns the names of the hosted standard libraries.
+    pub fn names(&self) -> Vec<String> {
+        self.stdlibs.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Returns the reloadable standard library with the given name.
+    pub fn reloadable(&self, name: &str) -> Option<Arc<ReloadableStdlib>> {
+        self.stdlibs.read().unwrap().get(name).cloned()
+    }
+
+    /// Returns the most recently loaded standard library with the given name.
+    pub fn get(&self, name: &str) -> Result<Arc<Stdlib>> {
+        self.reloadable(name)
+            .map(|stdlib| stdlib.current())
+            .ok_or_else(|| anyhow!(r#"stdlib "{}" is not hosted"#, name))
+    }
+
+    /// Reloads every hosted standard library whose source changed, returning the result of the
+    /// reload of each by name.
+    pub fn reload_all(&self) -> Vec<(String, Result<bool>)> {
+        // Reload outside of the lock so requests are not blocked while inferring.
+        #[allow(clippy::needless_collect)]
+        let stdlibs: Vec<_> = self
+            .stdlibs
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, stdlib)| (name.clone(), stdlib.clone()))
+            .collect();
+        stdlibs
+            .into_iter()
+            .map(|(name, stdlib)| (name, stdlib.reload()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    // Writes a minimal standard library, with the packages of the prelude, to a new directory.
+    fn write_stdlib(name: &str, universe: &str) -> Result<PathBuf> {
+        let dir = std::env::temp_dir()
+            .join(format!("{}_{}", name, std::process::id()))
+            .join("stdlib");
+        for (path, src) in [
+            ("internal/boolean", "package boolean\n"),
+            ("internal/location", "package location\n"),
+            ("universe", universe),
+            ("influxdata/influxdb", "package influxdb\n"),
+        ] {
+            let pkg = dir.join(path);
+            fs::create_dir_all(&pkg)?;
+            let file = pkg.join(format!("{}.flux", path.rsplit('/').next().unwrap()));
+            fs::write(file, src)?;
+        }
+        Ok(dir)
+    }
+
+    #[test]
+    fn reload() -> Result<()> {
+        let dir = write_stdlib("reload", "package universe\n\nx = 1\n")?;
+        let stdlib = ReloadableStdlib::load(&dir, AnalyzerConfig::default())?;
+        let before = stdlib.current();
+        assert!(before.prelude().lookup("x").is_some());
+
+        assert!(!stdlib.reload()?);
+
+        fs::write(
+            dir.join("universe/universe.flux"),
+            "package universe\n\ny = 1\n",
+        )?;
+        assert!(stdlib.reload()?);
+        let after = stdlib.current();
+        assert!(after.prelude().lookup("y").is_some());
+        // Sessions started before the reload keep the previous stdlib.
+        assert!(before.prelude().lookup("x").is_some());
+
+        fs::remove_dir_all(dir.parent().unwrap())?;
+        Ok(())
+    }
+
+    #[test]
+    fn registry() -> Result<()> {
+        let v1 = write_stdlib("registry_v1", "package universe\n\nx = 1\n")?;
+        let v2 = write_stdlib("registry_v2", "package universe\n\nx = \"a\"\n")?;
+        let registry = StdlibRegistry::new();
+        registry.register("v1", &v1, AnalyzerConfig::default())?;
+        registry.register("v2", &v2, AnalyzerConfig::default())?;
+        assert_eq!(registry.names(), vec!["v1", "v2"]);
+
+        let src = "y = x + 1";
+        let v1_stdlib = registry.get("v1")?;
+        let mut analyzer = v1_stdlib.analyzer(AnalyzerConfig::default());
+        assert!(analyzer
+            .analyze_source("main".to_string(), "main.flux".to_string(), src)
+            .is_ok());
+        let v2_stdlib = registry.get("v2")?;
+        let mut analyzer = v2_stdlib.analyzer(AnalyzerConfig::default());
+        assert!(analyzer
+            .analyze_source("main".to_string(), "main.flux".to_string(), src)
+            .is_err());
+
+        assert!(registry.remove("v1"));
+        assert!(registry.get("v1").is_err());
+
+        fs::remove_dir_all(v1.parent().unwrap())?;
+        fs::remove_dir_all(v2.parent().unwrap())?;
+        Ok(())
+    }
+}
@@ -1,9 +1,14 @@
 //! Module import defines the abstractions for importing Flux package types from various sources.
 
-use crate::semantic::{
-    nodes::Symbol,
-    types::{PolyType, SemanticMap},
-    PackageExports,
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    ast::callgraph::CallGraph,
+    semantic::{
+        nodes::Symbol,
+        types::{PolyType, SemanticMap},
+        PackageExports,
+    },
 };
 
 /// Importer defines an API for resolving Flux import paths to their corresponding types.
@@ -18,6 +23,18 @@ pub trait Importer {
     fn symbol(&mut self, _package_path: &str, _symbol_name: &str) -> Option<Symbol> {
         None
     }
+
+    /// Returns whether the package exists but was not loaded by this importer. Imports of such
+    /// packages are reported as packages that are not loaded rather than invalid import paths.
+    fn is_unloaded(&mut self, _path: &str) -> bool {
+        false
+    }
+
+    /// Returns the call graph of the user-defined functions of a package, for the packages
+    /// whose source the importer has. See [`CallGraph::link`].
+    fn call_graph(&mut self, _path: &str) -> Option<CallGraph> {
+        None
+    }
 }
 
 impl<T> Importer for &'_ mut T
@@ -30,6 +47,12 @@ where
     fn symbol(&mut self, package_path: &str, symbol_name: &str) -> Option<Symbol> {
         T::symbol(self, package_path, symbol_name)
     }
+    fn is_unloaded(&mut self, path: &str) -> bool {
+        T::is_unloaded(self, path)
+    }
+    fn call_graph(&mut self, path: &str) -> Option<CallGraph> {
+        T::call_graph(self, path)
+    }
 }
 
 /// In memory storage for packages
@@ -56,3 +79,50 @@ impl Importer for &'_ Packages {
             .cloned()
     }
 }
+
+/// An importer of some of the packages of a collection, such as the standard library, for
+/// analyzers that do not need the whole collection.
+#[derive(Debug, Clone, Default)]
+pub struct PartialImporter {
+    /// The packages that can be imported.
+    pub packages: Packages,
+    /// The import paths of the other packages of the collection.
+    pub unloaded: BTreeSet<String>,
+}
+
+impl Importer for PartialImporter {
+    fn import(&mut self, path: &str) -> Option<PolyType> {
+        self.packages.import(path)
+    }
+    fn symbol(&mut self, package_path: &str, symbol_name: &str) -> Option<Symbol> {
+        self.packages.symbol(package_path, symbol_name)
+    }
+    fn is_unloaded(&mut self, path: &str) -> bool {
+        self.unloaded.contains(path)
+    }
+}
+
+/// An importer that provides the call graphs of user packages along with the packages of
+/// another importer, so that the call graph of a package links to the functions it imports.
+#[derive(Debug, Clone, Default)]
+pub struct CallGraphImporter<I> {
+    /// The importer of the packages.
+    pub importer: I,
+    /// The call graphs of the user packages, by import path.
+    pub call_graphs: HashMap<String, CallGraph>,
+}
+
+impl<I: Importer> Importer for CallGraphImporter<I> {
+    fn import(&mut self, path: &str) -> Option<PolyType> {
+        self.importer.import(path)
+    }
+    fn symbol(&mut self, package_path: &str, symbol_name: &str) -> Option<Symbol> {
+        self.importer.symbol(package_path, symbol_name)
+    }
+    fn is_unloaded(&mut self, path: &str) -> bool {
+        self.importer.is_unloaded(path)
+    }
+    fn call_graph(&mut self, path: &str) -> Option<CallGraph> {
+        self.call_graphs.get(path).cloned()
+    }
+}
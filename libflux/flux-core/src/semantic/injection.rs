@@ -0,0 +1,210 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    ast::{
+        self,
+        walk::{walk, Node, Visitor},
+    },
+    errors::located,
+    semantic::{Warning, WarningKind},
+};
+
+// Functions that run a query given as a string, by import path, function and argument name.
+const QUERY_SINKS: &[(&str, &str, &str)] = &[("sql", "from", "query")];
+
+// A parameter that flows into a value.
+#[derive(Clone)]
+struct Taint<'a> {
+    parameter: &'a str,
+    // Where the parameter is built into a string, or where the value is used if it is not.
+    location: &'a ast::SourceLocation,
+    built: bool,
+}
+
+// The parameters of a function and the variables of its body that they flow into.
+struct Scope<'a> {
+    params: HashSet<&'a str>,
+    variables: HashMap<&'a str, Vec<Taint<'a>>>,
+}
+
+// Collects the uses of function parameters in queries built from strings.
+struct InjectionCollector<'a> {
+    // Import path of each imported package by the name it is referred to in the current file.
+    imports: HashMap<&'a str, &'a str>,
+    // The scopes of the enclosing functions, innermost last.
+    scopes: Vec<Scope<'a>>,
+    warnings: Vec<Warning>,
+}
+
+impl<'a> InjectionCollector<'a> {
+    fn sink(&self, call: &'a ast::CallExpr) -> Option<(String, &'a ast::Expression)> {
+        let member = match &call.callee {
+            ast::Expression::Member(member) => member,
+            _ => return None,
+        };
+        let object = match &member.object {
+            ast::Expression::Identifier(id) => id.name.as_str(),
+            _ => return None,
+        };
+        let path = self.imports.get(object)?;
+        let (_, _, arg) = QUERY_SINKS
+            .iter()
+            .find(|(p, f, _)| p == path && *f == member.property.key())?;
+        let query = call
+            .arguments
+            .iter()
+            .flat_map(|arg| match arg {
+                ast::Expression::Object(obj) => obj.properties.as_slice(),
+                _ => &[][..],
+            })
+            .find(|prop| prop.key.key() == *arg)?
+            .value
+            .as_ref()?;
+        Some((format!("{}.{}", object, member.property.key()), query))
+    }
+
+    // Returns the parameters an identifier refers to, directly or through variables.
+    fn lookup(&self, id: &'a ast::Identifier) -> Vec<Taint<'a>> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(taints) = scope.variables.get(id.name.as_str()) {
+                return taints.clone();
+            }
+            if scope.params.contains(id.name.as_str()) {
+                return vec![Taint {
+                    parameter: &id.name,
+                    location: &id.base.location,
+                    built: false,
+                }];
+            }
+        }
+        Vec::new()
+    }
+
+    // Returns the parameters that flow into an expression, and whether they are interpolated or
+    // concatenated into it.
+    fn taints(&self, expr: &'a ast::Expression, built: bool) -> Vec<Taint<'a>> {
+        match expr {
+            ast::Expression::Identifier(id) => self
+                .lookup(id)
+                .into_iter()
+                .map(|taint| {
+                    if taint.built {
+                        taint
+                    } else {
+                        Taint {
+                            location: &id.base.location,
+                            built,
+                            ..taint
+                        }
+                    }
+                })
+                .collect(),
+            ast::Expression::StringExpr(string) => string
+                .parts
+                .iter()
+                .flat_map(|part| match part {
+                    ast::StringExprPart::Interpolated(part) => self.taints(&part.expression, true),
+                    _ => Vec::new(),
+                })
+                .collect(),
+            ast::Expression::Binary(binary)
+                if binary.operator == ast::Operator::AdditionOperator =>
+            {
+                let mut taints = self.taints(&binary.left, true);
+                taints.extend(self.taints(&binary.right, true));
+                taints
+            }
+            ast::Expression::Paren(paren) => self.taints(&paren.expression, built),
+            ast::Expression::Member(member) => self.taints(&member.object, built),
+            // Conversions such as `string(v: x)` do not make a value safe.
+            ast::Expression::Call(inner) if built => inner
+                .arguments
+                .iter()
+                .flat_map(|arg| match arg {
+                    ast::Expression::Object(obj) => obj.properties.as_slice(),
+                    _ => &[][..],
+                })
+                .filter_map(|prop| prop.value.as_ref())
+                .flat_map(|value| self.taints(value, built))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    // Reports the parameters that are interpolated or concatenated into a query.
+    fn check_query(&mut self, call: &str, expr: &'a ast::Expression) {
+        for taint in self.taints(expr, false) {
+            if taint.built
+                && !self
+                    .warnings
+                    .iter()
+                    .any(|warning| warning.location == *taint.location)
+            {
+                self.warnings.push(located(
+                    taint.location.clone(),
+                    WarningKind::QueryInjection {
+                        call: call.to_string(),
+                        parameter: taint.parameter.to_string(),
+                    },
+                ));
+            }
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for InjectionCollector<'a> {
+    fn visit(&mut self, node: Node<'a>) -> bool {
+        match node {
+            Node::File(file) => {
+                self.imports = file
+                    .imports
+                    .iter()
+                    .map(|import| {
+                        let path = import.path.value.as_str();
+                        let name = match &import.alias {
+                            Some(alias) => alias.name.as_str(),
+                            None => path.rsplit('/').next().unwrap_or(path),
+                        };
+                        (name, path)
+                    })
+                    .collect();
+            }
+            Node::FunctionExpr(func) => self.scopes.push(Scope {
+                params: func.params.iter().map(|param| param.key.key()).collect(),
+                variables: HashMap::new(),
+            }),
+            Node::VariableAssgn(assign) if !self.scopes.is_empty() => {
+                let taints = self.taints(&assign.init, false);
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.variables.insert(&assign.id.name, taints);
+                }
+            }
+            Node::CallExpr(call) => {
+                if let Some((name, query)) = self.sink(call) {
+                    self.check_query(&name, query);
+                }
+            }
+            _ => (),
+        }
+        true
+    }
+
+    fn done(&mut self, node: Node<'a>) {
+        if let Node::FunctionExpr(_) = node {
+            self.scopes.pop();
+        }
+    }
+}
+
+/// Finds parameters of functions that are interpolated or concatenated into the query
+/// run by a function such as `sql.from`, which lets the callers inject arbitrary queries.
+/// Parameters are followed through the variables of the function body they are assigned to.
+pub fn query_injections(pkg: &ast::Package) -> Vec<Warning> {
+    let mut collector = InjectionCollector {
+        imports: HashMap::new(),
+        scopes: Vec::new(),
+        warnings: Vec::new(),
+    };
+    walk(&mut collector, Node::Package(pkg));
+    collector.warnings
+}
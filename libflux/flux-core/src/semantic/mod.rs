@@ -2,10 +2,18 @@
 
 pub mod convert;
 
+mod durations;
 mod fs;
 mod infer;
+mod injection;
+mod nulls;
+mod numeric;
+mod pipelines;
 mod symbols;
+#[cfg(feature = "timezones")]
+mod timezones;
 mod vectorize;
+mod windows;
 
 #[macro_use]
 pub mod types;
@@ -15,9 +23,12 @@ pub mod check;
 pub mod env;
 pub mod formatter;
 pub mod fresh;
+pub mod hosting;
 pub mod import;
 pub mod nodes;
+pub mod schema;
 pub mod sub;
+pub mod usage;
 pub mod walk;
 
 #[cfg(test)]
@@ -26,11 +37,18 @@ mod tests;
 #[allow(unused, non_snake_case)]
 pub mod flatbuffers;
 
-use std::{fmt, ops::Range, str::FromStr};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    ops::Range,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+};
 
+use anyhow::Context as _;
 use codespan_reporting::{
     diagnostic,
-    files::Files,
     term::{
         self,
         termcolor::{self, WriteColor},
@@ -39,14 +57,22 @@ use codespan_reporting::{
 use thiserror::Error;
 
 use crate::{
-    ast,
-    errors::{AsDiagnostic, Errors, Located, Salvage, SalvageResult},
+    ast::{
+        self,
+        lines::{encode_column, ColumnEncoding, LineIndex},
+        snippets::{Expansion, Snippets},
+        suppressions::{Suppressions, UNUSED_SUPPRESSION},
+    },
+    errors::{located, AsDiagnostic, Errors, Located, Salvage, SalvageResult},
+    map::HashMap,
     parser,
     semantic::{
+        fresh::Fresher,
         infer::Constraints,
         nodes::Symbol,
-        sub::Substitution,
+        sub::{Substitutable, Substitution},
         types::{MonoType, PolyType, PolyTypeHashMap, Property, Record, RecordLabel},
+        usage::Usage,
     },
 };
 
@@ -76,6 +102,12 @@ pub enum ErrorKind {
     /// Errors that occur because of incompatible/incomplete types
     #[error("{0}")]
     Inference(nodes::ErrorKind),
+    /// Warnings reported as errors because strict checks are enabled
+    #[error("{0}")]
+    Strict(WarningKind),
+    /// Errors found in the call graph of the user-defined functions
+    #[error("{0}")]
+    CallGraph(ast::callgraph::ErrorKind),
 }
 
 impl From<ast::check::Error> for Error {
@@ -87,6 +119,15 @@ impl From<ast::check::Error> for Error {
     }
 }
 
+impl From<ast::callgraph::Error> for Error {
+    fn from(error: ast::callgraph::Error) -> Self {
+        Self {
+            location: error.location,
+            error: ErrorKind::CallGraph(error.error),
+        }
+    }
+}
+
 impl From<convert::Error> for Error {
     fn from(error: convert::Error) -> Self {
         Self {
@@ -129,8 +170,151 @@ pub enum WarningKind {
     /// An unused symbol was found in the source
     #[error("symbol {0} is never used")]
     UnusedSymbol(String),
+    /// A symbol shadows a symbol of an enclosing scope
+    #[error("symbol {0} shadows a symbol of an enclosing scope")]
+    ShadowedSymbol(String),
+    /// The leading stages of a pipeline are repeated by other pipelines
+    #[error("`{pipeline}` is computed by {count} pipelines")]
+    RepeatedPipeline {
+        /// The source of the repeated stages
+        pipeline: String,
+        /// The number of pipelines that repeat the stages
+        count: usize,
+        /// The variable suggested to hold the result of the repeated stages
+        variable: String,
+        /// The position of the statement before which to insert the assignment of the
+        /// variable, if the stages can be computed once in a scope common to all pipelines
+        binding: Option<ast::Position>,
+        /// The source of the pipeline reading the variable instead of repeating the stages,
+        /// if it could be rewritten
+        rewrite: Option<String>,
+    },
+    /// A function parameter is interpolated or concatenated into a query
+    #[error("parameter `{parameter}` is built into the query run by `{call}`")]
+    QueryInjection {
+        /// The function that runs the query
+        call: String,
+        /// The parameter built into the query
+        parameter: String,
+    },
+    /// A time zone name is not in the time zone database
+    #[error("unknown time zone \"{0}\"")]
+    UnknownTimezone(String),
+    /// Two durations converted to nanoseconds are multiplied
+    #[error("product of two durations in nanoseconds")]
+    DurationProduct,
+    /// An integer is added to a duration converted to nanoseconds
+    #[error("integer added to a duration in nanoseconds")]
+    MixedDurationUnits,
+    /// An integer is converted to a duration of that many nanoseconds
+    #[error("duration of {0} nanoseconds")]
+    NanosecondDuration(i64),
+    /// The windows of `aggregateWindow` are longer than the range of the query
+    #[error("window `{every}` is longer than the range of `{range}`")]
+    WindowLongerThanRange {
+        /// The source of the window duration
+        every: String,
+        /// The source of the call to `range`
+        range: String,
+    },
+    /// `aggregateWindow` creates a very large number of windows, including empty ones
+    #[error("aggregateWindow creates {0} windows for each series")]
+    TooManyWindows(i64),
+    /// `exists` checks a value that is never null
+    #[error("`{0}` is never null, so `exists` is always true")]
+    NeverNull(String),
+    /// An integer literal makes a division an integer division, which truncates
+    #[error("`{0}` is an integer division, which truncates")]
+    IntegerDivision(String),
+    /// A bucket does not exist
+    #[error("bucket \"{0}\" does not exist")]
+    UnknownBucket(String),
+    /// A measurement does not exist in the bucket it is read from
+    #[error("measurement \"{measurement}\" does not exist in bucket \"{bucket}\"")]
+    UnknownMeasurement {
+        /// The bucket
+        bucket: String,
+        /// The measurement
+        measurement: String,
+    },
+    /// A field does not exist in the bucket it is read from
+    #[error("field \"{field}\" does not exist in bucket \"{bucket}\"")]
+    UnknownField {
+        /// The bucket
+        bucket: String,
+        /// The field
+        field: String,
+    },
+    /// A column is neither a tag nor a system column of the bucket it is read from
+    #[error("column \"{column}\" does not exist in bucket \"{bucket}\"")]
+    UnknownColumn {
+        /// The bucket
+        bucket: String,
+        /// The column
+        column: String,
+    },
+    /// A tag is compared to a value that is not a string
+    #[error("tag \"{0}\" is compared to a value that is not a string")]
+    TagComparedToNonString(String),
+    /// A suppression comment names a warning that is not reported on the line it applies to
+    #[error("suppression of `{0}` is never used")]
+    UnusedSuppression(String),
+    /// A function of the main package is never called or referenced
+    #[error("function \"{0}\" is never used")]
+    UnusedFunction(String),
 }
 
+impl WarningKind {
+    /// Returns the name of the kind of warning, which suppresses it in a suppression comment.
+    /// See [`ast::suppressions`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::UnusedSymbol(_) => "unused-symbol",
+            Self::ShadowedSymbol(_) => "shadowed-symbol",
+            Self::RepeatedPipeline { .. } => "repeated-pipeline",
+            Self::QueryInjection { .. } => "query-injection",
+            Self::UnknownTimezone(_) => "unknown-timezone",
+            Self::DurationProduct => "duration-product",
+            Self::MixedDurationUnits => "mixed-duration-units",
+            Self::NanosecondDuration(_) => "nanosecond-duration",
+            Self::WindowLongerThanRange { .. } => "window-longer-than-range",
+            Self::TooManyWindows(_) => "too-many-windows",
+            Self::NeverNull(_) => "never-null",
+            Self::IntegerDivision(_) => "integer-division",
+            Self::UnknownBucket(_) => "unknown-bucket",
+            Self::UnknownMeasurement { .. } => "unknown-measurement",
+            Self::UnknownField { .. } => "unknown-field",
+            Self::UnknownColumn { .. } => "unknown-column",
+            Self::TagComparedToNonString(_) => "tag-compared-to-non-string",
+            Self::UnusedSuppression(_) => UNUSED_SUPPRESSION,
+            Self::UnusedFunction(_) => "unused-function",
+        }
+    }
+}
+
+// The name of each kind of warning reported by the analyzer, see [`WarningKind::name`], and the
+// feature that enables it, or `None` if the schema of the configuration enables it.
+const WARNINGS: &[(&str, Option<Feature>)] = &[
+    ("unused-symbol", Some(Feature::UnusedSymbolWarnings)),
+    ("shadowed-symbol", Some(Feature::ShadowedSymbolWarnings)),
+    ("repeated-pipeline", Some(Feature::RepeatedPipelineWarnings)),
+    ("query-injection", Some(Feature::QueryInjectionWarnings)),
+    ("unknown-timezone", Some(Feature::TimezoneWarnings)),
+    ("duration-product", Some(Feature::DurationWarnings)),
+    ("mixed-duration-units", Some(Feature::DurationWarnings)),
+    ("nanosecond-duration", Some(Feature::DurationWarnings)),
+    ("window-longer-than-range", Some(Feature::WindowWarnings)),
+    ("too-many-windows", Some(Feature::WindowWarnings)),
+    ("never-null", Some(Feature::NullWarnings)),
+    ("integer-division", Some(Feature::NumericWarnings)),
+    ("unknown-bucket", None),
+    ("unknown-measurement", None),
+    ("unknown-field", None),
+    ("unknown-column", None),
+    ("tag-compared-to-non-string", None),
+    ("unused-function", Some(Feature::CallGraphChecks)),
+];
+
 /// `PackageEntry` contains the information for one exported item of a package
 #[derive(Debug, Clone, PartialEq)]
 pub struct PackageEntry {
@@ -290,6 +474,183 @@ impl PackageExports {
     }
 }
 
+/// The types of the expressions of a package, by their location in the source.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TypeMap {
+    // In the order the expressions are visited, so that every expression comes after the
+    // expressions that contain it.
+    types: Vec<(ast::SourceLocation, MonoType)>,
+}
+
+impl TypeMap {
+    /// Returns the location and type of every expression, outer expressions before the
+    /// expressions they contain.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&ast::SourceLocation, &MonoType)> {
+        self.types.iter().map(|entry| (&entry.0, &entry.1))
+    }
+
+    /// Returns the number of expressions in the map.
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    /// Reports whether the map contains no expressions.
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    /// Returns the location and type of the innermost expression containing `position`, such as
+    /// the expression an editor shows the type of when the cursor hovers over `position`.
+    pub fn type_at(&self, position: ast::Position) -> Option<(&ast::SourceLocation, &MonoType)> {
+        let key = |position: &ast::Position| (position.line, position.column);
+        self.iter().rev().find(|(location, _)| {
+            key(&location.start) <= key(&position) && key(&position) < key(&location.end)
+        })
+    }
+
+    /// Returns the location and type of the innermost expression containing `position`, like
+    /// [`TypeMap::type_at`], with the columns of `position` and of the location counting units
+    /// of `encoding`. `lines` indexes `source`, the source the map was built from.
+    pub fn type_at_in(
+        &self,
+        lines: &LineIndex,
+        source: &str,
+        position: ast::Position,
+        encoding: ColumnEncoding,
+    ) -> Option<(ast::SourceLocation, &MonoType)> {
+        let position = lines.decode_position(source, position, encoding);
+        self.type_at(position)
+            .map(|(location, typ)| (lines.encode_location(source, location, encoding), typ))
+    }
+
+    /// Returns the differences between the types of the expressions of `self` and of `other`,
+    /// e.g. the maps of a package analyzed without and with a feature, ordered by location.
+    ///
+    /// Expressions are matched by their location, so expressions that desugaring adds or
+    /// removes show up as added or removed. Types are compared up to the names of their type
+    /// variables, which depend on the order in which the analysis creates them.
+    pub fn diff(&self, other: &TypeMap) -> Vec<TypeChange> {
+        let before = self.by_location();
+        let after = other.by_location();
+        let keys: BTreeSet<_> = before.keys().chain(after.keys()).collect();
+        let mut changes = Vec::new();
+        for key in keys {
+            let before = before.get(key).map_or(&[][..], Vec::as_slice);
+            let after = after.get(key).map_or(&[][..], Vec::as_slice);
+            for i in 0..before.len().max(after.len()) {
+                match (before.get(i), after.get(i)) {
+                    (Some((location, old)), Some((_, new))) if old != new => {
+                        changes.push(TypeChange::Changed {
+                            location: (*location).clone(),
+                            before: old.clone(),
+                            after: new.clone(),
+                        })
+                    }
+                    (Some((location, typ)), None) => changes.push(TypeChange::Removed {
+                        location: (*location).clone(),
+                        typ: typ.clone(),
+                    }),
+                    (None, Some((location, typ))) => changes.push(TypeChange::Added {
+                        location: (*location).clone(),
+                        typ: typ.clone(),
+                    }),
+                    _ => (),
+                }
+            }
+        }
+        changes
+    }
+
+    // Groups the types by location, with their type variables renamed in the order they
+    // appear in the type.
+    #[allow(clippy::type_complexity)]
+    fn by_location(
+        &self,
+    ) -> BTreeMap<(&Option<String>, (u32, u32), (u32, u32)), Vec<(&ast::SourceLocation, MonoType)>>
+    {
+        let mut types: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for (location, typ) in self.iter() {
+            let key = (
+                &location.file,
+                (location.start.line, location.start.column),
+                (location.end.line, location.end.column),
+            );
+            let typ = typ.clone().apply(&mut Fresher::default());
+            types.entry(key).or_default().push((location, typ));
+        }
+        types
+    }
+}
+
+/// A difference between the types of the expressions at a location in two [`TypeMap`]s.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeChange {
+    /// An expression that only the second map has a type for.
+    Added {
+        /// The location of the expression.
+        location: ast::SourceLocation,
+        /// The type of the expression.
+        typ: MonoType,
+    },
+    /// An expression that only the first map has a type for.
+    Removed {
+        /// The location of the expression.
+        location: ast::SourceLocation,
+        /// The type of the expression.
+        typ: MonoType,
+    },
+    /// An expression whose type differs between the maps.
+    Changed {
+        /// The location of the expression.
+        location: ast::SourceLocation,
+        /// The type of the expression in the first map.
+        before: MonoType,
+        /// The type of the expression in the second map.
+        after: MonoType,
+    },
+}
+
+impl fmt::Display for TypeChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (sign, location) = match self {
+            TypeChange::Added { location, .. } => ('+', location),
+            TypeChange::Removed { location, .. } => ('-', location),
+            TypeChange::Changed { location, .. } => ('~', location),
+        };
+        write!(
+            f,
+            "{} {}:{}: {}",
+            sign,
+            location.start.line,
+            location.start.column,
+            location.source.as_deref().unwrap_or_default()
+        )?;
+        match self {
+            TypeChange::Added { typ, .. } | TypeChange::Removed { typ, .. } => {
+                write!(f, "\n    {}", typ)
+            }
+            TypeChange::Changed { before, after, .. } => {
+                write!(f, "\n    - {}\n    + {}", before, after)
+            }
+        }
+    }
+}
+
+/// Returns the types of all expressions of a package in one pass over the package. The types
+/// are only meaningful once the package has been inferred, e.g. by an [`Analyzer`].
+pub fn type_map(pkg: &nodes::Package) -> TypeMap {
+    let mut types = Vec::new();
+    walk::walk(
+        &mut |node: walk::Node| {
+            if let Some(typ) = node.type_of() {
+                types.push((node.loc().clone(), typ));
+            }
+        },
+        walk::Node::Package(pkg),
+    );
+    TypeMap { types }
+}
+
 /// Constructs a polytype, or more specifically a generic record type, from a hash map.
 pub fn build_polytype(
     from: impl IntoIterator<Item = (Symbol, PolyType)>,
@@ -335,14 +696,15 @@ fn build_record(
     (r, cons)
 }
 
-/// Wrapper around `FileErrors` which defaults to using codespan to print the errors
+/// Wrapper around `FileErrors` which defaults to using codespan to print the errors, in the given
+/// style
 #[derive(Error, Debug, PartialEq)]
-pub struct PrettyFileErrors(pub FileErrors);
+pub struct PrettyFileErrors(pub FileErrors, pub ErrorStyle);
 
 impl fmt::Display for PrettyFileErrors {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &self.0.source {
-            Some(source) => f.write_str(&self.0.pretty(source)),
+            Some(source) => f.write_str(&self.0.render(source, self.1)),
             None => self.0.fmt(f),
         }
     }
@@ -360,11 +722,28 @@ pub struct FileErrors {
     #[source]
     /// The collection of diagnostics
     pub diagnostics: Diagnostics<ErrorKind, WarningKind>,
+
+    /// The unit of the columns of the locations the errors are printed with, when the source is
+    /// known. The locations of the diagnostics always count bytes.
+    pub encoding: ColumnEncoding,
 }
 
 impl fmt::Display for FileErrors {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.diagnostics.fmt(f)
+        let source = match &self.source {
+            Some(source) if !self.encoding.is_utf8() => source,
+            _ => return self.diagnostics.fmt(f),
+        };
+        let lines = LineIndex::new(source);
+        for (i, error) in self.diagnostics.errors.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+                writeln!(f)?;
+            }
+            let location = error.location_in(&lines, source, self.encoding);
+            write!(f, "error {}: {}", location, error.error)?;
+        }
+        Ok(())
     }
 }
 
@@ -393,27 +772,27 @@ pub(crate) trait Source {
     fn codespan_range(&self, location: &ast::SourceLocation) -> Range<usize>;
 }
 
-impl Source for codespan_reporting::files::SimpleFile<&str, &str> {
+impl Source for LineIndex {
     fn codespan_range(&self, location: &ast::SourceLocation) -> Range<usize> {
-        (|| {
-            let start = self
-                .line_range((), (location.start.line as usize).saturating_sub(1))
-                .ok()?
-                .start;
-            let end = self
-                .line_range((), (location.end.line as usize).saturating_sub(1))
-                .ok()?
-                .start;
-            Some(
-                start + (location.start.column as usize).saturating_sub(1)
-                    ..end + (location.end.column as usize).saturating_sub(1),
-            )
-        })()
-        .unwrap_or_default()
+        self.range(location).unwrap_or_default()
     }
 }
 
 impl FileErrors {
+    /// Maps the locations of the diagnostics, found in the source expanded by `expansion`, to
+    /// the source it was expanded from.
+    pub fn map_to_original(&mut self, expansion: &Expansion) {
+        if !expansion.is_expanded() {
+            return;
+        }
+        for error in self.diagnostics.errors.iter_mut() {
+            error.location = expansion.original_location(&error.location);
+        }
+        for warning in self.diagnostics.warnings.iter_mut() {
+            warning.location = expansion.original_location(&warning.location);
+        }
+    }
+
     /// Prints the errors
     pub fn pretty(&self, source: &str) -> String {
         self.pretty_config(&term::Config::default(), source)
@@ -422,7 +801,24 @@ impl FileErrors {
     /// Wraps `FileErrors` in type which defaults to the more readable codespan error
     /// representation
     pub fn pretty_error(self) -> PrettyFileErrors {
-        PrettyFileErrors(self)
+        PrettyFileErrors(self, ErrorStyle::Rich)
+    }
+
+    /// Wraps `FileErrors` in type which defaults to the codespan error representation in the
+    /// given style
+    pub fn styled_error(self, style: ErrorStyle) -> PrettyFileErrors {
+        PrettyFileErrors(self, style)
+    }
+
+    /// Prints the errors in the given style
+    pub fn render(&self, source: &str, style: ErrorStyle) -> String {
+        self.pretty_config(
+            &term::Config {
+                display_style: style.into(),
+                ..term::Config::default()
+            },
+            source,
+        )
     }
 
     /// Prints the errors in their short form
@@ -463,27 +859,80 @@ impl FileErrors {
         source: &str,
         writer: &mut dyn WriteColor,
     ) -> Result<(), codespan_reporting::files::Error> {
-        let files = codespan_reporting::files::SimpleFile::new(&self.file[..], source);
+        let files = EncodedFile {
+            file: codespan_reporting::files::SimpleFile::new(&self.file[..], source),
+            encoding: self.encoding,
+        };
+        let lines = LineIndex::new(source);
         for warn in &self.diagnostics.warnings {
-            pretty_fmt(warn, config, &files, writer)?;
+            pretty_fmt(warn, config, &files, &lines, writer)?;
         }
         for err in &self.diagnostics.errors {
-            pretty_fmt(err, config, &files, writer)?;
+            pretty_fmt(err, config, &files, &lines, writer)?;
         }
         Ok(())
     }
 }
 
+// A file whose column numbers count units of an encoding rather than characters.
+struct EncodedFile<'f> {
+    file: codespan_reporting::files::SimpleFile<&'f str, &'f str>,
+    encoding: ColumnEncoding,
+}
+
+impl<'a, 'f: 'a> codespan_reporting::files::Files<'a> for EncodedFile<'f> {
+    type FileId = ();
+    type Name = &'f str;
+    type Source = &'f str;
+
+    fn name(&'a self, _: ()) -> Result<Self::Name, codespan_reporting::files::Error> {
+        Ok(*self.file.name())
+    }
+
+    fn source(&'a self, _: ()) -> Result<Self::Source, codespan_reporting::files::Error> {
+        Ok(*self.file.source())
+    }
+
+    fn line_index(
+        &'a self,
+        id: (),
+        byte_index: usize,
+    ) -> Result<usize, codespan_reporting::files::Error> {
+        self.file.line_index(id, byte_index)
+    }
+
+    fn line_range(
+        &'a self,
+        id: (),
+        line_index: usize,
+    ) -> Result<Range<usize>, codespan_reporting::files::Error> {
+        self.file.line_range(id, line_index)
+    }
+
+    fn column_number(
+        &'a self,
+        id: (),
+        line_index: usize,
+        byte_index: usize,
+    ) -> Result<usize, codespan_reporting::files::Error> {
+        let range = self.file.line_range(id, line_index)?;
+        let line = self.file.source()[range.clone()].trim_end_matches(&['\r', '\n'][..]);
+        let column = byte_index.saturating_sub(range.start) as u32 + 1;
+        Ok(encode_column(line, column, self.encoding) as usize)
+    }
+}
+
 fn pretty_fmt<E>(
     err: &Located<E>,
     config: &term::Config,
-    files: &codespan_reporting::files::SimpleFile<&str, &str>,
+    files: &EncodedFile,
+    lines: &LineIndex,
     writer: &mut dyn WriteColor,
 ) -> Result<(), codespan_reporting::files::Error>
 where
     E: AsDiagnostic,
 {
-    let diagnostic = err.as_diagnostic(files);
+    let diagnostic = err.as_diagnostic(lines);
 
     term::emit(writer, config, files, &diagnostic)?;
     Ok(())
@@ -496,13 +945,99 @@ impl AsDiagnostic for ErrorKind {
             Self::Convert(err) => err.as_diagnostic(source),
             Self::InvalidSemantic(err) => err.as_diagnostic(source),
             Self::Inference(err) => err.as_diagnostic(source),
+            Self::CallGraph(err) => err.as_diagnostic(source),
+            Self::Strict(warning) => {
+                let mut diagnostic = warning.as_diagnostic(source);
+                diagnostic.severity = diagnostic::Severity::Error;
+                diagnostic
+                    .notes
+                    .push("reported as an error because strict checks are enabled".to_string());
+                diagnostic
+            }
         }
     }
 }
 
 impl AsDiagnostic for WarningKind {
     fn as_diagnostic(&self, _source: &dyn Source) -> diagnostic::Diagnostic<()> {
-        diagnostic::Diagnostic::warning().with_message(self.to_string())
+        let diagnostic = diagnostic::Diagnostic::warning().with_message(self.to_string());
+        match self {
+            Self::UnusedSymbol(_) => diagnostic,
+            Self::ShadowedSymbol(name) => diagnostic.with_notes(vec![format!(
+                "uses of `{}` in this scope refer to this definition; consider renaming it",
+                name
+            )]),
+            Self::RepeatedPipeline {
+                pipeline,
+                variable,
+                binding,
+                rewrite,
+                ..
+            } => {
+                let mut notes = vec![format!(
+                    "compute it once by assigning it to a variable: `{} = {}`",
+                    variable, pipeline
+                )];
+                if let Some(binding) = binding {
+                    notes.push(format!(
+                        "insert the assignment before the statement at {}:{}",
+                        binding.line, binding.column
+                    ));
+                }
+                if let Some(rewrite) = rewrite {
+                    notes.push(format!("then rewrite this pipeline as `{}`", rewrite));
+                }
+                diagnostic.with_notes(notes)
+            }
+            Self::QueryInjection { parameter, .. } => diagnostic.with_notes(vec![format!(
+                "callers can change the query through `{}`; validate or escape it before building the query",
+                parameter
+            )]),
+            Self::UnknownTimezone(_) => diagnostic.with_notes(vec![
+                "time zones are named after the IANA time zone database, e.g. `America/New_York`"
+                    .to_string(),
+            ]),
+            Self::DurationProduct => diagnostic.with_notes(vec![
+                "the result is in square nanoseconds; convert only one of the durations, e.g. `int(v: 1h) * 2`"
+                    .to_string(),
+            ]),
+            Self::MixedDurationUnits => diagnostic.with_notes(vec![
+                "`int(v: d)` counts nanoseconds; add a duration instead, e.g. `int(v: d + 30s)`"
+                    .to_string(),
+            ]),
+            Self::NanosecondDuration(n) => diagnostic.with_notes(vec![format!(
+                "write a duration literal if another unit was meant, e.g. `{}s`",
+                n
+            )]),
+            Self::WindowLongerThanRange { .. } => diagnostic.with_notes(vec![
+                "each series is aggregated into a single, partial window".to_string(),
+            ]),
+            Self::TooManyWindows(_) => diagnostic.with_notes(vec![
+                "empty windows are created by default; use a longer `every` or set `createEmpty: false`"
+                    .to_string(),
+            ]),
+            Self::NeverNull(_) => diagnostic.with_notes(vec![
+                "only values read from outside the query, such as the columns of a record, can be null"
+                    .to_string(),
+            ]),
+            Self::IntegerDivision(_) => diagnostic.with_notes(vec![
+                "a number literal without a fraction is an int; to keep the fraction, convert the operands to floats, e.g. `float(v: x) / 2.0`"
+                    .to_string(),
+            ]),
+            Self::UnknownBucket(_)
+            | Self::UnknownMeasurement { .. }
+            | Self::UnknownField { .. }
+            | Self::UnknownColumn { .. } => diagnostic,
+            Self::TagComparedToNonString(_) => diagnostic.with_notes(vec![
+                "tag values are strings, so the comparison is never true".to_string(),
+            ]),
+            Self::UnusedSuppression(_) => diagnostic.with_notes(vec![
+                "remove the name from the suppression comment".to_string(),
+            ]),
+            Self::UnusedFunction(_) => diagnostic.with_notes(vec![
+                "only functions of library packages are exported; remove it or call it".to_string(),
+            ]),
+        }
     }
 }
 
@@ -512,10 +1047,12 @@ pub struct Analyzer<'env, I: import::Importer> {
     env: env::Environment<'env>,
     importer: I,
     config: AnalyzerConfig,
+    observer: Option<Box<dyn FnMut(&Usage) + 'env>>,
+    env_imports: HashMap<Symbol, String>,
 }
 
 /// Features used in the flux compiler
-#[derive(Clone, Eq, PartialEq, Debug, serde::Deserialize)]
+#[derive(Clone, Eq, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub enum Feature {
     /// Enables vectorization of logical operators
@@ -528,8 +1065,58 @@ pub enum Feature {
 
     /// Enables warnings for unused symbols
     UnusedSymbolWarnings,
+
+    /// Enables warnings for variables and parameters that shadow a symbol of an enclosing scope
+    ShadowedSymbolWarnings,
+
+    /// Enables warnings for pipelines that repeat the leading stages of other pipelines
+    RepeatedPipelineWarnings,
+
+    /// Enables warnings for function parameters built into queries run by functions such as
+    /// `sql.from`
+    QueryInjectionWarnings,
+
+    /// Enables warnings for time zone names that are not in the time zone database. Has no
+    /// effect unless flux-core is built with the `timezones` cargo feature.
+    TimezoneWarnings,
+
+    /// Enables warnings for arithmetic that mixes up the units of durations
+    DurationWarnings,
+
+    /// Enables warnings for `aggregateWindow` windows that do not fit the range of the query
+    WindowWarnings,
+
+    /// Enables typed holes (`_?`), which report the type expected in their place
+    TypedHoles,
+
+    /// Enables warnings for `exists` checks of values that are never null
+    NullWarnings,
+
+    /// Enables warnings for divisions that an integer literal makes integer divisions
+    NumericWarnings,
+
+    /// Enables the checks of the call graph of user-defined functions, which reports
+    /// recursive functions, including through the user packages whose call graphs the
+    /// importer provides, and warns about functions of the main package that are never used
+    CallGraphChecks,
+
+    /// Enables the warnings of [`STRICT_CHECKS`] and reports every warning as an error, for
+    /// checking Flux code in CI
+    StrictChecks,
 }
 
+/// The warnings enabled by [`Feature::StrictChecks`].
+pub const STRICT_CHECKS: &[Feature] = &[
+    Feature::UnusedSymbolWarnings,
+    Feature::ShadowedSymbolWarnings,
+    Feature::QueryInjectionWarnings,
+    Feature::TimezoneWarnings,
+    Feature::DurationWarnings,
+    Feature::WindowWarnings,
+    Feature::NullWarnings,
+    Feature::NumericWarnings,
+];
+
 impl FromStr for Feature {
     type Err = serde_json::Error;
 
@@ -538,21 +1125,196 @@ impl FromStr for Feature {
     }
 }
 
+/// Limits on the diagnostics an Analyzer reports, see [`AnalyzerConfig::limits`]. The
+/// [`Usage`] of an analysis counts every diagnostic.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "camelCase")]
+pub struct Limits {
+    /// The maximum number of errors reported for a package, the others are dropped. A package
+    /// that fails to be analyzed always reports at least one error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_errors: Option<usize>,
+    /// The maximum number of warnings reported for a package, the others are dropped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_warnings: Option<usize>,
+}
+
+impl Limits {
+    fn is_unlimited(&self) -> bool {
+        *self == Limits::default()
+    }
+}
+
+/// The style in which diagnostics are printed, see [`FileErrors::render`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorStyle {
+    /// The message, location and source snippet of each diagnostic, with its notes
+    Rich,
+    /// The message, location and source snippet of each diagnostic
+    Medium,
+    /// A single line with the message and location of each diagnostic
+    Short,
+}
+
+impl From<ErrorStyle> for term::DisplayStyle {
+    fn from(style: ErrorStyle) -> Self {
+        match style {
+            ErrorStyle::Rich => term::DisplayStyle::Rich,
+            ErrorStyle::Medium => term::DisplayStyle::Medium,
+            ErrorStyle::Short => term::DisplayStyle::Short,
+        }
+    }
+}
+
 /// A set of configuration options for the behavior of an Analyzer.
-#[derive(Clone, Default, Debug)]
+///
+/// A configuration can be read from JSON or TOML, e.g.
+/// `{"features": ["shadowedSymbolWarnings"], "bindings": {"bucket": "string"}}`.
+#[derive(Clone, Default, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct AnalyzerConfig {
     /// Features used in the flux compiler
     pub features: Vec<Feature>,
+    /// The schema of the buckets read by the analyzed queries. When set, the filters of the
+    /// queries are checked against it, see [`schema::check_schema`]. A schema in JSON is read by
+    /// a [`schema::JsonSchemaProvider`]; the schema is not serialized.
+    #[serde(deserialize_with = "deserialize_schema", skip_serializing)]
+    pub schema: Option<Arc<dyn schema::SchemaProvider + Send + Sync>>,
+    /// Limits on the diagnostics reported for a package
+    #[serde(skip_serializing_if = "Limits::is_unlimited")]
+    pub limits: Limits,
+    /// The style in which tools print diagnostics, or `None` for the default style of each tool
+    #[serde(rename = "errorStyle", skip_serializing_if = "Option::is_none")]
+    pub error_style: Option<ErrorStyle>,
+    /// Values in scope of the analyzed code in addition to the environment of the analyzer, with
+    /// their types written as Flux type expressions, e.g. `{"bucket": "string"}` for a host that
+    /// provides a `bucket` variable.
+    #[serde(
+        deserialize_with = "deserialize_bindings",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub bindings: BTreeMap<String, String>,
+    /// Templates of the snippets expanded in the sources analyzed by
+    /// [`Analyzer::analyze_source`], keyed by the name they are called by, see
+    /// [`ast::snippets`].
+    #[serde(skip_serializing_if = "Snippets::is_empty")]
+    pub snippets: Snippets,
+    /// The unit of the columns of the locations diagnostics are printed with, see
+    /// [`FileErrors::encoding`]
+    #[serde(
+        rename = "columnEncoding",
+        skip_serializing_if = "ColumnEncoding::is_utf8"
+    )]
+    pub column_encoding: ColumnEncoding,
+}
+
+// Configurations are equal if they share the same schema provider, since providers cannot be
+// compared.
+impl PartialEq for AnalyzerConfig {
+    fn eq(&self, other: &Self) -> bool {
+        let schema = match (&self.schema, &other.schema) {
+            (Some(a), Some(b)) => {
+                std::ptr::eq(Arc::as_ptr(a) as *const u8, Arc::as_ptr(b) as *const u8)
+            }
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        self.features == other.features
+            && schema
+            && self.limits == other.limits
+            && self.error_style == other.error_style
+            && self.bindings == other.bindings
+            && self.snippets == other.snippets
+            && self.column_encoding == other.column_encoding
+    }
+}
+
+// Parses the type of a binding of the configuration.
+fn binding_type(typ: &str) -> Result<PolyType, String> {
+    let typ_expr = parser::Parser::new(typ).parse_type_expression();
+    ast::check::check(ast::walk::Node::TypeExpression(&typ_expr)).map_err(|err| err.to_string())?;
+    convert::convert_polytype(&typ_expr, &AnalyzerConfig::default()).map_err(|err| err.to_string())
+}
+
+fn deserialize_bindings<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let bindings: BTreeMap<String, String> = serde::Deserialize::deserialize(deserializer)?;
+    for (name, typ) in &bindings {
+        if let Err(err) = binding_type(typ) {
+            return Err(serde::de::Error::custom(format!(
+                "invalid type of binding `{}`: {}",
+                name, err
+            )));
+        }
+    }
+    Ok(bindings)
+}
+
+fn deserialize_schema<'de, D>(
+    deserializer: D,
+) -> Result<Option<Arc<dyn schema::SchemaProvider + Send + Sync>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let provider: Option<schema::JsonSchemaProvider> =
+        serde::Deserialize::deserialize(deserializer)?;
+    Ok(
+        provider
+            .map(|provider| Arc::new(provider) as Arc<dyn schema::SchemaProvider + Send + Sync>),
+    )
+}
+
+impl AnalyzerConfig {
+    /// Returns the configuration with the feature enabled.
+    pub fn with_feature(mut self, feature: Feature) -> Self {
+        if !self.features.contains(&feature) {
+            self.features.push(feature);
+        }
+        self
+    }
+
+    /// Returns the configuration checking queries against the schema.
+    pub fn with_schema(
+        mut self,
+        schema: impl schema::SchemaProvider + Send + Sync + 'static,
+    ) -> Self {
+        self.schema = Some(Arc::new(schema));
+        self
+    }
+
+    /// Reads a configuration from a TOML file if its extension is `.toml`, or from a JSON file
+    /// otherwise.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("opening config file {}", path.display()))?;
+        if path.extension().map_or(false, |ext| ext == "toml") {
+            toml::from_str(&contents).map_err(anyhow::Error::from)
+        } else {
+            serde_json::from_str(&contents).map_err(anyhow::Error::from)
+        }
+        .with_context(|| format!("parsing config file {}", path.display()))
+    }
 }
 
 impl<'env, I: import::Importer> Analyzer<'env, I> {
     /// Create an analyzer with the given environment and importer.
-    /// The environment represents any values in scope.
-    pub fn new(env: env::Environment<'env>, importer: I, config: AnalyzerConfig) -> Self {
+    /// The environment represents any values in scope, to which the bindings of the
+    /// configuration are added.
+    pub fn new(mut env: env::Environment<'env>, importer: I, config: AnalyzerConfig) -> Self {
+        for (name, typ) in &config.bindings {
+            match binding_type(typ) {
+                Ok(typ) => env.add(Symbol::from(name.as_str()), typ),
+                Err(err) => log::warn!("ignoring the binding `{}`: {}", name, err),
+            }
+        }
         Analyzer {
             env,
             importer,
             config,
+            observer: None,
+            env_imports: HashMap::default(),
         }
     }
     /// Create an analyzer with the given environment and importer using default configuration.
@@ -562,13 +1324,46 @@ impl<'env, I: import::Importer> Analyzer<'env, I> {
     }
 
     /// Analyze Flux source code returning the semantic package and the package environment.
+    ///
+    /// Calls to registered snippets are expanded before the source is parsed, and the locations
+    /// of diagnostics are mapped back to `src`. The locations of the returned package are in
+    /// the expanded source.
     pub fn analyze_source(
         &mut self,
         pkgpath: String,
         file_name: String,
         src: &str,
     ) -> SalvageResult<(PackageExports, nodes::Package), FileErrors> {
-        let ast_file = parser::parse_string(file_name, src);
+        if self.config.snippets.is_empty() {
+            let ast_file = self.parse(file_name, src);
+            return self.analyze_file(pkgpath, ast_file, src);
+        }
+        let expansion = self.config.snippets.expand(src);
+        let ast_file = self.parse(file_name, &expansion.source);
+        self.analyze_file(pkgpath, ast_file, src)
+            .map_err(|mut err| {
+                err.error.map_to_original(&expansion);
+                err
+            })
+    }
+
+    // Parses `src`, with typed holes if they are enabled.
+    fn parse(&self, file_name: String, src: &str) -> ast::File {
+        let mut parser = parser::Parser::new(src);
+        if self.config.features.contains(&Feature::TypedHoles) {
+            parser = parser.with_typed_holes();
+        }
+        parser.parse_file(file_name)
+    }
+
+    // Analyzes the package of a single file parsed from `src`, or from the expansion of the
+    // snippets of `src`.
+    fn analyze_file(
+        &mut self,
+        pkgpath: String,
+        ast_file: ast::File,
+        src: &str,
+    ) -> SalvageResult<(PackageExports, nodes::Package), FileErrors> {
         let ast_pkg = ast::Package {
             base: ast_file.base.clone(),
             path: pkgpath,
@@ -581,6 +1376,25 @@ impl<'env, I: import::Importer> Analyzer<'env, I> {
         })
     }
 
+    /// Registers `template` as the Flux code that replaces calls `name()` in the sources
+    /// analyzed by [`Analyzer::analyze_source`]. See [`ast::snippets`].
+    pub fn register_snippet(&mut self, name: impl Into<String>, template: impl Into<String>) {
+        self.config.snippets.register(name, template);
+    }
+
+    /// Registers a callback that receives the [`Usage`] of every package this analyzer
+    /// analyzes, whether or not its analysis succeeds. See [`usage`].
+    pub fn set_observer(&mut self, observer: impl FnMut(&Usage) + 'env) {
+        self.observer = Some(Box::new(observer));
+    }
+
+    /// Sets the symbols of the environment that are bound to imported packages, with the path
+    /// of each package, e.g. the imports of the previous lines of a REPL. The [`Usage`] of an
+    /// analysis counts the calls to their members like calls to the members of its own imports.
+    pub fn set_env_imports(&mut self, imports: impl IntoIterator<Item = (Symbol, String)>) {
+        self.env_imports = imports.into_iter().collect();
+    }
+
     /// Analyze Flux AST returning the semantic package and the package environment.
     pub fn analyze_ast(
         &mut self,
@@ -645,22 +1459,133 @@ impl<'env, I: import::Importer> Analyzer<'env, I> {
 
         let mut sem_pkg = nodes::inject_pkg_types(sem_pkg, sub);
 
+        if self.config.features.contains(&Feature::TypedHoles) {
+            errors.extend(
+                nodes::typed_holes(&sem_pkg)
+                    .into_iter()
+                    .map(|(location, typ)| {
+                        Error::from(located(location, nodes::ErrorKind::TypedHole(typ)))
+                    }),
+            );
+        }
+
+        let strict = self.config.features.contains(&Feature::StrictChecks);
+        let enabled = |feature: Feature| {
+            self.config.features.contains(&feature) || (strict && STRICT_CHECKS.contains(&feature))
+        };
         let mut warnings = Errors::new();
 
-        if self
-            .config
-            .features
-            .contains(&Feature::UnusedSymbolWarnings)
-        {
+        if enabled(Feature::CallGraphChecks) {
+            let mut graph = ast::callgraph::CallGraph::from_package(ast_pkg);
+            graph.link(&mut self.importer);
+            if let Err(err) = graph.check() {
+                errors.extend(err.into_iter().map(Error::from));
+            }
+            if ast_pkg.package == "main" {
+                warnings.extend(graph.unused().into_iter().map(|function| {
+                    located(
+                        function.location.clone(),
+                        WarningKind::UnusedFunction(function.name.clone()),
+                    )
+                }));
+            }
+        }
+
+        if enabled(Feature::UnusedSymbolWarnings) {
             warnings.extend(symbols::unused_symbols(&sem_pkg));
         }
 
+        if enabled(Feature::ShadowedSymbolWarnings) {
+            warnings.extend(symbols::shadowed_symbols(&sem_pkg));
+        }
+
+        if enabled(Feature::RepeatedPipelineWarnings) {
+            warnings.extend(pipelines::repeated_pipelines(ast_pkg));
+        }
+
+        if enabled(Feature::QueryInjectionWarnings) {
+            warnings.extend(injection::query_injections(ast_pkg));
+        }
+
+        #[cfg(feature = "timezones")]
+        if enabled(Feature::TimezoneWarnings) {
+            warnings.extend(timezones::unknown_timezones(ast_pkg));
+        }
+
+        if enabled(Feature::DurationWarnings) {
+            warnings.extend(durations::duration_arithmetic(&sem_pkg));
+        }
+
+        if enabled(Feature::WindowWarnings) {
+            warnings.extend(windows::window_sizes(ast_pkg));
+        }
+
+        if enabled(Feature::NullWarnings) {
+            warnings.extend(nulls::never_null_checks(&sem_pkg));
+        }
+
+        if enabled(Feature::NumericWarnings) {
+            warnings.extend(numeric::integer_divisions(&sem_pkg));
+        }
+
+        if let Some(schema) = &self.config.schema {
+            warnings.extend(schema::check_schema(ast_pkg, schema.as_ref()));
+        }
+
+        // Suppressions are only looked for when the analyzer reports warnings they could name.
+        let checked: Vec<&str> = WARNINGS
+            .iter()
+            .filter(|(_, feature)| match feature {
+                Some(feature) => enabled(feature.clone()),
+                None => self.config.schema.is_some(),
+            })
+            .map(|(name, _)| *name)
+            .collect();
+        if !checked.is_empty() {
+            let mut suppressions = Suppressions::new(ast::walk::Node::Package(ast_pkg));
+            warnings = warnings
+                .into_iter()
+                .filter(|warning| !suppressions.suppresses(warning.error.name(), &warning.location))
+                .collect();
+            warnings.extend(
+                suppressions
+                    .unused(|name| checked.contains(&name))
+                    .into_iter()
+                    .map(|(name, location)| {
+                        located(
+                            location.clone(),
+                            WarningKind::UnusedSuppression(name.to_string()),
+                        )
+                    }),
+            );
+        }
+
+        if strict {
+            errors.extend(
+                warnings
+                    .into_iter()
+                    .map(|warning| warning.map(ErrorKind::Strict)),
+            );
+            warnings = Errors::new();
+        }
+
+        if let Some(observer) = &mut self.observer {
+            observer(&Usage::new(&sem_pkg, &self.env_imports, &errors, &warnings));
+        }
+
         if errors.has_errors() {
+            if let Some(max) = self.config.limits.max_errors {
+                errors = errors.into_iter().take(max.max(1)).collect();
+            }
+            if let Some(max) = self.config.limits.max_warnings {
+                warnings = warnings.into_iter().take(max).collect();
+            }
             return Err(Salvage {
                 error: FileErrors {
                     file: sem_pkg.package.clone(),
                     source: None,
                     diagnostics: Diagnostics { errors, warnings },
+                    encoding: self.config.column_encoding,
                 },
                 value: Some((env, sem_pkg)),
             });
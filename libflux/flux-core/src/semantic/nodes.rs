@@ -28,7 +28,7 @@ use crate::{
             self, BoundTvar, BoundTvarKinds, Dictionary, Function, Kind, Label, MonoType,
             MonoTypeMap, PolyType, RecordLabel, Tvar,
         },
-        AnalyzerConfig, Feature,
+        walk, AnalyzerConfig, Feature,
     },
 };
 
@@ -58,6 +58,8 @@ pub enum ErrorKind {
     InvalidUnaryOp(ast::Operator),
     #[display(fmt = "invalid import path {}", _0)]
     InvalidImportPath(String),
+    #[display(fmt = "package {} is not loaded by this analyzer", _0)]
+    UnloadedPackage(String),
     #[display(fmt = "return not valid in file block")]
     InvalidReturn,
     #[display(fmt = "can't vectorize function: {}", _0)]
@@ -66,6 +68,8 @@ pub enum ErrorKind {
     MissingConstraint { var: BoundTvar, kind: Kind },
     #[display(fmt = "{}. This is a bug in type inference", _0)]
     Bug(String),
+    #[display(fmt = "found hole of type {}", _0)]
+    TypedHole(MonoType),
 }
 
 impl std::error::Error for Error {}
@@ -74,6 +78,12 @@ impl AsDiagnostic for ErrorKind {
     fn as_diagnostic(&self, _source: &dyn crate::semantic::Source) -> diagnostic::Diagnostic<()> {
         match self {
             ErrorKind::Inference(err) => err.as_diagnostic(),
+            ErrorKind::UnloadedPackage(_) => diagnostic::Diagnostic::error()
+                .with_message(self.to_string())
+                .with_notes(vec![
+                    "the analyzer was created with a minimal prelude; add the package to the packages it loads"
+                        .to_string(),
+                ]),
             _ => diagnostic::Diagnostic::error().with_message(self.to_string()),
         }
     }
@@ -89,11 +99,13 @@ impl Substitutable for ErrorKind {
                     _ => None,
                 })
             }
+            Self::TypedHole(typ) => typ.visit(sub).map(Self::TypedHole),
             Self::UndefinedBuiltin(_)
             | Self::UndefinedIdentifier(_)
             | Self::InvalidBinOp(_)
             | Self::InvalidUnaryOp(_)
             | Self::InvalidImportPath(_)
+            | Self::UnloadedPackage(_)
             | Self::UnableToVectorize(_)
             | Self::InvalidReturn
             | Self::Bug(_) => None,
@@ -451,6 +463,24 @@ pub fn inject_pkg_types(mut pkg: Package, sub: &mut Substitution) -> Package {
     pkg
 }
 
+/// Returns the location and type of every typed hole (`_?`) in a package.
+/// The types are only meaningful once the package has been inferred and its
+/// types injected with [`inject_pkg_types`].
+pub fn typed_holes(pkg: &Package) -> Vec<(ast::SourceLocation, MonoType)> {
+    let mut holes = Vec::new();
+    walk::walk(
+        &mut |node: walk::Node| {
+            if let walk::Node::IdentifierExpr(id) = node {
+                if id.name.as_str() == ast::TYPED_HOLE {
+                    holes.push((id.loc.clone(), id.typ.clone()));
+                }
+            }
+        },
+        walk::Node::Package(pkg),
+    );
+    holes
+}
+
 struct FinalizeTypes<'a> {
     sub: &'a mut dyn Substituter,
 }
@@ -512,7 +542,12 @@ impl File {
             infer.imports.insert(name.clone(), path.clone());
 
             let poly = infer.importer.import(path).unwrap_or_else(|| {
-                infer.error(dec.loc.clone(), ErrorKind::InvalidImportPath(path.clone()));
+                let err = if infer.importer.is_unloaded(path) {
+                    ErrorKind::UnloadedPackage(path.clone())
+                } else {
+                    ErrorKind::InvalidImportPath(path.clone())
+                };
+                infer.error(dec.loc.clone(), err);
                 PolyType::error()
             });
 
@@ -1740,6 +1775,13 @@ pub struct IdentifierExpr {
 
 impl IdentifierExpr {
     fn infer(&mut self, infer: &mut InferState<'_, '_>) -> Result {
+        // A typed hole may have any type, its type is determined by how it is used.
+        if self.name.as_str() == ast::TYPED_HOLE
+            && infer.config.features.contains(&Feature::TypedHoles)
+        {
+            self.typ = MonoType::Var(infer.sub.fresh());
+            return Ok(());
+        }
         let poly = infer.lookup(&self.loc, &self.name);
 
         let (t, cons) = infer::instantiate(poly, infer.sub, self.loc.clone());
@@ -0,0 +1,62 @@
+use crate::{
+    ast::Operator,
+    errors::located,
+    map::HashSet,
+    semantic::{
+        nodes::{Expression, Package, Symbol},
+        walk::{walk, Node},
+        Warning, WarningKind,
+    },
+};
+
+// Returns whether the expression is a literal, a variable bound to one, or arithmetic on them.
+// Only values read from outside the program, such as the columns of a record, can be null.
+fn is_never_null(expr: &Expression, never_null: &HashSet<Symbol>) -> bool {
+    match expr {
+        Expression::Integer(_)
+        | Expression::Float(_)
+        | Expression::StringLit(_)
+        | Expression::Duration(_)
+        | Expression::Uint(_)
+        | Expression::Boolean(_)
+        | Expression::DateTime(_) => true,
+        Expression::Identifier(id) => never_null.contains(&id.name),
+        Expression::Binary(binary) => {
+            is_never_null(&binary.left, never_null) && is_never_null(&binary.right, never_null)
+        }
+        Expression::Unary(unary) => {
+            unary.operator != Operator::ExistsOperator && is_never_null(&unary.argument, never_null)
+        }
+        _ => false,
+    }
+}
+
+/// Finds `exists` checks of values that are never null, which are always true. They usually
+/// check the wrong value, e.g. a variable instead of the column of a record.
+pub fn never_null_checks(pkg: &Package) -> Vec<Warning> {
+    let mut never_null = HashSet::new();
+    let mut warnings = Vec::new();
+    walk(
+        &mut |node: Node| match node {
+            // Variables are defined before they are used, so the variables an initializer refers
+            // to have already been visited.
+            Node::VariableAssgn(assgn) => {
+                if is_never_null(&assgn.init, &never_null) {
+                    never_null.insert(assgn.id.name.clone());
+                }
+            }
+            Node::UnaryExpr(unary)
+                if unary.operator == Operator::ExistsOperator
+                    && is_never_null(&unary.argument, &never_null) =>
+            {
+                warnings.push(located(
+                    unary.loc.clone(),
+                    WarningKind::NeverNull(unary.argument.loc().source.clone().unwrap_or_default()),
+                ));
+            }
+            _ => (),
+        },
+        Node::Package(pkg),
+    );
+    warnings
+}
@@ -0,0 +1,34 @@
+use crate::{
+    ast::Operator,
+    errors::located,
+    semantic::{
+        nodes::{Expression, Package},
+        types::MonoType,
+        walk::{walk, Node},
+        Warning, WarningKind,
+    },
+};
+
+/// Finds divisions made integer divisions by an integer literal. Flux gives a number literal
+/// without a fraction the type `int`, so `x / 2` makes `x` an int and truncates the result.
+pub fn integer_divisions(pkg: &Package) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    walk(
+        &mut |node: Node| {
+            if let Node::BinaryExpr(binary) = node {
+                if binary.operator == Operator::DivisionOperator
+                    && binary.typ == MonoType::INT
+                    && (matches!(binary.left, Expression::Integer(_))
+                        || matches!(binary.right, Expression::Integer(_)))
+                {
+                    warnings.push(located(
+                        binary.loc.clone(),
+                        WarningKind::IntegerDivision(binary.loc.source.clone().unwrap_or_default()),
+                    ));
+                }
+            }
+        },
+        Node::Package(pkg),
+    );
+    warnings
+}
@@ -0,0 +1,240 @@
+use std::collections::HashSet;
+
+use crate::{
+    ast::{
+        self,
+        beautify::{fresh_variable, identifiers},
+        inline::{bound_names, free_variables},
+        lines::LineIndex,
+        pattern::{equivalent, equivalent_calls},
+        walk::{walk, Node, Visitor},
+    },
+    errors::located,
+    semantic::{Warning, WarningKind},
+};
+
+// Pipelines must share at least this many stages after their head to be reported.
+const MIN_SHARED_STAGES: usize = 2;
+
+struct Pipeline<'a> {
+    pipe: &'a ast::PipeExpr,
+    head: &'a ast::Expression,
+    // The nested pipe expressions that make up the pipeline, innermost first.
+    // The `n`th element is the prefix of the pipeline with `n + 1` stages.
+    prefixes: Vec<&'a ast::PipeExpr>,
+    // The file containing the pipeline and the functions enclosing it, outermost first.
+    file: &'a ast::File,
+    functions: Vec<&'a ast::FunctionExpr>,
+}
+
+impl<'a> Pipeline<'a> {
+    fn new(
+        pipe: &'a ast::PipeExpr,
+        file: &'a ast::File,
+        functions: Vec<&'a ast::FunctionExpr>,
+    ) -> Self {
+        let mut prefixes = vec![pipe];
+        let mut head = &pipe.argument;
+        loop {
+            match head {
+                ast::Expression::PipeExpr(inner) => {
+                    prefixes.push(inner);
+                    head = &inner.argument;
+                }
+                ast::Expression::Paren(paren) => head = &paren.expression,
+                _ => break,
+            }
+        }
+        prefixes.reverse();
+        Pipeline {
+            pipe,
+            head,
+            prefixes,
+            file,
+            functions,
+        }
+    }
+
+    // Returns the number of leading stages two pipelines have in common. The stages must
+    // read the same bindings of their free variables, not just the same names.
+    fn shared_stages(&self, other: &Pipeline) -> usize {
+        if !equivalent(self.head, other.head) {
+            return 0;
+        }
+        let mut shared = self
+            .prefixes
+            .iter()
+            .zip(&other.prefixes)
+            .take_while(|(a, b)| equivalent_calls(&a.call, &b.call))
+            .count();
+        while shared > 0 && self.binders(shared) != other.binders(shared) {
+            shared -= 1;
+        }
+        shared
+    }
+
+    // Returns the free variables of the first `stages` stages along with the function
+    // binding each of them, or `None` for variables bound outside of any function.
+    fn binders(&self, stages: usize) -> Vec<(String, Option<*const ast::FunctionExpr>)> {
+        let prefix = ast::Expression::PipeExpr(Box::new(self.prefixes[stages - 1].clone()));
+        let mut binders: Vec<_> = free_variables(&prefix)
+            .into_keys()
+            .map(|name| {
+                let binder = self
+                    .functions
+                    .iter()
+                    .rev()
+                    .find(|func| bound_names(func).contains(&name))
+                    .map(|func| *func as *const ast::FunctionExpr);
+                (name, binder)
+            })
+            .collect();
+        binders.sort();
+        binders
+    }
+
+    // Returns the position of the statement before which the first `stages` stages can be
+    // bound to a variable: the statement containing the pipeline in the body of the
+    // innermost function binding one of their free variables, or in the file otherwise.
+    fn binding_position(&self, stages: usize) -> Option<ast::Position> {
+        let binders = self.binders(stages);
+        let body = match self
+            .functions
+            .iter()
+            .rev()
+            .find(|func| binders.iter().any(|(_, b)| *b == Some(**func as *const _)))
+        {
+            Some(func) => match &func.body {
+                ast::FunctionBody::Block(block) => &block.body,
+                ast::FunctionBody::Expr(_) => return None,
+            },
+            None => &self.file.body,
+        };
+        let start = self.pipe.base.location.start;
+        body.iter()
+            .map(|stmt| stmt.base().location.start)
+            .take_while(|pos| (pos.line, pos.column) <= (start.line, start.column))
+            .last()
+    }
+}
+
+// Collects the outermost pipe expression of every pipeline.
+#[derive(Default)]
+struct PipelineCollector<'a> {
+    inner: HashSet<*const ast::PipeExpr>,
+    pipelines: Vec<Pipeline<'a>>,
+    file: Option<&'a ast::File>,
+    functions: Vec<&'a ast::FunctionExpr>,
+}
+
+impl<'a> Visitor<'a> for PipelineCollector<'a> {
+    fn visit(&mut self, node: Node<'a>) -> bool {
+        match node {
+            Node::File(file) => self.file = Some(file),
+            Node::FunctionExpr(func) => self.functions.push(func),
+            Node::PipeExpr(pipe) => {
+                if let Some(file) = self.file {
+                    if !self.inner.contains(&(pipe as *const ast::PipeExpr)) {
+                        let pipeline = Pipeline::new(pipe, file, self.functions.clone());
+                        self.inner
+                            .extend(pipeline.prefixes.iter().map(|p| *p as *const ast::PipeExpr));
+                        self.pipelines.push(pipeline);
+                    }
+                }
+            }
+            _ => (),
+        }
+        true
+    }
+
+    fn done(&mut self, node: Node<'a>) {
+        if let Node::FunctionExpr(_) = node {
+            self.functions.pop();
+        }
+    }
+}
+
+// Returns the source of `pipe` exactly as written.
+fn source(pipe: &ast::PipeExpr) -> &str {
+    pipe.base.location.source.as_deref().unwrap_or_default()
+}
+
+// Returns `position` relative to the start of `outer`.
+fn relative(outer: ast::Position, position: ast::Position) -> ast::Position {
+    ast::Position {
+        line: position.line - outer.line + 1,
+        column: if position.line == outer.line {
+            position.column - outer.column + 1
+        } else {
+            position.column
+        },
+    }
+}
+
+// Returns the source of `pipe` with the source of `prefix`, one of its nested pipe
+// expressions, replaced by `variable`.
+fn replace_prefix(pipe: &ast::PipeExpr, prefix: &ast::PipeExpr, variable: &str) -> Option<String> {
+    let outer = &pipe.base.location;
+    let inner = &prefix.base.location;
+    let source = outer.source.as_deref()?;
+    let lines = LineIndex::new(source);
+    let start = lines.offset(relative(outer.start, inner.start))?;
+    let end = lines.offset(relative(outer.start, inner.end))?;
+    Some(format!(
+        "{}{}{}",
+        source.get(..start)?,
+        variable,
+        source.get(end..)?
+    ))
+}
+
+/// Finds pipelines that repeat the same leading stages, which could be
+/// computed once and assigned to a variable instead.
+pub fn repeated_pipelines(pkg: &ast::Package) -> Vec<Warning> {
+    let mut collector = PipelineCollector::default();
+    walk(&mut collector, Node::Package(pkg));
+    let pipelines = collector.pipelines;
+    let mut names = identifiers(Node::Package(pkg));
+
+    let mut warnings = Vec::new();
+    let mut reported = vec![false; pipelines.len()];
+    for i in 0..pipelines.len() {
+        if reported[i] {
+            continue;
+        }
+        // Report the longest prefix this pipeline shares with any later pipeline.
+        let shared = (i + 1..pipelines.len())
+            .filter(|&j| !reported[j])
+            .map(|j| pipelines[i].shared_stages(&pipelines[j]))
+            .max()
+            .unwrap_or(0);
+        if shared < MIN_SHARED_STAGES {
+            continue;
+        }
+        let mut group = vec![i];
+        group.extend(
+            (i + 1..pipelines.len())
+                .filter(|&j| !reported[j] && pipelines[i].shared_stages(&pipelines[j]) >= shared),
+        );
+
+        let variable = fresh_variable(&names);
+        names.insert(variable.clone());
+        let binding = pipelines[i].binding_position(shared);
+        for &j in &group {
+            reported[j] = true;
+            let prefix = pipelines[j].prefixes[shared - 1];
+            let rewrite = replace_prefix(pipelines[j].pipe, prefix, &variable);
+            warnings.push(located(
+                prefix.base.location.clone(),
+                WarningKind::RepeatedPipeline {
+                    pipeline: source(prefix).to_string(),
+                    count: group.len(),
+                    variable: variable.clone(),
+                    binding,
+                    rewrite,
+                },
+            ));
+        }
+    }
+    warnings
+}
@@ -0,0 +1,542 @@
+//! Checking of queries against the schema of the buckets they read.
+//!
+//! A [`SchemaProvider`] describes the measurements of a bucket, with the tags and the types of
+//! the fields of each. [`check_schema`] uses it to find filters that refer to measurements,
+//! fields or columns that do not exist, or that compare a tag, which is always a string, to a
+//! value of another type. The analyzer runs it with the other warnings when its configuration
+//! has a [`schema`](crate::semantic::AnalyzerConfig::schema).
+//!
+//! Only filters applied directly to the data read by `from`, possibly after `range` or other
+//! filters, are checked, since other transformations change the columns of the data.
+//!
+//! [`predict_columns`] uses the schema to predict the columns of the tables produced by a
+//! pipeline.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::Path,
+};
+
+use anyhow::{Context as _, Result};
+
+use crate::{
+    ast::{
+        self,
+        walk::{walk, Node},
+    },
+    errors::located,
+    semantic::{Warning, WarningKind},
+};
+
+/// The type of the values of a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+#[allow(missing_docs)]
+pub enum FieldType {
+    Float,
+    Int,
+    Uint,
+    String,
+    Bool,
+}
+
+/// The tags and fields of a measurement.
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MeasurementSchema {
+    /// The tag keys of the measurement.
+    pub tags: BTreeSet<String>,
+    /// The type of each field of the measurement.
+    pub fields: BTreeMap<String, FieldType>,
+}
+
+/// The measurements of a bucket, by name.
+pub type BucketSchema = BTreeMap<String, MeasurementSchema>;
+
+/// Describes the schema of buckets.
+pub trait SchemaProvider: fmt::Debug {
+    /// Returns the schema of a bucket, or `None` if the bucket does not exist.
+    fn bucket_schema(&self, bucket: &str) -> Option<BucketSchema>;
+}
+
+/// A [`SchemaProvider`] reading the schema of buckets from JSON, e.g.
+/// `{"telegraf": {"cpu": {"tags": ["host"], "fields": {"usage_user": "float"}}}}`.
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(transparent)]
+pub struct JsonSchemaProvider {
+    buckets: BTreeMap<String, BucketSchema>,
+}
+
+impl JsonSchemaProvider {
+    /// Reads the schema of buckets from a JSON file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("opening schema file {}", path.display()))?;
+        serde_json::from_reader(std::io::BufReader::new(file))
+            .with_context(|| format!("parsing schema file {}", path.display()))
+    }
+}
+
+impl std::str::FromStr for JsonSchemaProvider {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
+
+impl SchemaProvider for JsonSchemaProvider {
+    fn bucket_schema(&self, bucket: &str) -> Option<BucketSchema> {
+        self.buckets.get(bucket).cloned()
+    }
+}
+
+fn argument<'a>(call: &'a ast::CallExpr, key: &str) -> Option<&'a ast::Expression> {
+    call.arguments
+        .iter()
+        .flat_map(|arg| match arg {
+            ast::Expression::Object(obj) => obj.properties.as_slice(),
+            _ => &[][..],
+        })
+        .find(|prop| prop.key.key() == key)
+        .and_then(|prop| prop.value.as_ref())
+}
+
+fn callee_name(call: &ast::CallExpr) -> Option<&str> {
+    match &call.callee {
+        ast::Expression::Identifier(id) => Some(id.name.as_str()),
+        _ => None,
+    }
+}
+
+// Returns the bucket read by `from` at the head of the pipeline an expression is piped from, if
+// only `range` and `filter` are applied in between.
+fn source_bucket(mut expr: &ast::Expression) -> Option<&ast::StringLit> {
+    loop {
+        match expr {
+            ast::Expression::PipeExpr(pipe)
+                if matches!(callee_name(&pipe.call), Some("range" | "filter")) =>
+            {
+                expr = &pipe.argument
+            }
+            ast::Expression::Call(call) if callee_name(call) == Some("from") => {
+                return match argument(call, "bucket") {
+                    Some(ast::Expression::StringLit(lit)) => Some(lit),
+                    _ => None,
+                }
+            }
+            _ => return None,
+        }
+    }
+}
+
+struct FilterChecker<'a> {
+    bucket: &'a str,
+    schema: &'a BucketSchema,
+    // The name of the record parameter of the predicate.
+    record: &'a str,
+    warnings: Vec<Warning>,
+}
+
+impl<'a> FilterChecker<'a> {
+    fn is_tag(&self, column: &str) -> bool {
+        self.schema.values().any(|m| m.tags.contains(column))
+    }
+
+    fn column<'b>(&self, expr: &'b ast::Expression) -> Option<&'b ast::MemberExpr> {
+        match expr {
+            ast::Expression::Member(member) => match &member.object {
+                ast::Expression::Identifier(id) if id.name == self.record => Some(member),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn comparison(&mut self, binary: &ast::BinaryExpr) {
+        let (member, value) = match (self.column(&binary.left), self.column(&binary.right)) {
+            (Some(member), None) => (member, &binary.right),
+            (None, Some(member)) => (member, &binary.left),
+            _ => return,
+        };
+        let column = member.property.key();
+        let warning = match (column, value) {
+            ("_measurement", ast::Expression::StringLit(lit))
+                if !self.schema.contains_key(&lit.value) =>
+            {
+                located(
+                    lit.base.location.clone(),
+                    WarningKind::UnknownMeasurement {
+                        bucket: self.bucket.to_string(),
+                        measurement: lit.value.clone(),
+                    },
+                )
+            }
+            ("_field", ast::Expression::StringLit(lit))
+                if !self
+                    .schema
+                    .values()
+                    .any(|m| m.fields.contains_key(&lit.value)) =>
+            {
+                located(
+                    lit.base.location.clone(),
+                    WarningKind::UnknownField {
+                        bucket: self.bucket.to_string(),
+                        field: lit.value.clone(),
+                    },
+                )
+            }
+            (column, _) if column.starts_with('_') => return,
+            (column, _) if !self.is_tag(column) => located(
+                member.base.location.clone(),
+                WarningKind::UnknownColumn {
+                    bucket: self.bucket.to_string(),
+                    column: column.to_string(),
+                },
+            ),
+            (
+                column,
+                ast::Expression::Integer(_)
+                | ast::Expression::Uint(_)
+                | ast::Expression::Float(_)
+                | ast::Expression::Duration(_)
+                | ast::Expression::DateTime(_),
+            ) => located(
+                binary.base.location.clone(),
+                WarningKind::TagComparedToNonString(column.to_string()),
+            ),
+            _ => return,
+        };
+        self.warnings.push(warning);
+    }
+}
+
+/// Checks the filters of the queries in a package against the schema of the buckets they read.
+pub fn check_schema(pkg: &ast::Package, provider: &dyn SchemaProvider) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    walk(
+        &mut |node: Node| match node {
+            Node::CallExpr(call) if callee_name(call) == Some("from") => {
+                if let Some(ast::Expression::StringLit(lit)) = argument(call, "bucket") {
+                    if provider.bucket_schema(&lit.value).is_none() {
+                        warnings.push(located(
+                            lit.base.location.clone(),
+                            WarningKind::UnknownBucket(lit.value.clone()),
+                        ));
+                    }
+                }
+            }
+            Node::PipeExpr(pipe) if callee_name(&pipe.call) == Some("filter") => {
+                let bucket = match source_bucket(&pipe.argument) {
+                    Some(bucket) => bucket,
+                    None => return,
+                };
+                let (predicate, schema) = match (
+                    argument(&pipe.call, "fn"),
+                    provider.bucket_schema(&bucket.value),
+                ) {
+                    (Some(ast::Expression::Function(predicate)), Some(schema)) => {
+                        (predicate, schema)
+                    }
+                    _ => return,
+                };
+                let record = match predicate.params.first() {
+                    Some(param) => param.key.key(),
+                    None => return,
+                };
+                let mut checker = FilterChecker {
+                    bucket: &bucket.value,
+                    schema: &schema,
+                    record,
+                    warnings: Vec::new(),
+                };
+                walk(
+                    &mut |node: Node| {
+                        if let Node::BinaryExpr(binary) = node {
+                            checker.comparison(binary);
+                        }
+                    },
+                    Node::FunctionExpr(predicate),
+                );
+                warnings.extend(checker.warnings);
+            }
+            _ => (),
+        },
+        Node::Package(pkg),
+    );
+    warnings
+}
+
+/// The type of the values of a column predicted by [`predict_columns`].
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, derive_more::Display, serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+#[allow(missing_docs)]
+pub enum ColumnType {
+    #[display(fmt = "time")]
+    Time,
+    #[display(fmt = "string")]
+    String,
+    #[display(fmt = "float")]
+    Float,
+    #[display(fmt = "int")]
+    Int,
+    #[display(fmt = "uint")]
+    Uint,
+    #[display(fmt = "bool")]
+    Bool,
+    /// The values of fields of different types.
+    #[display(fmt = "mixed")]
+    Mixed,
+}
+
+impl From<FieldType> for ColumnType {
+    fn from(typ: FieldType) -> Self {
+        match typ {
+            FieldType::Float => ColumnType::Float,
+            FieldType::Int => ColumnType::Int,
+            FieldType::Uint => ColumnType::Uint,
+            FieldType::String => ColumnType::String,
+            FieldType::Bool => ColumnType::Bool,
+        }
+    }
+}
+
+/// A column of the tables produced by a pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Column {
+    /// The name of the column.
+    pub name: String,
+    /// The type of the values of the column.
+    #[serde(rename = "type")]
+    pub typ: ColumnType,
+    /// Whether the column is part of the group key.
+    #[serde(rename = "groupKey")]
+    pub group_key: bool,
+}
+
+impl Column {
+    fn new(name: &str, typ: ColumnType, group_key: bool) -> Self {
+        Column {
+            name: name.to_string(),
+            typ,
+            group_key,
+        }
+    }
+}
+
+// The measurements and fields a pipeline reads, with the columns of its tables.
+struct Prediction {
+    // The type of each field read by measurement.
+    fields: BTreeMap<String, BTreeMap<String, FieldType>>,
+    columns: Vec<Column>,
+}
+
+impl Prediction {
+    fn from_schema(schema: BucketSchema) -> Self {
+        let mut prediction = Prediction {
+            fields: BTreeMap::new(),
+            columns: Vec::new(),
+        };
+        let mut tags = BTreeSet::new();
+        for (measurement, schema) in schema {
+            tags.extend(schema.tags);
+            prediction.fields.insert(measurement, schema.fields);
+        }
+        prediction.columns = vec![
+            Column::new("_start", ColumnType::Time, true),
+            Column::new("_stop", ColumnType::Time, true),
+            Column::new("_time", ColumnType::Time, false),
+            Column::new("_value", prediction.value_type(), false),
+            Column::new("_field", ColumnType::String, true),
+            Column::new("_measurement", ColumnType::String, true),
+        ];
+        prediction.columns.extend(
+            tags.iter()
+                .map(|tag| Column::new(tag, ColumnType::String, true)),
+        );
+        prediction
+    }
+
+    fn value_type(&self) -> ColumnType {
+        let types: BTreeSet<_> = self
+            .fields
+            .values()
+            .flat_map(|fields| fields.values())
+            .map(|typ| ColumnType::from(*typ))
+            .collect();
+        match types.len() {
+            1 => types.into_iter().next().unwrap(),
+            _ => ColumnType::Mixed,
+        }
+    }
+
+    fn column_mut(&mut self, name: &str) -> Option<&mut Column> {
+        self.columns.iter_mut().find(|c| c.name == name)
+    }
+
+    // Narrows the measurements and fields read to those a predicate compares
+    // `_measurement` and `_field` with.
+    fn filter(&mut self, predicate: &ast::FunctionExpr) {
+        let record = match predicate.params.first() {
+            Some(param) => param.key.key(),
+            None => return,
+        };
+        let (mut measurements, mut fields) = (BTreeSet::new(), BTreeSet::new());
+        walk(
+            &mut |node: Node| {
+                if let Node::BinaryExpr(binary) = node {
+                    if binary.operator != ast::Operator::EqualOperator {
+                        return;
+                    }
+                    if let (ast::Expression::Member(member), ast::Expression::StringLit(lit)) =
+                        (&binary.left, &binary.right)
+                    {
+                        if matches!(&member.object, ast::Expression::Identifier(id) if id.name == record)
+                        {
+                            match member.property.key() {
+                                "_measurement" => {
+                                    measurements.insert(lit.value.clone());
+                                }
+                                "_field" => {
+                                    fields.insert(lit.value.clone());
+                                }
+                                _ => (),
+                            }
+                        }
+                    }
+                }
+            },
+            Node::FunctionExpr(predicate),
+        );
+        if !measurements.is_empty() {
+            self.fields.retain(|m, _| measurements.contains(m));
+        }
+        if !fields.is_empty() {
+            for types in self.fields.values_mut() {
+                types.retain(|f, _| fields.contains(f));
+            }
+        }
+        let value_type = self.value_type();
+        if let Some(value) = self.column_mut("_value") {
+            value.typ = value_type;
+        }
+    }
+
+    fn group(&mut self, columns: &[String], except: bool) {
+        for column in &mut self.columns {
+            column.group_key = columns.contains(&column.name) != except;
+        }
+    }
+
+    // Pivots fields into columns. Only pivots on `_field` are predicted.
+    fn pivot(&mut self, row_key: &[String], column_key: &[String], value: &str) -> Option<()> {
+        if column_key != ["_field"] || value != "_value" {
+            return None;
+        }
+        let mut fields = BTreeMap::new();
+        for (field, typ) in self.fields.values().flat_map(|fields| fields.iter()) {
+            fields
+                .entry(field.clone())
+                .and_modify(|t| {
+                    if *t != ColumnType::from(*typ) {
+                        *t = ColumnType::Mixed
+                    }
+                })
+                .or_insert_with(|| ColumnType::from(*typ));
+        }
+        self.columns.retain(|c| {
+            (c.group_key || row_key.contains(&c.name)) && c.name != "_field" && c.name != "_value"
+        });
+        self.columns.extend(
+            fields
+                .into_iter()
+                .map(|(field, typ)| Column::new(&field, typ, false)),
+        );
+        Some(())
+    }
+}
+
+fn string_array(expr: Option<&ast::Expression>) -> Option<Vec<String>> {
+    match expr? {
+        ast::Expression::Array(arr) => arr
+            .elements
+            .iter()
+            .map(|item| match &item.expression {
+                ast::Expression::StringLit(lit) => Some(lit.value.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+fn string(expr: Option<&ast::Expression>) -> Option<&str> {
+    match expr? {
+        ast::Expression::StringLit(lit) => Some(&lit.value),
+        _ => None,
+    }
+}
+
+/// Predicts the columns of the tables produced by a pipeline that reads a bucket with `from`,
+/// using the schema of the bucket. Returns `None` when the pipeline does not read a known bucket
+/// or applies a transformation whose columns cannot be predicted.
+///
+/// Besides `range` and `filter`, which narrows the measurements and fields read, the effects of
+/// `group`, `keep`, `drop` and of `pivot` on `_field` are predicted.
+pub fn predict_columns(
+    expr: &ast::Expression,
+    provider: &dyn SchemaProvider,
+) -> Option<Vec<Column>> {
+    let mut stages = Vec::new();
+    let mut head = expr;
+    while let ast::Expression::PipeExpr(pipe) = head {
+        stages.push(&pipe.call);
+        head = &pipe.argument;
+    }
+    let bucket = match head {
+        ast::Expression::Call(call) if callee_name(call) == Some("from") => {
+            string(argument(call, "bucket"))?
+        }
+        _ => return None,
+    };
+    let mut prediction = Prediction::from_schema(provider.bucket_schema(bucket)?);
+    for call in stages.into_iter().rev() {
+        match callee_name(call)? {
+            "range" => (),
+            "filter" => match argument(call, "fn") {
+                Some(ast::Expression::Function(predicate)) => prediction.filter(predicate),
+                _ => return None,
+            },
+            "group" => {
+                let columns = match argument(call, "columns") {
+                    Some(columns) => string_array(Some(columns))?,
+                    None => Vec::new(),
+                };
+                let except = match string(argument(call, "mode")) {
+                    None | Some("by") => false,
+                    Some("except") => true,
+                    Some(_) => return None,
+                };
+                prediction.group(&columns, except);
+            }
+            "keep" => {
+                let columns = string_array(argument(call, "columns"))?;
+                prediction.columns.retain(|c| columns.contains(&c.name));
+            }
+            "drop" => {
+                let columns = string_array(argument(call, "columns"))?;
+                prediction.columns.retain(|c| !columns.contains(&c.name));
+            }
+            "pivot" => prediction.pivot(
+                &string_array(argument(call, "rowKey"))?,
+                &string_array(argument(call, "columnKey"))?,
+                string(argument(call, "valueColumn"))?,
+            )?,
+            _ => return None,
+        }
+    }
+    Some(prediction.columns)
+}
@@ -105,3 +105,65 @@ pub fn unused_symbols(node: &Package) -> Vec<Warning> {
 
     warnings
 }
+
+// Binds the names of each scope, the innermost last, and reports the definitions that shadow a
+// name of an enclosing scope.
+struct ShadowVisitor<'a> {
+    scopes: Vec<HashSet<&'a str>>,
+    warnings: Vec<Warning>,
+}
+
+impl<'a> ShadowVisitor<'a> {
+    fn bind(&mut self, symbol: &'a Symbol, loc: &ast::SourceLocation, enclosing: usize) {
+        let name = symbol.name();
+        if self.scopes[..enclosing]
+            .iter()
+            .any(|scope| scope.contains(name))
+        {
+            self.warnings.push(located(
+                loc.clone(),
+                WarningKind::ShadowedSymbol(name.to_string()),
+            ));
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name);
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for ShadowVisitor<'a> {
+    fn visit(&mut self, node: Node<'a>) -> bool {
+        let enclosing = self.scopes.len().saturating_sub(1);
+        match node {
+            Node::VariableAssgn(va) => self.bind(&va.id.name, &va.id.loc, enclosing),
+            Node::BuiltinStmt(builtin) => self.bind(&builtin.id.name, &builtin.id.loc, enclosing),
+            Node::ImportDeclaration(import) => {
+                self.bind(&import.import_symbol, &import.loc, enclosing)
+            }
+            Node::FunctionExpr(func) => {
+                // Parameters shadow the names of every scope the function is defined in.
+                self.scopes.push(HashSet::new());
+                for param in &func.params {
+                    self.bind(&param.key.name, &param.key.loc, enclosing + 1);
+                }
+            }
+            _ => (),
+        }
+        true
+    }
+
+    fn done(&mut self, node: Node<'a>) {
+        if let Node::FunctionExpr(_) = node {
+            self.scopes.pop();
+        }
+    }
+}
+
+pub fn shadowed_symbols(node: &Package) -> Vec<Warning> {
+    let mut visitor = ShadowVisitor {
+        scopes: vec![HashSet::new()],
+        warnings: Vec::new(),
+    };
+    walk(&mut visitor, node.into());
+    visitor.warnings
+}
@@ -27,7 +27,10 @@ use derive_more::Display;
 use expect_test::expect;
 
 use crate::{
-    ast,
+    ast::{
+        self,
+        lines::{ColumnEncoding, LineIndex},
+    },
     map::HashMap,
     parser,
     semantic::{
@@ -38,6 +41,7 @@ use crate::{
         import::Packages,
         nodes::Symbol,
         types::{BoundTvarKinds, MonoType, PolyType, PolyTypeHashMap, SemanticMap},
+        usage::Usage,
         Analyzer, AnalyzerConfig, Feature, PackageExports,
     },
 };
@@ -406,6 +410,7 @@ macro_rules! package {
 }
 
 mod labels;
+mod schema;
 
 #[test]
 fn dictionary_literals() {
@@ -4216,6 +4221,556 @@ fn unused_import() {
     }
 }
 
+#[test]
+fn repeated_pipeline() {
+    test_error_msg! {
+        config: AnalyzerConfig{
+            features: vec![Feature::RepeatedPipelineWarnings],
+            ..AnalyzerConfig::default()
+        },
+        env: map![
+            "f" => "(<-tables: A, v: int) => A",
+        ],
+        src: r#"
+            data = 1
+            x = data |> f(v: 1) |> f(v: 2) |> f(v: 3)
+            y = data |> f(v: 1) |> f(v: 2)
+            z = 1 + ""
+        "#,
+        expect: expect_test::expect![[r#"
+            warning: `data |> f(v: 1) |> f(v: 2)` is computed by 2 pipelines
+              ┌─ main:3:17
+              │
+            3 │             x = data |> f(v: 1) |> f(v: 2) |> f(v: 3)
+              │                 ^^^^^^^^^^^^^^^^^^^^^^^^^^
+              │
+              = compute it once by assigning it to a variable: `data2 = data |> f(v: 1) |> f(v: 2)`
+              = insert the assignment before the statement at 3:13
+              = then rewrite this pipeline as `data2 |> f(v: 3)`
+
+            warning: `data |> f(v: 1) |> f(v: 2)` is computed by 2 pipelines
+              ┌─ main:4:17
+              │
+            4 │             y = data |> f(v: 1) |> f(v: 2)
+              │                 ^^^^^^^^^^^^^^^^^^^^^^^^^^
+              │
+              = compute it once by assigning it to a variable: `data2 = data |> f(v: 1) |> f(v: 2)`
+              = insert the assignment before the statement at 3:13
+              = then rewrite this pipeline as `data2`
+
+            error: expected int but found string
+              ┌─ main:5:21
+              │
+            5 │             z = 1 + ""
+              │                     ^^
+
+        "#]]
+    }
+}
+
+#[test]
+fn repeated_pipeline_scopes() {
+    test_error_msg! {
+        config: AnalyzerConfig{
+            features: vec![Feature::RepeatedPipelineWarnings],
+            ..AnalyzerConfig::default()
+        },
+        env: map![
+            "f" => "(<-tables: A, v: int) => A",
+        ],
+        src: r#"
+            g = (data) => data |> f(v: 1) |> f(v: 2)
+            h = (data) => data |> f(v: 1) |> f(v: 2)
+            k = (data) => {
+                a = data
+                    |> f(v: 1)
+                    |> f(v: 2)
+                    |> f(v: 3)
+                return data |> f(v: 1) |> f(v: 2)
+            }
+            z = 1 + ""
+        "#,
+        expect: expect_test::expect![[r#"
+            warning: `data
+                                |> f(v: 1)
+                                |> f(v: 2)` is computed by 2 pipelines
+              ┌─ main:5:21
+              │  
+            5 │                   a = data
+              │ ╭─────────────────────^
+            6 │ │                     |> f(v: 1)
+            7 │ │                     |> f(v: 2)
+              │ ╰──────────────────────────────^
+              │  
+              = compute it once by assigning it to a variable: `data2 = data
+                                    |> f(v: 1)
+                                    |> f(v: 2)`
+              = insert the assignment before the statement at 5:17
+              = then rewrite this pipeline as `data2
+                                    |> f(v: 3)`
+
+            warning: `data |> f(v: 1) |> f(v: 2)` is computed by 2 pipelines
+              ┌─ main:9:24
+              │
+            9 │                 return data |> f(v: 1) |> f(v: 2)
+              │                        ^^^^^^^^^^^^^^^^^^^^^^^^^^
+              │
+              = compute it once by assigning it to a variable: `data2 = data |> f(v: 1) |> f(v: 2)`
+              = insert the assignment before the statement at 5:17
+              = then rewrite this pipeline as `data2`
+
+            error: expected int but found string
+               ┌─ main:11:21
+               │
+            11 │             z = 1 + ""
+               │                     ^^
+
+        "#]]
+    }
+}
+
+#[test]
+fn call_graph_checks() {
+    test_error_msg! {
+        config: AnalyzerConfig{
+            features: vec![Feature::CallGraphChecks],
+            ..AnalyzerConfig::default()
+        },
+        src: r#"
+            a = () => 1
+            b = () => a()
+            f = (n) => if n > 0 then f(n: n - 1) else 0
+            f(n: 1)
+        "#,
+        expect: expect_test::expect![[r#"
+            warning: function "b" is never used
+              ┌─ main:3:13
+              │
+            3 │             b = () => a()
+              │             ^
+              │
+              = only functions of library packages are exported; remove it or call it
+
+            error: undefined identifier f
+              ┌─ main:4:38
+              │
+            4 │             f = (n) => if n > 0 then f(n: n - 1) else 0
+              │                                      ^
+
+            error: function "f" is recursive: f -> f
+              ┌─ main:4:38
+              │
+            4 │             f = (n) => if n > 0 then f(n: n - 1) else 0
+              │                                      ^
+              │
+              = Flux does not support recursion
+
+        "#]]
+    }
+}
+
+#[test]
+fn query_injection() {
+    test_error_msg! {
+        config: AnalyzerConfig{
+            features: vec![Feature::QueryInjectionWarnings],
+            ..AnalyzerConfig::default()
+        },
+        imp: map![
+            "sql" => package![
+                "from" => "(driverName: string, dataSourceName: string, query: string) => int",
+            ],
+        ],
+        src: r#"
+            import "sql"
+
+            f = (table) => sql.from(driverName: "postgres", dataSourceName: "", query: "SELECT * FROM ${table}")
+            g = (table) => sql.from(driverName: "postgres", dataSourceName: "", query: table)
+            h = (table) => {
+                q = "SELECT * FROM " + table
+                return sql.from(driverName: "postgres", dataSourceName: "", query: q)
+            }
+            i = (table) => {
+                t = table
+                return sql.from(driverName: "postgres", dataSourceName: "", query: "SELECT * FROM ${t}")
+            }
+            z = 1 + ""
+        "#,
+        expect: expect_test::expect![[r#"
+            warning: parameter `table` is built into the query run by `sql.from`
+              ┌─ main:4:105
+              │
+            4 │             f = (table) => sql.from(driverName: "postgres", dataSourceName: "", query: "SELECT * FROM ${table}")
+              │                                                                                                         ^^^^^
+              │
+              = callers can change the query through `table`; validate or escape it before building the query
+
+            warning: parameter `table` is built into the query run by `sql.from`
+              ┌─ main:7:40
+              │
+            7 │                 q = "SELECT * FROM " + table
+              │                                        ^^^^^
+              │
+              = callers can change the query through `table`; validate or escape it before building the query
+
+            warning: parameter `table` is built into the query run by `sql.from`
+               ┌─ main:12:101
+               │
+            12 │                 return sql.from(driverName: "postgres", dataSourceName: "", query: "SELECT * FROM ${t}")
+               │                                                                                                     ^
+               │
+               = callers can change the query through `table`; validate or escape it before building the query
+
+            error: expected int but found string
+               ┌─ main:14:21
+               │
+            14 │             z = 1 + ""
+               │                     ^^
+
+        "#]]
+    }
+}
+
+#[test]
+fn shadowed_symbol() {
+    test_error_msg! {
+        config: AnalyzerConfig{
+            features: vec![Feature::ShadowedSymbolWarnings],
+            ..AnalyzerConfig::default()
+        },
+        src: r#"
+            x = 1
+            f = (x) => {
+                y = x
+                return y
+            }
+            z = 1 + ""
+        "#,
+        expect: expect_test::expect![[r#"
+            warning: symbol x shadows a symbol of an enclosing scope
+              ┌─ main:3:18
+              │
+            3 │             f = (x) => {
+              │                  ^
+              │
+              = uses of `x` in this scope refer to this definition; consider renaming it
+
+            error: expected int but found string
+              ┌─ main:7:21
+              │
+            7 │             z = 1 + ""
+              │                     ^^
+
+        "#]]
+    }
+}
+
+#[test]
+fn strict_checks() {
+    test_error_msg! {
+        config: AnalyzerConfig{
+            features: vec![Feature::StrictChecks],
+            ..AnalyzerConfig::default()
+        },
+        src: r#"
+            f = () => {
+                x = 1
+                return 2
+            }
+        "#,
+        expect: expect_test::expect![[r#"
+            error: symbol x is never used
+              ┌─ main:3:17
+              │
+            3 │                 x = 1
+              │                 ^
+              │
+              = reported as an error because strict checks are enabled
+
+        "#]]
+    }
+}
+
+#[test]
+fn suppressed_warnings() {
+    test_error_msg! {
+        config: AnalyzerConfig{
+            features: vec![Feature::ShadowedSymbolWarnings],
+            ..AnalyzerConfig::default()
+        },
+        src: r#"
+            x = 1
+            // flux-lint: ignore[shadowed-symbol]
+            f = (x) => {
+                // flux-lint: ignore[shadowed-symbol, unused-symbol]
+                y = x
+                return y
+            }
+            z = 1 + ""
+        "#,
+        expect: expect_test::expect![[r#"
+            warning: suppression of `shadowed-symbol` is never used
+              ┌─ main:6:17
+              │
+            6 │                 y = x
+              │                 ^
+              │
+              = remove the name from the suppression comment
+
+            error: expected int but found string
+              ┌─ main:9:21
+              │
+            9 │             z = 1 + ""
+              │                     ^^
+
+        "#]]
+    }
+}
+
+#[cfg(feature = "timezones")]
+#[test]
+fn unknown_timezone() {
+    test_error_msg! {
+        config: AnalyzerConfig{
+            features: vec![Feature::TimezoneWarnings],
+            ..AnalyzerConfig::default()
+        },
+        imp: map![
+            "timezone" => package![
+                "location" => "(name: string) => {zone: string, offset: duration}",
+            ],
+        ],
+        src: r#"
+            import "timezone"
+
+            option location = timezone.location(name: "America/New_Yrok")
+            x = timezone.location(name: "Europe/Paris")
+            z = 1 + ""
+        "#,
+        expect: expect_test::expect![[r#"
+            warning: unknown time zone "America/New_Yrok"
+              ┌─ main:4:55
+              │
+            4 │             option location = timezone.location(name: "America/New_Yrok")
+              │                                                       ^^^^^^^^^^^^^^^^^^
+              │
+              = time zones are named after the IANA time zone database, e.g. `America/New_York`
+
+            error: expected int but found string
+              ┌─ main:6:21
+              │
+            6 │             z = 1 + ""
+              │                     ^^
+
+        "#]]
+    }
+}
+
+#[test]
+fn duration_arithmetic() {
+    test_error_msg! {
+        config: AnalyzerConfig{
+            features: vec![Feature::DurationWarnings],
+            ..AnalyzerConfig::default()
+        },
+        env: map![
+            "int" => "(v: A) => int",
+            "duration" => "(v: A) => duration",
+        ],
+        src: r#"
+            a = int(v: 1h) * int(v: 2h)
+            b = int(v: 1h) + 30
+            c = duration(v: 60)
+            d = int(v: 1h) * 2
+            z = 1 + ""
+        "#,
+        expect: expect_test::expect![[r#"
+            warning: product of two durations in nanoseconds
+              ┌─ main:2:17
+              │
+            2 │             a = int(v: 1h) * int(v: 2h)
+              │                 ^^^^^^^^^^^^^^^^^^^^^^^
+              │
+              = the result is in square nanoseconds; convert only one of the durations, e.g. `int(v: 1h) * 2`
+
+            warning: integer added to a duration in nanoseconds
+              ┌─ main:3:17
+              │
+            3 │             b = int(v: 1h) + 30
+              │                 ^^^^^^^^^^^^^^^
+              │
+              = `int(v: d)` counts nanoseconds; add a duration instead, e.g. `int(v: d + 30s)`
+
+            warning: duration of 60 nanoseconds
+              ┌─ main:4:17
+              │
+            4 │             c = duration(v: 60)
+              │                 ^^^^^^^^^^^^^^^
+              │
+              = write a duration literal if another unit was meant, e.g. `60s`
+
+            error: expected int but found string
+              ┌─ main:6:21
+              │
+            6 │             z = 1 + ""
+              │                     ^^
+
+        "#]]
+    }
+}
+
+#[test]
+fn never_null_checks() {
+    test_error_msg! {
+        config: AnalyzerConfig{
+            features: vec![Feature::NullWarnings],
+            ..AnalyzerConfig::default()
+        },
+        src: r#"
+            limit = 10
+            a = exists limit
+            b = exists limit * 2
+            f = (r) => exists r.x
+            z = 1 + ""
+        "#,
+        expect: expect_test::expect![[r#"
+            warning: `limit` is never null, so `exists` is always true
+              ┌─ main:3:17
+              │
+            3 │             a = exists limit
+              │                 ^^^^^^^^^^^^
+              │
+              = only values read from outside the query, such as the columns of a record, can be null
+
+            warning: `limit * 2` is never null, so `exists` is always true
+              ┌─ main:4:17
+              │
+            4 │             b = exists limit * 2
+              │                 ^^^^^^^^^^^^^^^^
+              │
+              = only values read from outside the query, such as the columns of a record, can be null
+
+            error: expected int but found string
+              ┌─ main:6:21
+              │
+            6 │             z = 1 + ""
+              │                     ^^
+
+        "#]]
+    }
+}
+
+#[test]
+fn integer_divisions() {
+    test_error_msg! {
+        config: AnalyzerConfig{
+            features: vec![Feature::NumericWarnings],
+            ..AnalyzerConfig::default()
+        },
+        src: r#"
+            f = (used, total) => used / total * 100
+            g = (used) => used / 2
+            h = (used) => used / 2.0
+            z = 1 + ""
+        "#,
+        expect: expect_test::expect![[r#"
+            warning: `used / 2` is an integer division, which truncates
+              ┌─ main:3:27
+              │
+            3 │             g = (used) => used / 2
+              │                           ^^^^^^^^
+              │
+              = a number literal without a fraction is an int; to keep the fraction, convert the operands to floats, e.g. `float(v: x) / 2.0`
+
+            error: expected int but found string
+              ┌─ main:5:21
+              │
+            5 │             z = 1 + ""
+              │                     ^^
+
+        "#]]
+    }
+}
+
+#[test]
+fn window_sizes() {
+    test_error_msg! {
+        config: AnalyzerConfig{
+            features: vec![Feature::WindowWarnings],
+            ..AnalyzerConfig::default()
+        },
+        env: map![
+            "range" => "(<-tables: A, start: duration) => A",
+            "aggregateWindow" => "(<-tables: A, every: duration, fn: B, ?createEmpty: bool) => A",
+            "f" => "int",
+            "false" => "bool",
+        ],
+        src: r#"
+            a = 1 |> range(start: -1h) |> aggregateWindow(every: 2h, fn: f)
+            b = 1 |> range(start: -30d) |> aggregateWindow(every: 1m, fn: f)
+            c = 1 |> range(start: -30d) |> aggregateWindow(every: 1m, fn: f, createEmpty: false)
+            z = 1 + ""
+        "#,
+        expect: expect_test::expect![[r#"
+            warning: window `2h` is longer than the range of `range(start: -1h)`
+              ┌─ main:2:66
+              │
+            2 │             a = 1 |> range(start: -1h) |> aggregateWindow(every: 2h, fn: f)
+              │                                                                  ^^
+              │
+              = each series is aggregated into a single, partial window
+
+            warning: aggregateWindow creates 43200 windows for each series
+              ┌─ main:3:44
+              │
+            3 │             b = 1 |> range(start: -30d) |> aggregateWindow(every: 1m, fn: f)
+              │                                            ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+              │
+              = empty windows are created by default; use a longer `every` or set `createEmpty: false`
+
+            error: expected int but found string
+              ┌─ main:5:21
+              │
+            5 │             z = 1 + ""
+              │                     ^^
+
+        "#]]
+    }
+}
+
+#[test]
+fn window_sizes_overflow() {
+    let file = parser::parse_string(
+        "main".to_string(),
+        "a = 1 |> range(start: -200y, stop: 200y) |> aggregateWindow(every: 1m, fn: f)",
+    );
+    assert_eq!(semantic::windows::window_sizes(&file.into()), vec![]);
+}
+
+#[test]
+fn typed_hole() {
+    test_error_msg! {
+        config: AnalyzerConfig{
+            features: vec![Feature::TypedHoles],
+            ..AnalyzerConfig::default()
+        },
+        env: map![
+            "filter" => "(fn: (r: {a: int}) => bool) => int",
+        ],
+        src: r#"
+            filter(fn: _?)
+        "#,
+        expect: expect_test::expect![[r#"
+            error: found hole of type (r: {a: int}) => bool
+              ┌─ main:2:24
+              │
+            2 │             filter(fn: _?)
+              │                        ^^
+
+        "#]]
+    }
+}
+
 #[test]
 fn vec_type() {
     test_infer! {
@@ -4291,3 +4846,344 @@ fn multiple_builtins() {
         ],
     }
 }
+
+#[test]
+fn analyzer_config_json() {
+    let config: AnalyzerConfig =
+        serde_json::from_str(r#"{"features": ["labelPolymorphism", "typedHoles"]}"#).unwrap();
+    assert_eq!(
+        config,
+        AnalyzerConfig::default()
+            .with_feature(Feature::LabelPolymorphism)
+            .with_feature(Feature::TypedHoles),
+    );
+    assert_eq!(
+        serde_json::to_string(&config).unwrap(),
+        r#"{"features":["labelPolymorphism","typedHoles"]}"#,
+    );
+    assert_eq!(
+        serde_json::from_str::<AnalyzerConfig>("{}").unwrap(),
+        AnalyzerConfig::default(),
+    );
+    assert!(serde_json::from_str::<AnalyzerConfig>(r#"{"limits": 1}"#).is_err());
+
+    let config: AnalyzerConfig =
+        serde_json::from_str(r#"{"schema": {"telegraf": {"cpu": {"tags": ["host"]}}}}"#).unwrap();
+    let schema = config.schema.as_ref().unwrap();
+    assert!(schema.bucket_schema("telegraf").is_some());
+    assert!(schema.bucket_schema("missing").is_none());
+    assert_eq!(
+        serde_json::to_string(&config).unwrap(),
+        r#"{"features":[]}"#
+    );
+
+    let config: AnalyzerConfig = serde_json::from_str(r#"{"snippets": {"__one": "1"}}"#).unwrap();
+    let mut snippets = crate::ast::snippets::Snippets::new();
+    snippets.register("__one", "1");
+    assert_eq!(config.snippets, snippets);
+    assert_eq!(
+        serde_json::to_string(&config).unwrap(),
+        r#"{"features":[],"snippets":{"__one":"1"}}"#
+    );
+}
+
+#[test]
+fn analyzer_bindings_and_limits() {
+    let config: AnalyzerConfig =
+        serde_json::from_str(r#"{"bindings": {"bucket": "string"}, "limits": {"maxErrors": 1}}"#)
+            .unwrap();
+    let mut analyzer = Analyzer::new(Environment::default(), Packages::default(), config);
+
+    assert!(analyzer
+        .analyze_source("main".into(), "".into(), "x = bucket + \"s\"")
+        .is_ok());
+
+    let err =
+        match analyzer.analyze_source("main".into(), "".into(), "x = bucket + 1\ny = 1 + \"a\"") {
+            Ok(_) => panic!("Unexpected success"),
+            Err(err) => err.error,
+        };
+    assert_eq!(err.diagnostics.errors.len(), 1);
+
+    assert!(
+        serde_json::from_str::<AnalyzerConfig>(r#"{"bindings": {"bucket": "strin g"}}"#).is_err()
+    );
+}
+
+#[test]
+fn snippet_diagnostics_point_at_calls() {
+    let mut analyzer = Analyzer::new(
+        Environment::default(),
+        Packages::default(),
+        Default::default(),
+    );
+    analyzer.register_snippet("__bad", r#""a" + 1"#);
+    analyzer.register_snippet("__two", "1\n    + 1");
+    let src = r#"x = __bad()
+y = __two()
+z = y + "a""#;
+    let err = match analyzer.analyze_source("main".into(), "".into(), src) {
+        Ok(_) => panic!("Unexpected success"),
+        Err(err) => err.error,
+    };
+
+    assert_eq!(err.source.as_deref(), Some(src));
+    let lines: Vec<_> = err
+        .diagnostics
+        .errors
+        .iter()
+        .map(|err| err.location.start.line)
+        .collect();
+    assert_eq!(lines, [1, 3]);
+    let call = &err.diagnostics.errors[0].location;
+    assert_eq!((call.start.column, call.end.column), (5, 12));
+}
+
+#[test]
+fn observe_usage() {
+    let env = Environment::from(parse_map(
+        Some("universe"),
+        map!["double" => "(v: int) => int"],
+    ));
+    let importer: Packages = maplit::hashmap! {
+        "strings".to_string() => PackageExports::try_from(parse_map(
+            Some("strings"),
+            map!["title" => "(v: string) => string"],
+        ))
+        .unwrap(),
+    }
+    .into_iter()
+    .collect();
+    let mut usages = Vec::new();
+    {
+        let mut analyzer = Analyzer::new(
+            Environment::new(env),
+            importer,
+            AnalyzerConfig::default().with_feature(Feature::UnusedSymbolWarnings),
+        );
+        analyzer.set_observer(|usage| usages.push(usage.clone()));
+        let _ = analyzer.analyze_source("main".into(), "".into(), "x = 1");
+        let _ = analyzer.analyze_source(
+            "main".into(),
+            "".into(),
+            r#"import s "strings"
+
+secret = (v) => v + 1
+x = secret(v: double(v: 1))
+y = s.title(v: "a")
+z = s.title(v: 1)
+w = () => {
+    unused = 1
+    return 1
+}"#,
+        );
+    }
+
+    assert_eq!(usages.len(), 2);
+    assert_eq!(usages[0], Usage::default());
+    let usage = &usages[1];
+    assert_eq!(usage.imports.iter().collect::<Vec<_>>(), ["strings"]);
+    assert_eq!(
+        usage.functions.iter().collect::<Vec<_>>(),
+        [
+            (&"double".to_string(), &1),
+            (&"strings.title".to_string(), &2)
+        ]
+    );
+    assert_eq!(
+        usage.errors.iter().collect::<Vec<_>>(),
+        [(&"inference".to_string(), &1)]
+    );
+    assert_eq!(
+        usage.warnings.iter().collect::<Vec<_>>(),
+        [(&"unused-symbol".to_string(), &1)]
+    );
+}
+
+#[test]
+fn type_map_of_package() {
+    let mut analyzer = Analyzer::new(
+        Environment::default(),
+        Packages::default(),
+        Default::default(),
+    );
+    let (_, pkg) = analyzer
+        .analyze_source("main".into(), "".into(), "f = (x) => x + 1\ny = f(x: 2)")
+        .unwrap();
+    let types = semantic::type_map(&pkg);
+
+    let mut entries: Vec<_> = types
+        .iter()
+        .map(|(location, typ)| format!("{}: {}", location.source.as_deref().unwrap(), typ))
+        .collect();
+    entries.sort();
+    assert_eq!(
+        entries,
+        [
+            "(x) => x + 1: (x: int) => int",
+            "1: int",
+            "2: int",
+            "f(x: 2): int",
+            "f: (x: int) => int",
+            "x + 1: int",
+            "x: int",
+        ]
+    );
+
+    let source_at = |line, column| {
+        types
+            .type_at(ast::Position { line, column })
+            .map(|(location, _)| location.source.as_deref().unwrap())
+    };
+    assert_eq!(source_at(1, 12), Some("x"));
+    assert_eq!(source_at(1, 14), Some("x + 1"));
+    assert_eq!(source_at(2, 10), Some("2"));
+    assert_eq!(source_at(3, 1), None);
+}
+
+#[test]
+fn column_encodings() {
+    let source = "s = \"été\" + \"!\"\nt = s + 1";
+    let config: AnalyzerConfig = serde_json::from_str(r#"{"columnEncoding": "utf-16"}"#).unwrap();
+    let mut analyzer = Analyzer::new(Environment::default(), Packages::default(), config);
+    let mut err = analyzer
+        .analyze_source("main".into(), "main.flux".into(), source)
+        .unwrap_err()
+        .error;
+    assert_eq!(err.encoding, ColumnEncoding::Utf16);
+    assert_eq!(
+        err.to_string(),
+        "error main.flux@2:9-2:10: expected string but found int"
+    );
+
+    let source = "s = \"été\" + 1";
+    err.source = Some(source.into());
+    err.diagnostics.errors[0].location = ast::SourceLocation {
+        start: ast::Position {
+            line: 1,
+            column: 15,
+        },
+        end: ast::Position {
+            line: 1,
+            column: 16,
+        },
+        ..ast::SourceLocation::default()
+    };
+    assert_eq!(
+        err.to_string(),
+        "error @1:13-1:14: expected string but found int"
+    );
+    assert!(err.pretty_short(source).starts_with("main:1:13: error"));
+    err.encoding = ColumnEncoding::Utf8;
+    assert!(err.pretty_short(source).starts_with("main:1:15: error"));
+
+    let mut analyzer = Analyzer::new(
+        Environment::default(),
+        Packages::default(),
+        Default::default(),
+    );
+    let source = "s = \"été\"\nt = s";
+    let (_, pkg) = analyzer
+        .analyze_source("main".into(), "".into(), source)
+        .unwrap();
+    let types = semantic::type_map(&pkg);
+    let lines = LineIndex::new(source);
+    let position = |line, column| ast::Position { line, column };
+    let (location, typ) = types
+        .type_at_in(&lines, source, position(1, 6), ColumnEncoding::Utf16)
+        .unwrap();
+    assert_eq!(typ.to_string(), "string");
+    assert_eq!(
+        (location.start, location.end),
+        (position(1, 5), position(1, 10))
+    );
+    assert_eq!(
+        types.type_at(position(1, 6)).unwrap().0.end,
+        position(1, 12)
+    );
+}
+
+#[test]
+fn type_map_diff() {
+    let type_map = |source: &str| {
+        let mut analyzer = Analyzer::new(
+            Environment::default(),
+            Packages::default(),
+            Default::default(),
+        );
+        let (_, pkg) = analyzer
+            .analyze_source("main".into(), "".into(), source)
+            .unwrap();
+        semantic::type_map(&pkg)
+    };
+    let before = type_map("x = 1.0\ny = x\nf = (a) => a");
+    let after = type_map("x = \"a\"\ny = x\nf = (a) => a\nz = 1");
+    assert!(before.diff(&before).is_empty());
+
+    let changes: Vec<_> = before
+        .diff(&after)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        changes,
+        [
+            "~ 1:5: 1.0\n    - float\n    + string",
+            "~ 2:5: x\n    - float\n    + string",
+            "+ 4:5: 1\n    int",
+        ]
+    );
+}
+
+#[test]
+fn warning_names() {
+    use semantic::{WarningKind, WARNINGS};
+
+    let s = String::new;
+    let kinds = [
+        WarningKind::UnusedSymbol(s()),
+        WarningKind::ShadowedSymbol(s()),
+        WarningKind::RepeatedPipeline {
+            pipeline: s(),
+            count: 2,
+            variable: s(),
+            binding: None,
+            rewrite: None,
+        },
+        WarningKind::QueryInjection {
+            call: s(),
+            parameter: s(),
+        },
+        WarningKind::UnknownTimezone(s()),
+        WarningKind::DurationProduct,
+        WarningKind::MixedDurationUnits,
+        WarningKind::NanosecondDuration(1),
+        WarningKind::WindowLongerThanRange {
+            every: s(),
+            range: s(),
+        },
+        WarningKind::TooManyWindows(1),
+        WarningKind::NeverNull(s()),
+        WarningKind::IntegerDivision(s()),
+        WarningKind::UnknownBucket(s()),
+        WarningKind::UnknownMeasurement {
+            bucket: s(),
+            measurement: s(),
+        },
+        WarningKind::UnknownField {
+            bucket: s(),
+            field: s(),
+        },
+        WarningKind::UnknownColumn {
+            bucket: s(),
+            column: s(),
+        },
+        WarningKind::TagComparedToNonString(s()),
+        WarningKind::UnusedFunction(s()),
+    ];
+    // Every warning the analyzer reports is in the table of features, in the same order.
+    assert_eq!(
+        kinds.iter().map(WarningKind::name).collect::<Vec<_>>(),
+        WARNINGS.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+    );
+}
@@ -0,0 +1,149 @@
+use super::*;
+
+use crate::{
+    ast,
+    parser::parse_string,
+    semantic::schema::{check_schema, predict_columns, JsonSchemaProvider},
+};
+
+#[test]
+fn check() {
+    let provider: JsonSchemaProvider = r#"{
+        "telegraf": {
+            "cpu": {"tags": ["host", "cpu"], "fields": {"usage_user": "float"}},
+            "mem": {"tags": ["host"], "fields": {"used": "int"}}
+        }
+    }"#
+    .parse()
+    .unwrap();
+    let pkg = ast::Package::from(parse_string(
+        "main.flux".to_string(),
+        r#"from(bucket: "telegraf")
+|> range(start: -1h)
+|> filter(fn: (r) => r._measurement == "cpuu" and r._field == "usage_user")
+|> filter(fn: (r) => r._field == "free" and r.region == "eu" and r.cpu == 0)
+from(bucket: "missing") |> range(start: -1h)
+from(bucket: "telegraf") |> map(fn: (r) => r) |> filter(fn: (r) => r.a == "b")
+"#,
+    ));
+    let warnings: Vec<_> = check_schema(&pkg, &provider)
+        .into_iter()
+        .map(|w| w.error.to_string())
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            // The outermost filter of a pipeline is checked first.
+            r#"field "free" does not exist in bucket "telegraf""#,
+            r#"column "region" does not exist in bucket "telegraf""#,
+            r#"tag "cpu" is compared to a value that is not a string"#,
+            r#"measurement "cpuu" does not exist in bucket "telegraf""#,
+            r#"bucket "missing" does not exist"#,
+        ]
+    );
+}
+
+#[test]
+fn predict() {
+    let provider: JsonSchemaProvider = r#"{
+        "telegraf": {
+            "cpu": {"tags": ["host", "cpu"], "fields": {"usage_user": "float", "usage_system": "float"}},
+            "mem": {"tags": ["host"], "fields": {"used": "int"}}
+        }
+    }"#
+    .parse()
+    .unwrap();
+    let columns = |source: &str| {
+        let file = parse_string("main.flux".to_string(), source);
+        let expr = match &file.body[0] {
+            ast::Statement::Expr(stmt) => stmt.expression.clone(),
+            _ => unreachable!(),
+        };
+        predict_columns(&expr, &provider).map(|columns| {
+            columns
+                .iter()
+                .map(|c| {
+                    format!(
+                        "{}: {}{}",
+                        c.name,
+                        c.typ,
+                        if c.group_key { " *" } else { "" }
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+
+    assert_eq!(
+        columns(r#"from(bucket: "telegraf") |> range(start: -1h) |> group(columns: ["host"])"#)
+            .unwrap(),
+        vec![
+            "_start: time",
+            "_stop: time",
+            "_time: time",
+            "_value: mixed",
+            "_field: string",
+            "_measurement: string",
+            "cpu: string",
+            "host: string *",
+        ],
+    );
+    assert_eq!(
+        columns(
+            r#"from(bucket: "telegraf")
+|> range(start: -1h)
+|> filter(fn: (r) => r._measurement == "cpu")
+|> pivot(rowKey: ["_time"], columnKey: ["_field"], valueColumn: "_value")
+|> drop(columns: ["_start", "_stop"])"#
+        )
+        .unwrap(),
+        vec![
+            "_time: time",
+            "_measurement: string *",
+            "cpu: string *",
+            "host: string *",
+            "usage_system: float",
+            "usage_user: float",
+        ],
+    );
+    assert_eq!(
+        columns(r#"from(bucket: "telegraf") |> map(fn: (r) => r)"#),
+        None
+    );
+    assert_eq!(columns(r#"from(bucket: "missing")"#), None);
+}
+
+#[test]
+fn analyzer_checks_schema() {
+    test_error_msg! {
+        config: AnalyzerConfig::default().with_schema(
+            r#"{"telegraf": {"cpu": {"tags": ["host"], "fields": {"usage_user": "float"}}}}"#
+                .parse::<JsonSchemaProvider>()
+                .unwrap(),
+        ),
+        env: map![
+            "from" => "(bucket: string) => [{A with _measurement: string, _field: string}]",
+            "filter" => "(<-tables: [A], fn: (r: A) => bool) => [A]",
+        ],
+        src: r#"
+            a = from(bucket: "telegraf") |> filter(fn: (r) => r._measurement == "mem")
+            // flux-lint: ignore[unknown-bucket]
+            b = from(bucket: "missing")
+            z = 1 + ""
+        "#,
+        expect: expect_test::expect![[r#"
+            warning: measurement "mem" does not exist in bucket "telegraf"
+              ┌─ main:2:81
+              │
+            2 │             a = from(bucket: "telegraf") |> filter(fn: (r) => r._measurement == "mem")
+              │                                                                                 ^^^^^
+
+            error: expected int but found string
+              ┌─ main:5:21
+              │
+            5 │             z = 1 + ""
+              │                     ^^
+
+        "#]]
+    }
+}
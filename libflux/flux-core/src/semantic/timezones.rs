@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use chrono_tz::Tz;
+
+use crate::{
+    ast::{
+        self,
+        walk::{walk, Node, Visitor},
+    },
+    errors::located,
+    semantic::{Warning, WarningKind},
+};
+
+// Collects the time zone names that are not in the time zone database.
+struct TimezoneCollector<'a> {
+    // Import path of each imported package by the name it is referred to in the current file.
+    imports: HashMap<&'a str, &'a str>,
+    warnings: Vec<Warning>,
+}
+
+impl<'a> TimezoneCollector<'a> {
+    // Checks a location record such as `{zone: "America/New_York", offset: 0h}`.
+    fn check_location(&mut self, expr: &ast::Expression) {
+        if let ast::Expression::Object(obj) = expr {
+            if let Some(zone) = property(&obj.properties, "zone") {
+                self.check_zone(zone);
+            }
+        }
+    }
+
+    fn check_zone(&mut self, expr: &ast::Expression) {
+        if let ast::Expression::StringLit(lit) = expr {
+            if !is_known_zone(&lit.value) {
+                self.warnings.push(located(
+                    lit.base.location.clone(),
+                    WarningKind::UnknownTimezone(lit.value.clone()),
+                ));
+            }
+        }
+    }
+
+    fn is_timezone_location(&self, callee: &ast::Expression) -> bool {
+        match callee {
+            ast::Expression::Member(member) => {
+                matches!(&member.object, ast::Expression::Identifier(id)
+                    if self.imports.get(id.name.as_str()) == Some(&"timezone"))
+                    && member.property.key() == "location"
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for TimezoneCollector<'a> {
+    fn visit(&mut self, node: Node<'a>) -> bool {
+        match node {
+            Node::File(file) => {
+                self.imports = file
+                    .imports
+                    .iter()
+                    .map(|import| {
+                        let path = import.path.value.as_str();
+                        let name = match &import.alias {
+                            Some(alias) => alias.name.as_str(),
+                            None => path.rsplit('/').next().unwrap_or(path),
+                        };
+                        (name, path)
+                    })
+                    .collect();
+            }
+            Node::OptionStmt(opt) => {
+                if let ast::Assignment::Variable(assgn) = &opt.assignment {
+                    if assgn.id.name == "location" {
+                        self.check_location(&assgn.init);
+                    }
+                }
+            }
+            Node::CallExpr(call) => {
+                let is_timezone_location = self.is_timezone_location(&call.callee);
+                for arg in &call.arguments {
+                    if let ast::Expression::Object(obj) = arg {
+                        if is_timezone_location {
+                            if let Some(name) = property(&obj.properties, "name") {
+                                self.check_zone(name);
+                            }
+                        }
+                        // Functions of the `date` package take the location as an argument.
+                        if let Some(location) = property(&obj.properties, "location") {
+                            self.check_location(location);
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+        true
+    }
+}
+
+fn property<'a>(properties: &'a [ast::Property], key: &str) -> Option<&'a ast::Expression> {
+    properties
+        .iter()
+        .find(|prop| prop.key.key() == key)
+        .and_then(|prop| prop.value.as_ref())
+}
+
+// Whether the time zone can be loaded at runtime. Like Go, the runtime also accepts
+// an empty name for UTC and "Local" for the time zone of the host.
+fn is_known_zone(name: &str) -> bool {
+    name.is_empty() || name == "Local" || name.parse::<Tz>().is_ok()
+}
+
+/// Finds time zone names, in calls to `timezone.location` and in location records, that are
+/// not in the time zone database and so would fail to load at runtime.
+pub fn unknown_timezones(pkg: &ast::Package) -> Vec<Warning> {
+    let mut collector = TimezoneCollector {
+        imports: HashMap::new(),
+        warnings: Vec::new(),
+    };
+    walk(&mut collector, Node::Package(pkg));
+    collector.warnings
+}
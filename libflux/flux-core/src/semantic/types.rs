@@ -9,20 +9,24 @@ use std::{
     str::FromStr,
 };
 
+use anyhow::{anyhow, bail};
 use codespan_reporting::diagnostic;
 use derive_more::Display;
 use serde::ser::{Serialize, Serializer};
 
 use crate::{
+    ast,
     errors::{Errors, Located},
     map::HashMap,
+    parser,
     semantic::{
-        formatter,
+        convert, formatter,
         fresh::Fresher,
         nodes::Symbol,
         sub::{
             apply2, apply3, apply4, merge3, merge_collect, Substitutable, Substituter, Substitution,
         },
+        AnalyzerConfig,
     },
 };
 
@@ -330,6 +334,31 @@ impl PolyType {
     pub fn normal(&self) -> PolyType {
         self.clone().fresh(&mut Fresher::default())
     }
+
+    /// Returns a string representation of the type that is the same for all equal types,
+    /// whatever the names of their type variables. [`PolyType::from_canonical_str`] parses it
+    /// back into an equal type.
+    ///
+    /// Useful for storing types in caches, baselines and golden files.
+    pub fn to_string_canonical(&self) -> String {
+        self.normal().to_string()
+    }
+
+    /// Parses a type written in Flux type syntax, such as the output of
+    /// [`PolyType::to_string_canonical`].
+    pub fn from_canonical_str(s: &str) -> anyhow::Result<PolyType> {
+        // Parse the type as part of a builtin statement so that trailing input is reported.
+        let file = parser::parse_string("".to_string(), &format!("builtin x : {}", s));
+        ast::check::check(ast::walk::Node::File(&file))
+            .map_err(|err| anyhow!("invalid type `{}`: {}", s, err))?;
+        match &file.body[..] {
+            [ast::Statement::Builtin(builtin)] => {
+                convert::convert_polytype(&builtin.ty, &AnalyzerConfig::default())
+                    .map_err(|err| anyhow!("invalid type `{}`: {}", s, err))
+            }
+            _ => bail!("invalid type `{}`", s),
+        }
+    }
 }
 
 /// Helper function that concatenates two vectors into a single vector while removing duplicates.
@@ -3248,4 +3277,27 @@ mod tests {
             "Expected that `Kind`s were specified in lexical order"
         );
     }
+
+    #[test]
+    fn canonical_string() {
+        let a = polytype("(x: T, y: B) => T where T: Comparable + Addable");
+        let b = polytype("(x: A, y: Z) => A where A: Addable + Comparable");
+        assert_eq!(a.to_string_canonical(), b.to_string_canonical());
+        assert_eq!(
+            a.to_string_canonical(),
+            "(x: A, y: B) => A where A: Addable + Comparable"
+        );
+        for typ in [
+            "(<-tables: stream[{A with _value: B}], ?fn: (r: B) => C) => stream[{A with _value: C}]",
+            "[string:{a: int, b: [float]}]",
+            "(a: A, b: B) => {A with b: B} where A: Record",
+        ] {
+            let canonical = polytype(typ).to_string_canonical();
+            let parsed = PolyType::from_canonical_str(&canonical).unwrap();
+            assert_eq!(parsed, polytype(typ));
+            assert_eq!(parsed.to_string_canonical(), canonical);
+        }
+        assert!(PolyType::from_canonical_str("int int").is_err());
+        assert!(PolyType::from_canonical_str("(x: int) =>").is_err());
+    }
 }
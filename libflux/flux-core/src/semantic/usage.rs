@@ -0,0 +1,127 @@
+//! Usage of the language in the packages an [`Analyzer`](super::Analyzer) analyzes.
+//!
+//! Hosts that embed the analyzer can build their own usage statistics from the [`Usage`] of
+//! each analysis, reported through [`Analyzer::set_observer`](super::Analyzer::set_observer),
+//! without parsing the sources again. A usage only names the packages, the functions of the
+//! prelude and of imported packages, and the kinds of diagnostics involved, never the literals,
+//! identifiers or messages of the source, so it can be collected from queries that contain private
+//! data.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    errors::Errors,
+    map::HashMap,
+    semantic::{
+        nodes::{self, Expression, Symbol},
+        walk::{walk, Node},
+        Error, ErrorKind, Warning,
+    },
+};
+
+/// The packages, functions and diagnostics involved in the analysis of a package.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct Usage {
+    /// The paths of the imported packages.
+    pub imports: BTreeSet<String>,
+    /// The number of calls to each function of the prelude or of an imported package, by the name
+    /// of the function, e.g. `range`, or by the path of its package and its name, e.g.
+    /// `strings.title`. Calls to functions defined by the analyzed code are not counted.
+    pub functions: BTreeMap<String, usize>,
+    /// The number of errors of each kind, e.g. `inference`. Warnings reported as errors are
+    /// counted by the name of their warning, e.g. `unused-symbol`.
+    pub errors: BTreeMap<String, usize>,
+    /// The number of warnings of each kind, by the name that suppresses them, e.g.
+    /// `unused-symbol`.
+    pub warnings: BTreeMap<String, usize>,
+}
+
+impl Usage {
+    // `env_imports` are the symbols of the environment of the analyzer bound to imported
+    // packages, with the path of each package.
+    pub(crate) fn new(
+        pkg: &nodes::Package,
+        env_imports: &HashMap<Symbol, String>,
+        errors: &Errors<Error>,
+        warnings: &Errors<Warning>,
+    ) -> Self {
+        let mut usage = Usage::default();
+        let mut imports = env_imports.clone();
+        for import in pkg.files.iter().flat_map(|file| &file.imports) {
+            usage.imports.insert(import.path.value.clone());
+            imports.insert(import.import_symbol.clone(), import.path.value.clone());
+        }
+        walk(
+            &mut |node: Node| {
+                if let Node::CallExpr(call) = node {
+                    if let Some(name) = callee_name(&call.callee, &pkg.package, &imports) {
+                        *usage.functions.entry(name).or_insert(0) += 1;
+                    }
+                }
+            },
+            Node::Package(pkg),
+        );
+        for error in errors.iter() {
+            *usage
+                .errors
+                .entry(error_kind(&error.error).to_string())
+                .or_insert(0) += 1;
+        }
+        for warning in warnings.iter() {
+            *usage
+                .warnings
+                .entry(warning.error.name().to_string())
+                .or_insert(0) += 1;
+        }
+        usage
+    }
+
+    /// Adds the imports, calls and diagnostics of `other` to this usage, e.g. to sum up the
+    /// usage of every package analyzed in a session.
+    pub fn add(&mut self, other: &Usage) {
+        self.imports.extend(other.imports.iter().cloned());
+        for (counts, other) in [
+            (&mut self.functions, &other.functions),
+            (&mut self.errors, &other.errors),
+            (&mut self.warnings, &other.warnings),
+        ] {
+            for (name, count) in other {
+                *counts.entry(name.clone()).or_insert(0) += count;
+            }
+        }
+    }
+}
+
+// Returns the name of a function of the prelude or of an imported package. The symbols defined at
+// the top level of a package are qualified by it, so a qualified symbol of another package comes
+// from the prelude, while the local symbols of the analyzed code are never named.
+fn callee_name(
+    callee: &Expression,
+    package: &str,
+    imports: &HashMap<Symbol, String>,
+) -> Option<String> {
+    match callee {
+        Expression::Identifier(id) => match id.name.package() {
+            Some(p) if p != package => Some(id.name.name().to_string()),
+            _ => None,
+        },
+        Expression::Member(member) => match &member.object {
+            Expression::Identifier(object) => imports
+                .get(&object.name)
+                .map(|path| format!("{}.{}", path, member.property.name())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn error_kind(error: &ErrorKind) -> &'static str {
+    match error {
+        ErrorKind::InvalidAST(_) => "invalid-ast",
+        ErrorKind::Convert(_) => "convert",
+        ErrorKind::InvalidSemantic(_) => "invalid-semantic",
+        ErrorKind::Inference(_) => "inference",
+        ErrorKind::CallGraph(_) => "call-graph",
+        ErrorKind::Strict(warning) => warning.name(),
+    }
+}
@@ -166,41 +166,27 @@ macro_rules! mk_node {
             /// Returns the type of a semantic graph node.
             pub fn type_of(&self) -> Option<MonoType> {
                 match self {
-                    Self::IdentifierExpr(n) => Some(Expression::Identifier((*n).clone()).type_of()),
-                    Self::ArrayExpr(n) => Some(Expression::Array(Box::new((*n).clone())).type_of()),
-                    Self::DictExpr(n) => Some(Expression::Dict(Box::new((*n).clone())).type_of()),
-                    Self::FunctionExpr(n) => {
-                        Some(Expression::Function(Box::new((*n).clone())).type_of())
-                    }
-                    Self::LogicalExpr(n) => {
-                        Some(Expression::Logical(Box::new((*n).clone())).type_of())
-                    }
-                    Self::ObjectExpr(n) => {
-                        Some(Expression::Object(Box::new((*n).clone())).type_of())
-                    }
-                    Self::MemberExpr(n) => {
-                        Some(Expression::Member(Box::new((*n).clone())).type_of())
-                    }
-                    Self::IndexExpr(n) => Some(Expression::Index(Box::new((*n).clone())).type_of()),
-                    Self::BinaryExpr(n) => {
-                        Some(Expression::Binary(Box::new((*n).clone())).type_of())
-                    }
-                    Self::UnaryExpr(n) => Some(Expression::Unary(Box::new((*n).clone())).type_of()),
-                    Self::CallExpr(n) => Some(Expression::Call(Box::new((*n).clone())).type_of()),
-                    Self::ConditionalExpr(n) => {
-                        Some(Expression::Conditional(Box::new((*n).clone())).type_of())
-                    }
-                    Self::StringExpr(n) => {
-                        Some(Expression::StringExpr(Box::new((*n).clone())).type_of())
-                    }
-                    Self::IntegerLit(n) => Some(Expression::Integer((*n).clone()).type_of()),
-                    Self::FloatLit(n) => Some(Expression::Float((*n).clone()).type_of()),
-                    Self::StringLit(n) => Some(Expression::StringLit((*n).clone()).type_of()),
-                    Self::DurationLit(n) => Some(Expression::Duration((*n).clone()).type_of()),
-                    Self::UintLit(n) => Some(Expression::Uint((*n).clone()).type_of()),
-                    Self::BooleanLit(n) => Some(Expression::Boolean((*n).clone()).type_of()),
-                    Self::DateTimeLit(n) => Some(Expression::DateTime((*n).clone()).type_of()),
-                    Self::RegexpLit(n) => Some(Expression::Regexp((*n).clone()).type_of()),
+                    Self::IdentifierExpr(n) => Some(n.typ.clone()),
+                    Self::ArrayExpr(n) => Some(n.typ.clone()),
+                    Self::DictExpr(n) => Some(n.typ.clone()),
+                    Self::FunctionExpr(n) => Some(n.typ.clone()),
+                    Self::LogicalExpr(n) => Some(n.typ.clone()),
+                    Self::ObjectExpr(n) => Some(n.typ.clone()),
+                    Self::MemberExpr(n) => Some(n.typ.clone()),
+                    Self::IndexExpr(n) => Some(n.typ.clone()),
+                    Self::BinaryExpr(n) => Some(n.typ.clone()),
+                    Self::UnaryExpr(n) => Some(n.typ.clone()),
+                    Self::CallExpr(n) => Some(n.typ.clone()),
+                    Self::ConditionalExpr(n) => Some(n.typ.clone()),
+                    Self::StringExpr(_) => Some(MonoType::STRING),
+                    Self::IntegerLit(_) => Some(MonoType::INT),
+                    Self::FloatLit(_) => Some(MonoType::FLOAT),
+                    Self::StringLit(n) => Some(n.typ.clone().unwrap_or(MonoType::STRING)),
+                    Self::DurationLit(_) => Some(MonoType::DURATION),
+                    Self::UintLit(_) => Some(MonoType::UINT),
+                    Self::BooleanLit(_) => Some(MonoType::BOOL),
+                    Self::DateTimeLit(_) => Some(MonoType::TIME),
+                    Self::RegexpLit(_) => Some(MonoType::REGEXP),
                     _ => None,
                 }
             }
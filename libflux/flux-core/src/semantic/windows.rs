@@ -0,0 +1,130 @@
+use crate::{
+    ast::{
+        self,
+        walk::{walk, Node},
+    },
+    errors::located,
+    semantic::{nodes::convert_duration, Warning, WarningKind},
+};
+
+// Aggregating into more windows than this per series is reported when empty windows are created.
+const MAX_WINDOWS: i64 = 10_000;
+
+// Months do not have a fixed length, so spans are approximated with 30 day months.
+const MONTH_NANOSECONDS: i64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+
+// Returns the number of nanoseconds of a duration literal, which may be negated.
+fn nanoseconds(expr: &ast::Expression) -> Option<i64> {
+    match expr {
+        ast::Expression::Duration(lit) => {
+            let d = convert_duration(&lit.values).ok()?;
+            let n = d
+                .months
+                .checked_mul(MONTH_NANOSECONDS)?
+                .checked_add(d.nanoseconds)?;
+            if d.negative {
+                n.checked_neg()
+            } else {
+                Some(n)
+            }
+        }
+        ast::Expression::Unary(unary) if unary.operator == ast::Operator::SubtractionOperator => {
+            nanoseconds(&unary.argument)?.checked_neg()
+        }
+        _ => None,
+    }
+}
+
+fn argument<'a>(call: &'a ast::CallExpr, key: &str) -> Option<&'a ast::Expression> {
+    call.arguments
+        .iter()
+        .flat_map(|arg| match arg {
+            ast::Expression::Object(obj) => obj.properties.as_slice(),
+            _ => &[][..],
+        })
+        .find(|prop| prop.key.key() == key)
+        .and_then(|prop| prop.value.as_ref())
+}
+
+fn is_call_to(call: &ast::CallExpr, name: &str) -> bool {
+    matches!(&call.callee, ast::Expression::Identifier(id) if id.name == name)
+}
+
+// Returns the length of the time range a call to `range` selects, when it is given by literals
+// and fits in an `i64` of nanoseconds.
+fn range_span(range: &ast::CallExpr) -> Option<i64> {
+    let start = argument(range, "start")?;
+    let stop = argument(range, "stop");
+    match (start, stop) {
+        (ast::Expression::DateTime(start), Some(ast::Expression::DateTime(stop))) => stop
+            .value
+            .signed_duration_since(start.value)
+            .num_nanoseconds(),
+        // Relative times are relative to now, which is also the default stop.
+        (start, None) => nanoseconds(start)?.checked_neg(),
+        (start, Some(stop)) => nanoseconds(stop)?.checked_sub(nanoseconds(start)?),
+    }
+}
+
+// Returns the closest call to `range` earlier in the pipeline an expression is piped from.
+fn find_range(mut expr: &ast::Expression) -> Option<&ast::CallExpr> {
+    loop {
+        match expr {
+            ast::Expression::PipeExpr(pipe) if is_call_to(&pipe.call, "range") => {
+                return Some(&pipe.call)
+            }
+            ast::Expression::PipeExpr(pipe) => expr = &pipe.argument,
+            ast::Expression::Paren(paren) => expr = &paren.expression,
+            _ => return None,
+        }
+    }
+}
+
+/// Finds calls to `aggregateWindow` whose windows are longer than the range of the query, or
+/// that create a very large number of windows, including empty ones, for each series.
+pub fn window_sizes(pkg: &ast::Package) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    walk(
+        &mut |node: Node| {
+            let pipe = match node {
+                Node::PipeExpr(pipe) if is_call_to(&pipe.call, "aggregateWindow") => pipe,
+                _ => return,
+            };
+            let every = match argument(&pipe.call, "every") {
+                Some(every) => every,
+                None => return,
+            };
+            let range = match find_range(&pipe.argument) {
+                Some(range) => range,
+                None => return,
+            };
+            let (every_ns, span) = match (nanoseconds(every), range_span(range)) {
+                (Some(every_ns), Some(span)) if every_ns > 0 && span > 0 => (every_ns, span),
+                _ => return,
+            };
+            let source =
+                |expr: &ast::Expression| expr.base().location.source.clone().unwrap_or_default();
+            if every_ns > span {
+                warnings.push(located(
+                    every.base().location.clone(),
+                    WarningKind::WindowLongerThanRange {
+                        every: source(every),
+                        range: range.base.location.source.clone().unwrap_or_default(),
+                    },
+                ));
+            } else if span / every_ns > MAX_WINDOWS
+                && !matches!(
+                    argument(&pipe.call, "createEmpty"),
+                    Some(ast::Expression::Identifier(id)) if id.name == "false"
+                )
+            {
+                warnings.push(located(
+                    pipe.call.base.location.clone(),
+                    WarningKind::TooManyWindows(span / every_ns),
+                ));
+            }
+        },
+        Node::Package(pkg),
+    );
+    warnings
+}
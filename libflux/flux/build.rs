@@ -79,6 +79,10 @@ fn main() -> Result<()> {
     let path = dir.join("stdlib.data");
     serialize(imports, fb::build_packages, &path)?;
 
+    let path = dir.join("stdlib.hash");
+    let hash = bootstrap::stdlib_hash(stdlib_path, &Default::default())?;
+    fs::write(&path, hash.to_le_bytes())?;
+
     #[cfg(feature = "cffi")]
     {
         let path = dir.join("packages.data");
@@ -0,0 +1,1052 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use fluxcore::{
+    ast::{
+        self,
+        callgraph::CallGraph,
+        dataflow::Dataflow,
+        joins::{self, JoinIdiom},
+        pattern::{rules, Pattern},
+        similar::{Fingerprint, Index},
+        suppressions::Suppressions,
+        tasks::{self, TaskOptions},
+        versions, walk,
+    },
+    cli::{config::ConfigArgs, man},
+    formatter::{self, Instability},
+    parser,
+    semantic::{
+        self, bootstrap,
+        hosting::Stdlib,
+        import::{CallGraphImporter, Packages},
+        schema::{self, JsonSchemaProvider},
+        AnalyzerConfig, TypeMap,
+    },
+};
+use structopt::{
+    clap::{self, Shell},
+    StructOpt,
+};
+use walkdir::WalkDir;
+
+#[derive(Debug)]
+enum AstFormat {
+    Json,
+    Sexpr,
+    Debug,
+}
+
+impl FromStr for AstFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(AstFormat::Json),
+            "sexpr" => Ok(AstFormat::Sexpr),
+            "debug" => Ok(AstFormat::Debug),
+            _ => Err(anyhow!("unknown AST format \"{}\"", s)),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "fluxc", about = "compile the Flux source code")]
+struct Args {
+    /// Print the help of fluxc and of all of its subcommands, as found in its man page.
+    #[structopt(long)]
+    help_long: bool,
+    #[structopt(subcommand)]
+    command: Option<FluxC>,
+}
+
+const EXIT_STATUS: &[(i32, &str)] = &[
+    (0, "The command succeeded."),
+    (1, "The command failed or found problems."),
+];
+
+#[derive(Debug, StructOpt)]
+enum FluxC {
+    /// Dump JSON encoding of documentation from Flux source code.
+    Stdlib {
+        /// Directory containing Flux source code.
+        #[structopt(short, long, parse(from_os_str))]
+        srcdir: PathBuf,
+        /// Output directory for compiled Flux files.
+        #[structopt(short, long, parse(from_os_str))]
+        outdir: PathBuf,
+        /// Directory caching compiled stdlibs by a hash of their source, reused when the
+        /// source did not change.
+        #[structopt(long, parse(from_os_str))]
+        cache_dir: Option<PathBuf>,
+        #[structopt(flatten)]
+        config: ConfigArgs,
+    },
+    /// Parse Flux source code and print its AST, reporting any syntax errors after it.
+    Parse {
+        /// Format of the AST: `json`, `sexpr` or `debug`.
+        #[structopt(short, long, default_value = "json")]
+        format: AstFormat,
+        /// Version of the JSON format to print, the current version if not present.
+        #[structopt(long)]
+        ast_version: Option<u32>,
+        /// Flux file to parse, stdin if not present or `-`.
+        #[structopt(parse(from_os_str))]
+        file: Option<PathBuf>,
+    },
+    /// Print a readable version of a Flux query: imports are sorted and pipeline prefixes
+    /// repeated by several pipelines are extracted into variables before formatting.
+    Beautify {
+        /// Flux file to beautify, stdin if not present or `-`.
+        #[structopt(parse(from_os_str))]
+        file: Option<PathBuf>,
+    },
+    /// Print a Flux query with its comments removed and its string and regular expression
+    /// literals replaced with placeholders, so that it can be shared.
+    Redact {
+        /// Flux file to redact, stdin if not present or `-`.
+        #[structopt(parse(from_os_str))]
+        file: Option<PathBuf>,
+    },
+    /// Print exploratory Flux code as a task: the last result becomes the output of the task,
+    /// other results are removed and the task option is set from the arguments.
+    Task {
+        /// Name of the task.
+        #[structopt(long)]
+        name: String,
+        /// How often the task runs, e.g. `1h`.
+        #[structopt(long, required_unless = "cron", conflicts_with = "cron")]
+        every: Option<String>,
+        /// Cron expression of when the task runs, e.g. `0 * * * *`.
+        #[structopt(long)]
+        cron: Option<String>,
+        /// How long the task waits after its scheduled time before it runs, e.g. `5m`.
+        #[structopt(long)]
+        offset: Option<String>,
+        /// Flux file to compile, stdin if not present or `-`.
+        #[structopt(parse(from_os_str))]
+        file: Option<PathBuf>,
+    },
+    /// Search Flux source code for expressions matching a structural pattern.
+    ///
+    /// `$name` in the pattern matches any expression, and `|> $name` at the end
+    /// of a pipeline matches any remaining stages.
+    Search {
+        /// Pattern to search for, e.g. `from(bucket: $b) |> range(start: $s) |> $rest`.
+        pattern: String,
+        /// Flux files or directories containing Flux files to search.
+        #[structopt(required = true, parse(from_os_str))]
+        paths: Vec<PathBuf>,
+    },
+    /// Check Flux source code against lint rules defined as structural patterns.
+    Lint {
+        /// JSON or TOML (`.toml`) file containing the rule definitions.
+        #[structopt(short, long, parse(from_os_str))]
+        rules: PathBuf,
+        /// Flux files or directories containing Flux files to check.
+        #[structopt(required = true, parse(from_os_str))]
+        paths: Vec<PathBuf>,
+    },
+    /// Format Flux files under two formatter configurations and group the files whose output
+    /// differs by the kind of difference.
+    ///
+    /// Configurations are comma separated `name=value` formatter options, such as
+    /// `trailing_newline=false`.
+    CompareFormat {
+        /// Formatter options of the baseline configuration.
+        #[structopt(long, default_value = "")]
+        base: formatter::Options,
+        /// Formatter options of the configuration compared with the baseline.
+        #[structopt(long)]
+        other: formatter::Options,
+        /// Flux files or directories containing Flux files to format.
+        #[structopt(required = true, parse(from_os_str))]
+        paths: Vec<PathBuf>,
+    },
+    /// Check that formatting Flux files is idempotent and does not change their AST.
+    CheckFormat {
+        /// Formatter options to check.
+        #[structopt(long, default_value = "")]
+        options: formatter::Options,
+        /// Flux files or directories containing Flux files to format.
+        #[structopt(required = true, parse(from_os_str))]
+        paths: Vec<PathBuf>,
+    },
+    /// Sort and group the imports of Flux files in place, leaving the rest of each file unchanged.
+    FixImports {
+        /// List the files whose imports are not sorted instead of rewriting them, and fail if
+        /// there are any.
+        #[structopt(long)]
+        check: bool,
+        /// Flux files or directories containing Flux files to fix.
+        #[structopt(required = true, parse(from_os_str))]
+        paths: Vec<PathBuf>,
+    },
+    /// Check the filters of Flux queries against the schema of the buckets they read.
+    CheckSchema {
+        /// JSON file describing the measurements, tags and fields of each bucket.
+        #[structopt(short, long, parse(from_os_str))]
+        schema: PathBuf,
+        /// Flux files or directories containing Flux files to check.
+        #[structopt(required = true, parse(from_os_str))]
+        paths: Vec<PathBuf>,
+    },
+    /// Print the columns predicted for the queries of a Flux file from the schema of their buckets.
+    Columns {
+        /// JSON file describing the measurements, tags and fields of each bucket.
+        #[structopt(short, long, parse(from_os_str))]
+        schema: PathBuf,
+        /// Flux file containing the queries.
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+    /// Report recursive and unused functions using the call graph of a package.
+    CallGraph {
+        /// Print the call graph in DOT format instead of reporting problems.
+        #[structopt(long)]
+        dot: bool,
+        /// Directory of the user packages the package imports, each in the directory named
+        /// by its import path.
+        #[structopt(long, parse(from_os_str))]
+        packages: Option<PathBuf>,
+        /// Flux file or directory containing the Flux files of the package.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+    /// Print a Flux file with calls to small user-defined functions inlined.
+    Inline {
+        /// The largest function body, counted in AST nodes, to inline.
+        #[structopt(long, default_value = "32")]
+        max_size: usize,
+        /// Flux file to rewrite.
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+    /// Show the dataflow between the pipeline stages of a Flux file.
+    Pipeline {
+        /// Print the dataflow graph in DOT format.
+        #[structopt(long)]
+        pipeline_dot: bool,
+        /// Flux file to inspect.
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+    /// Report the ways Flux source code joins tables, with rewrites to the `join` package.
+    Joins {
+        /// Only print the number of uses of each idiom.
+        #[structopt(long)]
+        summary: bool,
+        /// Flux files or directories containing Flux files to inspect.
+        #[structopt(required = true, parse(from_os_str))]
+        paths: Vec<PathBuf>,
+    },
+    /// List the Flux files most similar to a query, most similar first.
+    Similar {
+        /// Maximum number of files to list.
+        #[structopt(long, default_value = "10")]
+        limit: usize,
+        /// Flux file to find similar files to.
+        #[structopt(parse(from_os_str))]
+        query: PathBuf,
+        /// Flux files or directories containing Flux files to search.
+        #[structopt(required = true, parse(from_os_str))]
+        paths: Vec<PathBuf>,
+    },
+    /// Check that the stdlib loads and that a built-in suite of queries analyzes against it and
+    /// formats, printing whether each subsystem passes.
+    SelfTest {
+        /// Directory containing the Flux source code of the stdlib. Defaults to the stdlib
+        /// embedded in fluxc.
+        #[structopt(short, long, parse(from_os_str))]
+        srcdir: Option<PathBuf>,
+        #[structopt(flatten)]
+        config: ConfigArgs,
+    },
+    /// Print how the features of the configuration change the types of the expressions of a
+    /// Flux file, compared with analyzing it without features.
+    SemanticDiff {
+        /// Directory containing the Flux source code of the stdlib.
+        #[structopt(short, long, parse(from_os_str))]
+        srcdir: PathBuf,
+        #[structopt(flatten)]
+        config: ConfigArgs,
+        /// Flux file to analyze.
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+    /// Write the man page of this command.
+    GenMan {
+        /// Directory to write the man page to, stdout if not present.
+        #[structopt(short, long, parse(from_os_str))]
+        outdir: Option<PathBuf>,
+    },
+    /// Print a shell completion script to stdout.
+    Completions {
+        /// The shell to generate completions for.
+        #[structopt(possible_values = &Shell::variants(), case_insensitive = true)]
+        shell: Shell,
+    },
+}
+
+fn main() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    let args = Args::from_args();
+    if args.help_long {
+        print!("{}", man::long_help_text(&Args::clap(), EXIT_STATUS));
+        return Ok(());
+    }
+    let app = match args.command {
+        Some(command) => command,
+        None => clap::Error::with_description(
+            "a subcommand is required, see --help",
+            clap::ErrorKind::MissingSubcommand,
+        )
+        .exit(),
+    };
+    match app {
+        FluxC::Stdlib {
+            srcdir,
+            outdir,
+            cache_dir,
+            config,
+        } => {
+            let resolved = config.resolve()?;
+            if !config.print_if_requested(&resolved)? {
+                stdlib(&srcdir, &outdir, cache_dir.as_deref(), resolved)?
+            }
+        }
+        FluxC::Parse {
+            format,
+            ast_version,
+            file,
+        } => parse(format, ast_version, file.as_deref())?,
+        FluxC::Beautify { file } => beautify(file.as_deref())?,
+        FluxC::Redact { file } => redact(file.as_deref())?,
+        FluxC::Task {
+            name,
+            every,
+            cron,
+            offset,
+            file,
+        } => {
+            let options = TaskOptions {
+                name,
+                every,
+                cron,
+                offset,
+            };
+            task(&options, file.as_deref())?
+        }
+        FluxC::Search { pattern, paths } => search(&pattern, &paths)?,
+        FluxC::Lint { rules, paths } => lint(&rules, &paths)?,
+        FluxC::CompareFormat { base, other, paths } => compare_format(base, other, &paths)?,
+        FluxC::CheckFormat { options, paths } => check_format(options, &paths)?,
+        FluxC::FixImports { check, paths } => fix_imports(check, &paths)?,
+        FluxC::CheckSchema { schema, paths } => check_schema(&schema, &paths)?,
+        FluxC::Columns { schema, file } => columns(&schema, &file)?,
+        FluxC::CallGraph {
+            dot,
+            packages,
+            path,
+        } => call_graph(dot, packages.as_deref(), &path)?,
+        FluxC::Inline { max_size, file } => inline(max_size, &file)?,
+        FluxC::Pipeline { pipeline_dot, file } => pipeline(pipeline_dot, &file)?,
+        FluxC::Joins { summary, paths } => join_uses(summary, &paths)?,
+        FluxC::Similar {
+            limit,
+            query,
+            paths,
+        } => similar(limit, &query, &paths)?,
+        FluxC::SelfTest { srcdir, config } => {
+            let resolved = config.resolve()?;
+            if !config.print_if_requested(&resolved)? {
+                self_test(srcdir.as_deref(), resolved)?
+            }
+        }
+        FluxC::SemanticDiff {
+            srcdir,
+            config,
+            file,
+        } => {
+            let resolved = config.resolve()?;
+            if !config.print_if_requested(&resolved)? {
+                semantic_diff(&srcdir, resolved, &file)?
+            }
+        }
+        FluxC::GenMan { outdir } => gen_man(outdir.as_deref())?,
+        FluxC::Completions { shell } => {
+            Args::clap().gen_completions_to("fluxc", shell, &mut io::stdout())
+        }
+    };
+    Ok(())
+}
+
+fn gen_man(outdir: Option<&Path>) -> Result<()> {
+    let page = man::man_page(&Args::clap(), EXIT_STATUS);
+    match outdir {
+        Some(outdir) => {
+            fs::create_dir_all(outdir)?;
+            fs::write(outdir.join("fluxc.1"), page)?;
+        }
+        None => print!("{}", page),
+    }
+    Ok(())
+}
+
+fn stdlib(
+    srcdir: &Path,
+    outdir: &Path,
+    cache_dir: Option<&Path>,
+    config: AnalyzerConfig,
+) -> Result<()> {
+    let cache_dir = match cache_dir {
+        Some(cache_dir) => cache_dir,
+        None => {
+            bootstrap::compile_stdlib_with_config(srcdir, outdir, config)?;
+            return Ok(());
+        }
+    };
+    let compiled = bootstrap::cached_compile_stdlib(srcdir, cache_dir, config)?;
+    for entry in WalkDir::new(&compiled) {
+        let entry = entry?;
+        let target = outdir.join(entry.path().strip_prefix(&compiled)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+// Reads the file at `path`, or stdin if `path` is absent or `-`, returning its name and contents.
+fn read_source(path: Option<&Path>) -> Result<(String, String)> {
+    Ok(match path {
+        Some(path) if path != Path::new("-") => (
+            path.to_string_lossy().into_owned(),
+            fs::read_to_string(path)?,
+        ),
+        _ => {
+            let mut source = String::new();
+            io::stdin().read_to_string(&mut source)?;
+            ("stdin".to_string(), source)
+        }
+    })
+}
+
+fn beautify(path: Option<&Path>) -> Result<()> {
+    let (_, source) = read_source(path)?;
+    print!("{}", ast::beautify::beautify_source(&source)?);
+    Ok(())
+}
+
+fn redact(path: Option<&Path>) -> Result<()> {
+    let (_, source) = read_source(path)?;
+    print!("{}", ast::redact::redact_source(&source)?);
+    Ok(())
+}
+
+fn task(options: &TaskOptions, path: Option<&Path>) -> Result<()> {
+    let (_, source) = read_source(path)?;
+    print!("{}", tasks::compile_task(&source, options)?);
+    Ok(())
+}
+
+fn parse(format: AstFormat, version: Option<u32>, path: Option<&Path>) -> Result<()> {
+    let (name, source) = read_source(path)?;
+    // The parser recovers from syntax errors, so the AST is printed even when the source is
+    // invalid. The errors are recorded on the nodes where they were found.
+    let file = parser::parse_string(name, &source);
+    match format {
+        AstFormat::Json => {
+            let version = version.unwrap_or(versions::CURRENT_VERSION);
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&versions::to_json(&file, version)?)?
+            );
+        }
+        AstFormat::Sexpr => {
+            let mut out = String::new();
+            sexpr(&serde_json::to_value(&file)?, &mut out);
+            println!("{}", out);
+        }
+        AstFormat::Debug => walk::walk(&mut DebugTree { depth: 0 }, walk::Node::File(&file)),
+    }
+    if let Err(errors) = ast::check::check(walk::Node::File(&file)) {
+        bail!("{}", errors);
+    }
+    Ok(())
+}
+
+// Writes the JSON encoding of an AST as an S-expression, headed by the type of each node.
+// Locations are left out.
+fn sexpr(value: &serde_json::Value, out: &mut String) {
+    use serde_json::Value;
+    match value {
+        Value::Object(fields) => {
+            out.push('(');
+            out.push_str(fields.get("type").and_then(Value::as_str).unwrap_or(""));
+            for (key, value) in fields {
+                if key == "type" || key == "location" {
+                    continue;
+                }
+                out.push_str(" :");
+                out.push_str(key);
+                out.push(' ');
+                sexpr(value, out);
+            }
+            out.push(')');
+        }
+        Value::Array(items) => {
+            out.push('(');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                sexpr(item, out);
+            }
+            out.push(')');
+        }
+        Value::Null => out.push_str("nil"),
+        value => out.push_str(&value.to_string()),
+    }
+}
+
+// Prints each node of an AST on its own line, indented by its depth.
+struct DebugTree {
+    depth: usize,
+}
+
+impl<'a> walk::Visitor<'a> for DebugTree {
+    fn visit(&mut self, node: walk::Node<'a>) -> bool {
+        let location = &node.base().location;
+        print!(
+            "{:indent$}{} {}:{}-{}:{}",
+            "",
+            node,
+            location.start.line,
+            location.start.column,
+            location.end.line,
+            location.end.column,
+            indent = 2 * self.depth,
+        );
+        match location.source.as_deref() {
+            // Print the source of leaves, whose children are not printed.
+            Some(source)
+                if matches!(
+                    node,
+                    walk::Node::Identifier(_)
+                        | walk::Node::IntegerLit(_)
+                        | walk::Node::FloatLit(_)
+                        | walk::Node::StringLit(_)
+                        | walk::Node::DurationLit(_)
+                        | walk::Node::UintLit(_)
+                        | walk::Node::BooleanLit(_)
+                        | walk::Node::DateTimeLit(_)
+                        | walk::Node::RegexpLit(_)
+                        | walk::Node::TextPart(_)
+                ) =>
+            {
+                println!(" {}", source)
+            }
+            _ => println!(),
+        }
+        self.depth += 1;
+        true
+    }
+
+    fn done(&mut self, _: walk::Node<'a>) {
+        self.depth -= 1;
+    }
+}
+
+fn search(pattern: &str, paths: &[PathBuf]) -> Result<()> {
+    let pattern = Pattern::new(pattern)?;
+    for file in parse_files(paths)? {
+        for m in pattern.find(walk::Node::File(&file)) {
+            let location = &m.node.base().location;
+            println!(
+                "{}:{}:{}: {}",
+                file.name,
+                location.start.line,
+                location.start.column,
+                location.source.as_deref().unwrap_or_default(),
+            );
+            for (name, binding) in &m.bindings {
+                println!("    ${} = {}", name, binding.source());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn lint(rules_file: &Path, paths: &[PathBuf]) -> Result<()> {
+    let rules = rules::load_rules(rules_file)?;
+    let mut count = 0;
+    for file in parse_files(paths)? {
+        for violation in rules::check_rules(&rules, walk::Node::File(&file)) {
+            count += 1;
+            println!(
+                "{}:{}:{}: {}: {}",
+                file.name,
+                violation.location.start.line,
+                violation.location.start.column,
+                violation.rule,
+                violation.message,
+            );
+            if let Some(replacement) = violation.replacement {
+                println!("    suggestion: {}", replacement);
+            }
+        }
+    }
+    if count > 0 {
+        bail!("found {} rule violation(s)", count);
+    }
+    Ok(())
+}
+
+fn compare_format(
+    base: formatter::Options,
+    other: formatter::Options,
+    paths: &[PathBuf],
+) -> Result<()> {
+    let mut buckets: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut total = 0;
+    for path in flux_paths(paths)? {
+        total += 1;
+        let source = fs::read_to_string(&path)?;
+        let bucket = match (
+            formatter::format_with(&source, base.clone()),
+            formatter::format_with(&source, other.clone()),
+        ) {
+            (Ok(base), Ok(other)) => {
+                let without_whitespace = |s: &str| s.split_whitespace().collect::<String>();
+                if base == other {
+                    continue;
+                } else if base.trim_end() == other.trim_end() {
+                    "trailing whitespace"
+                } else if without_whitespace(&base) == without_whitespace(&other) {
+                    "indentation and line breaks"
+                } else {
+                    "tokens"
+                }
+            }
+            _ => "formatting errors",
+        };
+        buckets
+            .entry(bucket)
+            .or_default()
+            .push(path.to_string_lossy().into_owned());
+    }
+    let changed: usize = buckets.values().map(Vec::len).sum();
+    println!("{} of {} file(s) format differently", changed, total);
+    for (bucket, files) in buckets {
+        println!("{} ({}):", bucket, files.len());
+        for file in files {
+            println!("    {}", file);
+        }
+    }
+    Ok(())
+}
+
+fn check_format(options: formatter::Options, paths: &[PathBuf]) -> Result<()> {
+    let (mut total, mut unstable, mut skipped) = (0, 0, 0);
+    for path in flux_paths(paths)? {
+        total += 1;
+        let source = fs::read_to_string(&path)?;
+        match formatter::check_stability(&source, options.clone()) {
+            Ok(None) => (),
+            Ok(Some(Instability::NotIdempotent { once, twice })) => {
+                unstable += 1;
+                println!("{}: formatting is not idempotent", path.display());
+                let mut lines = once.lines().zip(twice.lines()).enumerate();
+                if let Some((i, (once, twice))) = lines.find(|(_, (a, b))| a != b) {
+                    println!("    line {}: {:?} becomes {:?}", i + 1, once, twice);
+                }
+            }
+            Ok(Some(Instability::AstChanged { .. })) => {
+                unstable += 1;
+                println!("{}: formatting changes the AST", path.display());
+            }
+            Err(err) => {
+                skipped += 1;
+                println!("{}: skipped: {}", path.display(), err);
+            }
+        }
+    }
+    println!(
+        "{} of {} file(s) format unstably, {} skipped",
+        unstable, total, skipped
+    );
+    if unstable > 0 {
+        bail!("formatting is unstable for {} file(s)", unstable);
+    }
+    Ok(())
+}
+
+fn fix_imports(check: bool, paths: &[PathBuf]) -> Result<()> {
+    let mut unsorted = 0;
+    for path in flux_paths(paths)? {
+        let source = fs::read_to_string(&path)?;
+        let fixed = formatter::fix_imports(&source)
+            .with_context(|| format!("failed to fix the imports of {}", path.display()))?;
+        if fixed == source {
+            continue;
+        }
+        unsorted += 1;
+        if check {
+            println!("{}", path.display());
+        } else {
+            fs::write(&path, fixed)?;
+        }
+    }
+    if check && unsorted > 0 {
+        bail!("the imports of {} file(s) are not sorted", unsorted);
+    }
+    Ok(())
+}
+
+fn check_schema(schema_file: &Path, paths: &[PathBuf]) -> Result<()> {
+    let provider = JsonSchemaProvider::from_file(schema_file)?;
+    let mut count = 0;
+    for file in parse_files(paths)? {
+        let name = file.name.clone();
+        let pkg = ast::Package::from(file);
+        let mut suppressions = Suppressions::new(walk::Node::Package(&pkg));
+        for warning in schema::check_schema(&pkg, &provider) {
+            if suppressions.suppresses(warning.error.name(), &warning.location) {
+                continue;
+            }
+            count += 1;
+            println!(
+                "{}:{}:{}: {}",
+                name, warning.location.start.line, warning.location.start.column, warning.error,
+            );
+        }
+    }
+    if count > 0 {
+        bail!("found {} schema problem(s)", count);
+    }
+    Ok(())
+}
+
+fn columns(schema_file: &Path, path: &Path) -> Result<()> {
+    let provider = JsonSchemaProvider::from_file(schema_file)?;
+    let source = fs::read_to_string(path)?;
+    let file = parser::parse_string(path.to_string_lossy().into_owned(), &source);
+    for stmt in &file.body {
+        let expr = match stmt {
+            ast::Statement::Expr(stmt) => &stmt.expression,
+            ast::Statement::Variable(assign) => &assign.init,
+            _ => continue,
+        };
+        if !matches!(
+            expr,
+            ast::Expression::PipeExpr(_) | ast::Expression::Call(_)
+        ) {
+            continue;
+        }
+        let start = &stmt.base().location.start;
+        match schema::predict_columns(expr, &provider) {
+            Some(columns) => {
+                println!("{}:{}:", start.line, start.column);
+                for column in columns {
+                    let group_key = if column.group_key { " (group key)" } else { "" };
+                    println!("    {}: {}{}", column.name, column.typ, group_key);
+                }
+            }
+            None => println!(
+                "{}:{}: columns cannot be predicted",
+                start.line, start.column
+            ),
+        }
+    }
+    Ok(())
+}
+
+fn call_graph(dot: bool, packages: Option<&Path>, path: &Path) -> Result<()> {
+    let files = parse_files(&[path.to_path_buf()])?;
+    let mut graph = CallGraph::from_files(&files);
+    if let Some(dir) = packages {
+        let mut importer = CallGraphImporter::<Packages>::default();
+        let mut package_files: BTreeMap<String, Vec<ast::File>> = BTreeMap::new();
+        for file in parse_files(&[dir.to_path_buf()])? {
+            let import_path = Path::new(&file.name)
+                .parent()
+                .and_then(|parent| parent.strip_prefix(dir).ok())
+                .map(|parent| parent.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default();
+            package_files.entry(import_path).or_default().push(file);
+        }
+        for (import_path, files) in package_files {
+            importer
+                .call_graphs
+                .insert(import_path, CallGraph::from_files(&files));
+        }
+        graph.link(&mut importer);
+    }
+    if dot {
+        print!("{}", graph.to_dot());
+        return Ok(());
+    }
+    for function in graph.unused() {
+        println!(
+            "warning {}: function \"{}\" is never used",
+            function.location, function.name
+        );
+    }
+    if let Err(errors) = graph.check() {
+        bail!("{}", errors);
+    }
+    Ok(())
+}
+
+fn inline(max_size: usize, path: &Path) -> Result<()> {
+    let source = fs::read_to_string(path)?;
+    let mut file = parser::parse_string(path.to_string_lossy().into_owned(), &source);
+    ast::inline::inline_functions(&mut file, &ast::inline::Options { max_size });
+    println!("{}", formatter::convert_to_string(&file)?);
+    Ok(())
+}
+
+fn pipeline(dot: bool, path: &Path) -> Result<()> {
+    let source = fs::read_to_string(path)?;
+    let file = parser::parse_string(path.to_string_lossy().into_owned(), &source);
+    let graph = Dataflow::from_file(&file);
+    if dot {
+        print!("{}", graph.to_dot());
+    } else {
+        for (from, to) in graph.edges() {
+            println!(
+                "{} -> {}",
+                graph.stages()[*from].label,
+                graph.stages()[*to].label
+            );
+        }
+    }
+    Ok(())
+}
+
+fn join_uses(summary: bool, paths: &[PathBuf]) -> Result<()> {
+    let mut uses = Vec::new();
+    for file in parse_files(paths)? {
+        for u in joins::join_uses(&file) {
+            if !summary {
+                println!(
+                    "{}:{}:{}: {:?}",
+                    file.name, u.location.start.line, u.location.start.column, u.idiom
+                );
+                if let Some(suggestion) = &u.suggestion {
+                    println!("    suggestion: {}", suggestion);
+                }
+            }
+            uses.push(u);
+        }
+    }
+    let counts = joins::count_idioms(&uses);
+    for idiom in [
+        JoinIdiom::Universe,
+        JoinIdiom::Experimental,
+        JoinIdiom::JoinPackage,
+        JoinIdiom::UnionPivot,
+    ] {
+        println!("{:?}: {}", idiom, counts.get(&idiom).unwrap_or(&0));
+    }
+    Ok(())
+}
+
+fn similar(limit: usize, query: &Path, paths: &[PathBuf]) -> Result<()> {
+    let source = fs::read_to_string(query)?;
+    let query = Fingerprint::of(&parser::parse_string(
+        query.to_string_lossy().into_owned(),
+        &source,
+    ));
+    let mut index = Index::new();
+    for file in parse_files(paths)? {
+        index.insert(file.name.clone(), &file);
+    }
+    for similar in index.similar(&query, limit) {
+        println!("{:.3} {}", similar.score, similar.name);
+    }
+    Ok(())
+}
+
+fn semantic_diff(srcdir: &Path, config: AnalyzerConfig, path: &Path) -> Result<()> {
+    let source = fs::read_to_string(path)?;
+    let name = path.to_string_lossy().into_owned();
+    // Prints the errors of an analysis and returns the types of what could be analyzed.
+    let analyze = |label: &str, config: AnalyzerConfig| -> Result<TypeMap> {
+        let stdlib = Stdlib::load(srcdir, &config)?;
+        let result =
+            stdlib
+                .analyzer(config)
+                .analyze_source("main".to_string(), name.clone(), &source);
+        let pkg = match result {
+            Ok((_, pkg)) => pkg,
+            Err(salvage) => {
+                println!("{} errors:\n{}", label, salvage.error);
+                match salvage.value {
+                    Some((_, pkg)) => pkg,
+                    None => bail!("{} analysis failed", label),
+                }
+            }
+        };
+        Ok(semantic::type_map(&pkg))
+    };
+    let baseline = analyze("baseline", AnalyzerConfig::default())?;
+    let features = analyze("features", config)?;
+    let changes = baseline.diff(&features);
+    println!("{} expression type(s) differ", changes.len());
+    for change in changes {
+        println!("{}", change);
+    }
+    Ok(())
+}
+
+// Representative queries analyzed and formatted by `fluxc self-test`, by name.
+const SELF_TEST_QUERIES: &[(&str, &str)] = &[
+    (
+        "pipeline",
+        r#"from(bucket: "telegraf")
+    |> range(start: -1h)
+    |> filter(fn: (r) => r._measurement == "cpu" and r._field == "usage_idle")
+    |> aggregateWindow(every: 1m, fn: mean)
+    |> yield(name: "mean")
+"#,
+    ),
+    (
+        "import",
+        r#"import "strings"
+
+strings.toUpper(v: "flux")
+"#,
+    ),
+    (
+        "map",
+        r#"import "array"
+
+array.from(rows: [{a: 1}, {a: 2}])
+    |> map(fn: (r) => ({r with b: r.a * 2}))
+"#,
+    ),
+    (
+        "join",
+        r#"import "array"
+import "join"
+
+left = array.from(rows: [{id: 1, a: 1}])
+right = array.from(rows: [{id: 1, b: 2}])
+
+join.inner(left: left, right: right, on: (l, r) => l.id == r.id, as: (l, r) => ({l with b: r.b}))
+"#,
+    ),
+    (
+        "function",
+        r#"f = (x, y=1) => x * y
+
+f(x: 2) + f(x: 3, y: 4)
+"#,
+    ),
+];
+
+fn self_test(srcdir: Option<&Path>, config: AnalyzerConfig) -> Result<()> {
+    let mut checks: Vec<(&str, Result<String>)> = Vec::new();
+    let stdlib = match srcdir {
+        Some(srcdir) => Stdlib::load(srcdir, &config),
+        None => flux::stdlib().context("loading the embedded stdlib"),
+    };
+    match stdlib {
+        Ok(stdlib) => {
+            let source = match srcdir {
+                Some(srcdir) => srcdir.display().to_string(),
+                None => "embedded".to_string(),
+            };
+            checks.push((
+                "stdlib",
+                Ok(format!("loaded {}, hash {:016x}", source, stdlib.hash())),
+            ));
+            checks.push(("analyzer", self_test_analyzer(&stdlib, &config)));
+        }
+        Err(err) => {
+            checks.push(("stdlib", Err(err)));
+            checks.push(("analyzer", Err(anyhow!("skipped, the stdlib did not load"))));
+        }
+    }
+    checks.push(("formatter", self_test_formatter()));
+
+    let total = checks.len();
+    let mut failures = 0;
+    for (subsystem, result) in checks {
+        match result {
+            Ok(summary) => println!("pass {}: {}", subsystem, summary),
+            Err(err) => {
+                failures += 1;
+                println!("fail {}: {:#}", subsystem, err);
+            }
+        }
+    }
+    if failures > 0 {
+        bail!("{} of {} subsystems failed", failures, total);
+    }
+    Ok(())
+}
+
+fn self_test_analyzer(stdlib: &Stdlib, config: &AnalyzerConfig) -> Result<String> {
+    for (name, source) in SELF_TEST_QUERIES {
+        stdlib
+            .analyzer(config.clone())
+            .analyze_source("main".to_string(), format!("{}.flux", name), source)
+            .map_err(|err| anyhow!("query \"{}\": {}", name, err.error))?;
+    }
+    Ok(format!("{} queries analyzed", SELF_TEST_QUERIES.len()))
+}
+
+// Checks that formatting each query is stable and produces valid Flux.
+fn self_test_formatter() -> Result<String> {
+    for (name, source) in SELF_TEST_QUERIES {
+        let formatted = formatter::format(source).with_context(|| format!("query \"{}\"", name))?;
+        if formatter::format(&formatted)? != formatted {
+            bail!(
+                "query \"{}\": formatting the formatted query changes it",
+                name
+            );
+        }
+        let file = parser::parse_string(format!("{}.flux", name), &formatted);
+        ast::check::check(walk::Node::File(&file))
+            .with_context(|| format!("query \"{}\": the formatted query is invalid", name))?;
+    }
+    Ok(format!("{} queries formatted", SELF_TEST_QUERIES.len()))
+}
+
+// Returns the path of every `.flux` file in `paths`, descending into directories.
+fn flux_paths(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut flux_paths = Vec::new();
+    for path in paths {
+        for entry in WalkDir::new(path).sort_by_file_name() {
+            let entry = entry?;
+            if entry.file_type().is_file() && entry.path().extension() == Some("flux".as_ref()) {
+                flux_paths.push(entry.into_path());
+            }
+        }
+    }
+    Ok(flux_paths)
+}
+
+fn parse_files(paths: &[PathBuf]) -> Result<Vec<ast::File>> {
+    flux_paths(paths)?
+        .into_iter()
+        .map(|path| {
+            let source = fs::read_to_string(&path)?;
+            Ok(parser::parse_string(
+                path.to_string_lossy().into_owned(),
+                &source,
+            ))
+        })
+        .collect()
+}
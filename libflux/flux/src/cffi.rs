@@ -1,15 +1,14 @@
-use std::{
-    any::Any,
-    ffi::*,
-    mem,
-    os::raw::c_char,
-    panic::{catch_unwind, resume_unwind},
-};
+use std::{ffi::*, mem, os::raw::c_char, path::PathBuf};
 
 use anyhow::anyhow;
 use fluxcore::semantic::flatbuffers::types::{build_env, build_type};
 use fluxcore::semantic::import::Importer;
-use fluxcore::{ast, formatter, merge_packages, semantic};
+use fluxcore::{
+    ast::{self, lines::ColumnEncoding, snippets::Snippets},
+    formatter, merge_packages,
+    safe::{self, CaughtPanic},
+    semantic,
+};
 use fluxcore::{
     parser::Parser,
     semantic::{
@@ -19,6 +18,7 @@ use fluxcore::{
         nodes::{Package, Symbol},
         sub::Substitution,
         types::{MonoType, PolyType},
+        usage::Usage,
         Analyzer, AnalyzerConfig, Feature, PackageExports,
     },
 };
@@ -46,20 +46,9 @@ impl From<Error> for Box<ErrorHandle> {
     }
 }
 
-impl From<Box<dyn Any + Send>> for Box<ErrorHandle> {
-    fn from(err: Box<dyn Any + Send>) -> Self {
-        // `panic!` will make `err` a `&str` or `String` so we try to extract those
-        // If there is something else we resume the unwinding and let the caller deal with it
-        let msg = err
-            .downcast::<&str>()
-            .map(|s| s.to_string())
-            .or_else(|err| err.downcast::<String>().map(|s| *s))
-            .unwrap_or_else(|err| resume_unwind(err));
-        let err = Error::Other(anyhow!("{}", msg));
-        Box::new(ErrorHandle {
-            message: CString::new(msg).unwrap(),
-            err,
-        })
+impl From<CaughtPanic> for Box<ErrorHandle> {
+    fn from(err: CaughtPanic) -> Self {
+        Error::from(anyhow::Error::from(err)).into()
     }
 }
 
@@ -135,23 +124,26 @@ pub extern "C" fn flux_ast_format(
     ast_pkg: &ast::Package,
     out: &mut flux_buffer_t,
 ) -> Option<Box<ErrorHandle>> {
-    let mut out_str = String::new();
-    for file in &ast_pkg.files {
-        let s = match formatter::convert_to_string(file) {
-            Ok(v) => v,
-            Err(e) => return Some(Error::from(e).into()),
-        };
-        out_str.push_str(&s);
-    }
+    safe::catch(|| {
+        let mut out_str = String::new();
+        for file in &ast_pkg.files {
+            let s = match formatter::convert_to_string(file) {
+                Ok(v) => v,
+                Err(e) => return Some(Error::from(e).into()),
+            };
+            out_str.push_str(&s);
+        }
 
-    let len = out_str.len();
-    let cstr = match CString::new(out_str) {
-        Ok(bytes) => bytes,
-        Err(e) => return Some(Error::from(anyhow::Error::from(e)).into()),
-    };
-    out.data = cstr.into_raw() as *mut u8;
-    out.len = len;
-    None
+        let len = out_str.len();
+        let cstr = match CString::new(out_str) {
+            Ok(bytes) => bytes,
+            Err(e) => return Some(Error::from(anyhow::Error::from(e)).into()),
+        };
+        out.data = cstr.into_raw() as *mut u8;
+        out.len = len;
+        None
+    })
+    .unwrap_or_else(|err| Some(err.into()))
 }
 
 /// flux_ast_get_error returns the first error in the given AST.
@@ -163,7 +155,7 @@ pub extern "C" fn flux_ast_format(
 pub unsafe extern "C" fn flux_ast_get_error(
     ast_pkg: *const ast::Package,
 ) -> Option<Box<ErrorHandle>> {
-    catch_unwind(|| {
+    safe::catch(|| {
         let ast_pkg = ast::walk::Node::Package(&*ast_pkg);
         match ast::check::check(ast_pkg) {
             Err(e) => Some(Error::from(anyhow::Error::from(e)).into()),
@@ -185,6 +177,8 @@ pub unsafe extern "C" fn flux_ast_get_error(
 #[no_mangle]
 pub extern "C" fn flux_free_ast_pkg(_: Option<Box<ast::Package>>) {}
 
+/// Deserializes an AST package from any supported version of the JSON format.
+///
 /// # Safety
 ///
 /// This function is unsafe because it dereferences a raw pointer passed
@@ -195,20 +189,24 @@ pub unsafe extern "C" fn flux_parse_json(
     cstr: *mut c_char,
     out_pkg: *mut Option<Box<ast::Package>>,
 ) -> Option<Box<ErrorHandle>> {
-    catch_unwind(|| {
+    safe::catch(|| {
         let buf = CStr::from_ptr(cstr).to_bytes(); // Unsafe
-        let res: Result<ast::Package, serde_json::error::Error> = serde_json::from_slice(buf);
+        let res = serde_json::from_slice(buf)
+            .map_err(anyhow::Error::from)
+            .and_then(ast::versions::from_json::<ast::Package>);
         match res {
             Ok(pkg) => {
                 *out_pkg = Some(Box::new(pkg));
                 None
             }
-            Err(err) => Some(Error::from(anyhow::Error::from(err)).into()),
+            Err(err) => Some(Error::from(err).into()),
         }
     })
     .unwrap_or_else(|err| Some(err.into()))
 }
 
+/// Serializes an AST package to the current version of the JSON format.
+///
 /// # Safety
 ///
 /// This function is unsafe because it dereferences raw pointers passed
@@ -219,12 +217,14 @@ pub unsafe extern "C" fn flux_ast_marshal_json(
     ast_pkg: *const ast::Package,
     buf: *mut flux_buffer_t,
 ) -> Option<Box<ErrorHandle>> {
-    catch_unwind(|| {
+    safe::catch(|| {
         let ast_pkg = &*ast_pkg;
-        let data = match serde_json::to_vec(ast_pkg) {
+        let data = match ast::versions::to_json(ast_pkg, ast::versions::CURRENT_VERSION)
+            .and_then(|value| Ok(serde_json::to_vec(&value)?))
+        {
             Ok(v) => v,
             Err(err) => {
-                return Some(Error::from(anyhow::Error::from(err)).into());
+                return Some(Error::from(err).into());
             }
         };
 
@@ -252,7 +252,7 @@ pub unsafe extern "C" fn flux_semantic_marshal_fb(
     sem_pkg: *const semantic::nodes::Package,
     buf: *mut flux_buffer_t,
 ) -> Option<Box<ErrorHandle>> {
-    catch_unwind(|| {
+    safe::catch(|| {
         let sem_pkg = &*sem_pkg;
         let (mut vec, offset) = match semantic::flatbuffers::serialize_pkg(sem_pkg) {
             Ok(vec_offset) => vec_offset,
@@ -307,7 +307,7 @@ pub unsafe extern "C" fn flux_merge_ast_pkgs(
     out_pkg: *mut ast::Package,
     in_pkg: *mut ast::Package,
 ) -> Option<Box<ErrorHandle>> {
-    catch_unwind(|| {
+    safe::catch(|| {
         // Do not change ownership here so that Go maintains ownership of packages
         let out_pkg = &mut *out_pkg;
         let in_pkg = &mut *in_pkg;
@@ -335,7 +335,7 @@ pub unsafe extern "C" fn flux_analyze(
     options: *const c_char,
     out_sem_pkg: *mut Option<Box<semantic::nodes::Package>>,
 ) -> Option<Box<ErrorHandle>> {
-    catch_unwind(|| {
+    safe::catch(|| {
         let options = match Options::from_c_str(options) {
             Ok(x) => x,
             Err(err) => return Some(err.into()),
@@ -365,7 +365,7 @@ pub unsafe extern "C" fn flux_find_var_type(
     var_name: *const c_char,
     out_type: *mut flux_buffer_t,
 ) -> Option<Box<ErrorHandle>> {
-    catch_unwind(|| {
+    safe::catch(|| {
         let buf = CStr::from_ptr(var_name).to_bytes(); // Unsafe
         let name = String::from_utf8(buf.to_vec()).unwrap();
         find_var_type(&ast_pkg, name).map_or_else(
@@ -394,6 +394,50 @@ pub unsafe extern "C" fn flux_find_var_type(
     .unwrap_or_else(|err| Some(err.into()))
 }
 
+/// Predicts the columns of the tables produced by a Flux expression that reads a bucket with
+/// `from`, using the schema of the bucket, see [`semantic::schema::predict_columns`]. `schema` is
+/// the JSON read by [`semantic::schema::JsonSchemaProvider`]. The buffer receives the columns as
+/// a JSON array of `{"name", "type", "groupKey"}` objects, or `null` if they cannot be predicted.
+///
+/// # Safety
+///
+/// This function is unsafe because it dereferences raw pointers passed
+/// in as parameters. For example, if that pointer is NULL, undefined behavior
+/// could occur.
+#[no_mangle]
+pub unsafe extern "C" fn flux_predict_columns(
+    schema: *const c_char,
+    src: *const c_char,
+    buf: *mut flux_buffer_t,
+) -> Option<Box<ErrorHandle>> {
+    safe::catch(|| {
+        let schema = CStr::from_ptr(schema).to_bytes(); // Unsafe
+        let src = CStr::from_ptr(src).to_string_lossy(); // Unsafe
+        match predict_columns(schema, &src) {
+            Ok(data) => {
+                (*buf).len = data.len();
+                (*buf).data = Box::into_raw(data.into_boxed_slice()) as *mut u8;
+                None
+            }
+            Err(err) => Some(err.into()),
+        }
+    })
+    .unwrap_or_else(|err| Some(err.into()))
+}
+
+fn predict_columns(schema: &[u8], src: &str) -> Result<Vec<u8>> {
+    let provider: semantic::schema::JsonSchemaProvider =
+        serde_json::from_slice(schema).map_err(anyhow::Error::from)?;
+    let file = fluxcore::parser::parse_string("".to_string(), src);
+    ast::check::check(ast::walk::Node::File(&file)).map_err(anyhow::Error::from)?;
+    let expr = match &file.body[..] {
+        [ast::Statement::Expr(stmt)] => &stmt.expression,
+        _ => return Err(anyhow!("expected a single expression").into()),
+    };
+    let columns = semantic::schema::predict_columns(expr, &provider);
+    Ok(serde_json::to_vec(&columns).map_err(anyhow::Error::from)?)
+}
+
 fn new_stateful_analyzer(options: Options) -> Result<StatefulAnalyzer> {
     let env = match prelude() {
         Some(prelude) => prelude,
@@ -403,29 +447,94 @@ fn new_stateful_analyzer(options: Options) -> Result<StatefulAnalyzer> {
         Some(imports) => imports,
         None => return Err(anyhow!("missing stdlib imports").into()),
     };
+    let usage = if options.usage {
+        Some(Usage::default())
+    } else {
+        None
+    };
     Ok(StatefulAnalyzer {
         env,
         imports,
         options,
+        env_imports: Vec::new(),
+        usage,
+        usage_callback: None,
     })
 }
 
+/// A callback that receives the [`Usage`] of every package a stateful analyzer analyzes, as JSON
+/// in the buffer, which is only valid during the call. The first argument is the value given to
+/// [`flux_analyzer_set_usage_callback`], for the host to find its own state.
+#[allow(non_camel_case_types)]
+pub type flux_usage_callback_t = unsafe extern "C" fn(usize, *mut flux_buffer_t);
+
 /// StatefulAnalyzer updates its environment with the contents of any previously analyzed package.
 /// This enables uses cases where analysis is performed iteratively, for example in a REPL.
 pub struct StatefulAnalyzer {
     env: PackageExports,
     imports: &'static Packages,
     options: Options,
+    // The symbols of the environment bound to the packages imported by previous packages, with
+    // the path of each package.
+    env_imports: Vec<(Symbol, String)>,
+    // The sum of the usage of every analyzed package, when the options enable usage.
+    usage: Option<Usage>,
+    usage_callback: Option<(flux_usage_callback_t, usize)>,
 }
 
 impl StatefulAnalyzer {
-    fn analyze(&mut self, ast_pkg: &ast::Package) -> Result<fluxcore::semantic::nodes::Package> {
-        let Options { features } = self.options.clone();
+    // Analyzes `ast_pkg`, which was parsed from `src` if it is given. When calls to snippets are
+    // expanded in a package of a single file, the package is parsed again from the expanded
+    // source, and the diagnostics are mapped back to `src`.
+    fn analyze(
+        &mut self,
+        ast_pkg: &ast::Package,
+        src: Option<&str>,
+    ) -> Result<fluxcore::semantic::nodes::Package> {
+        let expansion = match (src, &ast_pkg.files[..]) {
+            (Some(src), [file]) if !self.options.snippets.is_empty() => {
+                let expansion = self.options.snippets.expand(src);
+                if expansion.is_expanded() {
+                    let file = Parser::new(&expansion.source).parse_file(file.name.clone());
+                    Some((expansion, file))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        let expanded_pkg;
+        let ast_pkg = match &expansion {
+            Some((_, file)) => {
+                expanded_pkg = ast::Package {
+                    files: vec![file.clone()],
+                    ..ast_pkg.clone()
+                };
+                &expanded_pkg
+            }
+            None => ast_pkg,
+        };
         let mut analyzer = Analyzer::new(
             Environment::from(&self.env),
             self.imports,
-            AnalyzerConfig { features },
+            self.options.clone().into_config(),
         );
+        if let Some(usage) = &mut self.usage {
+            let callback = self.usage_callback;
+            analyzer.set_env_imports(self.env_imports.iter().cloned());
+            analyzer.set_observer(move |u| {
+                usage.add(u);
+                if let Some((callback, data)) = callback {
+                    if let Ok(json) = serde_json::to_vec(u) {
+                        let mut buf = flux_buffer_t {
+                            data: json.as_ptr(),
+                            len: json.len(),
+                        };
+                        unsafe { callback(data, &mut buf) };
+                    }
+                }
+            });
+        }
         let (mut env, sem_pkg) = match analyzer.analyze_ast(ast_pkg) {
             Ok(r) => r,
             Err(e) => {
@@ -433,7 +542,11 @@ impl StatefulAnalyzer {
                 // back from the analyzer.
                 let (_env, imports) = analyzer.drop();
                 self.imports = imports;
-                return Err(e.error.into());
+                let mut err = e.error;
+                if let Some((expansion, _)) = &expansion {
+                    err.map_to_original(expansion);
+                }
+                return Err(err.into());
             }
         };
         // Restore the imports.
@@ -453,6 +566,8 @@ impl StatefulAnalyzer {
                 // imports would have failed.
                 if let Some(typ) = self.imports.import(path) {
                     env.add(dec.import_symbol.clone(), typ);
+                    self.env_imports
+                        .push((dec.import_symbol.clone(), path.clone()));
                 }
             }
         }
@@ -470,11 +585,10 @@ impl StatefulAnalyzer {
 pub unsafe extern "C" fn flux_new_stateful_analyzer(
     options: *const c_char,
 ) -> Box<Result<StatefulAnalyzer>> {
-    let options = match Options::from_c_str(options) {
-        Ok(x) => x,
-        Err(err) => return Box::new(Err(err)),
-    };
-    Box::new(new_stateful_analyzer(options))
+    Box::new(
+        safe::catch(|| new_stateful_analyzer(Options::from_c_str(options)?))
+            .unwrap_or_else(|err| Err(anyhow::Error::from(err).into())),
+    )
 }
 
 /// Free a previously allocated semantic analyzer
@@ -492,7 +606,7 @@ pub unsafe extern "C" fn flux_analyze_with(
     ast_pkg: Box<ast::Package>,
     out_sem_pkg: *mut Option<Box<semantic::nodes::Package>>,
 ) -> Option<Box<ErrorHandle>> {
-    catch_unwind(|| {
+    safe::catch(|| {
         let ast_pkg = &ast_pkg;
         let analyzer = &mut *analyzer;
         let analyzer = match analyzer {
@@ -516,7 +630,7 @@ pub unsafe extern "C" fn flux_analyze_with(
             Some(std::str::from_utf8(CStr::from_ptr(csrc).to_bytes()).unwrap())
         };
 
-        let sem_pkg = Box::new(match analyzer.analyze(ast_pkg) {
+        let sem_pkg = Box::new(match analyzer.analyze(ast_pkg, src) {
             Ok(sem_pkg) => sem_pkg,
             Err(mut err) => {
                 if let Some(src) = src {
@@ -534,6 +648,156 @@ pub unsafe extern "C" fn flux_analyze_with(
     .unwrap_or_else(|err| Some(err.into()))
 }
 
+/// Writes the usage of the language in every package analyzed by the analyzer so far, see
+/// [`Usage`], into the buffer as a JSON object with `imports`, `functions`, `errors` and
+/// `warnings` members. Fails unless the options of the analyzer enable usage.
+///
+/// # Safety
+///
+/// Ths function is unsafe because it dereferences raw pointers.
+#[no_mangle]
+pub unsafe extern "C" fn flux_analyzer_usage(
+    analyzer: *const Result<StatefulAnalyzer>,
+    buf: *mut flux_buffer_t,
+) -> Option<Box<ErrorHandle>> {
+    safe::catch(|| {
+        let analyzer = match &*analyzer {
+            Ok(a) => a,
+            Err(_) => return Some(Error::from(anyhow!("invalid analyzer")).into()),
+        };
+        let usage = match &analyzer.usage {
+            Some(usage) => usage,
+            None => return Some(Error::from(anyhow!("usage is not enabled")).into()),
+        };
+        match serde_json::to_vec(usage) {
+            Ok(data) => {
+                (*buf).len = data.len();
+                (*buf).data = Box::into_raw(data.into_boxed_slice()) as *mut u8;
+                None
+            }
+            Err(err) => Some(Error::from(anyhow::Error::from(err)).into()),
+        }
+    })
+    .unwrap_or_else(|err| Some(err.into()))
+}
+
+/// Registers a callback that receives the usage of every package the analyzer analyzes from
+/// then on, see [`flux_usage_callback_t`]. `data` is passed back to the callback. Fails unless the
+/// options of the analyzer enable usage.
+///
+/// # Safety
+///
+/// Ths function is unsafe because it dereferences a raw pointer.
+#[no_mangle]
+pub unsafe extern "C" fn flux_analyzer_set_usage_callback(
+    analyzer: *mut Result<StatefulAnalyzer>,
+    callback: flux_usage_callback_t,
+    data: usize,
+) -> Option<Box<ErrorHandle>> {
+    safe::catch(|| {
+        let analyzer = match &mut *analyzer {
+            Ok(a) => a,
+            Err(_) => return Some(Error::from(anyhow!("invalid analyzer")).into()),
+        };
+        if analyzer.usage.is_none() {
+            return Some(Error::from(anyhow!("usage is not enabled")).into());
+        }
+        analyzer.usage_callback = Some((callback, data));
+        None
+    })
+    .unwrap_or_else(|err| Some(err.into()))
+}
+
+/// Sets the schema of the buckets read by the queries analyzed by the analyzer from then on,
+/// replacing the schema of its options. `schema` is the JSON read by
+/// [`semantic::schema::JsonSchemaProvider`].
+///
+/// # Safety
+///
+/// Ths function is unsafe because it dereferences raw pointers.
+#[no_mangle]
+pub unsafe extern "C" fn flux_analyzer_set_schema(
+    analyzer: *mut Result<StatefulAnalyzer>,
+    schema: *const c_char,
+) -> Option<Box<ErrorHandle>> {
+    safe::catch(|| {
+        let analyzer = match &mut *analyzer {
+            Ok(a) => a,
+            Err(_) => return Some(Error::from(anyhow!("invalid analyzer")).into()),
+        };
+        let schema = CStr::from_ptr(schema).to_bytes(); // Unsafe
+        match serde_json::from_slice(schema) {
+            Ok(schema) => {
+                analyzer.options.schema = Some(schema);
+                None
+            }
+            Err(err) => Some(Error::InvalidOptions(err.to_string()).into()),
+        }
+    })
+    .unwrap_or_else(|err| Some(err.into()))
+}
+
+/// Sets the unit of the columns of the locations in the messages of the errors of the packages
+/// analyzed by the analyzer from then on, one of `utf-8`, `utf-16` or `graphemes`, see
+/// [`ColumnEncoding`]. Columns are only converted in the errors of [`flux_analyze_with`], which
+/// is given the source.
+///
+/// # Safety
+///
+/// Ths function is unsafe because it dereferences raw pointers.
+#[no_mangle]
+pub unsafe extern "C" fn flux_analyzer_set_column_encoding(
+    analyzer: *mut Result<StatefulAnalyzer>,
+    encoding: *const c_char,
+) -> Option<Box<ErrorHandle>> {
+    safe::catch(|| {
+        let analyzer = match &mut *analyzer {
+            Ok(a) => a,
+            Err(_) => return Some(Error::from(anyhow!("invalid analyzer")).into()),
+        };
+        let encoding = CStr::from_ptr(encoding).to_string_lossy(); // Unsafe
+        match encoding.parse() {
+            Ok(encoding) => {
+                analyzer.options.column_encoding = encoding;
+                None
+            }
+            Err(err) => Some(Error::InvalidOptions(format!("{}", err)).into()),
+        }
+    })
+    .unwrap_or_else(|err| Some(err.into()))
+}
+
+/// Registers `template` as the Flux code that replaces calls `name()` in the sources analyzed by
+/// the analyzer from then on, see [`ast::snippets`]. Only calls in the sources given to
+/// [`flux_analyze_with`] are expanded.
+///
+/// # Safety
+///
+/// Ths function is unsafe because it dereferences raw pointers.
+#[no_mangle]
+pub unsafe extern "C" fn flux_analyzer_register_snippet(
+    analyzer: *mut Result<StatefulAnalyzer>,
+    name: *const c_char,
+    template: *const c_char,
+) -> Option<Box<ErrorHandle>> {
+    safe::catch(|| {
+        let analyzer = match &mut *analyzer {
+            Ok(a) => a,
+            Err(_) => return Some(Error::from(anyhow!("invalid analyzer")).into()),
+        };
+        let name = CStr::from_ptr(name).to_str(); // Unsafe
+        let template = CStr::from_ptr(template).to_str(); // Unsafe
+        match (name, template) {
+            (Ok(name), Ok(template)) => {
+                analyzer.options.snippets.register(name, template);
+                None
+            }
+            (Err(err), _) | (_, Err(err)) => Some(Error::InvalidOptions(err.to_string()).into()),
+        }
+    })
+    .unwrap_or_else(|err| Some(err.into()))
+}
+
 /// Compilation options. Deserialized from json when called via the C API
 #[derive(Clone, Default, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize))]
@@ -541,9 +805,62 @@ pub struct Options {
     /// Features used in the flux compiler
     #[serde(default)]
     pub features: Vec<Feature>,
+    /// The schema of the buckets read by the analyzed queries, see [`AnalyzerConfig::schema`]
+    #[serde(default)]
+    pub schema: Option<semantic::schema::JsonSchemaProvider>,
+    /// Whether a stateful analyzer collects the [`Usage`] of the packages it analyzes
+    #[serde(default)]
+    pub usage: bool,
+    /// The snippets expanded in the sources given to [`flux_analyze_with`], see
+    /// [`AnalyzerConfig::snippets`]
+    #[serde(default)]
+    pub snippets: Snippets,
+    /// The unit of the columns of the locations in the messages of errors, see
+    /// [`AnalyzerConfig::column_encoding`]
+    #[serde(default, rename = "columnEncoding")]
+    pub column_encoding: ColumnEncoding,
+    /// A JSON or TOML file containing the configuration the other options are applied to, see
+    /// [`AnalyzerConfig::from_file`]. The features of the options replace those of the file
+    /// unless they are empty.
+    #[serde(default, rename = "configFile")]
+    pub config_file: Option<PathBuf>,
+    // The configuration read from `config_file`.
+    #[serde(skip)]
+    config: AnalyzerConfig,
 }
 
 impl Options {
+    fn into_config(self) -> AnalyzerConfig {
+        let Options {
+            features,
+            schema,
+            snippets,
+            column_encoding,
+            mut config,
+            ..
+        } = self;
+        if !features.is_empty() {
+            config.features = features;
+        }
+        config.snippets.merge(snippets);
+        if !column_encoding.is_utf8() {
+            config.column_encoding = column_encoding;
+        }
+        match schema {
+            Some(schema) => config.with_schema(schema),
+            None => config,
+        }
+    }
+
+    // Reads the configuration of `config_file`, if any.
+    fn read_config_file(mut self) -> Result<Self> {
+        if let Some(path) = &self.config_file {
+            self.config = AnalyzerConfig::from_file(path)
+                .map_err(|err| Error::InvalidOptions(format!("{:#}", err)))?;
+        }
+        Ok(self)
+    }
+
     unsafe fn from_c_str(options: *const c_char) -> Result<Self> {
         let options = CStr::from_ptr(options).to_bytes();
         if options.is_empty() {
@@ -558,8 +875,8 @@ impl Options {
         }
 
         #[cfg(feature = "serde")]
-        match serde_json::from_slice(options) {
-            Ok(x) => Ok(x),
+        match serde_json::from_slice::<Self>(options) {
+            Ok(x) => x.read_config_file(),
             Err(err) => Err(Error::InvalidOptions(err.to_string())),
         }
     }
@@ -569,8 +886,7 @@ impl Options {
 /// that has been type-inferred.  This function is aware of the standard library
 /// and prelude.
 pub fn analyze(ast_pkg: &ast::Package, options: Options) -> Result<Package> {
-    let Options { features } = options;
-    let mut analyzer = new_semantic_analyzer(AnalyzerConfig { features })?;
+    let mut analyzer = new_semantic_analyzer(options.into_config())?;
     let (_, sem_pkg) = analyzer.analyze_ast(ast_pkg).map_err(|err| err.error)?;
     Ok(sem_pkg)
 }
@@ -668,6 +984,7 @@ mod tests {
     };
 
     use super::*;
+    use crate::new_minimal_semantic_analyzer;
 
     use crate::parser;
 
@@ -925,6 +1242,262 @@ from(bucket: v.bucket)
         );
     }
 
+    #[test]
+    fn ast_json_is_versioned() {
+        let pkg: ast::Package = crate::parser::parse_string("test".to_string(), "x = 1").into();
+        let mut buf = flux_buffer_t {
+            data: std::ptr::null(),
+            len: 0,
+        };
+        assert!(unsafe { flux_ast_marshal_json(&pkg, &mut buf) }.is_none());
+        let data: Box<[u8]> =
+            unsafe { Box::from_raw(std::slice::from_raw_parts_mut(buf.data as *mut u8, buf.len)) };
+        let value: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        assert_eq!(value["version"], ast::versions::CURRENT_VERSION);
+
+        let json = CString::new(data).unwrap();
+        let mut out = None;
+        assert!(unsafe { flux_parse_json(json.as_ptr() as *mut c_char, &mut out) }.is_none());
+        assert_eq!(*out.unwrap(), pkg);
+    }
+
+    #[test]
+    fn predict_columns_json() {
+        let schema = br#"{"b": {"m": {"tags": ["host"], "fields": {"v": "int"}}}}"#;
+        let columns = |src: &str| String::from_utf8(predict_columns(schema, src).unwrap()).unwrap();
+        assert_eq!(
+            columns(r#"from(bucket: "b") |> keep(columns: ["_value", "host"])"#),
+            concat!(
+                r#"[{"name":"_value","type":"int","groupKey":false},"#,
+                r#"{"name":"host","type":"string","groupKey":true}]"#,
+            ),
+        );
+        assert_eq!(columns(r#"from(bucket: "other")"#), "null");
+        assert!(predict_columns(schema, "x = 1").is_err());
+    }
+
+    #[test]
+    fn analyzer_usage_json() {
+        unsafe extern "C" fn callback(data: usize, buf: *mut flux_buffer_t) {
+            let events = &mut *(data as *mut Vec<String>);
+            let json = std::slice::from_raw_parts((*buf).data, (*buf).len);
+            events.push(String::from_utf8(json.to_vec()).unwrap());
+        }
+
+        let mut buf = flux_buffer_t {
+            data: std::ptr::null(),
+            len: 0,
+        };
+        let mut analyzer = Ok(new_stateful_analyzer(Options::default()).unwrap());
+        assert!(unsafe { flux_analyzer_usage(&analyzer, &mut buf) }.is_some());
+        assert!(unsafe { flux_analyzer_set_usage_callback(&mut analyzer, callback, 0) }.is_some());
+
+        let mut events: Vec<String> = Vec::new();
+        let mut analyzer = Ok(new_stateful_analyzer(Options {
+            usage: true,
+            ..Options::default()
+        })
+        .unwrap());
+        let data = &mut events as *mut Vec<String> as usize;
+        assert!(
+            unsafe { flux_analyzer_set_usage_callback(&mut analyzer, callback, data) }.is_none()
+        );
+        for src in [
+            "import \"strings\"",
+            "x = strings.title(v: \"a\")",
+            "f = (v) => strings.title(v: v)",
+            "y = f(v: x) + string(v: length(arr: [1]))",
+        ] {
+            let pkg: ast::Package = crate::parser::parse_string("".to_string(), src).into();
+            analyzer.as_mut().unwrap().analyze(&pkg, None).unwrap();
+        }
+        assert_eq!(
+            events[1],
+            r#"{"imports":[],"functions":{"strings.title":1},"errors":{},"warnings":{}}"#,
+        );
+        assert_eq!(events.len(), 4);
+
+        assert!(unsafe { flux_analyzer_usage(&analyzer, &mut buf) }.is_none());
+        let data: Box<[u8]> =
+            unsafe { Box::from_raw(std::slice::from_raw_parts_mut(buf.data as *mut u8, buf.len)) };
+        assert_eq!(
+            std::str::from_utf8(&data).unwrap(),
+            r#"{"imports":["strings"],"functions":{"length":1,"string":1,"strings.title":2},"errors":{},"warnings":{}}"#,
+        );
+        analyzer = Err(Error::from(anyhow!("failed")));
+        assert!(unsafe { flux_analyzer_usage(&analyzer, &mut buf) }.is_some());
+    }
+
+    #[test]
+    fn panics_are_errors() {
+        let pkg = Box::new(crate::parser::parse_string("".to_string(), "x = 1").into());
+        let name = CString::new(vec![0xff]).unwrap();
+        let mut buf = flux_buffer_t {
+            data: std::ptr::null(),
+            len: 0,
+        };
+        let err = unsafe { flux_find_var_type(pkg, name.as_ptr(), &mut buf) }.unwrap();
+        let message = err.message.to_str().unwrap();
+        assert!(message.starts_with("panic: "), "{}", message);
+        assert!(message.contains("cffi.rs:"), "{}", message);
+    }
+
+    #[test]
+    fn analyzer_schema() {
+        let src = "a = from(bucket: \"missing\")\nb = 1 + \"\"";
+        let pkg: ast::Package = crate::parser::parse_string("".to_string(), src).into();
+        let warnings = |analyzer: &mut Result<StatefulAnalyzer>| match analyzer
+            .as_mut()
+            .unwrap()
+            .analyze(&pkg, None)
+        {
+            Err(Error::Semantic(err)) => err
+                .diagnostics
+                .warnings
+                .iter()
+                .map(|warning| warning.to_string())
+                .collect::<Vec<_>>(),
+            _ => panic!("expected a semantic error"),
+        };
+        let mut analyzer = Ok(new_stateful_analyzer(Options::default()).unwrap());
+        assert_eq!(warnings(&mut analyzer), Vec::<String>::new());
+
+        let schema = CString::new(r#"{"telegraf": {"cpu": {"tags": ["host"]}}}"#).unwrap();
+        assert!(unsafe { flux_analyzer_set_schema(&mut analyzer, schema.as_ptr()) }.is_none());
+        assert_eq!(
+            warnings(&mut analyzer),
+            ["error @1:18-1:27: bucket \"missing\" does not exist"],
+        );
+
+        let schema = CString::new("[]").unwrap();
+        assert!(unsafe { flux_analyzer_set_schema(&mut analyzer, schema.as_ptr()) }.is_some());
+    }
+
+    #[test]
+    fn options_config_file() {
+        let file = std::env::temp_dir().join(format!("cffi_config_{}.json", std::process::id()));
+        std::fs::write(
+            &file,
+            r#"{"features": ["labelPolymorphism"], "columnEncoding": "utf-16"}"#,
+        )
+        .unwrap();
+        let options = |json: String| {
+            let json = CString::new(json).unwrap();
+            unsafe { Options::from_c_str(json.as_ptr()) }
+        };
+
+        let config = options(format!(r#"{{"configFile": {:?}}}"#, file))
+            .unwrap()
+            .into_config();
+        assert_eq!(config.features, vec![Feature::LabelPolymorphism]);
+        assert_eq!(config.column_encoding, ColumnEncoding::Utf16);
+
+        let config = options(format!(
+            r#"{{"configFile": {:?}, "features": ["vectorizedMap"]}}"#,
+            file
+        ))
+        .unwrap()
+        .into_config();
+        assert_eq!(config.features, vec![Feature::VectorizedMap]);
+        std::fs::remove_file(&file).unwrap();
+
+        assert!(matches!(
+            options(format!(r#"{{"configFile": {:?}}}"#, file)),
+            Err(Error::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn analyzer_column_encoding() {
+        let src = "s = \"été\" + 1";
+        let analyze = |analyzer: &mut Result<StatefulAnalyzer>| {
+            let csrc = CString::new(src).unwrap();
+            let pkg = Box::new(crate::parser::parse_string("".to_string(), src).into());
+            let mut sem_pkg = None;
+            unsafe { flux_analyze_with(analyzer, csrc.as_ptr(), pkg, &mut sem_pkg) }
+                .map(|err| err.message.to_str().unwrap().to_string())
+                .unwrap()
+        };
+        let mut analyzer = Ok(new_stateful_analyzer(Options::default()).unwrap());
+        assert_eq!(
+            analyze(&mut analyzer),
+            "error @1:15-1:16: expected string but found int"
+        );
+
+        let encoding = CString::new("utf-16").unwrap();
+        assert!(
+            unsafe { flux_analyzer_set_column_encoding(&mut analyzer, encoding.as_ptr()) }
+                .is_none()
+        );
+        assert_eq!(
+            analyze(&mut analyzer),
+            "error @1:13-1:14: expected string but found int"
+        );
+
+        let options: Options = serde_json::from_str(r#"{"columnEncoding": "graphemes"}"#).unwrap();
+        let mut analyzer = Ok(new_stateful_analyzer(options).unwrap());
+        assert_eq!(
+            analyze(&mut analyzer),
+            "error @1:13-1:14: expected string but found int"
+        );
+
+        let encoding = CString::new("utf-32").unwrap();
+        assert!(
+            unsafe { flux_analyzer_set_column_encoding(&mut analyzer, encoding.as_ptr()) }
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn analyzer_snippets() {
+        let analyze = |analyzer: &mut Result<StatefulAnalyzer>, src: &str| {
+            let csrc = CString::new(src).unwrap();
+            let pkg = Box::new(crate::parser::parse_string("".to_string(), src).into());
+            let mut sem_pkg = None;
+            unsafe { flux_analyze_with(analyzer, csrc.as_ptr(), pkg, &mut sem_pkg) }
+                .map(|err| err.message.to_str().unwrap().to_string())
+        };
+        let options: Options =
+            serde_json::from_str(r#"{"snippets": {"__all": "from(bucket: \"all\")"}}"#).unwrap();
+        let mut analyzer = Ok(new_stateful_analyzer(options).unwrap());
+        assert_eq!(
+            analyze(&mut analyzer, "x = __all() |> range(start: -1h)"),
+            None
+        );
+        // The error is located at the call, not at the template.
+        let err = analyze(&mut analyzer, "y = __all() + 1").unwrap();
+        assert!(err.contains("error @1:5-1:16: stream["), "{}", err);
+        assert_eq!(
+            analyze(&mut analyzer, "z = __cpu()"),
+            Some("error @1:5-1:10: undefined identifier __cpu".to_string()),
+        );
+
+        let name = CString::new("__cpu").unwrap();
+        let template =
+            CString::new("__all() |> filter(fn: (r) => r._measurement == \"cpu\")").unwrap();
+        assert!(unsafe {
+            flux_analyzer_register_snippet(&mut analyzer, name.as_ptr(), template.as_ptr())
+        }
+        .is_none());
+        // The text of a template is not expanded again.
+        assert_eq!(
+            analyze(&mut analyzer, "z = __cpu()"),
+            Some("error @1:5-1:12: undefined identifier __all".to_string()),
+        );
+    }
+
+    #[test]
+    fn embedded_stdlib() {
+        let stdlib = crate::stdlib().unwrap();
+        let hash = semantic::bootstrap::stdlib_hash(
+            std::path::Path::new("../../stdlib"),
+            &AnalyzerConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(stdlib.hash(), hash);
+        assert!(stdlib.imports().import("strings").is_some());
+    }
+
     #[test]
     fn test_ast_get_error() {
         let ast = crate::parser::parse_string("test".to_string(), "x = 3 + / 10 - \"");
@@ -1083,4 +1656,35 @@ from(bucket: v.bucket)
 
         assert_eq!(identifier.unwrap().name.package(), Some("universe"));
     }
+
+    #[test]
+    fn minimal_prelude() {
+        let mut analyzer =
+            new_minimal_semantic_analyzer(AnalyzerConfig::default(), &["strings"]).unwrap();
+
+        let src = r#"
+            import "strings"
+
+            x = strings.toUpper(v: string(v: 1))
+        "#;
+        analyzer
+            .analyze_source("".to_string(), "main.flux".to_string(), src)
+            .unwrap();
+
+        let src = r#"
+            import "array"
+
+            x = array.from(rows: [{a: 1}])
+        "#;
+        let err = analyzer
+            .analyze_source("".to_string(), "main.flux".to_string(), src)
+            .unwrap_err()
+            .error;
+        assert!(
+            err.to_string()
+                .contains("package array is not loaded by this analyzer"),
+            "{}",
+            err
+        );
+    }
 }
@@ -6,7 +6,10 @@
 use anyhow::anyhow;
 use fluxcore::semantic::env::Environment;
 use fluxcore::semantic::flatbuffers::semantic_generated::fbsemantic as fb;
-use fluxcore::semantic::import::Packages;
+use fluxcore::semantic::flatbuffers::types::deserialize_packages_where;
+use fluxcore::semantic::hosting::Stdlib;
+use fluxcore::semantic::import::{Packages, PartialImporter};
+use fluxcore::semantic::types::PolyTypeHashMap;
 use fluxcore::semantic::{Analyzer, AnalyzerConfig, PackageExports};
 use once_cell::sync::Lazy;
 use thiserror::Error;
@@ -66,6 +69,17 @@ pub fn imports() -> Option<Packages> {
 
 static IMPORTS: Lazy<Option<Packages>> = Lazy::new(imports);
 
+/// Returns the stdlib embedded in this crate, inferred from the Flux source of the stdlib without
+/// any features when the crate was built.
+pub fn stdlib() -> Result<Stdlib> {
+    let prelude = PRELUDE.clone().ok_or_else(|| anyhow!("missing prelude"))?;
+    let imports = IMPORTS
+        .clone()
+        .ok_or_else(|| anyhow!("missing stdlib imports"))?;
+    let hash = u64::from_le_bytes(*include_bytes!(concat!(env!("OUT_DIR"), "/stdlib.hash")));
+    Ok(Stdlib::new(prelude, imports, hash))
+}
+
 /// Creates a new analyzer that can semantically analyze Flux source code.
 ///
 /// The analyzer is aware of the stdlib and prelude.
@@ -80,3 +94,37 @@ pub fn new_semantic_analyzer(
 
     Ok(Analyzer::new(Environment::from(env), importer, config))
 }
+
+/// Creates a new analyzer with a minimal prelude, for embedded uses that do not need the whole
+/// stdlib.
+///
+/// Only the values of the `universe` package are in scope, and only the stdlib packages listed in
+/// `packages` can be imported. The other packages are not loaded; importing one of them is
+/// reported as a package that is not loaded.
+pub fn new_minimal_semantic_analyzer(
+    config: AnalyzerConfig,
+    packages: &[&str],
+) -> Result<Analyzer<'static, PartialImporter>> {
+    let _ = env_logger::try_init();
+
+    let buf = include_bytes!(concat!(env!("OUT_DIR"), "/stdlib.data"));
+    let fb_packages = flatbuffers::root::<fb::Packages>(buf).map_err(|err| anyhow!("{}", err))?;
+    let (mut loaded, unloaded) = deserialize_packages_where(fb_packages, |path| {
+        path == "universe" || packages.contains(&path)
+    })
+    .ok_or_else(|| anyhow!("missing stdlib imports"))?;
+
+    let universe = if packages.contains(&"universe") {
+        loaded.get("universe").cloned()
+    } else {
+        loaded.remove("universe")
+    }
+    .ok_or_else(|| anyhow!("missing universe package"))?;
+    let env = Environment::from(universe.into_bindings().collect::<PolyTypeHashMap<_>>());
+
+    let importer = PartialImporter {
+        packages: loaded,
+        unloaded,
+    };
+    Ok(Analyzer::new(env, importer, config))
+}
@@ -2,13 +2,17 @@ package libflux
 
 // #include "influxdata/flux.h"
 // #include <stdlib.h>
+// extern void fluxUsageCallback(uintptr_t, struct flux_buffer_t *);
 import "C"
 
 import (
 	"context"
 	"encoding/json"
+	"os"
 	"path"
 	"runtime"
+	"runtime/cgo"
+	"strings"
 	"unsafe"
 
 	flatbuffers "github.com/google/flatbuffers/go"
@@ -35,6 +39,9 @@ func SemanticPackages() (map[string]*semantic.Package, error) {
 		if !packages.Packages(&fbpkg, i) {
 			return nil, errors.Newf(codes.Internal, "Unable to extract semantic packages")
 		}
+		if v := fbpkg.Version(); v > semantic.Version {
+			return nil, errors.Newf(codes.Internal, "unsupported semantic graph version %d, expected at most %d", v, semantic.Version)
+		}
 
 		if err := pkg.FromBuf(&fbpkg); err != nil {
 			return nil, err
@@ -48,6 +55,22 @@ func SemanticPackages() (map[string]*semantic.Package, error) {
 
 type Options struct {
 	Features []string `json:"features,omitempty"`
+	// Schema is the JSON schema of the buckets read by the analyzed queries.
+	// The filters of the queries are checked against it.
+	Schema json.RawMessage `json:"schema,omitempty"`
+	// Usage enables the collection of the usage of the language,
+	// see Analyzer.Usage and Analyzer.OnUsage.
+	Usage bool `json:"usage,omitempty"`
+	// Snippets are the templates of the Flux code that replaces calls name()
+	// in the sources given to Analyzer.Analyze, keyed by name.
+	Snippets map[string]string `json:"snippets,omitempty"`
+	// ColumnEncoding is the unit of the columns of the locations in the
+	// messages of errors: "utf-8", the default, "utf-16" or "graphemes".
+	ColumnEncoding string `json:"columnEncoding,omitempty"`
+	// ConfigFile is a JSON or TOML file containing the analyzer
+	// configuration the other options are applied to. Features replace
+	// the features of the file unless they are empty.
+	ConfigFile string `json:"configFile,omitempty"`
 }
 
 func NewOptions(ctx context.Context) Options {
@@ -59,6 +82,25 @@ func NewOptions(ctx context.Context) Options {
 	return Options{Features: features}
 }
 
+// ApplyEnv configures the options from the environment variables read by
+// the command line tools: $FLUX_CONFIG names the config file and
+// $FLUX_FEATURES lists the features to enable, separated by commas,
+// replacing the other features.
+func (o *Options) ApplyEnv() {
+	if file := os.Getenv("FLUX_CONFIG"); file != "" {
+		o.ConfigFile = file
+	}
+	if value := os.Getenv("FLUX_FEATURES"); value != "" {
+		var features []string
+		for _, name := range strings.Split(value, ",") {
+			if name = strings.TrimSpace(name); name != "" {
+				features = append(features, name)
+			}
+		}
+		o.Features = features
+	}
+}
+
 func addFlag(ctx context.Context, features []string, flag feature.BoolFlag) []string {
 	if flag.Enabled(ctx) {
 		features = append(features, flag.Key())
@@ -175,8 +217,44 @@ func FindVarType(astPkg *ASTPkg, varName string) (semantic.MonoType, error) {
 	return semantic.NewMonoType(table, monotype.TypType())
 }
 
+// Column is a column of the tables produced by a Flux expression, as predicted by PredictColumns.
+type Column struct {
+	Name     string `json:"name"`
+	Type     string `json:"type"`
+	GroupKey bool   `json:"groupKey"`
+}
+
+// PredictColumns predicts the columns of the tables produced by a Flux expression
+// that reads a bucket with from(), using the JSON schema of the buckets, e.g.
+// {"telegraf": {"cpu": {"tags": ["host"], "fields": {"usage_user": "float"}}}}.
+// It returns nil if the columns cannot be predicted.
+func PredictColumns(schema []byte, expr string) ([]Column, error) {
+	cschema := C.CString(string(schema))
+	defer C.free(unsafe.Pointer(cschema))
+	cexpr := C.CString(expr)
+	defer C.free(unsafe.Pointer(cexpr))
+
+	var buf C.struct_flux_buffer_t
+	if err := C.flux_predict_columns(cschema, cexpr, &buf); err != nil {
+		defer C.flux_free_error(err)
+		cstr := C.flux_error_str(err)
+		str := C.GoString(cstr)
+		return nil, errors.New(codes.Invalid, str)
+	}
+	defer C.flux_free_bytes(buf.data)
+	data := C.GoBytes(unsafe.Pointer(buf.data), C.int(buf.len))
+
+	var columns []Column
+	if err := json.Unmarshal(data, &columns); err != nil {
+		return nil, errors.Wrap(err, codes.Internal, "could not unmarshal predicted columns")
+	}
+	return columns, nil
+}
+
 type Analyzer struct {
 	ptr *C.struct_flux_stateful_analyzer_t
+	// usageCallback is the handle of the callback registered with OnUsage.
+	usageCallback cgo.Handle
 }
 
 func NewAnalyzer() *Analyzer {
@@ -227,12 +305,136 @@ func (p *Analyzer) Analyze(src string, astPkg *ASTPkg) (*SemanticPkg, *FluxError
 	return pkg, nil
 }
 
+// Usage is the usage of the language in the snippets analyzed by an Analyzer.
+type Usage struct {
+	// Imports are the paths of the imported packages.
+	Imports []string `json:"imports"`
+	// Functions is the number of calls to each function of the prelude or of an imported
+	// package, e.g. range or strings.title. Calls to functions defined by the snippets
+	// are not counted.
+	Functions map[string]int `json:"functions"`
+	// Errors is the number of errors of each kind, e.g. inference.
+	Errors map[string]int `json:"errors"`
+	// Warnings is the number of warnings of each kind, e.g. unused-symbol.
+	Warnings map[string]int `json:"warnings"`
+}
+
+// Usage returns the usage of the language in every snippet analyzed by the analyzer so far.
+// The options of the analyzer must enable usage.
+func (p *Analyzer) Usage() (*Usage, error) {
+	var buf C.struct_flux_buffer_t
+	if err := C.flux_analyzer_usage(p.ptr, &buf); err != nil {
+		defer C.flux_free_error(err)
+		cstr := C.flux_error_str(err)
+		str := C.GoString(cstr)
+		return nil, errors.New(codes.Internal, str)
+	}
+	runtime.KeepAlive(p)
+	defer C.flux_free_bytes(buf.data)
+	data := C.GoBytes(unsafe.Pointer(buf.data), C.int(buf.len))
+
+	var usage Usage
+	if err := json.Unmarshal(data, &usage); err != nil {
+		return nil, errors.Wrap(err, codes.Internal, "could not unmarshal usage")
+	}
+	return &usage, nil
+}
+
+// SetSchema sets the JSON schema of the buckets read by the queries
+// analyzed from then on. The filters of the queries are checked against it.
+func (p *Analyzer) SetSchema(schema []byte) error {
+	cschema := C.CString(string(schema))
+	defer C.free(unsafe.Pointer(cschema))
+
+	if err := C.flux_analyzer_set_schema(p.ptr, cschema); err != nil {
+		defer C.flux_free_error(err)
+		cstr := C.flux_error_str(err)
+		str := C.GoString(cstr)
+		return errors.New(codes.Invalid, str)
+	}
+	runtime.KeepAlive(p)
+	return nil
+}
+
+// SetColumnEncoding sets the unit of the columns of the locations in the
+// messages of the errors of the snippets analyzed from then on: "utf-8",
+// "utf-16" or "graphemes".
+func (p *Analyzer) SetColumnEncoding(encoding string) error {
+	cencoding := C.CString(encoding)
+	defer C.free(unsafe.Pointer(cencoding))
+
+	if err := C.flux_analyzer_set_column_encoding(p.ptr, cencoding); err != nil {
+		defer C.flux_free_error(err)
+		cstr := C.flux_error_str(err)
+		str := C.GoString(cstr)
+		return errors.New(codes.Invalid, str)
+	}
+	runtime.KeepAlive(p)
+	return nil
+}
+
+// RegisterSnippet registers template as the Flux code that replaces calls
+// name() in the sources analyzed from then on. The locations of the
+// diagnostics are mapped back to the source, but the semantic graph is
+// located in the expanded source.
+func (p *Analyzer) RegisterSnippet(name, template string) error {
+	cname := C.CString(name)
+	defer C.free(unsafe.Pointer(cname))
+	ctemplate := C.CString(template)
+	defer C.free(unsafe.Pointer(ctemplate))
+
+	if err := C.flux_analyzer_register_snippet(p.ptr, cname, ctemplate); err != nil {
+		defer C.flux_free_error(err)
+		cstr := C.flux_error_str(err)
+		str := C.GoString(cstr)
+		return errors.New(codes.Invalid, str)
+	}
+	runtime.KeepAlive(p)
+	return nil
+}
+
+// OnUsage registers a callback that receives the usage of every snippet
+// analyzed from then on. The options of the analyzer must enable usage.
+func (p *Analyzer) OnUsage(callback func(*Usage)) error {
+	handle := cgo.NewHandle(callback)
+	if err := C.flux_analyzer_set_usage_callback(p.ptr, C.flux_usage_callback_t(C.fluxUsageCallback), C.uintptr_t(handle)); err != nil {
+		handle.Delete()
+		defer C.flux_free_error(err)
+		cstr := C.flux_error_str(err)
+		str := C.GoString(cstr)
+		return errors.New(codes.Invalid, str)
+	}
+	runtime.KeepAlive(p)
+	if p.usageCallback != 0 {
+		p.usageCallback.Delete()
+	}
+	p.usageCallback = handle
+	return nil
+}
+
+// fluxUsageCallback is the flux_usage_callback_t of OnUsage,
+// which calls the callback of the handle.
+//
+//export fluxUsageCallback
+func fluxUsageCallback(handle C.uintptr_t, buf *C.struct_flux_buffer_t) {
+	data := C.GoBytes(unsafe.Pointer(buf.data), C.int(buf.len))
+	var usage Usage
+	if err := json.Unmarshal(data, &usage); err != nil {
+		return
+	}
+	cgo.Handle(handle).Value().(func(*Usage))(&usage)
+}
+
 // Free frees the memory allocated by Rust for the semantic graph.
 func (p *Analyzer) Free() {
 	if p.ptr != nil {
 		C.flux_free_stateful_analyzer(p.ptr)
 	}
 	p.ptr = nil
+	if p.usageCallback != 0 {
+		p.usageCallback.Delete()
+		p.usageCallback = 0
+	}
 
 	// See the equivalent method in ASTPkg for why
 	// this is needed.
@@ -2,6 +2,8 @@ package libflux_test
 
 import (
 	"errors"
+	"os"
+	"path/filepath"
 	"testing"
 
 	"github.com/google/go-cmp/cmp"
@@ -117,3 +119,159 @@ p = s.timeRangeStop                // transitive reference
 		})
 	}
 }
+
+func TestPredictColumns(t *testing.T) {
+	schema := []byte(`{"telegraf": {"cpu": {"tags": ["host"], "fields": {"usage_user": "float"}}}}`)
+
+	got, err := libflux.PredictColumns(schema, `from(bucket: "telegraf") |> keep(columns: ["_value", "host"])`)
+	if err != nil {
+		t.Fatal(err)
+	}
+	want := []libflux.Column{
+		{Name: "_value", Type: "float"},
+		{Name: "host", Type: "string", GroupKey: true},
+	}
+	if diff := cmp.Diff(want, got); diff != "" {
+		t.Errorf("unexpected columns -want/+got:\n%s", diff)
+	}
+
+	got, err = libflux.PredictColumns(schema, `from(bucket: "missing")`)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if got != nil {
+		t.Errorf("expected no prediction, got %v", got)
+	}
+
+	if _, err := libflux.PredictColumns(schema, `x = 1`); err == nil {
+		t.Error("expected an error for a statement that is not an expression")
+	}
+}
+
+func TestAnalyzerUsage(t *testing.T) {
+	disabled := libflux.NewAnalyzer()
+	defer disabled.Free()
+	if _, err := disabled.Usage(); err == nil {
+		t.Error("expected an error for an analyzer whose options do not enable usage")
+	}
+
+	analyzer, err := libflux.NewAnalyzerWithOptions(libflux.Options{Usage: true})
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer analyzer.Free()
+	var events []*libflux.Usage
+	if err := analyzer.OnUsage(func(usage *libflux.Usage) {
+		events = append(events, usage)
+	}); err != nil {
+		t.Fatal(err)
+	}
+	for _, src := range []string{`import "strings"`, `x = strings.title(v: "a")`, `f = (v) => strings.title(v: v)`, `y = f(v: x)`} {
+		if _, err := analyzer.AnalyzeString(src); err != nil {
+			t.Fatal(err.GoError())
+		}
+	}
+	if len(events) != 4 {
+		t.Errorf("expected the usage of 4 snippets, got %d", len(events))
+	}
+
+	got, err := analyzer.Usage()
+	if err != nil {
+		t.Fatal(err)
+	}
+	want := &libflux.Usage{
+		Imports:   []string{"strings"},
+		Functions: map[string]int{"strings.title": 2},
+		Errors:    map[string]int{},
+		Warnings:  map[string]int{},
+	}
+	if diff := cmp.Diff(want, got); diff != "" {
+		t.Errorf("unexpected usage -want/+got:\n%s", diff)
+	}
+}
+
+func TestAnalyzerSetSchema(t *testing.T) {
+	analyzer := libflux.NewAnalyzer()
+	defer analyzer.Free()
+	if err := analyzer.SetSchema([]byte(`{"telegraf": {"cpu": {"tags": ["host"]}}}`)); err != nil {
+		t.Fatal(err)
+	}
+	if _, err := analyzer.AnalyzeString(`a = from(bucket: "telegraf")`); err != nil {
+		t.Fatal(err.GoError())
+	}
+	if err := analyzer.SetSchema([]byte(`[]`)); err == nil {
+		t.Error("expected an error for a schema that is not an object")
+	}
+}
+
+func TestAnalyzerSetColumnEncoding(t *testing.T) {
+	analyzer := libflux.NewAnalyzer()
+	defer analyzer.Free()
+	if err := analyzer.SetColumnEncoding("utf-16"); err != nil {
+		t.Fatal(err)
+	}
+	_, ferr := analyzer.AnalyzeString(`s = "été" + 1`)
+	if ferr == nil {
+		t.Fatal("expected a type error")
+	}
+	if want, got := "error @1:13-1:14: expected string but found int", ferr.GoError().Error(); got != want {
+		t.Errorf("unexpected error -want/+got:\n\t- %s\n\t+ %s", want, got)
+	}
+	if err := analyzer.SetColumnEncoding("utf-32"); err == nil {
+		t.Error("expected an error for an unknown encoding")
+	}
+}
+
+func TestAnalyzerSnippets(t *testing.T) {
+	analyzer, err := libflux.NewAnalyzerWithOptions(libflux.Options{
+		Snippets: map[string]string{"__all": `from(bucket: "all")`},
+	})
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer analyzer.Free()
+	if _, err := analyzer.AnalyzeString(`x = __all() |> range(start: -1h)`); err != nil {
+		t.Fatal(err.GoError())
+	}
+	if err := analyzer.RegisterSnippet("__cpu", `filter(fn: (r) => r._measurement == "cpu")`); err != nil {
+		t.Fatal(err)
+	}
+	if _, err := analyzer.AnalyzeString(`y = x |> __cpu()`); err != nil {
+		t.Fatal(err.GoError())
+	}
+	_, ferr := analyzer.AnalyzeString(`z = __mem()`)
+	if ferr == nil {
+		t.Fatal("expected an error for a call to an unregistered snippet")
+	}
+	if want, got := "error @1:5-1:10: undefined identifier __mem", ferr.GoError().Error(); got != want {
+		t.Errorf("unexpected error -want/+got:\n\t- %s\n\t+ %s", want, got)
+	}
+}
+
+func TestOptionsApplyEnv(t *testing.T) {
+	file := filepath.Join(t.TempDir(), "config.json")
+	if err := os.WriteFile(file, []byte(`{"columnEncoding": "utf-16"}`), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	t.Setenv("FLUX_CONFIG", file)
+	t.Setenv("FLUX_FEATURES", "labelPolymorphism, vectorizedMap")
+
+	options := libflux.Options{Features: []string{"unusedSymbolWarnings"}}
+	options.ApplyEnv()
+	if diff := cmp.Diff([]string{"labelPolymorphism", "vectorizedMap"}, options.Features); diff != "" {
+		t.Errorf("unexpected features -want/+got:\n%s", diff)
+	}
+
+	analyzer, err := libflux.NewAnalyzerWithOptions(options)
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer analyzer.Free()
+	_, ferr := analyzer.AnalyzeString(`s = "été" + 1`)
+	if ferr == nil {
+		t.Fatal("expected a type error")
+	}
+	if want, got := "error @1:13-1:14: expected string but found int", ferr.GoError().Error(); got != want {
+		t.Errorf("unexpected error -want/+got:\n\t- %s\n\t+ %s", want, got)
+	}
+}
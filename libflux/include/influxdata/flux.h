@@ -2,6 +2,7 @@
 #define _INFLUXDATA_FLUX_H
 
 #include <stddef.h>
+#include <stdint.h>
 
 #ifdef __cplusplus
 extern "C" {
@@ -105,6 +106,38 @@ void flux_free_stateful_analyzer(struct flux_stateful_analyzer_t *);
 // a semantic graph for that snippet.
 struct flux_error_t *flux_analyze_with(struct flux_stateful_analyzer_t *, const char * src, struct flux_ast_pkg_t *, struct flux_semantic_pkg_t **);
 
+// flux_analyzer_usage writes the usage of the language in every snippet analyzed by the
+// flux_stateful_analyzer_t so far into the buffer, as a JSON object with the imported packages,
+// the number of calls of each function of the prelude or of an imported package and the number
+// of diagnostics of each kind. It fails unless the "usage" option of the analyzer is true.
+struct flux_error_t *flux_analyzer_usage(struct flux_stateful_analyzer_t *, struct flux_buffer_t *);
+
+// flux_usage_callback_t receives the usage of a snippet as JSON, in the same format as
+// flux_analyzer_usage. The buffer is only valid during the call. The first parameter is the
+// value given to flux_analyzer_set_usage_callback.
+typedef void (*flux_usage_callback_t)(uintptr_t, struct flux_buffer_t *);
+
+// flux_analyzer_set_usage_callback registers a callback that receives the usage of every snippet
+// the flux_stateful_analyzer_t analyzes from then on. It fails unless the "usage" option of the
+// analyzer is true. It is the caller's responsibility to free the error if it is non-null.
+struct flux_error_t *flux_analyzer_set_usage_callback(struct flux_stateful_analyzer_t *, flux_usage_callback_t, uintptr_t);
+
+// flux_analyzer_set_schema sets the JSON schema of the buckets read by the queries analyzed by
+// the flux_stateful_analyzer_t from then on. The filters of the queries are checked against it.
+// It is the caller's responsibility to free the error if it is non-null.
+struct flux_error_t *flux_analyzer_set_schema(struct flux_stateful_analyzer_t *, const char * schema);
+
+// flux_analyzer_set_column_encoding sets the unit of the columns of the locations in the messages
+// of the errors of flux_analyze_with from then on: "utf-8", "utf-16" or "graphemes".
+// It is the caller's responsibility to free the error if it is non-null.
+struct flux_error_t *flux_analyzer_set_column_encoding(struct flux_stateful_analyzer_t *, const char * encoding);
+
+// flux_analyzer_register_snippet registers a template as the Flux code that replaces calls
+// name() in the sources given to flux_analyze_with from then on. Only the locations of the
+// diagnostics are mapped back to the source; the semantic graph is located in the expanded
+// source. It is the caller's responsibility to free the error if it is non-null.
+struct flux_error_t *flux_analyzer_register_snippet(struct flux_stateful_analyzer_t *, const char * name, const char * template);
+
 // flux_analyze analyzes the given AST and will populate the second pointer argument with
 // a pointer to the resulting semantic graph.
 // It is the caller's responsibility to free the resulting semantic graph with a call to flux_free_semantic_pkg().
@@ -123,6 +156,13 @@ struct flux_error_t *flux_analyze(struct flux_ast_pkg_t *, const char * options,
 // The second parameter is the variable identifier string.
 struct flux_error_t *flux_find_var_type(struct flux_ast_pkg_t *, const char *, struct flux_buffer_t *);
 
+// flux_predict_columns predicts the columns of the tables produced by a Flux expression
+// that reads a bucket with from(), using the JSON schema of the buckets given as the first
+// parameter. The buffer receives a JSON array of {"name", "type", "groupKey"} objects, or
+// null if the columns cannot be predicted. It is the caller's responsibility to free the
+// data of the buffer, and the error if it is non-null.
+struct flux_error_t *flux_predict_columns(const char * schema, const char * src, struct flux_buffer_t *);
+
 // flux_free_semantic_pkg will release the memory associated with the given pointer.
 void flux_free_semantic_pkg(struct flux_semantic_pkg_t*);
 
@@ -12,6 +12,8 @@ import (
 	"os"
 	"os/signal"
 	"path/filepath"
+	"sort"
+	"strings"
 	"sync"
 	"syscall"
 
@@ -39,7 +41,11 @@ type ScopeHolder struct {
 	cancelMu   sync.Mutex
 	cancelFunc context.CancelFunc
 
-	resChan chan string
+	resChan chan Response
+
+	// schema is the JSON schema of the buckets loaded with :schema, used by :columns.
+	// The analyzer checks the filters of queries against it too.
+	schema []byte
 }
 
 type Option interface {
@@ -57,7 +63,10 @@ func New(ctx context.Context, opts ...Option) *ScopeHolder {
 		pkg.Range(scope.Set)
 	}
 
-	analyzer, err := libflux.NewAnalyzerWithOptions(libflux.NewOptions(ctx))
+	options := libflux.NewOptions(ctx)
+	options.ApplyEnv()
+	options.Usage = true
+	analyzer, err := libflux.NewAnalyzerWithOptions(options)
 	if err != nil {
 		panic(err)
 	}
@@ -124,8 +133,11 @@ func (rw rwCloser) Close() error {
 	return err
 }
 
+// Response is the reply to a line of input: the displayed result and, for expressions
+// whose value can be represented in JSON, that value. See values.ToJSON.
 type Response struct {
 	Result string
+	Value  interface{} `json:",omitempty"`
 }
 
 type Testing struct {
@@ -151,7 +163,7 @@ type Input []struct {
 
 type Service struct {
 	c   chan string
-	res chan string
+	res chan Response
 }
 
 // {"jsonrpc":"2.0", "method": "Service.DidOutput", "id": "1", "title":"testing","body":"dog", "params":[{"input":"x=1"}]}
@@ -161,8 +173,7 @@ type Service struct {
 
 func (s *Service) DidOutput(req Testing, resp *Response) error {
 	s.c <- req.A
-	result := <-s.res
-	*resp = Response{result}
+	*resp = <-s.res
 	return nil
 }
 
@@ -173,7 +184,7 @@ func (r *ScopeHolder) Run() {
 	s := rpc.NewServer()
 	c := make(chan string)
 	//for the input result
-	calc_chan := make(chan string)
+	calc_chan := make(chan Response)
 	r.resChan = calc_chan
 
 	serv := Service{c, calc_chan}
@@ -266,6 +277,15 @@ func (r *ScopeHolder) evalWithFluxError(t string) ([]interpreter.SideEffect, *li
 // executeLine processes a line of input.
 // If the input evaluates to a valid value, that value is returned.
 func (r *ScopeHolder) executeLine(t string) (*libflux.FluxError, error) {
+	if strings.HasPrefix(t, ":") {
+		out, err := r.command(t)
+		if err != nil {
+			return nil, err
+		}
+		r.resChan <- Response{Result: out}
+		return nil, nil
+	}
+
 	ses, fluxError, err := r.evalWithFluxError(t)
 	if err != nil {
 		return fluxError, err
@@ -298,7 +318,9 @@ func (r *ScopeHolder) executeLine(t string) (*libflux.FluxError, error) {
 				values.Display(buf, se.Value)
 				//send flux result
 
-				r.resChan <- buf.String()
+				// Values without a JSON representation, such as functions, are only displayed.
+				value, _ := values.ToJSON(se.Value)
+				r.resChan <- Response{Result: buf.String(), Value: value}
 				// fmt.Println(buf.String(), "testing")
 			}
 		}
@@ -306,6 +328,115 @@ func (r *ScopeHolder) executeLine(t string) (*libflux.FluxError, error) {
 	return nil, nil
 }
 
+// command runs a REPL command, a line starting with ':', and returns its output.
+//
+//	:schema <file>         loads the JSON schema of the buckets read by queries,
+//	                       the filters of the queries analyzed from then on are checked against it
+//	:columns <expression>  predicts the columns of the tables produced by the expression
+//	:encoding <encoding>   sets the unit of the columns of the locations in errors,
+//	                       one of utf-8, utf-16 or graphemes
+//	:snippet <name> <flux> replaces the calls name() in the lines entered from then on
+//	                       with the Flux code
+//	:usage                 lists the packages, functions and diagnostics used in the session
+func (r *ScopeHolder) command(t string) (string, error) {
+	name, arg := t, ""
+	if i := strings.IndexAny(t, " \t"); i >= 0 {
+		name, arg = t[:i], strings.TrimSpace(t[i+1:])
+	}
+	switch name {
+	case ":schema":
+		schema, err := ioutil.ReadFile(arg)
+		if err != nil {
+			return "", err
+		}
+		if err := r.analyzer.SetSchema(schema); err != nil {
+			return "", err
+		}
+		r.schema = schema
+		return fmt.Sprintf("loaded the schema of %s", arg), nil
+	case ":encoding":
+		if err := r.analyzer.SetColumnEncoding(arg); err != nil {
+			return "", err
+		}
+		return fmt.Sprintf("columns count %s", arg), nil
+	case ":snippet":
+		name, template := arg, ""
+		if i := strings.IndexAny(arg, " \t"); i >= 0 {
+			name, template = arg[:i], strings.TrimSpace(arg[i+1:])
+		}
+		if name == "" || template == "" {
+			return "", fmt.Errorf("usage: :snippet <name> <flux>")
+		}
+		if err := r.analyzer.RegisterSnippet(name, template); err != nil {
+			return "", err
+		}
+		return fmt.Sprintf("registered the snippet %s()", name), nil
+	case ":columns":
+		return r.columns(arg)
+	case ":usage":
+		return r.usage()
+	default:
+		return "", fmt.Errorf("unknown command %s", name)
+	}
+}
+
+// columns predicts the columns of the tables produced by a Flux expression
+// from the schema loaded with :schema.
+// The expression must read the bucket with from() itself,
+// the variables defined in the REPL are not looked up.
+func (r *ScopeHolder) columns(expr string) (string, error) {
+	if r.schema == nil {
+		return "", fmt.Errorf("no schema loaded, load one with :schema <file>")
+	}
+	columns, err := libflux.PredictColumns(r.schema, expr)
+	if err != nil {
+		return "", err
+	}
+	if columns == nil {
+		return "columns cannot be predicted", nil
+	}
+	var b strings.Builder
+	for _, c := range columns {
+		fmt.Fprintf(&b, "%s: %s", c.Name, c.Type)
+		if c.GroupKey {
+			b.WriteString(" (group key)")
+		}
+		b.WriteString("\n")
+	}
+	return b.String(), nil
+}
+
+// usage lists the packages imported, the functions called and the diagnostics reported
+// in the lines analyzed so far in the session, with the number of times each occurred.
+func (r *ScopeHolder) usage() (string, error) {
+	usage, err := r.analyzer.Usage()
+	if err != nil {
+		return "", err
+	}
+	var b strings.Builder
+	for _, path := range usage.Imports {
+		fmt.Fprintf(&b, "import %q\n", path)
+	}
+	for _, section := range []struct {
+		title  string
+		counts map[string]int
+	}{
+		{"calls", usage.Functions},
+		{"errors", usage.Errors},
+		{"warnings", usage.Warnings},
+	} {
+		names := make([]string, 0, len(section.counts))
+		for name := range section.counts {
+			names = append(names, name)
+		}
+		sort.Strings(names)
+		for _, name := range names {
+			fmt.Fprintf(&b, "%s %s: %d\n", section.title, name, section.counts[name])
+		}
+	}
+	return b.String(), nil
+}
+
 func (r *ScopeHolder) analyzeLine(t string) (*semantic.Package, *libflux.FluxError, error) {
 	pkg, fluxError := r.analyzer.AnalyzeString(t)
 	if fluxError != nil {
@@ -11,8 +11,16 @@ import (
 	"github.com/influxdata/flux/internal/fbsemantic"
 )
 
+// Version is the latest version of the serialization of the semantic graph
+// that DeserializeFromFlatBuffer reads. It must match SEMANTIC_VERSION in
+// libflux/flux-core/src/semantic/flatbuffers/mod.rs.
+const Version = 1
+
 func DeserializeFromFlatBuffer(buf []byte) (*Package, error) {
 	fbPkg := fbsemantic.GetRootAsPackage(buf, 0)
+	if v := fbPkg.Version(); v > Version {
+		return nil, errors.Newf(codes.Internal, "unsupported semantic graph version %d, expected at most %d", v, Version)
+	}
 	p := &Package{}
 	if err := p.FromBuf(fbPkg); err != nil {
 		return nil, err
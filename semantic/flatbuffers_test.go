@@ -109,6 +109,16 @@ func TestDeserializeFromFlatBuffer(t *testing.T) {
 	}
 }
 
+func TestDeserializeFromFlatBufferVersion(t *testing.T) {
+	b := flatbuffers.NewBuilder(256)
+	fbsemantic.PackageStart(b)
+	fbsemantic.PackageAddVersion(b, semantic.Version+1)
+	b.Finish(fbsemantic.PackageEnd(b))
+	if _, err := semantic.DeserializeFromFlatBuffer(b.FinishedBytes()); err == nil {
+		t.Fatal("expected an error for a package of a later version")
+	}
+}
+
 func getUnaryOpFlatBuffer() (*semantic.Package, []byte) {
 	src := `x = -3.5`
 	b := flatbuffers.NewBuilder(256)
@@ -41,48 +41,5 @@ func unmarshalToValue(bytes []byte) (values.Value, error) {
 	if err != nil {
 		return nil, err
 	}
-	return toValue(i)
-}
-
-// toValue converts a Go value that can be produced by json.Unmarshal into its corresponding Flux value.
-func toValue(i interface{}) (values.Value, error) {
-	switch t := i.(type) {
-	case string:
-		return values.NewString(t), nil
-	case bool:
-		return values.NewBool(t), nil
-	case float64:
-		return values.NewFloat(t), nil
-	case []interface{}:
-		vals := make([]values.Value, len(t))
-		var elemTyp semantic.MonoType
-		for i, v := range t {
-			val, err := toValue(v)
-			if err != nil {
-				return nil, err
-			}
-			if elemTyp.Nature() == semantic.Invalid {
-				elemTyp = val.Type()
-			}
-			if !val.Type().Equal(elemTyp) {
-				return nil, errors.New(codes.Invalid, "array values must all be the same type")
-			}
-			vals[i] = val
-		}
-		return values.NewArrayWithBacking(semantic.NewArrayType(elemTyp), vals), nil
-	case map[string]interface{}:
-		vals := make(map[string]values.Value, len(t))
-		for k, v := range t {
-			val, err := toValue(v)
-			if err != nil {
-				return nil, err
-			}
-			vals[k] = val
-		}
-		return values.NewObjectWithValues(vals), nil
-	}
-	if i == nil {
-		return values.Null, nil
-	}
-	return nil, errors.Newf(codes.Internal, "unsupported json type %T", i)
+	return values.FromJSON(i)
 }
@@ -0,0 +1,162 @@
+package values
+
+import (
+	"strings"
+	"time"
+
+	"github.com/influxdata/flux/codes"
+	"github.com/influxdata/flux/internal/errors"
+	"github.com/influxdata/flux/semantic"
+)
+
+// ToJSON converts a value into a Go value that json.Marshal encodes as JSON.
+// Records become objects and dictionaries become objects keyed by the display of their keys.
+// Bytes are base64 encoded, times formatted as RFC 3339 timestamps, durations in their Flux syntax, regular
+// expressions as their pattern and nulls as null.
+// Streams and functions have no JSON representation and are an error.
+func ToJSON(v Value) (interface{}, error) {
+	if v.IsNull() {
+		return nil, nil
+	}
+	switch n := v.Type().Nature(); n {
+	case semantic.String:
+		return v.Str(), nil
+	case semantic.Bytes:
+		return v.Bytes(), nil
+	case semantic.Int:
+		return v.Int(), nil
+	case semantic.UInt:
+		return v.UInt(), nil
+	case semantic.Float:
+		return v.Float(), nil
+	case semantic.Bool:
+		return v.Bool(), nil
+	case semantic.Time:
+		return v.Time().Time().Format(time.RFC3339Nano), nil
+	case semantic.Duration:
+		return v.Duration(), nil
+	case semantic.Regexp:
+		return v.Regexp().String(), nil
+	case semantic.Array:
+		arr := v.Array()
+		a := make([]interface{}, arr.Len())
+		var rangeErr error
+		arr.Range(func(i int, v Value) {
+			if rangeErr != nil {
+				return // short circuit if we already hit an error
+			}
+			val, err := ToJSON(v)
+			if err != nil {
+				rangeErr = err
+				return
+			}
+			a[i] = val
+		})
+		if rangeErr != nil {
+			return nil, rangeErr
+		}
+		return a, nil
+	case semantic.Stream:
+		return nil, errors.New(
+			codes.Invalid,
+			"got table stream instead of array. "+
+				"Try using tableFind() or findRecord() to extract data from stream")
+	case semantic.Object:
+		obj := v.Object()
+		o := make(map[string]interface{}, obj.Len())
+		var rangeErr error
+		obj.Range(func(k string, v Value) {
+			if rangeErr != nil {
+				return // short circuit if we already hit an error
+			}
+			val, err := ToJSON(v)
+			if err != nil {
+				rangeErr = err
+				return
+			}
+			o[k] = val
+		})
+		if rangeErr != nil {
+			return nil, rangeErr
+		}
+		return o, nil
+	case semantic.Function:
+		return nil, errors.New(codes.Invalid, "cannot encode a function value")
+	case semantic.Dictionary:
+		dict := v.Dict()
+		// Go JSON encoder requires that map key type is either a primitive type or implements encoding.TextMarshaler interface.
+		// Since Go maps are encoded as JSON objects with string keys (https://www.json.org/json-en.html), and dictionary keys
+		// are primitive Flux types, we can safely convert Flux dictionary to Go map with string keys.
+		d := make(map[string]interface{}, dict.Len())
+		var rangeErr error
+		var b strings.Builder
+		dict.Range(func(k, v Value) {
+			if rangeErr != nil {
+				return // short circuit if we already hit an error
+			}
+			b.Reset()
+			err := Display(&b, k)
+			if err != nil {
+				rangeErr = err
+				return
+			}
+			key := b.String()
+			val, err := ToJSON(v)
+			if err != nil {
+				rangeErr = err
+				return
+			}
+			d[key] = val
+		})
+		if rangeErr != nil {
+			return nil, rangeErr
+		}
+		return d, nil
+	default:
+		return nil, errors.Newf(codes.Unknown, "unknown nature %v", n)
+	}
+}
+
+// FromJSON converts a Go value that can be produced by json.Unmarshal into its corresponding Flux value.
+// Numbers become floats and the elements of an array must all have the same type.
+func FromJSON(i interface{}) (Value, error) {
+	switch t := i.(type) {
+	case string:
+		return NewString(t), nil
+	case bool:
+		return NewBool(t), nil
+	case float64:
+		return NewFloat(t), nil
+	case []interface{}:
+		vals := make([]Value, len(t))
+		var elemTyp semantic.MonoType
+		for i, v := range t {
+			val, err := FromJSON(v)
+			if err != nil {
+				return nil, err
+			}
+			if elemTyp.Nature() == semantic.Invalid {
+				elemTyp = val.Type()
+			}
+			if !val.Type().Equal(elemTyp) {
+				return nil, errors.New(codes.Invalid, "array values must all be the same type")
+			}
+			vals[i] = val
+		}
+		return NewArrayWithBacking(semantic.NewArrayType(elemTyp), vals), nil
+	case map[string]interface{}:
+		vals := make(map[string]Value, len(t))
+		for k, v := range t {
+			val, err := FromJSON(v)
+			if err != nil {
+				return nil, err
+			}
+			vals[k] = val
+		}
+		return NewObjectWithValues(vals), nil
+	}
+	if i == nil {
+		return Null, nil
+	}
+	return nil, errors.Newf(codes.Internal, "unsupported json type %T", i)
+}
@@ -0,0 +1,66 @@
+package values_test
+
+import (
+	"encoding/json"
+	"testing"
+	"time"
+
+	"github.com/influxdata/flux/semantic"
+	"github.com/influxdata/flux/values"
+)
+
+func TestToJSON(t *testing.T) {
+	v := values.NewObjectWithValues(map[string]values.Value{
+		"a": values.NewInt(1),
+		"b": values.NewArrayWithBacking(semantic.NewArrayType(semantic.BasicString), []values.Value{
+			values.NewString("x"),
+			values.NewString("y"),
+		}),
+		"c": values.NewDuration(values.ConvertDurationNsecs(90 * time.Minute)),
+		"d": values.NewNull(semantic.BasicFloat),
+	})
+	i, err := values.ToJSON(v)
+	if err != nil {
+		t.Fatal(err)
+	}
+	got, err := json.Marshal(i)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if want := `{"a":1,"b":["x","y"],"c":"1h30m","d":null}`; string(got) != want {
+		t.Errorf("unexpected JSON: want %s, got %s", want, got)
+	}
+
+	fn := values.NewFunction("f", semantic.NewFunctionType(semantic.BasicInt, nil), nil, false)
+	if _, err := values.ToJSON(fn); err == nil {
+		t.Error("expected an error converting a function")
+	}
+}
+
+func TestFromJSON(t *testing.T) {
+	var i interface{}
+	if err := json.Unmarshal([]byte(`{"a":1.5,"b":["x","y"]}`), &i); err != nil {
+		t.Fatal(err)
+	}
+	got, err := values.FromJSON(i)
+	if err != nil {
+		t.Fatal(err)
+	}
+	want := values.NewObjectWithValues(map[string]values.Value{
+		"a": values.NewFloat(1.5),
+		"b": values.NewArrayWithBacking(semantic.NewArrayType(semantic.BasicString), []values.Value{
+			values.NewString("x"),
+			values.NewString("y"),
+		}),
+	})
+	if !got.Equal(want) {
+		t.Errorf("unexpected value: want %v, got %v", want, got)
+	}
+
+	if err := json.Unmarshal([]byte(`[1, "a"]`), &i); err != nil {
+		t.Fatal(err)
+	}
+	if _, err := values.FromJSON(i); err == nil {
+		t.Error("expected an error converting an array of mixed types")
+	}
+}